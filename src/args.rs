@@ -6,8 +6,17 @@ use crate::metadata::Settings;
 
 const MAX_CONSECUTIVE: usize = 40;
 const DEFAULT_LANG: &str = "en";
+const WEBTOON_HEIGHT: u32 = 2000;
+/// Default time in seconds for which cached API responses are used without asking the server.
+const CACHE_TTL: u64 = 3600;
+#[cfg(feature = "processing")]
+const TRIM_TOLERANCE: u8 = 16;
 
 lazy_static! {
+    /// Indicates whether arguments are parsed from the command line; set by `run` of the binary.
+    /// Programs using the library keep the defaults, so their own arguments are not parsed.
+    pub(crate) static ref COMMAND_LINE: Mutex<bool> = Mutex::new(false);
+
     /// A globally accessible, thread-safe instance of the parsed command-line arguments.
     ///
     /// This instance is protected by a `Mutex` to allow safe concurrent access from multiple threads.
@@ -22,14 +31,32 @@ lazy_static! {
     /// Indicates whether the `quiet` mode is enabled.
     pub(crate) static ref ARGS_QUIET: bool = ARGS.lock().quiet;
 
+    /// Indicates whether progress is printed as JSON events, see `events`.
+    pub(crate) static ref ARGS_OUTPUT_JSON: bool = ARGS.lock().output == "json";
+
     /// Indicates whether logging is enabled.
     pub(crate) static ref ARGS_LOG: bool = ARGS.lock().log;
 
     /// The encoding format specified by the user.
     pub(crate) static ref ARGS_ENCODE: String = ARGS.lock().encode.clone();
 
-    /// Indicates whether development mode is enabled.
-    pub(crate) static ref ARGS_DEV: bool = ARGS.lock().dev;
+    /// Indicates whether long strip processing is forced for the manga.
+    pub(crate) static ref ARGS_WEBTOON: bool = ARGS.lock().webtoon;
+
+    /// Indicates whether covers of all volumes should be downloaded.
+    pub(crate) static ref ARGS_ALL_COVERS: bool = ARGS.lock().all_covers;
+
+    /// Indicates whether dry run is enabled; nothing will be downloaded or written.
+    /// Indicates whether cached API responses should be fetched again.
+    pub(crate) static ref ARGS_REFRESH: bool = ARGS.lock().refresh;
+
+    pub(crate) static ref ARGS_DRY_RUN: bool = ARGS.lock().dry_run.is_some();
+
+    /// Indicates whether result of dry run should be printed as JSON.
+    pub(crate) static ref ARGS_DRY_RUN_JSON: bool = match ARGS.lock().dry_run {
+        Some(Some(ref format)) => format == "json",
+        _ => false,
+    };
 
     /// The music setting specified by the user, if any.
     pub(crate) static ref ARGS_MUSIC: Option<Option<String>> = ARGS.lock().music.clone();
@@ -118,6 +145,31 @@ lazy_static! {
         Some(_) => false,
         None => false,
     };
+
+    /// If true names of downloaded files will be sanitized and `dat.json` updated.
+    pub(crate) static ref ARGS_SANITIZE: bool = match ARGS.lock().subcommands {
+        Some(Commands::Database { sanitize, .. }) => sanitize,
+        Some(_) => false,
+        None => false,
+    };
+    /// If true missing authors, artists and publication info of manga in library will be fetched.
+    pub(crate) static ref ARGS_DETAILS: bool = match ARGS.lock().subcommands {
+        Some(Commands::Database { details, .. }) => details,
+        Some(_) => false,
+        None => false,
+    };
+    /// Library subcommand to run, if any.
+    pub(crate) static ref ARGS_LIBRARY: Option<LibraryCommands> = match ARGS.lock().subcommands {
+        Some(Commands::Library { ref command }) => Some(command.clone()),
+        Some(_) => None,
+        None => None,
+    };
+    /// Users subcommand to run, if any.
+    pub(crate) static ref ARGS_USERS: Option<UsersCommands> = match ARGS.lock().subcommands {
+        Some(Commands::Users { ref command }) => Some(command.clone()),
+        Some(_) => None,
+        None => None,
+    };
     /// Indicates whether to update app.
     pub(crate) static ref ARGS_APP_UPDATE: bool = match ARGS.lock().subcommands {
         Some(Commands::App { update, .. }) => update,
@@ -126,6 +178,26 @@ lazy_static! {
     };
 }
 
+#[cfg(any(feature = "server", feature = "web"))]
+lazy_static! {
+    /// Indicates whether development mode is enabled.
+    pub(crate) static ref ARGS_DEV: bool = ARGS.lock().dev;
+
+    /// Address the server of server or web mode listens on, if set.
+    pub(crate) static ref ARGS_BIND: Option<String> = ARGS.lock().bind.clone();
+}
+
+#[cfg(feature = "processing")]
+lazy_static! {
+    /// Indicates whether double-page spreads should be split into two pages.
+    pub(crate) static ref ARGS_SPLIT_SPREADS: bool = ARGS.lock().split_spreads;
+
+    /// Tolerance for trimming uniform borders of pages; `None` if trimming is disabled.
+    pub(crate) static ref ARGS_TRIM_BORDERS: Option<u8> = ARGS.lock().trim_borders.map(|tolerance| {
+        tolerance.unwrap_or(TRIM_TOLERANCE)
+    });
+}
+
 /// Mangadex Manga downloader
 #[derive(Parser)]
 #[command(
@@ -186,10 +258,19 @@ pub(crate) struct ParserArgs {
         long,
         default_value_t = String::from("."),
         next_line_help = true,
-        help = "put all chapters in folder specified,\n- if folder name is name it will put in folder same as manga name\n- if folder name is name and title is specified it will make folder same as title\n"
+        help = "put all chapters in folder specified,\n- if folder name is name it will put in folder same as manga name\n- if folder name is name and title is specified it will make folder same as title\n- folder can be template e.g. library/{manga}; only manga fields are available\n"
     )]
     pub(crate) folder: String,
 
+    /// Template of chapter file names, e.g. `{manga}/{volume:02}/{manga} c{chapter:03.1} [{group}]`.
+    #[arg(
+        long,
+        default_value_t = String::new(),
+        next_line_help = true,
+        help = "template of chapter file names relative to folder, / makes subfolders;\nfields: {manga} {manga_id} {volume} {chapter} {chapter_id} {title} {group} {group_id} {language} {date}\n{chapter:03.1} pads number with zeroes to 3 digits and keeps 1 decimal digit, {date:%Y} formats date;\ntemplate is remembered for the manga in database; default will use built-in naming\n"
+    )]
+    pub(crate) template: String,
+
     /// Download only the specified volume.
     #[arg(
         short,
@@ -227,10 +308,29 @@ pub(crate) struct ParserArgs {
     )]
     pub(crate) stat: bool,
 
+    /// Download covers of all volumes, not just the main cover.
+    #[arg(
+        long,
+        next_line_help = true,
+        help = "download covers of all volumes and locales into _covers folder;\ncover of chapter's volume is added as page zero of the chapter;\nsetting is remembered for the manga in database\n"
+    )]
+    pub(crate) all_covers: bool,
+
     /// Suppress the use of curses window.
     #[arg(long, next_line_help = true, help = "Won't use curses window")]
     pub(crate) quiet: bool,
 
+    /// Format of progress output; `json` prints one JSON event per line instead of curses window.
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        default_value_t = String::from("text"),
+        value_parser = ["text", "json"],
+        next_line_help = true,
+        help = "format of progress output; text or json;\njson prints one JSON event per line on stdout instead of curses window;\nwhen stdout is not a terminal, text is printed as plain lines\n"
+    )]
+    pub(crate) output: String,
+
     /// Number of manga images to download concurrently. Recommended to use a maximum of 50 to avoid performance issues and incomplete downloads.
     #[arg(
         short,
@@ -245,6 +345,68 @@ pub(crate) struct ParserArgs {
     #[arg(long, next_line_help = true, help = "download manga even if it already exists")]
     pub(crate) force: bool,
 
+    /// Print chapters that would be downloaded and their estimated size without downloading or writing anything; `json` prints it as JSON.
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        next_line_help = true,
+        help = "print chapters that would be downloaded with estimated size;\nnothing will be downloaded or written\n[default: text; json for json output]"
+    )]
+    pub(crate) dry_run: Option<Option<String>>,
+
+    /// Stitch pages of every chapter vertically and re-slice them at whitespace gutters; manga with `Long Strip` format tag are processed automatically.
+    #[arg(
+        long,
+        next_line_help = true,
+        help = "stitch pages of chapter vertically and re-slice them at whitespace gutters into pages of --webtoon-height;\nmanga with Long Strip format tag are processed automatically and setting is remembered in database\n"
+    )]
+    pub(crate) webtoon: bool,
+
+    /// Target height of pages in pixels when re-slicing long strip chapters.
+    #[arg(
+        long,
+        value_name = "HEIGHT",
+        default_value_t = WEBTOON_HEIGHT,
+        next_line_help = true,
+        help = "target height of pages in pixels when re-slicing long strip chapters\n"
+    )]
+    pub(crate) webtoon_height: u32,
+
+    /// Split landscape double-page spreads into two pages in reading order (right-to-left for Japanese manga).
+    #[arg(
+        long,
+        next_line_help = true,
+        help = "split landscape double-page spreads into two pages in reading order;\nright page goes first for manga with japanese as original language\n"
+    )]
+    pub(crate) split_spreads: bool,
+
+    /// Trim uniform white or black borders of pages; optional value is tolerance (0-255) of border colour.
+    #[arg(
+        long,
+        value_name = "TOLERANCE",
+        next_line_help = true,
+        help = "trim uniform white or black borders of pages;\nvalue is tolerance of border colour from 0 to 255\n[default: 16]"
+    )]
+    pub(crate) trim_borders: Option<Option<u8>>,
+
+    /// Ignore cached API responses and fetch them again.
+    #[arg(
+        long,
+        next_line_help = true,
+        help = "fetch manga, feeds, statistics, groups and covers from mangadex again instead of using cached responses\n"
+    )]
+    pub(crate) refresh: bool,
+
+    /// Time in seconds for which cached API responses are used without asking the server.
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = CACHE_TTL,
+        next_line_help = true,
+        help = "time in seconds for which cached api responses are used without asking mangadex;\nolder responses are revalidated and used when mangadex can't be reached\n"
+    )]
+    pub(crate) cache_ttl: u64,
+
     /// Start offset for downloading chapters. For example, "50" starts from chapter 50.
     #[arg(
         short,
@@ -328,6 +490,15 @@ pub(crate) struct ParserArgs {
     #[arg(long, next_line_help = true, help = "Starts server")]
     pub(crate) server: bool,
 
+    /// Address of the interface the server of server or web mode listens on.
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        next_line_help = true,
+        help = "IP address of interface which server of --server or --web listens on;\n[default: --server will ask for it, --web uses 127.0.0.1]"
+    )]
+    pub(crate) bind: Option<String>,
+
     /// Start a gui mode
     #[arg(long, next_line_help = true, help = "Gui version of mdown")]
     pub(crate) gui: bool,
@@ -387,6 +558,22 @@ pub(crate) enum Commands {
         /// You will choose which backup to retrieve.
         #[arg(long, next_line_help = true, help = "You will choose which backup to retrieve")]
         backup_choose: bool,
+
+        /// Renames downloaded files and folders to sanitized names.
+        #[arg(
+            long,
+            next_line_help = true,
+            help = "Renames downloaded manga folders and chapters to names safe on all platforms and updates database; with --dry-run it will only print changes"
+        )]
+        sanitize: bool,
+
+        /// Fetches details of manga downloaded by older versions.
+        #[arg(
+            long,
+            next_line_help = true,
+            help = "Fetches authors, artists, alternative titles and publication info of manga in database which don't have them yet, e.g. downloaded by older versions; with --dry-run it will only print them"
+        )]
+        details: bool,
     },
 
     /// Subcommands related to application settings.
@@ -412,6 +599,20 @@ pub(crate) enum Commands {
             help = "Will set default of backup files n[default: Will remove current backup setting; 1 is for yes, 0 for no][default for backup is 1]"
         )]
         backup: Option<Option<String>>,
+        /// Set default filename template.
+        #[arg(
+            long,
+            next_line_help = true,
+            help = "set default template of chapter file names, see --template\n[default: Will remove current template setting]"
+        )]
+        template: Option<Option<String>>,
+        /// Set folders searched for manga moved outside of mdown.
+        #[arg(
+            long,
+            next_line_help = true,
+            help = "set folders which database --check and --update search for manga whose folder was moved and which --server gives access to, separated by ';'\n[default: Will remove current library roots setting]"
+        )]
+        library_roots: Option<Option<String>>,
         /// Will start music
         #[arg(
             long,
@@ -440,7 +641,7 @@ pub(crate) enum Commands {
         force_delete: bool,
 
         /// Delete `dat.json`.
-        #[arg(long, next_line_help = true, help = "Delete dat.json")]
+        #[arg(long, next_line_help = true, help = "Delete the library index")]
         delete: bool,
 
         /// Delete all files created by the program.
@@ -455,9 +656,188 @@ pub(crate) enum Commands {
         #[arg(long, next_line_help = true, help = "Will update app")]
         update: bool,
     },
+
+    /// Subcommands related to the library of downloaded manga.
+    Library {
+        #[command(subcommand)]
+        command: LibraryCommands,
+    },
+
+    /// Subcommands related to users of server and web mode.
+    Users {
+        #[command(subcommand)]
+        command: UsersCommands,
+    },
     Default,
 }
 
+/// Enum representing the subcommands of `users`.
+#[derive(Subcommand, Clone, Debug)]
+pub(crate) enum UsersCommands {
+    /// Add a user or replace password and role of an existing one.
+    Add {
+        /// Name of the user.
+        #[arg(next_line_help = true, help = "Name of user")]
+        name: String,
+
+        /// Role of the user.
+        #[arg(
+            long,
+            default_value_t = String::from("read"),
+            next_line_help = true,
+            help = "Role of user; read can browse and read library, admin can also download"
+        )]
+        role: String,
+
+        /// Password of the user; it ends up in shell history and is visible to other users in the
+        /// process list, so asking for it is safer.
+        #[arg(
+            long,
+            next_line_help = true,
+            help = "Password of user; default will ask for it without echo. Given here it stays in shell history and process list"
+        )]
+        password: Option<String>,
+    },
+
+    /// Remove a user.
+    Remove {
+        /// Name of the user.
+        #[arg(next_line_help = true, help = "Name of user")]
+        name: String,
+    },
+
+    /// Generate a new token of a user.
+    Token {
+        /// Name of the user.
+        #[arg(next_line_help = true, help = "Name of user")]
+        name: String,
+    },
+
+    /// Show users.
+    List,
+}
+
+/// Enum representing the subcommands of `library`.
+#[derive(Subcommand, Clone, Debug)]
+pub(crate) enum LibraryCommands {
+    /// Import folders of `.cbz` files into the database.
+    Import {
+        /// Folder of manga folders, or a single manga folder.
+        #[arg(
+            next_line_help = true,
+            help = "Folder with manga folders of .cbz files, or a single manga folder; chapters are identified by _metadata, ComicInfo.xml or their file names and added to database; with --dry-run it will only print what would be imported"
+        )]
+        path: String,
+    },
+
+    /// Move manga folder to a different location.
+    Move {
+        /// Id or name of the manga.
+        #[arg(next_line_help = true, help = "Id or name of manga in database")]
+        id: String,
+
+        /// New location of the manga folder.
+        #[arg(
+            next_line_help = true,
+            help = "New path of manga folder; if it is an existing folder, manga folder is moved into it"
+        )]
+        path: String,
+    },
+
+    /// Rename manga and its folder.
+    Rename {
+        /// Id or name of the manga.
+        #[arg(next_line_help = true, help = "Id or name of manga in database")]
+        id: String,
+
+        /// New name of the manga.
+        #[arg(next_line_help = true, help = "New name of manga and its folder")]
+        name: String,
+    },
+
+    /// Export manga in the library as a portable manifest.
+    Export {
+        /// Format of the manifest.
+        #[arg(
+            long,
+            default_value_t = String::from("json"),
+            next_line_help = true,
+            help = "Format of the manifest; json or csv"
+        )]
+        format: String,
+
+        /// File to write the manifest to.
+        #[arg(
+            long,
+            next_line_help = true,
+            help = "File to write the manifest to; default will print it"
+        )]
+        output: Option<String>,
+    },
+
+    /// Add manga from a manifest written by `library export` to the library.
+    ImportManifest {
+        /// Path of the manifest.
+        #[arg(
+            next_line_help = true,
+            help = "Manifest written by library export; .csv files are read as CSV, others as JSON"
+        )]
+        path: String,
+
+        /// Folder where manga folders are created.
+        #[arg(
+            long,
+            next_line_help = true,
+            help = "Folder where manga folders are created; default is the first library root or current folder"
+        )]
+        root: Option<String>,
+
+        /// Download chapters after importing.
+        #[arg(
+            long,
+            next_line_help = true,
+            help = "Download chapters of all manga in library after importing, like database --update"
+        )]
+        download: bool,
+    },
+
+    /// Show statistics of the library.
+    Stats {
+        /// Format of the report.
+        #[arg(
+            long,
+            default_value_t = String::from("table"),
+            next_line_help = true,
+            help = "Format of the report; table, json or markdown"
+        )]
+        format: String,
+
+        /// Number of the largest series and series with the most missing chapters.
+        #[arg(
+            long,
+            default_value_t = 10,
+            next_line_help = true,
+            help = "Number of series in lists of the largest series and of series with the most missing chapters"
+        )]
+        top: usize,
+    },
+
+    /// Show chapters missing between downloaded chapters of manga in the library.
+    Gaps {
+        /// Id or name of the manga.
+        #[arg(long, next_line_help = true, help = "Id or name of manga in database; default is all manga")]
+        id: Option<String>,
+
+        /// Download missing chapters which are available.
+        #[arg(
+            long,
+            next_line_help = true,
+            help = "Download missing chapters which are uploaded in language of the manga"
+        )]
+        download: bool,
+    },
+}
+
 /// Enum for different types of values used in the application.
 pub(crate) enum Value {
     /// A boolean value.
@@ -477,13 +857,26 @@ pub(crate) struct Args {
     pub(crate) lang: String,
     pub(crate) title: String,
     pub(crate) folder: String,
+    pub(crate) template: String,
     pub(crate) volume: String,
     pub(crate) chapter: String,
     pub(crate) saver: bool,
     pub(crate) stat: bool,
+    pub(crate) all_covers: bool,
     pub(crate) quiet: bool,
+    pub(crate) output: String,
     pub(crate) max_consecutive: usize,
     pub(crate) force: bool,
+    pub(crate) dry_run: Option<Option<String>>,
+    pub(crate) webtoon: bool,
+    #[cfg(feature = "processing")]
+    pub(crate) webtoon_height: u32,
+    #[cfg(feature = "processing")]
+    pub(crate) split_spreads: bool,
+    #[cfg(feature = "processing")]
+    pub(crate) trim_borders: Option<Option<u8>>,
+    pub(crate) refresh: bool,
+    pub(crate) cache_ttl: u64,
     pub(crate) offset: String,
     pub(crate) database_offset: String,
     pub(crate) unsorted: bool,
@@ -501,10 +894,13 @@ pub(crate) struct Args {
     pub(crate) show_settings: bool,
     pub(crate) web: bool,
     pub(crate) server: bool,
+    #[cfg(any(feature = "server", feature = "web"))]
+    pub(crate) bind: Option<String>,
     pub(crate) gui: bool,
     pub(crate) debug: bool,
     pub(crate) debug_file: bool,
     pub(crate) backup: bool,
+    #[cfg(any(feature = "server", feature = "web"))]
     pub(crate) dev: bool,
     pub(crate) music: Option<Option<String>>,
     pub(crate) subcommands: Option<Commands>,
@@ -595,6 +991,8 @@ impl Args {
     ///
     /// # Behavior
     /// - If no subcommand is provided, it defaults to `Commands::Default`.
+    /// - If `COMMAND_LINE` is not set, defaults with `--quiet` are used instead of the arguments of
+    ///   the process.
     /// - Fields are assigned directly from `ParserArgs`, with specific handling for database and app subcommands.
     ///
     /// # Example
@@ -603,7 +1001,10 @@ impl Args {
     /// println!("{:?}", args);
     /// ```
     pub(crate) fn from_args() -> Args {
-        let args = ParserArgs::parse();
+        let args = match *COMMAND_LINE.lock() {
+            true => ParserArgs::parse(),
+            false => ParserArgs::parse_from(["mdown", "--quiet"]),
+        };
         let subcommands = match args.subcommands {
             Some(ref value) => value,
             None => &Commands::Default,
@@ -613,13 +1014,26 @@ impl Args {
             lang: args.lang,
             title: args.title,
             folder: args.folder,
+            template: args.template,
             volume: args.volume,
             chapter: args.chapter,
             saver: args.saver,
             stat: args.stat,
+            all_covers: args.all_covers,
             quiet: args.quiet,
+            output: args.output,
             max_consecutive: args.max_consecutive,
             force: args.force,
+            dry_run: args.dry_run,
+            webtoon: args.webtoon,
+            #[cfg(feature = "processing")]
+            webtoon_height: args.webtoon_height,
+            #[cfg(feature = "processing")]
+            split_spreads: args.split_spreads,
+            #[cfg(feature = "processing")]
+            trim_borders: args.trim_borders,
+            refresh: args.refresh,
+            cache_ttl: args.cache_ttl,
             offset: args.offset,
             database_offset: args.database_offset,
             unsorted: args.unsorted,
@@ -632,6 +1046,10 @@ impl Args {
             },
             update: match subcommands {
                 Commands::Database { update, .. } => *update,
+                Commands::Library {
+                    command: LibraryCommands::ImportManifest { download, .. },
+                } => *download,
+                Commands::Library { command: LibraryCommands::Gaps { download, .. } } => *download,
                 _ => false,
             },
             show: match subcommands {
@@ -656,10 +1074,13 @@ impl Args {
             },
             web: args.web,
             server: args.server,
+            #[cfg(any(feature = "server", feature = "web"))]
+            bind: args.bind,
             search: args.search,
             gui: args.gui,
             debug: args.debug,
             debug_file: args.debug_file,
+            #[cfg(any(feature = "server", feature = "web"))]
             dev: args.dev,
             music: args.music,
             tutorial: args.tutorial,
@@ -734,13 +1155,26 @@ impl Args {
             lang,
             title,
             folder,
+            template: ARGS.lock().template.clone(),
             volume,
             chapter,
             saver,
             stat,
+            all_covers: *ARGS_ALL_COVERS,
             quiet: *ARGS_QUIET,
+            output: ARGS.lock().output.clone(),
             max_consecutive,
             force,
+            dry_run: None,
+            webtoon: *ARGS_WEBTOON,
+            #[cfg(feature = "processing")]
+            webtoon_height: ARGS.lock().webtoon_height,
+            #[cfg(feature = "processing")]
+            split_spreads: *ARGS_SPLIT_SPREADS,
+            #[cfg(feature = "processing")]
+            trim_borders: ARGS.lock().trim_borders,
+            refresh: *ARGS_REFRESH,
+            cache_ttl: ARGS.lock().cache_ttl,
             offset,
             database_offset,
             unsorted: *ARGS_UNSORTED,
@@ -755,10 +1189,13 @@ impl Args {
             show_settings: *ARGS_SHOW_SETTINGS,
             web: *ARGS_WEB,
             server: *ARGS_SERVER,
+            #[cfg(any(feature = "server", feature = "web"))]
+            bind: ARGS_BIND.clone(),
             search: String::new(),
             gui: *ARGS_GUI,
             debug: *ARGS_DEBUG,
             debug_file: *ARGS_DEBUG_FILE,
+            #[cfg(any(feature = "server", feature = "web"))]
             dev: *ARGS_DEV,
             backup: ARGS_BACKUP.clone(),
             // ARGS_MUSIC is not synchronized with database
@@ -0,0 +1,446 @@
+//! Authentication and access control of server and web modes.
+//!
+//! Users are stored in the `users` table of `resources.db` and managed by `users` subcommands. The
+//! password of a user is stored as salted PBKDF2-HMAC-SHA256 hash and the token as SHA-256 hash,
+//! so neither can be read back from the database; the token is printed once when it is generated.
+//!
+//! When there is at least one user, every request has to be authenticated by one of:
+//! - `Authorization: Basic ...` with name and password of the user, which browsers ask for,
+//! - `Authorization: Bearer <token>`,
+//! - `?token=<token>` in the query, for clients which can't send headers, e.g. some OPDS readers.
+//!
+//! Users with role `read` can browse and read the library; `admin` can also download, i.e. queue
+//! jobs, zip folders and stop the server. Without users everyone who can reach the server is
+//! `admin`, so the server should be bound only to a trusted interface, see `--bind`. Users are
+//! loaded when the server starts, so changes of users take effect after its restart.
+
+use base64::{ Engine, engine::general_purpose::STANDARD };
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use pbkdf2::pbkdf2_hmac_array;
+use rusqlite::{ Connection, params };
+use sha2::{ Digest, Sha256 };
+use std::{ collections::HashMap, net::IpAddr };
+
+use crate::{ args::UsersCommands, error::MdownError, getter, log, utils };
+
+/// Number of PBKDF2 iterations of new password hashes.
+const ITERATIONS: u32 = 100_000;
+
+/// Length of the token of a user.
+const TOKEN_LENGTH: usize = 40;
+
+lazy_static! {
+    /// Users loaded by `load`.
+    static ref USERS: Mutex<Vec<User>> = Mutex::new(Vec::new());
+
+    /// Roles of already verified `Authorization` headers by SHA-256 of the header, so the password
+    /// hash isn't computed for every request.
+    static ref VERIFIED: Mutex<HashMap<String, Role>> = Mutex::new(HashMap::new());
+}
+
+/// Role of a user; `Admin` has all permissions of `Read`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Role {
+    Read,
+    Admin,
+}
+
+impl Role {
+    /// Returns the role named `name`, i.e. `read` or `admin`.
+    fn parse(name: &str) -> Option<Role> {
+        match name {
+            "read" => Some(Role::Read),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Role::Read => "read",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+/// User stored in the database.
+struct User {
+    name: String,
+    salt: String,
+    /// `pbkdf2-sha256$<iterations>$<hex of hash>`
+    hash: String,
+    role: Role,
+    /// Hex of SHA-256 of the token.
+    token: String,
+}
+
+/// Opens the database and creates the `users` table if it doesn't exist.
+fn open() -> Result<Connection, MdownError> {
+    let db_path = match getter::get_db_path() {
+        Ok(path) => path,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 13300));
+        }
+    };
+    let conn = match Connection::open(&db_path) {
+        Ok(conn) => conn,
+        Err(err) => {
+            return Err(MdownError::DatabaseError(err, 13301));
+        }
+    };
+    match
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS users (
+                name TEXT PRIMARY KEY,
+                salt TEXT NOT NULL,
+                hash TEXT NOT NULL,
+                role TEXT NOT NULL,
+                token TEXT NOT NULL
+            )",
+            []
+        )
+    {
+        Ok(_) => Ok(conn),
+        Err(err) => Err(MdownError::DatabaseError(err, 13302)),
+    }
+}
+
+/// Reads all users from the database.
+fn read_users(conn: &Connection) -> Result<Vec<User>, MdownError> {
+    let mut stmt = match conn.prepare("SELECT name, salt, hash, role, token FROM users ORDER BY name") {
+        Ok(stmt) => stmt,
+        Err(err) => {
+            return Err(MdownError::DatabaseError(err, 13303));
+        }
+    };
+    let rows = stmt.query_map([], |row| {
+        let role: String = row.get(3)?;
+        Ok(User {
+            name: row.get(0)?,
+            salt: row.get(1)?,
+            hash: row.get(2)?,
+            // Unknown roles, e.g. edited by hand, get the least permissions
+            role: Role::parse(&role).unwrap_or(Role::Read),
+            token: row.get(4)?,
+        })
+    });
+    match rows.and_then(|rows| rows.collect()) {
+        Ok(users) => Ok(users),
+        Err(err) => Err(MdownError::DatabaseError(err, 13304)),
+    }
+}
+
+/// Loads users for authentication of requests; warns if there are none and the server listens on
+/// `address` reachable from other devices.
+pub(crate) fn load(address: &str) -> Result<(), MdownError> {
+    let users = match open().and_then(|conn| read_users(&conn)) {
+        Ok(users) => users,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 13305));
+        }
+    };
+    let loopback = address
+        .parse::<IpAddr>()
+        .map(|ip| ip.is_loopback())
+        .unwrap_or(false);
+    if users.is_empty() && !loopback {
+        println!(
+            "Warning: there are no users, so everyone who can reach {} has full access; add users with `users add`",
+            address
+        );
+    }
+    *USERS.lock() = users;
+    VERIFIED.lock().clear();
+    Ok(())
+}
+
+/// Runs a `users` subcommand.
+pub(crate) fn run(command: &UsersCommands) -> Result<(), MdownError> {
+    let conn = match open() {
+        Ok(conn) => conn,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 13306));
+        }
+    };
+    match command {
+        UsersCommands::Add { name, role, password } => {
+            let role = match Role::parse(role) {
+                Some(role) => role,
+                None => {
+                    return Err(
+                        MdownError::CustomError(
+                            format!("Unknown role {}; use read or admin", role),
+                            String::from("Role"),
+                            13307
+                        )
+                    );
+                }
+            };
+            let password = match password {
+                Some(password) => password.clone(),
+                None => {
+                    // Typed password isn't echoed to the terminal
+                    match rpassword::prompt_password("Password: ") {
+                        Ok(password) => password,
+                        Err(err) => {
+                            return Err(MdownError::IoError(err, String::new(), 13308));
+                        }
+                    }
+                }
+            };
+            if password.is_empty() {
+                return Err(
+                    MdownError::CustomError(
+                        String::from("Password can't be empty"),
+                        String::from("Password"),
+                        13309
+                    )
+                );
+            }
+            let salt = utils::generate_random_id(16).to_string();
+            let token = utils::generate_random_id(TOKEN_LENGTH).to_string();
+            match
+                conn.execute(
+                    "INSERT OR REPLACE INTO users (name, salt, hash, role, token) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        name,
+                        salt,
+                        hash_password(&password, &salt, ITERATIONS),
+                        role.as_str(),
+                        sha256(&token)
+                    ]
+                )
+            {
+                Ok(_) => (),
+                Err(err) => {
+                    return Err(MdownError::DatabaseError(err, 13310));
+                }
+            }
+            println!("User {} ({}) saved", name, role.as_str());
+            println!("Token: {}", token);
+        }
+        UsersCommands::Remove { name } => {
+            match conn.execute("DELETE FROM users WHERE name = ?1", params![name]) {
+                Ok(0) => {
+                    return Err(MdownError::NotFoundError(format!("user {}", name), 13311));
+                }
+                Ok(_) => println!("User {} removed", name),
+                Err(err) => {
+                    return Err(MdownError::DatabaseError(err, 13312));
+                }
+            }
+        }
+        UsersCommands::Token { name } => {
+            let token = utils::generate_random_id(TOKEN_LENGTH).to_string();
+            match
+                conn.execute(
+                    "UPDATE users SET token = ?1 WHERE name = ?2",
+                    params![sha256(&token), name]
+                )
+            {
+                Ok(0) => {
+                    return Err(MdownError::NotFoundError(format!("user {}", name), 13313));
+                }
+                Ok(_) => println!("Token: {}", token),
+                Err(err) => {
+                    return Err(MdownError::DatabaseError(err, 13314));
+                }
+            }
+        }
+        UsersCommands::List => {
+            let users = match read_users(&conn) {
+                Ok(users) => users,
+                Err(err) => {
+                    return Err(MdownError::ChainedError(Box::new(err), 13315));
+                }
+            };
+            if users.is_empty() {
+                println!("No users; server and web mode don't require authentication");
+            }
+            for user in users.iter() {
+                println!("{} ({})", user.name, user.role.as_str());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks whether `request` may access a resource requiring `role`.
+///
+/// # Parameters
+/// - `request`: Request line and headers of the request.
+/// - `role`: Role required by the resource.
+///
+/// # Returns
+/// - `None`: If the request is allowed.
+/// - `Some(&str)`: HTTP response to send instead, `401 Unauthorized` if the request isn't
+///   authenticated or `403 Forbidden` if the user doesn't have `role`.
+pub(crate) fn reject(request: &str, role: Role) -> Option<&'static str> {
+    let users = USERS.lock();
+    if users.is_empty() {
+        return None;
+    }
+    let route = request
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .split('?')
+        .next()
+        .unwrap_or_default();
+    match authenticate(&users, request) {
+        Some(user_role) if user_role >= role => None,
+        Some(_) => {
+            log!(&format!("[auth] Forbidden {}", route));
+            Some("HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n")
+        }
+        None => {
+            log!(&format!("[auth] Unauthorized {}", route));
+            Some(
+                "HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Basic realm=\"mdown\", charset=\"UTF-8\"\r\nContent-Length: 0\r\n\r\n"
+            )
+        }
+    }
+}
+
+/// Returns role of the user who sent `request`, or `None` if the request isn't authenticated.
+fn authenticate(users: &[User], request: &str) -> Option<Role> {
+    let authorization = request
+        .lines()
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("authorization").then(|| value.trim())
+        });
+
+    if let Some(authorization) = authorization {
+        let (scheme, credentials) = authorization.split_once(' ').unwrap_or((authorization, ""));
+        if scheme.eq_ignore_ascii_case("bearer") {
+            return find_token(users, credentials.trim());
+        }
+        if !scheme.eq_ignore_ascii_case("basic") {
+            return None;
+        }
+        let key = sha256(authorization);
+        if let Some(role) = VERIFIED.lock().get(&key) {
+            return Some(*role);
+        }
+        let decoded = STANDARD.decode(credentials.trim()).ok()?;
+        let decoded = String::from_utf8(decoded).ok()?;
+        let (name, password) = decoded.split_once(':')?;
+        let user = users.iter().find(|user| user.name == name)?;
+        if !verify_password(password, &user.salt, &user.hash) {
+            return None;
+        }
+        VERIFIED.lock().insert(key, user.role);
+        return Some(user.role);
+    }
+
+    let query = request.split_whitespace().nth(1)?.split_once('?')?.1;
+    let token = query.split('&').find_map(|pair| pair.strip_prefix("token="))?;
+    find_token(users, token)
+}
+
+/// Returns role of the user with `token`.
+fn find_token(users: &[User], token: &str) -> Option<Role> {
+    if token.is_empty() {
+        return None;
+    }
+    let hash = sha256(token);
+    users
+        .iter()
+        .find(|user| equals(user.token.as_bytes(), hash.as_bytes()))
+        .map(|user| user.role)
+}
+
+/// Returns hash of `password` in the format stored in the database.
+fn hash_password(password: &str, salt: &str, iterations: u32) -> String {
+    format!(
+        "pbkdf2-sha256${}${}",
+        iterations,
+        hex(&pbkdf2_hmac_array::<Sha256, 32>(password.as_bytes(), salt.as_bytes(), iterations))
+    )
+}
+
+/// Checks `password` against `hash` made by `hash_password`.
+fn verify_password(password: &str, salt: &str, hash: &str) -> bool {
+    let iterations = match hash.split('$').nth(1).and_then(|count| count.parse().ok()) {
+        Some(iterations) => iterations,
+        None => {
+            return false;
+        }
+    };
+    equals(hash_password(password, salt, iterations).as_bytes(), hash.as_bytes())
+}
+
+/// Compares `a` and `b` in time which doesn't depend on where they differ.
+fn equals(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() &&
+        a
+            .iter()
+            .zip(b)
+            .fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn sha256(value: &str) -> String {
+    hex(&Sha256::digest(value.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// Test of PBKDF2 against the test vector of RFC 7914 and of verifying passwords
+#[test]
+fn test_hash_password() {
+    assert_eq!(
+        hex(&pbkdf2_hmac_array::<Sha256, 32>(b"passwd", b"salt", 1)),
+        "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc"
+    );
+    let hash = hash_password("secret", "salt", 10);
+    assert!(hash.starts_with("pbkdf2-sha256$10$"));
+    assert!(verify_password("secret", "salt", &hash));
+    assert!(!verify_password("secret", "other", &hash));
+    assert!(!verify_password("Secret", "salt", &hash));
+    assert!(!verify_password("secret", "salt", "pbkdf2-sha256$x$"));
+}
+
+// Test of authenticating requests by basic authentication, bearer token and token in query
+#[test]
+fn test_authenticate() {
+    let users = vec![
+        User {
+            name: String::from("reader"),
+            salt: String::from("abc"),
+            hash: hash_password("pass", "abc", 10),
+            role: Role::Read,
+            token: sha256("reader_token"),
+        },
+        User {
+            name: String::from("admin"),
+            salt: String::from("def"),
+            hash: hash_password("word", "def", 10),
+            role: Role::Admin,
+            token: sha256("admin_token"),
+        }
+    ];
+    let basic = |credentials: &str| {
+        format!("GET / HTTP/1.1\r\nHost: x\r\nauthorization: Basic {}\r\n\r\n", STANDARD.encode(credentials))
+    };
+    assert_eq!(authenticate(&users, &basic("reader:pass")), Some(Role::Read));
+    assert_eq!(authenticate(&users, &basic("admin:word")), Some(Role::Admin));
+    assert_eq!(authenticate(&users, &basic("admin:pass")), None);
+    assert_eq!(authenticate(&users, &basic("nobody:pass")), None);
+    assert_eq!(
+        authenticate(&users, "GET / HTTP/1.1\r\nAuthorization: Bearer admin_token\r\n\r\n"),
+        Some(Role::Admin)
+    );
+    assert_eq!(authenticate(&users, "GET /opds?token=reader_token HTTP/1.1\r\n\r\n"), Some(Role::Read));
+    assert_eq!(authenticate(&users, "GET /opds?token= HTTP/1.1\r\n\r\n"), None);
+    assert_eq!(authenticate(&users, "GET / HTTP/1.1\r\n\r\n"), None);
+    assert!(Role::Admin > Role::Read);
+}
@@ -0,0 +1,140 @@
+//! `ComicInfo.xml` of downloaded chapters.
+//!
+//! Comic readers and library servers read series, numbering and credits of a `.cbz` file from
+//! `ComicInfo.xml` in the root of the archive. The file is written next to `_metadata` before the
+//! chapter is zipped; empty values are left out. `read` parses the file back, e.g. of archives made
+//! by other downloaders when they are imported.
+
+use std::{ collections::HashMap, fs };
+
+use crate::{ error::MdownError, metadata::TagMetadata, resolute, utils };
+
+/// Name of the file inside the chapter archive.
+pub(crate) const FILE_NAME: &str = "ComicInfo.xml";
+
+/// Writes `ComicInfo.xml` of the chapter described by `filename` into `folder`.
+///
+/// `translator` is the name of the scanlation group.
+pub(crate) fn write(
+    folder: &str,
+    filename: &utils::FileName,
+    translator: &str
+) -> Result<(), MdownError> {
+    let path = format!("{}{}", folder, FILE_NAME);
+    match fs::write(&path, create(filename, translator)) {
+        Ok(()) => Ok(()),
+        Err(err) => Err(MdownError::IoError(err, path, 12200)),
+    }
+}
+
+/// Returns `ComicInfo.xml` of the chapter described by `filename`.
+pub(crate) fn create(filename: &utils::FileName, translator: &str) -> String {
+    let details = resolute::MANGA_DETAILS.lock().clone();
+    let tags = |tags: &[TagMetadata]| -> String {
+        tags.iter()
+            .map(|tag| tag.name.clone())
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    let manga = if details.original_language == "ja" { "YesAndRightToLeft" } else { "Yes" };
+    let fields = [
+        ("Title", filename.title.clone()),
+        ("Series", filename.manga_name.clone()),
+        ("Number", filename.chapter_num.clone()),
+        ("Volume", filename.volume.clone()),
+        ("Writer", details.authors.join(", ")),
+        ("Penciller", details.artists.join(", ")),
+        ("Translator", translator.to_string()),
+        ("Genre", tags(&resolute::GENRES.lock())),
+        ("Tags", tags(&resolute::THEMES.lock())),
+        ("Year", details.year.map(|year| year.to_string()).unwrap_or_default()),
+        ("LanguageISO", filename.language.clone()),
+        ("Web", format!("https://mangadex.org/chapter/{}", filename.chapter_id)),
+        ("Manga", manga.to_string()),
+    ];
+    to_xml(&fields)
+}
+
+fn to_xml(fields: &[(&str, String)]) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<ComicInfo xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\n"
+    );
+    for (name, value) in fields.iter() {
+        if value.is_empty() {
+            continue;
+        }
+        xml.push_str(&format!("  <{}>{}</{}>\n", name, escape(value), name));
+    }
+    xml.push_str("</ComicInfo>\n");
+    xml
+}
+
+/// Returns values of `ComicInfo.xml` by their element names, e.g. `Series` or `Number`.
+///
+/// Only flat elements with text are read, which covers all fields written by `create`.
+pub(crate) fn read(xml: &str) -> HashMap<String, String> {
+    let re = match regex::Regex::new(r"<(\w+)>([^<]*)</(\w+)>") {
+        Ok(re) => re,
+        Err(_err) => {
+            return HashMap::new();
+        }
+    };
+    re.captures_iter(xml)
+        .filter(|cap| cap[1] == cap[3])
+        .map(|cap| (cap[1].to_string(), unescape(cap[2].trim())))
+        .collect()
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// Test of escaping values and leaving out empty ones
+#[test]
+fn test_to_xml() {
+    let xml = to_xml(
+        &[
+            ("Series", String::from("Tom & Jerry <3")),
+            ("Volume", String::new()),
+            ("Writer", String::from("O'Neil")),
+        ]
+    );
+    assert!(xml.contains("  <Series>Tom &amp; Jerry &lt;3</Series>\n"));
+    assert!(xml.contains("  <Writer>O&apos;Neil</Writer>\n"));
+    assert!(!xml.contains("Volume"));
+    assert!(xml.ends_with("</ComicInfo>\n"));
+}
+
+// Test of reading values written by to_xml
+#[test]
+fn test_read() {
+    let xml = to_xml(
+        &[
+            ("Series", String::from("Tom & Jerry <3")),
+            ("Number", String::from("12.5")),
+        ]
+    );
+    let values = read(&xml);
+    assert_eq!(values.get("Series").map(String::as_str), Some("Tom & Jerry <3"));
+    assert_eq!(values.get("Number").map(String::as_str), Some("12.5"));
+    assert_eq!(values.get("Volume"), None);
+}
@@ -7,7 +7,9 @@ use crate::{
     debug,
     error::{ MdownError, suspend_error },
     getter,
+    library,
     metadata,
+    template,
     tutorial::TUTORIAL,
 };
 
@@ -17,6 +19,8 @@ pub const DB_FOLDER: &str = "2001";
 pub const DB_STAT: &str = "2002";
 pub const DB_TUTORIAL: &str = "2003";
 pub const DB_BACKUP: &str = "2004";
+pub const DB_TEMPLATE: &str = "2005";
+pub const DB_LIBRARY_ROOTS: &str = "2006";
 #[cfg(feature = "music")]
 pub const DB_MUSIC: &str = "2101";
 pub const DB_UPDATE_TIME: &str = "2201";
@@ -131,6 +135,175 @@ pub(crate) fn get_update_time() -> Result<Option<String>, MdownError> {
     };
 }
 
+/// Retrieves the global filename template from the database.
+///
+/// # Errors
+/// - `MdownError::ChainedError(10684)`: If `getter::get_db_path` returns an error.
+/// - `MdownError::DatabaseError(10685)`: If there is an issue opening the database connection.
+/// - `MdownError::CustomError(10686)`: If there is a failure converting the byte value to a `String` from UTF-8.
+/// - `MdownError::ChainedError(10687)`: If there is an error during UTF-8 conversion of the template.
+/// - `MdownError::ChainedError(10688)`: If there is an error reading the resource from the database.
+///
+/// # Returns
+/// - `Ok(Some(String))`: If the template is set in the database.
+/// - `Ok(None)`: If no template is set.
+/// - `Err(MdownError)`: In case of any errors during the process.
+pub(crate) fn get_template() -> Result<Option<String>, MdownError> {
+    let db_path = match getter::get_db_path() {
+        Ok(path) => path,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 10684));
+        }
+    };
+
+    // Open a connection to the database
+    let conn = match Connection::open(&db_path) {
+        Ok(conn) => conn,
+        Err(err) => {
+            return Err(MdownError::DatabaseError(err, 10685));
+        }
+    };
+    match read_resource(&conn, DB_TEMPLATE) {
+        Ok(Some(value)) =>
+            match
+                String::from_utf8(value).map_err(|e|
+                    MdownError::CustomError(e.to_string(), String::from("Base64Error"), 10686)
+                )
+            {
+                Ok(template) => {
+                    debug!("template from database: {:?}", template);
+                    Ok(Some(template))
+                }
+                Err(err) => Err(MdownError::ChainedError(Box::new(err), 10687)),
+            }
+        Ok(None) => Ok(None),
+        Err(err) => Err(MdownError::ChainedError(Box::new(err), 10688)),
+    }
+}
+
+/// Opens the database; tables are created by `setup_settings` at start.
+fn open_db() -> Result<Connection, MdownError> {
+    let db_path = match getter::get_db_path() {
+        Ok(path) => path,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 10694));
+        }
+    };
+    match Connection::open(&db_path) {
+        Ok(conn) => Ok(conn),
+        Err(err) => Err(MdownError::DatabaseError(err, 10695)),
+    }
+}
+
+/// Retrieves the time of the last successful feed check of the manga with `id`.
+///
+/// # Errors
+/// - `MdownError::ChainedError(10694)`, `MdownError::DatabaseError(10695)`: If the database can't be opened.
+/// - `MdownError::DatabaseError(10696)`: If the time can't be read.
+///
+/// # Returns
+/// - `Ok(Some(String))`: Time in `%Y-%m-%dT%H:%M:%S` format (UTC).
+/// - `Ok(None)`: If the feed of the manga was never cached.
+pub(crate) fn get_feed_time(id: &str) -> Result<Option<String>, MdownError> {
+    let conn = open_db()?;
+    match
+        conn
+            .query_row(
+                "SELECT checked_at FROM feed_times WHERE manga_id = ?1",
+                params![id],
+                |row| row.get::<_, String>(0)
+            )
+            .optional()
+    {
+        Ok(time) => {
+            debug!("feed time of {} from database: {:?}", id, time);
+            Ok(time)
+        }
+        Err(err) => Err(MdownError::DatabaseError(err, 10696)),
+    }
+}
+
+/// Retrieves the cached chapter feed of the manga with `id`.
+///
+/// # Errors
+/// - `MdownError::ChainedError(10694)`, `MdownError::DatabaseError(10695)`: If the database can't be opened.
+/// - `MdownError::DatabaseError(10697)`: If the chapters can't be read.
+/// - `MdownError::JsonError(10698)`: If a cached chapter is not valid JSON.
+///
+/// # Returns
+/// Chapters as returned by the `/manga/{id}/feed` endpoint; empty if nothing is cached.
+pub(crate) fn get_feed(id: &str) -> Result<Vec<serde_json::Value>, MdownError> {
+    let conn = open_db()?;
+    let rows: Result<Vec<String>, rusqlite::Error> = conn
+        .prepare("SELECT data FROM feed_chapters WHERE manga_id = ?1")
+        .and_then(|mut stmt| {
+            stmt.query_map(params![id], |row| row.get::<_, String>(0))?.collect()
+        });
+    let rows = match rows {
+        Ok(rows) => rows,
+        Err(err) => {
+            return Err(MdownError::DatabaseError(err, 10697));
+        }
+    };
+    let mut chapters = Vec::new();
+    for data in rows.iter() {
+        match serde_json::from_str(data) {
+            Ok(chapter) => chapters.push(chapter),
+            Err(err) => {
+                return Err(MdownError::JsonError(err.to_string(), 10698));
+            }
+        }
+    }
+    Ok(chapters)
+}
+
+/// Merges `chapters` into the cached feed of the manga with `id` and stores `checked_at` as the
+/// time of the last successful check.
+///
+/// Chapters are matched by their id, so updated chapters replace the cached ones. If `replace` is
+/// true, the cached feed is dropped first, e.g. after the whole feed was fetched.
+///
+/// # Errors
+/// - `MdownError::ChainedError(10694)`, `MdownError::DatabaseError(10695)`: If the database can't be opened.
+/// - `MdownError::DatabaseError(10699)`: If the feed can't be written.
+pub(crate) fn update_feed(
+    id: &str,
+    chapters: &[serde_json::Value],
+    checked_at: &str,
+    replace: bool
+) -> Result<(), MdownError> {
+    let mut conn = open_db()?;
+    let result = conn.transaction().and_then(|tx| {
+        if replace {
+            tx.execute("DELETE FROM feed_chapters WHERE manga_id = ?1", params![id])?;
+        }
+        for chapter in chapters.iter() {
+            let chapter_id = chapter
+                .get("id")
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or_default();
+            if chapter_id.is_empty() {
+                continue;
+            }
+            tx.execute(
+                "INSERT INTO feed_chapters (manga_id, chapter_id, data) VALUES (?1, ?2, ?3)
+                ON CONFLICT(manga_id, chapter_id) DO UPDATE SET data = excluded.data",
+                params![id, chapter_id, chapter.to_string()]
+            )?;
+        }
+        tx.execute(
+            "INSERT INTO feed_times (manga_id, checked_at) VALUES (?1, ?2)
+            ON CONFLICT(manga_id) DO UPDATE SET checked_at = excluded.checked_at",
+            params![id, checked_at]
+        )?;
+        tx.commit()
+    });
+    match result {
+        Ok(()) => Ok(()),
+        Err(err) => Err(MdownError::DatabaseError(err, 10699)),
+    }
+}
+
 /// Retrieves a resource from the database by name.
 ///
 /// This function attempts to fetch a resource from the database by reading the resource
@@ -293,6 +466,33 @@ fn initialize_db(conn: &Connection) -> Result<(), MdownError> {
             return Err(MdownError::DatabaseError(err, 10600));
         }
     }
+    // Cached chapter feeds of manga, used by incremental update checks
+    match
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS feed_times (
+            manga_id TEXT PRIMARY KEY,
+            checked_at TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS feed_chapters (
+            manga_id TEXT NOT NULL,
+            chapter_id TEXT NOT NULL,
+            data TEXT NOT NULL,
+            PRIMARY KEY (manga_id, chapter_id)
+        );
+        CREATE TABLE IF NOT EXISTS http_cache (
+            url TEXT PRIMARY KEY,
+            body TEXT NOT NULL,
+            etag TEXT,
+            last_modified TEXT,
+            fetched_at INTEGER NOT NULL
+        );"
+        )
+    {
+        Ok(()) => (),
+        Err(err) => {
+            return Err(MdownError::DatabaseError(err, 10693));
+        }
+    }
     Ok(())
 }
 /// Reads a resource from the database by its name.
@@ -1108,6 +1308,8 @@ pub(crate) fn setup_settings() -> Result<(metadata::Settings, bool), MdownError>
                 folder,
                 stat,
                 backup,
+                template,
+                library_roots,
                 clear,
                 #[cfg(feature = "music")]
                 music,
@@ -1181,6 +1383,33 @@ pub(crate) fn setup_settings() -> Result<(metadata::Settings, bool), MdownError>
                 }
                 None => (),
             }
+            match template {
+                Some(Some(template)) => {
+                    match template::validate(&template) {
+                        Ok(()) => {
+                            match write_resource(&conn, DB_TEMPLATE, template.as_bytes(), false) {
+                                Ok(_id) => (),
+                                Err(err) => {
+                                    return Err(MdownError::ChainedError(Box::new(err), 10689));
+                                }
+                            }
+                        }
+                        Err(err) => suspend_error(err),
+                    }
+                }
+                Some(None) => {
+                    match delete_resource(&conn, DB_TEMPLATE) {
+                        Ok(_id) => (),
+                        Err(err) => {
+                            return Err(MdownError::ChainedError(Box::new(err), 10690));
+                        }
+                    }
+                }
+                None => (),
+            }
+            if let Some(library_roots) = library_roots {
+                library::set_roots(library_roots.as_deref().unwrap_or_default())?;
+            }
             #[cfg(feature = "music")]
             match music {
                 Some(Some(music)) => {
@@ -1226,6 +1455,13 @@ pub(crate) fn setup_settings() -> Result<(metadata::Settings, bool), MdownError>
                         return Err(MdownError::ChainedError(Box::new(err), 10663));
                     }
                 }
+                match delete_resource(&conn, DB_TEMPLATE) {
+                    Ok(_id) => (),
+                    Err(err) => {
+                        return Err(MdownError::ChainedError(Box::new(err), 10691));
+                    }
+                }
+                library::set_roots("")?;
                 #[cfg(feature = "music")]
                 match delete_resource(&conn, DB_MUSIC) {
                     Ok(_id) => (),
@@ -1333,6 +1569,14 @@ pub(crate) fn setup_settings() -> Result<(metadata::Settings, bool), MdownError>
         }
     };
 
+    // Read the template setting from the database
+    let template = match get_template() {
+        Ok(template) => template.unwrap_or_default(),
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 10692));
+        }
+    };
+
     #[cfg(feature = "music")]
     // Read the music setting from the database
     let music = match read_resource(&conn, DB_MUSIC) {
@@ -1356,8 +1600,19 @@ pub(crate) fn setup_settings() -> Result<(metadata::Settings, bool), MdownError>
         }
     };
 
+    // Read folders searched for moved manga
+    let library_roots = library::get_roots()?;
+
     // Create and return the settings object
-    let settings = metadata::Settings { folder, stat, backup, #[cfg(feature = "music")] music };
+    let settings = metadata::Settings {
+        folder,
+        stat,
+        backup,
+        template,
+        library_roots,
+        #[cfg(feature = "music")]
+        music,
+    };
 
     debug!("{:?}\n", settings);
 
@@ -13,10 +13,11 @@ use crate::{
     error::{ MdownError, suspend_error },
     getter,
     IS_END,
+    job::{ self, Job },
     log,
     MAXPOINTS,
     metadata,
-    resolute::{ CURRENT_PAGE, MWD },
+    sanitize,
     string,
     tutorial,
     utils,
@@ -119,6 +120,64 @@ pub(crate) async fn get_response(
     }
 }
 
+/// Retrieves the size of an image on the at-home server without downloading it.
+///
+/// This asynchronous function sends an HTTP HEAD request for the image and reads its size from the
+/// `Content-Length` header.
+///
+/// # Arguments
+/// * `base_url` - The base URL of the at-home server.
+/// * `c_hash` - The hash of the chapter.
+/// * `f_name` - The file name of the image.
+/// * `mode` - `data` or `data-saver`.
+///
+/// # Returns
+/// * `Result<u64, MdownError>` - Size of the image in bytes; `0` if server did not send the size.
+///
+/// # Errors
+/// * `MdownError::NetworkError` - If the client could not be created or the request failed.
+/// * `MdownError::ConversionError` - If the URL could not be parsed.
+pub(crate) async fn get_image_size(
+    base_url: Arc<str>,
+    c_hash: Arc<str>,
+    f_name: Arc<str>,
+    mode: Arc<str>
+) -> Result<u64, MdownError> {
+    let client = match get_client() {
+        Ok(client) => client,
+        Err(err) => {
+            return Err(MdownError::NetworkError(err, 10333));
+        }
+    };
+    let base_url = match url::Url::parse(base_url.as_ref()) {
+        Ok(url) => url,
+        Err(err) => {
+            return Err(MdownError::ConversionError(err.to_string(), 10334));
+        }
+    };
+    let full_url = match base_url.join(&format!("\\{}\\{}\\{}", mode, c_hash, f_name)) {
+        Ok(url) => url,
+        Err(err) => {
+            return Err(MdownError::ConversionError(err.to_string(), 10335));
+        }
+    };
+
+    debug!("sending HEAD request to: {}", full_url);
+
+    match client.head(full_url).send().await {
+        Ok(response) =>
+            Ok(
+                response
+                    .headers()
+                    .get(reqwest::header::CONTENT_LENGTH)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .unwrap_or_default()
+            ),
+        Err(err) => Err(MdownError::NetworkError(err, 10336)),
+    }
+}
+
 /// Retrieves the size of the content in a `reqwest::Response` and formats it into a human-readable string.
 ///
 /// This function extracts the content length from the HTTP response, returning it as a tuple containing
@@ -321,7 +380,8 @@ pub(crate) async fn download_cover(
         match File::create("_cover.png") {
             Ok(file) => file,
             Err(err) => {
-                return Err(MdownError::IoError(err, format!("{}\\_cover.png", MWD.lock()), 10306));
+                let mwd = job::current().mwd.lock().clone();
+                return Err(MdownError::IoError(err, format!("{}\\_cover.png", mwd), 10306));
             }
         }
     } else {
@@ -401,6 +461,137 @@ pub(crate) async fn download_cover(
     Ok(())
 }
 
+/// Downloads cover arts of all volumes and locales of the manga with `id`.
+///
+/// Covers are listed page by page and saved into `_covers` folder inside `folder` as
+/// `Vol.N (locale).ext`; covers without a volume are saved as `No volume (locale).ext`. Covers
+/// which are already downloaded are skipped.
+///
+/// # Arguments
+/// * `id` - Id of the manga.
+/// * `folder` - Folder of the manga, `.` in update mode.
+///
+/// # Returns
+/// * `Result<Vec<metadata::CoverMetadata>, MdownError>` - All covers of the manga, with paths
+///   relative to `folder`.
+///
+/// # Errors
+/// * Returns `MdownError::JsonError` if the cover list can't be parsed.
+/// * Returns `MdownError::IoError` if the `_covers` folder or a cover can't be written.
+/// * Returns `MdownError::NetworkError` if a cover can't be downloaded.
+pub(crate) async fn download_covers(
+    id: &str,
+    folder: &str
+) -> Result<Vec<metadata::CoverMetadata>, MdownError> {
+    if
+        *args::ARGS_WEB ||
+        *args::ARGS_GUI ||
+        *args::ARGS_CHECK ||
+        *args::ARGS_UPDATE ||
+        *args::ARGS_LOG
+    {
+        log!("Downloading volume covers");
+    }
+    string(2, 0, "Downloading volume covers ...");
+
+    let covers_folder = format!("{}\\_covers", folder);
+    match fs::create_dir_all(&covers_folder) {
+        Ok(()) => (),
+        Err(err) => {
+            return Err(MdownError::IoError(err, covers_folder, 10337));
+        }
+    }
+
+    let mut covers: Vec<metadata::CoverMetadata> = Vec::new();
+    let mut offset = 0;
+    loop {
+        let json = match getter::get_covers_json(id, offset).await {
+            Ok(json) => json,
+            Err(err) => {
+                return Err(MdownError::ChainedError(Box::new(err), 10338));
+            }
+        };
+        let list = match serde_json::from_str::<metadata::CoverList>(&json) {
+            Ok(list) => list,
+            Err(err) => {
+                return Err(MdownError::JsonError(err.to_string(), 10339));
+            }
+        };
+        for cover in list.data.iter() {
+            let attr = &cover.attributes;
+            let volume = attr.volume.clone().unwrap_or_default();
+            let locale = attr.locale.clone().unwrap_or_default();
+            let extension = match attr.fileName.rsplit_once('.') {
+                Some((_, extension)) => extension,
+                None => "jpg",
+            };
+            let name = if volume.is_empty() {
+                format!("No volume ({})", locale)
+            } else {
+                format!("Vol.{} ({})", volume, locale)
+            };
+            let mut file = format!("_covers\\{}.{}", sanitize::name(&name), extension);
+            // Volume can have more covers in the same locale, e.g. variant covers
+            if covers.iter().any(|item| item.file == file) {
+                file = format!(
+                    "_covers\\{}.{}",
+                    sanitize::name(&sanitize::with_id(&name, &cover.id)),
+                    extension
+                );
+            }
+            let path = format!("{}\\{}", folder, file);
+            string(2, 0, &format!("Downloading volume covers {}", file));
+
+            if fs::metadata(&path).is_err() {
+                let response = match
+                    get_response(
+                        Arc::from("https://uploads.mangadex.org/"),
+                        Arc::from(id),
+                        Arc::from(attr.fileName.as_str()),
+                        "covers"
+                    ).await
+                {
+                    Ok(response) => response,
+                    Err(err) => {
+                        return Err(MdownError::ChainedError(Box::new(err), 10340));
+                    }
+                };
+                let bytes = match response.bytes().await {
+                    Ok(bytes) => bytes,
+                    Err(err) => {
+                        return Err(MdownError::NetworkError(err, 10341));
+                    }
+                };
+                match fs::write(&path, &bytes) {
+                    Ok(()) => (),
+                    Err(err) => {
+                        return Err(MdownError::IoError(err, path, 10342));
+                    }
+                }
+                debug!("downloaded cover {}", path);
+            }
+            covers.push(metadata::CoverMetadata { volume, locale, file });
+        }
+        offset += list.data.len();
+        if list.data.is_empty() || offset >= list.total {
+            break;
+        }
+    }
+
+    let message = format!("Downloading volume covers DONE ({})", covers.len());
+    string(2, 0, &format!("{}{}", message, " ".repeat((MAXPOINTS.max_x as usize) - message.len())));
+    if
+        *args::ARGS_WEB ||
+        *args::ARGS_GUI ||
+        *args::ARGS_CHECK ||
+        *args::ARGS_UPDATE ||
+        *args::ARGS_LOG
+    {
+        log!(&message);
+    }
+    Ok(covers)
+}
+
 /// Fetches statistics for a given manga and saves them to a Markdown file.
 ///
 /// This asynchronous function retrieves statistics data for a manga based on the provided `id` and `manga_name`.
@@ -460,9 +651,8 @@ pub(crate) async fn download_stat(id: &str, manga_name: &str) -> Result<(), Mdow
         match File::create("_statistics.md") {
             Ok(file) => file,
             Err(err) => {
-                return Err(
-                    MdownError::IoError(err, format!("{}\\_statistics.md", MWD.lock()), 10309)
-                );
+                let mwd = job::current().mwd.lock().clone();
+                return Err(MdownError::IoError(err, format!("{}\\_statistics.md", mwd), 10309));
             }
         }
     } else {
@@ -606,6 +796,7 @@ fn get_dist(distribution: &metadata::RatingDistribution, i: usize) -> String {
 /// caching and logging information based on various application modes.
 ///
 /// # Arguments
+/// * `job` - The job whose page counter is advanced when the image is downloaded.
 /// * `image_base_url` - The base URL for the image, typically including the server address and endpoint.
 /// * `c_hash` - A hash string used to identify the specific image or resource on the server.
 /// * `f_name` - The file name or identifier for the image to download.
@@ -643,7 +834,7 @@ fn get_dist(distribution: &metadata::RatingDistribution, i: usize) -> String {
 ///
 /// // Call the function (in an async context)
 /// tokio::spawn(async move {
-///     if let Err(e) = download_image(image_base_url, c_hash, f_name, 1, folder_name, file_name_brief, full_path, saver, start).await {
+///     if let Err(e) = download_image(&job, image_base_url, c_hash, f_name, 1, folder_name, file_name_brief, full_path, saver, start).await {
 ///         eprintln!("Failed to download image: {:?}", e);
 ///     }
 /// });
@@ -653,6 +844,7 @@ fn get_dist(distribution: &metadata::RatingDistribution, i: usize) -> String {
 /// * **Progress Tracking:** The function updates progress on the console or logs it based on the application's mode.
 /// * **Caching:** Lock files are used to manage concurrent downloads and cache metadata.
 pub(crate) async fn download_image(
+    job: &Job,
     image_base_url: Arc<str>,
     c_hash: Arc<str>,
     f_name: Arc<str>,
@@ -753,7 +945,7 @@ pub(crate) async fn download_image(
             }
         }
     {
-        if *IS_END.lock() {
+        if *IS_END.lock() || job.is_cancelled() {
             return Ok(());
         }
         match file.write_all(&chunk) {
@@ -839,7 +1031,7 @@ pub(crate) async fn download_image(
         }
     }
 
-    *CURRENT_PAGE.lock() += 1;
+    *job.current_page.lock() += 1;
 
     if !*args::ARGS_WEB && !*args::ARGS_GUI && !*args::ARGS_CHECK && !*args::ARGS_UPDATE {
         if download {
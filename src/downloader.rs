@@ -0,0 +1,441 @@
+//! Downloader API of the library.
+//!
+//! A `Downloader` runs the same code as the command-line tool: manga is searched and fetched
+//! through the response cache of `getter` and `http_cache`, and downloaded by `resolute` in a
+//! `Job` with options of its `Config`, so chapters get names from templates, `ComicInfo.xml`,
+//! covers and image processing, and are added to the library. Arguments of the process are not
+//! parsed, see `args::COMMAND_LINE`. Downloads use global state of `resolute`, so they run one at a
+//! time, see `job::DOWNLOAD`.
+
+use lazy_static::lazy_static;
+use serde::{ Deserialize, Serialize };
+use serde_json::Value;
+use std::{ sync::Arc, time::Duration };
+use tokio::sync::Mutex as AsyncMutex;
+
+use crate::{
+    db,
+    error::MdownError,
+    getter,
+    job::{ self, Job },
+    metadata::{ ChapterResponse, JobOptions, MangaResponse },
+    resolute,
+    utils,
+};
+
+/// Interval of calls of the progress callback of `Downloader::download`.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+lazy_static! {
+    /// Set when the database and the cache folder were created.
+    static ref SETUP: AsyncMutex<bool> = AsyncMutex::new(false);
+}
+
+/// Options of a `Downloader`, set by chained methods; they are options of jobs of its downloads,
+/// with the same defaults as arguments of the command-line tool.
+///
+/// ```
+/// let config = mdown::Config::new().language("ja").saver(true).max_consecutive(10);
+/// assert_eq!(config.get_language(), "ja");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    options: JobOptions,
+}
+
+impl Config {
+    /// Returns the default options: English chapters, original quality and 40 pages at once.
+    pub fn new() -> Config {
+        Config::default()
+    }
+
+    /// Sets the language of chapters; `*` downloads chapters in all languages.
+    pub fn language(mut self, language: &str) -> Config {
+        self.options.lang = language.to_string();
+        self
+    }
+
+    /// Sets whether pages are downloaded in the compressed data saver quality.
+    pub fn saver(mut self, saver: bool) -> Config {
+        self.options.saver = saver;
+        self
+    }
+
+    /// Sets the maximum number of pages downloaded at once; at least one page is.
+    pub fn max_consecutive(mut self, max_consecutive: usize) -> Config {
+        self.options.max_consecutive = max_consecutive.max(1);
+        self
+    }
+
+    /// Sets the folder of downloaded manga, e.g. `library/{manga}`, like `--folder`.
+    pub fn folder(mut self, folder: &str) -> Config {
+        self.options.folder = folder.to_string();
+        self
+    }
+
+    /// Sets the volumes to download, e.g. `1` or `2-4`, like `--volume`.
+    pub fn volume(mut self, volume: &str) -> Config {
+        self.options.volume = volume.to_string();
+        self
+    }
+
+    /// Sets the chapters to download, e.g. `12` or `1-10`, like `--chapter`.
+    pub fn chapter(mut self, chapter: &str) -> Config {
+        self.options.chapter = chapter.to_string();
+        self
+    }
+
+    /// Sets whether chapters already in the library are downloaded again.
+    pub fn force(mut self, force: bool) -> Config {
+        self.options.force = force;
+        self
+    }
+
+    pub fn get_language(&self) -> &str {
+        &self.options.lang
+    }
+
+    pub fn get_saver(&self) -> bool {
+        self.options.saver
+    }
+
+    pub fn get_max_consecutive(&self) -> usize {
+        self.options.max_consecutive
+    }
+}
+
+/// Manga on mangadex.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Manga {
+    pub id: String,
+
+    /// English title, or the first title if the manga has none.
+    pub title: String,
+
+    /// English description.
+    pub description: String,
+    pub authors: Vec<String>,
+    pub artists: Vec<String>,
+    pub alt_titles: Vec<String>,
+    pub year: Option<u64>,
+    pub status: String,
+    pub demographic: String,
+    pub original_language: String,
+    pub available_languages: Vec<String>,
+
+    /// English names of genres, themes and other tags.
+    pub tags: Vec<String>,
+
+    /// URL of the cover art, if it was included in the response.
+    pub cover: Option<String>,
+}
+
+/// Chapter of a manga on mangadex.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Chapter {
+    pub id: String,
+
+    /// Chapter number, `None` for oneshots.
+    pub number: Option<String>,
+    pub volume: Option<String>,
+    pub title: Option<String>,
+    pub language: String,
+
+    /// Number of pages; chapters hosted externally have none.
+    pub pages: u64,
+
+    /// Link to the chapter if it is hosted on another site.
+    pub external_url: Option<String>,
+
+    /// Ids of the scanlation groups.
+    pub groups: Vec<String>,
+    pub updated_at: String,
+}
+
+/// Progress of `Downloader::download`, passed to its callback while the manga downloads.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Progress {
+    /// Name of the manga.
+    pub manga: String,
+
+    /// Folder name of the chapter being downloaded.
+    pub chapter: String,
+
+    /// Number of downloaded pages of the chapter and of all its pages.
+    pub page: u64,
+    pub pages: u64,
+
+    /// Number of parsed chapters and of all chapters of the manga.
+    pub chapters_parsed: u64,
+    pub chapters: u64,
+}
+
+/// Downloader of manga with its own options.
+#[derive(Debug, Clone, Default)]
+pub struct Downloader {
+    config: Config,
+}
+
+impl Downloader {
+    /// Creates a downloader with options `config`.
+    pub fn new(config: Config) -> Downloader {
+        Downloader { config }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns at most `limit` manga matching `title`.
+    pub async fn search(&self, title: &str, limit: usize) -> Result<Vec<Manga>, MdownError> {
+        if let Err(err) = setup().await {
+            return Err(MdownError::ChainedError(Box::new(err), 13400));
+        }
+        match utils::search_manga(title, limit).await {
+            Ok(data) => Ok(data.iter().map(Manga::from_value).collect()),
+            Err(err) => Err(MdownError::ChainedError(Box::new(err), 13401)),
+        }
+    }
+
+    /// Returns the manga with `id`.
+    pub async fn manga(&self, id: &str) -> Result<Manga, MdownError> {
+        if let Err(err) = setup().await {
+            return Err(MdownError::ChainedError(Box::new(err), 13402));
+        }
+        let json = match getter::get_manga_json(id).await {
+            Ok(value) => value,
+            Err(err) => {
+                return Err(MdownError::ChainedError(Box::new(err), 13403));
+            }
+        };
+        let json = match utils::get_json(&json) {
+            Ok(value) => value,
+            Err(err) => {
+                return Err(MdownError::ChainedError(Box::new(err), 13404));
+            }
+        };
+        match json.get("data") {
+            Some(data) => Ok(Manga::from_value(data)),
+            None => Err(MdownError::NotFoundError(format!("manga {}", id), 13405)),
+        }
+    }
+
+    /// Returns chapters of manga `id` in the configured language, sorted like by the
+    /// command-line tool.
+    pub async fn chapters(&self, id: &str) -> Result<Vec<Chapter>, MdownError> {
+        if let Err(err) = setup().await {
+            return Err(MdownError::ChainedError(Box::new(err), 13406));
+        }
+        let job = Job::with_options(&utils::generate_random_id(12), self.config.options.clone());
+        let json = match job.scope(getter::get_manga(id, 0)).await {
+            Ok((json, _count)) => json,
+            Err(err) => {
+                return Err(MdownError::ChainedError(Box::new(err), 13407));
+            }
+        };
+        let feed = match serde_json::from_str::<MangaResponse>(&json) {
+            Ok(value) => value,
+            Err(err) => {
+                return Err(MdownError::JsonError(err.to_string(), 13408));
+            }
+        };
+        let language = self.config.get_language();
+        Ok(
+            utils
+                ::sort(&feed.data)
+                .into_iter()
+                .filter(|chapter| {
+                    language == "*" ||
+                        chapter.attributes.translatedLanguage.as_deref() == Some(language)
+                })
+                .map(Chapter::from_response)
+                .collect()
+        )
+    }
+
+    /// Downloads the manga with id or link `manga` into the configured folder, like the
+    /// command-line tool with `--url`; `progress` is called while it downloads. Waits for other
+    /// downloads of the process to finish first.
+    ///
+    /// # Returns
+    /// - `Ok(Vec<String>)`: Paths of the downloaded chapters.
+    /// - `Err(MdownError)`: If the manga is not found or its download fails.
+    pub async fn download(
+        &self,
+        manga: &str,
+        progress: impl Fn(&Progress)
+    ) -> Result<Vec<String>, MdownError> {
+        if let Err(err) = setup().await {
+            return Err(MdownError::ChainedError(Box::new(err), 13409));
+        }
+        let _download = job::DOWNLOAD.lock().await;
+        let mut options = self.config.options.clone();
+        options.url = manga.to_string();
+        let job = Job::with_options(&utils::generate_random_id(12), options);
+        // Tasks spawned by the download don't have the job of this task
+        job::set_current(Arc::clone(&job));
+        *resolute::HANDLE_ID.lock() = job.id.clone().into_boxed_str();
+
+        let download = resolute::resolve_url(&job, manga);
+        tokio::pin!(download);
+        let mut interval = tokio::time::interval(PROGRESS_INTERVAL);
+        let result = loop {
+            tokio::select! {
+                result = &mut download => {
+                    break result;
+                }
+                _ = interval.tick() => progress(&Progress::from_job(&job)),
+            }
+        };
+        *resolute::HANDLE_ID.lock() = String::new().into_boxed_str();
+
+        match result {
+            Ok(name) if name == "!" => {
+                Err(MdownError::NotFoundError(format!("manga {}", manga), 13410))
+            }
+            Ok(_) => {
+                progress(&Progress::from_job(&job));
+                Ok(job.downloaded.lock().clone())
+            }
+            Err(err) => Err(MdownError::ChainedError(Box::new(err), 13411)),
+        }
+    }
+}
+
+impl Manga {
+    fn from_value(data: &Value) -> Manga {
+        let id = data["id"].as_str().unwrap_or_default().to_string();
+        let attributes = &data["attributes"];
+        let details = getter::get_manga_details(data);
+        let cover = data["relationships"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|relationship| relationship["type"] == "cover_art")
+            .and_then(|relationship| relationship["attributes"]["fileName"].as_str())
+            .map(|file| format!("https://uploads.mangadex.org/covers/{}/{}", id, file));
+        Manga {
+            id,
+            title: match attributes["title"]["en"].as_str() {
+                Some(title) => title.to_string(),
+                None =>
+                    attributes["title"]
+                        .as_object()
+                        .and_then(|titles| titles.values().find_map(Value::as_str))
+                        .unwrap_or_default()
+                        .to_string(),
+            },
+            description: attributes["description"]["en"].as_str().unwrap_or_default().to_string(),
+            authors: details.authors,
+            artists: details.artists,
+            alt_titles: details.alt_titles,
+            year: details.year,
+            status: details.status,
+            demographic: details.demographic,
+            original_language: details.original_language,
+            available_languages: attributes["availableTranslatedLanguages"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect(),
+            tags: attributes["tags"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|tag| tag["attributes"]["name"]["en"].as_str())
+                .map(str::to_string)
+                .collect(),
+            cover,
+        }
+    }
+}
+
+impl Chapter {
+    fn from_response(chapter: ChapterResponse) -> Chapter {
+        let attributes = chapter.attributes;
+        Chapter {
+            id: chapter.id,
+            number: attributes.chapter,
+            volume: attributes.volume,
+            title: attributes.title,
+            language: attributes.translatedLanguage.unwrap_or_default(),
+            pages: attributes.pages,
+            external_url: attributes.externalUrl,
+            groups: chapter.relationships
+                .into_iter()
+                .filter(|relationship| relationship.r#type == "scanlation_group")
+                .map(|relationship| relationship.id)
+                .collect(),
+            updated_at: attributes.updatedAt,
+        }
+    }
+}
+
+impl Progress {
+    fn from_job(job: &Job) -> Progress {
+        Progress {
+            manga: job.manga_name.lock().clone(),
+            chapter: job.current_chapter.lock().clone(),
+            page: *job.current_page.lock(),
+            pages: *job.current_page_max.lock(),
+            chapters_parsed: *job.current_chapter_parsed.lock(),
+            chapters: *job.current_chapter_parsed_max.lock(),
+        }
+    }
+}
+
+/// Creates the database and the cache folder, like the command-line tool does on start.
+async fn setup() -> Result<(), MdownError> {
+    let mut setup = SETUP.lock().await;
+    if !*setup {
+        if let Err(err) = db::init().await {
+            return Err(MdownError::ChainedError(Box::new(err), 13412));
+        }
+        if let Err(err) = utils::create_cache_folder() {
+            return Err(MdownError::ChainedError(Box::new(err), 13413));
+        }
+        *setup = true;
+    }
+    Ok(())
+}
+
+// Test of options set by chained methods
+#[test]
+fn test_config() {
+    let config = Config::new().language("ja").saver(true).max_consecutive(0).chapter("1-3");
+    assert_eq!(config.get_language(), "ja");
+    assert!(config.get_saver());
+    assert_eq!(config.get_max_consecutive(), 1);
+    assert_eq!(config.options.chapter, "1-3");
+    assert_eq!(config.options.volume, "*");
+}
+
+// Test of manga and chapters read from API responses
+#[test]
+fn test_from_value() {
+    let manga: Value = serde_json::from_str(
+        r#"{"id":"m","attributes":{"title":{"en":"Frieren"},"description":{"en":"Elf"},"status":"ongoing",
+        "year":2020,"originalLanguage":"ja","availableTranslatedLanguages":["en"],
+        "tags":[{"attributes":{"name":{"en":"Fantasy"}}}]},
+        "relationships":[{"type":"cover_art","attributes":{"fileName":"c.jpg"}},
+        {"type":"author","attributes":{"name":"Yamada"}}]}"#
+    ).unwrap();
+    let manga = Manga::from_value(&manga);
+    assert_eq!((manga.title.as_str(), manga.description.as_str()), ("Frieren", "Elf"));
+    assert_eq!(manga.authors, vec!["Yamada"]);
+    assert_eq!(manga.tags, vec!["Fantasy"]);
+    assert_eq!(manga.cover.as_deref(), Some("https://uploads.mangadex.org/covers/m/c.jpg"));
+
+    let chapter: ChapterResponse = serde_json::from_str(
+        r#"{"id":"c","type":"chapter","attributes":{"chapter":"12","volume":null,"title":"A",
+        "translatedLanguage":"en","externalUrl":null,"publishAt":"","readableAt":"","createdAt":"",
+        "updatedAt":"2024-01-01T00:00:00+00:00","pages":20,"version":1},
+        "relationships":[{"id":"g","type":"scanlation_group"},{"id":"m","type":"manga"}]}"#
+    ).unwrap();
+    let chapter = Chapter::from_response(chapter);
+    assert_eq!(chapter.number.as_deref(), Some("12"));
+    assert_eq!(chapter.groups, vec!["g"]);
+    assert_eq!(chapter.pages, 20);
+}
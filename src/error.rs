@@ -70,7 +70,7 @@ use parking_lot::Mutex;
 use thiserror::Error;
 use smallvec::{ SmallVec, smallvec };
 
-use crate::{ MAXPOINTS, resolute::INITSCR_INIT, string };
+use crate::{ events, MAXPOINTS, metadata::Event, resolute::INITSCR_INIT, string };
 
 lazy_static! {
     pub static ref SUSPENDED: Mutex<SmallVec<[MdownError; 3]>> = Mutex::new(smallvec![]);
@@ -247,6 +247,7 @@ impl MdownError {
 /// * The optional `from` argument provides additional context that helps in understanding where the error occurred.
 #[cold]
 pub(crate) fn handle_error(err: &MdownError, from: Option<String>) {
+    events::emit(Event::Error { code: err.code(), message: err.to_string(), context: from.clone() });
     let to = match from {
         Some(value) => format!(" ({})", value),
         None => String::new(),
@@ -317,7 +318,7 @@ pub(crate) fn handle_suspended() {
             string(start + (times as u32), 0, &message);
         }
     } else {
-        println!("Suspended errors:");
+        events::message("Suspended errors:");
         for i in suspended.iter() {
             handle_error!(i, String::from("suspended"));
         }
@@ -0,0 +1,226 @@
+//! Progress events for scripts and runs without a terminal.
+//!
+//! Progress of downloads is normally drawn in the curses window by `string`. With `--output json`
+//! every step is printed instead as one JSON object per line (NDJSON) on stdout: manga resolved,
+//! chapter skipped, chapter started, page finished, chapter zipped, error and a summary at the end.
+//! When stdout is not a terminal, e.g. in pipes and CI, the same steps are printed as plain lines.
+//! In web mode the events are sent with id of their job to clients of the event stream instead.
+
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{ io::IsTerminal, time::Instant };
+#[cfg(feature = "web")]
+use tokio::sync::broadcast;
+
+use crate::{ args, error::{ MdownError, suspend_error }, metadata::Event };
+#[cfg(feature = "web")]
+use crate::job;
+
+/// Number of events kept for a client of the event stream which didn't read them yet.
+#[cfg(feature = "web")]
+const STREAM_CAPACITY: usize = 1024;
+
+lazy_static! {
+    /// Progress is printed as plain lines, because stdout is not a terminal.
+    static ref PLAIN: bool =
+        !*args::ARGS_OUTPUT_JSON &&
+        !std::io::stdout().is_terminal() &&
+        !*args::ARGS_QUIET &&
+        !*args::ARGS_WEB &&
+        !*args::ARGS_GUI &&
+        !*args::ARGS_SERVER;
+    static ref TOTALS: Mutex<Totals> = Mutex::new(Totals::default());
+
+    /// Time of the first event since the last summary.
+    static ref START: Mutex<Option<Instant>> = Mutex::new(None);
+}
+
+#[cfg(feature = "web")]
+lazy_static! {
+    static ref STREAM: broadcast::Sender<String> = broadcast::channel(STREAM_CAPACITY).0;
+}
+
+/// Numbers reported by the summary event.
+#[derive(Default)]
+struct Totals {
+    manga: usize,
+    downloaded: usize,
+    skipped: usize,
+    pages: usize,
+    errors: usize,
+}
+
+/// Returns whether events are printed; the curses window is not used then.
+pub(crate) fn is_enabled() -> bool {
+    *args::ARGS_OUTPUT_JSON || *PLAIN
+}
+
+/// Prints `event` as a JSON line or as a plain line, if events are enabled; in web mode it is sent
+/// to the event stream.
+pub(crate) fn emit(event: Event) {
+    #[cfg(feature = "web")]
+    stream(&event);
+    if !is_enabled() && !*args::ARGS_WEB {
+        return;
+    }
+    START.lock().get_or_insert_with(Instant::now);
+    count(&event);
+    if *args::ARGS_OUTPUT_JSON {
+        match serde_json::to_string(&event) {
+            Ok(line) => println!("{}", line),
+            Err(err) => suspend_error(MdownError::JsonError(err.to_string(), 12900)),
+        }
+    } else if *PLAIN {
+        if let Some(line) = text(&event) {
+            println!("{}", line);
+        }
+    }
+}
+
+/// Sends `event` with id of the job of the task to clients of the event stream.
+#[cfg(feature = "web")]
+fn stream(event: &Event) {
+    if STREAM.receiver_count() == 0 {
+        return;
+    }
+    let mut value = match serde_json::to_value(event) {
+        Ok(value) => value,
+        Err(err) => {
+            suspend_error(MdownError::JsonError(err.to_string(), 12901));
+            return;
+        }
+    };
+    value["job"] = serde_json::Value::String(job::current().id.clone());
+    let _ = STREAM.send(value.to_string());
+}
+
+/// Returns receiver of events as JSON objects with id of their job.
+#[cfg(feature = "web")]
+pub(crate) fn subscribe() -> broadcast::Receiver<String> {
+    STREAM.subscribe()
+}
+
+/// Emits event of chapter `id` with number `chapter` skipped because of `reason`.
+pub(crate) fn skipped(id: &str, chapter: &str, reason: &str) {
+    emit(Event::ChapterSkipped {
+        id: id.to_string(),
+        chapter: chapter.to_string(),
+        reason: reason.to_string(),
+    });
+}
+
+/// Prints a line of output which is not a progress step; with `--output json` it is printed as
+/// a message event, so stdout stays valid NDJSON.
+pub(crate) fn message(text: &str) {
+    if *args::ARGS_OUTPUT_JSON {
+        emit(Event::Message { text: text.to_string() });
+    } else {
+        println!("{}", text);
+    }
+}
+
+/// Prints the summary event if any manga was resolved; totals are counted again from the next
+/// event, so every download of web mode has its own summary.
+pub(crate) fn summary() {
+    let event = {
+        let totals = std::mem::take(&mut *TOTALS.lock());
+        let start = START.lock().take();
+        if totals.manga == 0 {
+            return;
+        }
+        Event::Summary {
+            manga: totals.manga,
+            downloaded: totals.downloaded,
+            skipped: totals.skipped,
+            pages: totals.pages,
+            errors: totals.errors,
+            elapsed: start.map(|start| start.elapsed().as_secs_f64()).unwrap_or_default(),
+        }
+    };
+    emit(event);
+}
+
+fn count(event: &Event) {
+    let mut totals = TOTALS.lock();
+    match event {
+        Event::MangaResolved { .. } => {
+            totals.manga += 1;
+        }
+        Event::ChapterSkipped { .. } => {
+            totals.skipped += 1;
+        }
+        Event::PageFinished { .. } => {
+            totals.pages += 1;
+        }
+        Event::ChapterZipped { .. } => {
+            totals.downloaded += 1;
+        }
+        Event::Error { .. } => {
+            totals.errors += 1;
+        }
+        Event::ChapterStarted { .. } | Event::Message { .. } | Event::Summary { .. } => (),
+    }
+}
+
+/// Returns plain line of `event`; errors are already printed to stderr, so they have none.
+fn text(event: &Event) -> Option<String> {
+    match event {
+        Event::MangaResolved { id, name, chapters, .. } =>
+            Some(format!("Manga {} ({}): {} chapters", name, id, chapters)),
+        Event::ChapterSkipped { chapter, reason, .. } =>
+            Some(format!("Skipped chapter {}: {}", chapter, reason)),
+        Event::ChapterStarted { chapter, pages, .. } =>
+            Some(format!("Downloading chapter {} ({} pages)", chapter, pages)),
+        Event::PageFinished { chapter, page, pages, .. } =>
+            Some(format!("Chapter {}: page {}/{}", chapter, page, pages)),
+        Event::ChapterZipped { path, .. } => Some(format!("Saved {}", path)),
+        Event::Error { .. } => None,
+        Event::Message { text } => Some(text.clone()),
+        Event::Summary { manga, downloaded, skipped, pages, errors, elapsed } =>
+            Some(
+                format!(
+                    "Downloaded {} chapters ({} pages) of {} manga; skipped {}; {} errors; {:.1} s",
+                    downloaded,
+                    pages,
+                    manga,
+                    skipped,
+                    errors,
+                    elapsed
+                )
+            ),
+    }
+}
+
+// Test of JSON lines of events
+#[test]
+fn test_event_json() {
+    let event = Event::ChapterSkipped {
+        id: String::from("a"),
+        chapter: String::from("1"),
+        reason: String::from("offset"),
+    };
+    assert_eq!(
+        serde_json::to_string(&event).unwrap(),
+        r#"{"event":"chapter_skipped","id":"a","chapter":"1","reason":"offset"}"#
+    );
+    let event = Event::Error { code: 10101, message: String::from("error"), context: None };
+    assert_eq!(
+        serde_json::to_string(&event).unwrap(),
+        r#"{"event":"error","code":10101,"message":"error","context":null}"#
+    );
+}
+
+// Test of events sent to the event stream
+#[cfg(feature = "web")]
+#[tokio::test]
+async fn test_stream() {
+    let mut receiver = subscribe();
+    let job = job::Job::new();
+    job.scope(async {
+        emit(Event::Message { text: String::from("Hello") });
+    }).await;
+    let value: serde_json::Value = serde_json::from_str(&receiver.recv().await.unwrap()).unwrap();
+    assert_eq!(value["event"], "message");
+    assert_eq!(value["text"], "Hello");
+    assert_eq!(value["job"], job.id);
+}
@@ -0,0 +1,267 @@
+//! Missing chapters in the library.
+//!
+//! `library gaps` lists whole chapter numbers between the first and the last downloaded chapter of
+//! every manga which are not downloaded, e.g. 14 if chapters 12, 13 and 15 are in the library. Each
+//! gap is compared with the chapter feed on mangadex: the chapter may not be uploaded yet, be
+//! uploaded only in other languages, be uploaded in the language of the manga but filtered out,
+//! or have been deleted since the feed was cached. With `--download` gaps which can be filled are
+//! downloaded like by `database --update`.
+
+use serde_json::Value;
+use std::collections::{ BTreeSet, HashMap };
+
+use crate::{
+    args,
+    db,
+    error::{ MdownError, suspend_error },
+    library,
+    library_db,
+    metadata::{ ChapterMetadata, ChapterResponse, Gap, GapKind, MangaGaps, MangaMetadata },
+    resolute,
+};
+
+/// Prints gaps of manga `id`, or of all manga if `id` is `None`; with `download` fillable gaps are
+/// downloaded afterwards.
+pub(crate) async fn show(id: Option<&str>, download: bool) -> Result<(), MdownError> {
+    let gaps = match collect(id).await {
+        Ok(gaps) => gaps,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12800));
+        }
+    };
+    if gaps.is_empty() {
+        println!("No gaps found");
+        return Ok(());
+    }
+    for manga in gaps.iter() {
+        println!("{} ({})", manga.name, manga.id);
+        for gap in manga.gaps.iter() {
+            let mut line = format!(" {}: {}", gap.number, gap.kind.description());
+            if !gap.languages.is_empty() {
+                line += &format!(" ({})", gap.languages.join(", "));
+            }
+            if gap.fillable {
+                line += " [fillable]";
+            }
+            println!("{}", line);
+        }
+    }
+
+    if !download || *args::ARGS_DRY_RUN {
+        return Ok(());
+    }
+    let filter = fillable(&gaps);
+    if filter.is_empty() {
+        println!("No fillable gaps");
+        return Ok(());
+    }
+    *resolute::CHAPTER_FILTER.lock() = filter;
+    match resolute::resolve_check().await {
+        Ok(()) => Ok(()),
+        Err(err) => Err(MdownError::ChainedError(Box::new(err), 12801)),
+    }
+}
+
+/// Returns manga of the library with gaps; `id` can also be the name of the manga.
+///
+/// Feeds are fetched from mangadex, or taken from the cache when offline.
+pub(crate) async fn collect(id: Option<&str>) -> Result<Vec<MangaGaps>, MdownError> {
+    let dat = match library_db::load() {
+        Ok(dat) => dat,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12802));
+        }
+    };
+    let items: Vec<&MangaMetadata> = match id {
+        Some(id) =>
+            match library::find_item(&dat, id) {
+                Some(index) => vec![&dat.data[index]],
+                None => {
+                    return Err(
+                        MdownError::NotFoundError(format!("Manga {} in database", id), 12803)
+                    );
+                }
+            }
+        None => dat.data.iter().collect(),
+    };
+
+    let mut gaps = Vec::new();
+    for item in items {
+        let numbers = missing_numbers(&item.chapters);
+        if numbers.is_empty() {
+            continue;
+        }
+        let feed: Vec<FeedChapter> = match library::get_feed(&item.id).await {
+            Ok(feed) => feed.iter().map(FeedChapter::from_response).collect(),
+            Err(err) => {
+                suspend_error(MdownError::ChainedError(Box::new(err), 12804));
+                continue;
+            }
+        };
+        let cached: Vec<FeedChapter> = db
+            ::get_feed(&item.id)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(FeedChapter::from_value)
+            .collect();
+        gaps.push(MangaGaps {
+            id: item.id.clone(),
+            name: item.name.clone(),
+            language: item.current_language.clone(),
+            gaps: numbers
+                .into_iter()
+                .map(|number| classify(number, &item.current_language, &feed, &cached))
+                .collect(),
+        });
+    }
+    Ok(gaps)
+}
+
+/// Returns numbers of fillable gaps by manga id, as used by `resolute::CHAPTER_FILTER`.
+pub(crate) fn fillable(gaps: &[MangaGaps]) -> HashMap<String, Vec<String>> {
+    gaps.iter()
+        .filter_map(|manga| {
+            let numbers: Vec<String> = manga.gaps
+                .iter()
+                .filter(|gap| gap.fillable)
+                .map(|gap| gap.number.to_string())
+                .collect();
+            if numbers.is_empty() { None } else { Some((manga.id.clone(), numbers)) }
+        })
+        .collect()
+}
+
+/// Chapter of a feed reduced to what is needed to classify gaps.
+struct FeedChapter {
+    /// Chapter number parsed by `ChapterMetadata::parse_number`.
+    number: Vec<i32>,
+
+    /// Language of the chapter.
+    language: String,
+
+    /// Whether the chapter has pages on mangadex.
+    downloadable: bool,
+}
+
+impl FeedChapter {
+    fn from_response(chapter: &ChapterResponse) -> FeedChapter {
+        let attributes = &chapter.attributes;
+        FeedChapter {
+            number: parse(attributes.chapter.as_deref().unwrap_or_default()),
+            language: attributes.translatedLanguage.clone().unwrap_or_default(),
+            downloadable: attributes.pages > 0 && attributes.externalUrl.is_none(),
+        }
+    }
+
+    fn from_value(chapter: &Value) -> Option<FeedChapter> {
+        let attributes = chapter.get("attributes")?;
+        Some(FeedChapter {
+            number: parse(attributes.get("chapter").and_then(Value::as_str)?),
+            language: attributes
+                .get("translatedLanguage")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            downloadable: false,
+        })
+    }
+}
+
+fn parse(number: &str) -> Vec<i32> {
+    ChapterMetadata::new(number, "", "").parse_number()
+}
+
+/// Returns whole chapter numbers between the first and the last of `chapters` which are not in
+/// `chapters`; a downloaded chapter 12.5 does not fill gap 12.
+fn missing_numbers(chapters: &[ChapterMetadata]) -> Vec<i32> {
+    let numbers: Vec<Vec<i32>> = chapters
+        .iter()
+        .map(|chapter| chapter.parse_number())
+        .filter(|number| !number.is_empty())
+        .collect();
+    let (first, last) = match
+        (
+            numbers.iter().map(|number| number[0]).min(),
+            numbers.iter().map(|number| number[0]).max(),
+        )
+    {
+        (Some(first), Some(last)) => (first, last),
+        _ => {
+            return Vec::new();
+        }
+    };
+    let downloaded: BTreeSet<i32> = numbers
+        .iter()
+        .filter(|number| number.len() == 1)
+        .map(|number| number[0])
+        .collect();
+    (first..=last).filter(|number| !downloaded.contains(number)).collect()
+}
+
+/// Classifies missing chapter `number` of a manga in `language` by the current `feed` and the
+/// `cached` feed of the manga.
+fn classify(number: i32, language: &str, feed: &[FeedChapter], cached: &[FeedChapter]) -> Gap {
+    let matching: Vec<&FeedChapter> = feed
+        .iter()
+        .filter(|chapter| chapter.number == [number])
+        .collect();
+    let ours: Vec<&&FeedChapter> = matching
+        .iter()
+        .filter(|chapter| language == "*" || chapter.language == language)
+        .collect();
+    if !ours.is_empty() {
+        return Gap {
+            number,
+            kind: GapKind::Filtered,
+            languages: Vec::new(),
+            fillable: ours.iter().any(|chapter| chapter.downloadable),
+        };
+    }
+    if !matching.is_empty() {
+        let languages: BTreeSet<String> = matching
+            .iter()
+            .map(|chapter| chapter.language.clone())
+            .collect();
+        return Gap {
+            number,
+            kind: GapKind::OtherLanguage,
+            languages: languages.into_iter().collect(),
+            fillable: false,
+        };
+    }
+    let kind = if cached.iter().any(|chapter| chapter.number == [number]) {
+        GapKind::Deleted
+    } else {
+        GapKind::NotUploaded
+    };
+    Gap { number, kind, languages: Vec::new(), fillable: false }
+}
+
+// Test of gaps between downloaded chapters
+#[test]
+fn test_missing_numbers() {
+    let chapters: Vec<ChapterMetadata> = ["12", "13", "14.5", "17", "Oneshot"]
+        .iter()
+        .map(|number| ChapterMetadata::new(number, "", ""))
+        .collect();
+    assert_eq!(missing_numbers(&chapters), vec![14, 15, 16]);
+    assert!(missing_numbers(&[]).is_empty());
+}
+
+// Test of classification of gaps by feeds
+#[test]
+fn test_classify() {
+    let chapter = |number: &str, language: &str, downloadable: bool| FeedChapter {
+        number: parse(number),
+        language: language.to_string(),
+        downloadable,
+    };
+    let feed = vec![chapter("14", "en", true), chapter("15", "fr", true), chapter("15", "de", true)];
+    let cached = vec![chapter("16", "en", false)];
+    let gap = classify(14, "en", &feed, &cached);
+    assert_eq!((gap.kind, gap.fillable), (GapKind::Filtered, true));
+    let gap = classify(15, "en", &feed, &cached);
+    assert_eq!((gap.kind, gap.languages), (GapKind::OtherLanguage, vec![String::from("de"), String::from("fr")]));
+    assert_eq!(classify(16, "en", &feed, &cached).kind, GapKind::Deleted);
+    assert_eq!(classify(17, "en", &feed, &cached).kind, GapKind::NotUploaded);
+}
@@ -2,14 +2,19 @@ use serde_json::Value;
 use std::process::exit;
 
 use crate::{
-    args::{ self, ARGS },
+    args,
+    db,
     download::get_response_client,
     debug,
-    error::MdownError,
+    error::{ MdownError, suspend_error },
+    http_cache,
+    job,
     log,
     metadata,
     resolute,
+    sanitize,
     string,
+    template,
     tutorial,
     utils,
 };
@@ -339,12 +344,13 @@ pub(crate) fn get_query(parts: Vec<&str>) -> std::collections::HashMap<String, S
         .collect()
 }
 
-/// Retrieves the folder name based on the current ARGS settings.
+/// Retrieves the folder name based on the options of the current job.
 ///
-/// This function processes the folder name from the global `ARGS` configuration and returns it as a
-/// static string slice. It utilizes `utils::process_filename` to process the folder name. If the
-/// processed folder name equals "name", it returns the value from `resolute::MANGA_NAME`. Otherwise,
-/// it returns the processed folder name itself.
+/// This function processes the folder name from the options of `job::current` and returns it as a
+/// static string slice. It utilizes `sanitize::name` to process the folder name. If the
+/// processed folder name equals "name", it returns the manga name of `job::current`. If the
+/// folder is a template (e.g. `library/{manga}`), it is rendered with `{manga}` and `{manga_id}`;
+/// other fields are empty. Otherwise, it returns the processed folder name itself.
 ///
 /// # Returns
 /// * A `&'static str` representing the folder name. This string is guaranteed to be valid for the
@@ -363,17 +369,98 @@ pub(crate) fn get_query(parts: Vec<&str>) -> std::collections::HashMap<String, S
 /// ```
 ///
 /// # Note
-/// * Ensure that `utils::process_filename` and the `ARGS` global configuration are properly initialized
+/// * Ensure that the current job is set up with the intended options
 ///   before calling this function. Misconfigured or uninitialized values could lead to incorrect results.
 pub(crate) fn get_folder_name() -> &'static str {
-    let folder_name = utils::process_filename(&ARGS.lock().folder.clone());
+    let folder = job::current().options.folder.clone();
+    if folder.contains('{') {
+        let rendered = template::render(&folder, |field| {
+            match field {
+                "manga" => Some(job::current().manga_name.lock().clone()),
+                "manga_id" => Some(job::current().manga_id.lock().clone()),
+                field => get_details_field(field),
+            }
+        });
+        match rendered {
+            Ok(rendered) => {
+                return Box::leak(rendered.into_boxed_str());
+            }
+            Err(err) => suspend_error(MdownError::ChainedError(Box::new(err), 10840)),
+        }
+    }
+    let folder_name = sanitize::name(&folder);
     if folder_name == "name" {
-        Box::leak(resolute::MANGA_NAME.lock().clone().into_boxed_str())
+        Box::leak(job::current().manga_name.lock().clone().into_boxed_str())
     } else {
         Box::leak(folder_name.into_boxed_str())
     }
 }
 
+/// Reads authors, artists, alternative titles and publication info from manga `data`.
+///
+/// Names of authors and artists are available only if the manga was requested with
+/// `includes[]=author&includes[]=artist`.
+pub(crate) fn get_manga_details(data: &Value) -> metadata::MangaDetails {
+    let names = |kind: &str| -> Vec<String> {
+        let mut names = Vec::new();
+        if let Some(relationships) = data.get("relationships").and_then(Value::as_array) {
+            for relationship in relationships.iter() {
+                if relationship.get("type").and_then(Value::as_str) != Some(kind) {
+                    continue;
+                }
+                let name = relationship
+                    .get("attributes")
+                    .and_then(|attr| attr.get("name"))
+                    .and_then(Value::as_str);
+                if let Some(name) = name {
+                    if !names.iter().any(|item| item == name) {
+                        names.push(name.to_string());
+                    }
+                }
+            }
+        }
+        names
+    };
+    let attr = data.get("attributes").unwrap_or(&Value::Null);
+    let text = |key: &str| -> String {
+        attr.get(key).and_then(Value::as_str).unwrap_or_default().to_string()
+    };
+    let mut alt_titles = Vec::new();
+    if let Some(titles) = attr.get("altTitles").and_then(Value::as_array) {
+        for title in titles.iter().filter_map(Value::as_object) {
+            for title in title.values().filter_map(Value::as_str) {
+                if !alt_titles.iter().any(|item| item == title) {
+                    alt_titles.push(title.to_string());
+                }
+            }
+        }
+    }
+    metadata::MangaDetails {
+        authors: names("author"),
+        artists: names("artist"),
+        alt_titles,
+        year: attr.get("year").and_then(Value::as_u64),
+        status: text("status"),
+        demographic: text("publicationDemographic"),
+        original_language: text("originalLanguage"),
+    }
+}
+
+/// Returns value of template field which describes the current manga, e.g. `{author}`.
+///
+/// More authors or artists are joined with `, `. Returns `None` for other fields.
+pub(crate) fn get_details_field(field: &str) -> Option<String> {
+    let details = resolute::MANGA_DETAILS.lock();
+    match field {
+        "author" => Some(details.authors.join(", ")),
+        "artist" => Some(details.artists.join(", ")),
+        "year" => Some(details.year.map(|year| year.to_string()).unwrap_or_default()),
+        "status" => Some(details.status.clone()),
+        "demographic" => Some(details.demographic.clone()),
+        _ => None,
+    }
+}
+
 /// Retrieves and processes the manga name from the given JSON `title_data`.
 ///
 /// This function attempts to extract the manga title based on a preferred language. It first checks
@@ -390,8 +477,8 @@ pub(crate) fn get_folder_name() -> &'static str {
 /// # Returns
 ///
 /// A `String` containing the processed manga name. If a suitable title cannot be found, it returns
-/// `"Unrecognized title"`. The resulting string is trimmed and cleaned of certain characters. If
-/// the name exceeds 70 characters, it is truncated to 70 characters and appended with `"__"`.
+/// `"Unrecognized title"`. The resulting string is cleaned with `sanitize::manga_name`. If the name
+/// exceeds 70 graphemes, it is truncated to 70 graphemes and appended with `"__"`.
 ///
 /// # Details
 ///
@@ -401,7 +488,7 @@ pub(crate) fn get_folder_name() -> &'static str {
 /// 3. **Fallback:** If no suitable title is found, it tries a general fallback to English and Japanese
 ///    romanized titles in the `title` field of the JSON data.
 /// 4. **Cleanup:** Removes quotes and question marks from the title and trims it to a maximum of 70
-///    characters if necessary.
+///    graphemes if necessary; multi-byte titles are never cut in the middle of a character.
 ///
 /// # Examples
 ///
@@ -495,11 +582,8 @@ pub(crate) fn get_manga_name(title_data: &Value) -> String {
         }
     )
         .replace("\"", "")
-        .replace("?", "")
-        .trim()
-        .to_string();
-    let name = if name.len() > 70 { format!("{}__", &name[0..70]) } else { name };
-    utils::process_filename(&name)
+        .replace("?", "");
+    sanitize::manga_name(&name)
 }
 
 /// Asynchronously fetches the JSON data for a manga from the MangaDex API.
@@ -519,9 +603,8 @@ pub(crate) fn get_manga_name(title_data: &Value) -> String {
 /// # Errors
 ///
 /// The function will return an `MdownError` if:
-/// - The HTTP request fails (`get_response_client` returns an error).
+/// - The HTTP request fails and the response is not cached.
 /// - The HTTP response status is not successful.
-/// - An error occurs while reading the response body as text.
 ///
 /// # Examples
 ///
@@ -535,57 +618,44 @@ pub(crate) fn get_manga_name(title_data: &Value) -> String {
 ///
 /// # Notes
 ///
-/// Ensure the `get_response_client` function is properly implemented to handle HTTP requests.
+/// Response is cached by `http_cache`, so it is available offline.
 pub(crate) async fn get_manga_json(id: &str) -> Result<String, MdownError> {
-    let full_url = format!("https://api.mangadex.org/manga/{}?includes[]=cover_art", id);
-
-    debug!("sending request to: {}", full_url);
+    let full_url = format!(
+        "https://api.mangadex.org/manga/{}?includes[]=cover_art&includes[]=author&includes[]=artist",
+        id
+    );
 
-    let response = match get_response_client(&full_url).await {
-        Ok(res) => res,
+    match http_cache::get(&full_url).await {
+        Ok(text) => Ok(text),
         Err(err) => {
-            return Err(MdownError::ChainedError(Box::new(err), 10832));
+            eprintln!("Error: get manga json Failed to fetch data from the API. {}", err);
+            Err(MdownError::ChainedError(Box::new(err), 10832))
         }
-    };
+    }
+}
 
-    debug!("got response (get_manga_response)");
-
-    if response.status().is_success() {
-        debug!("response is success (get_manga_response)\n");
-        match response.text().await {
-            Ok(text) => Ok(text),
-            Err(err) =>
-                Err(
-                    MdownError::StatusError(
-                        match err.status() {
-                            Some(status) => status,
-                            None => {
-                                return Err(
-                                    MdownError::NotFoundError(
-                                        String::from("StatusCode (get_manga_json)"),
-                                        10803
-                                    )
-                                );
-                            }
-                        },
-                        10804
-                    )
-                ),
-        }
-    } else {
-        debug!("response is error (get_manga_response)");
-        eprintln!(
-            "Error: get manga json Failed to fetch data from the API. Status code: {:?}",
-            response.status()
-        );
-        Err(MdownError::StatusError(response.status(), 10805))
+/// Fetches one page of cover arts of the manga with `id`, ordered by volume.
+///
+/// # Errors
+/// Returns an error if the request fails, the response status is not successful or the body
+/// can't be read.
+pub(crate) async fn get_covers_json(id: &str, offset: usize) -> Result<String, MdownError> {
+    let full_url = format!(
+        "https://api.mangadex.org/cover?manga[]={}&limit=100&offset={}&order[volume]=asc",
+        id,
+        offset
+    );
+
+    match http_cache::get(&full_url).await {
+        Ok(text) => Ok(text),
+        Err(err) => Err(MdownError::ChainedError(Box::new(err), 10841)),
     }
 }
 
 /// Asynchronously fetches the JSON data for manga statistics from the MangaDex API.
 ///
-/// This function constructs a URL to fetch manga statistics by its ID. It sends an HTTP GET request to
-/// the MangaDex API and processes the response.
+/// This function constructs a URL to fetch manga statistics by its ID and gets it through
+/// `http_cache`; cached statistics younger than `--cache-ttl` are used without asking MangaDex.
 ///
 /// # Arguments
 ///
@@ -599,7 +669,7 @@ pub(crate) async fn get_manga_json(id: &str) -> Result<String, MdownError> {
 /// # Errors
 ///
 /// The function will return an `MdownError` if:
-/// - The HTTP request fails (`get_response_client` returns an error).
+/// - The HTTP request fails and the response is not cached.
 /// - The HTTP response status is not successful.
 /// - An error occurs while reading the response body as text.
 ///
@@ -615,36 +685,17 @@ pub(crate) async fn get_manga_json(id: &str) -> Result<String, MdownError> {
 ///
 /// # Notes
 ///
-/// Ensure the `get_response_client` function is properly implemented to handle HTTP requests.
+/// Response is cached by `http_cache`; if MangaDex can't be reached, the cached statistics are
+/// used regardless of their age, so they are available offline.
 pub(crate) async fn get_statistic_json(id: &str) -> Result<String, MdownError> {
     let full_url = format!("https://api.mangadex.org/statistics/manga/{}", id);
 
-    debug!("sending request to: {}", full_url);
-
-    let response = match get_response_client(&full_url).await {
-        Ok(res) => res,
+    match http_cache::get(&full_url).await {
+        Ok(json) => Ok(json),
         Err(err) => {
-            return Err(MdownError::ChainedError(Box::new(err), 10833));
+            eprintln!("Error: get statistic json Failed to fetch data from the API. {}", err);
+            Err(MdownError::ChainedError(Box::new(err), 10833))
         }
-    };
-    debug!("got response (get_statistic_json)");
-    if response.status().is_success() {
-        debug!("response is success (get_statistic_json)");
-        let json = match response.text().await {
-            Ok(res) => res,
-            Err(err) => {
-                return Err(MdownError::JsonError(err.to_string(), 10806));
-            }
-        };
-
-        Ok(json)
-    } else {
-        debug!("response is error (get_statistic_json)");
-        eprintln!(
-            "Error: get statistic json Failed to fetch data from the API. Status code: {:?}",
-            response.status()
-        );
-        Err(MdownError::StatusError(response.status(), 10820))
     }
 }
 
@@ -825,7 +876,7 @@ pub(crate) fn get_scanlation_group(json: &Vec<metadata::ChapterRelResponse>) ->
 /// # Errors
 ///
 /// The function will return an `MdownError` if:
-/// - The HTTP request fails (`get_response_client` returns an error).
+/// - The HTTP request fails and the page is not cached by `http_cache`.
 /// - The HTTP response status is not successful, and an error occurs while reading the response body as text.
 /// - There is an error parsing the JSON response or combining the data.
 ///
@@ -845,12 +896,25 @@ pub(crate) fn get_scanlation_group(json: &Vec<metadata::ChapterRelResponse>) ->
 /// }
 /// ```
 pub(crate) async fn get_manga(id: &str, offset: u32) -> Result<(String, usize), MdownError> {
+    // Time is taken before fetching, so chapters updated meanwhile are fetched by the next check
+    let checked_at = chrono::Utc::now().naive_utc().format(FEED_TIME_FORMAT).to_string();
+    if (*args::ARGS_CHECK || *args::ARGS_UPDATE) && offset == 0 {
+        match get_manga_since(id, &checked_at).await {
+            Ok(Some(result)) => {
+                return Ok(result);
+            }
+            Ok(None) => debug!("feed of {} is not cached; fetching whole feed", id),
+            Err(err) => suspend_error(MdownError::ChainedError(Box::new(err), 10844)),
+        }
+    }
     let mut times = 0;
     let mut json;
     let mut json_2 = String::new();
     let mut times_offset: u32;
+    // Time when the oldest page was fetched; pages can come from cache
+    let mut fetched_at = i64::MAX;
     let max_per_session = 500;
-    let stat = match ARGS.lock().stat {
+    let stat = match job::current().options.stat {
         true => 1,
         false => 0,
     };
@@ -872,43 +936,20 @@ pub(crate) async fn get_manga(id: &str, offset: u32) -> Result<(String, usize),
             tutorial::feed(stat);
         }
 
-        debug!("sending request to: {}", full_url);
-
-        let response = match get_response_client(&full_url).await {
-            Ok(res) => res,
-            Err(err) => {
-                return Err(MdownError::ChainedError(Box::new(err), 10835));
+        // Cached feed is always revalidated, so newly released chapters are not missed and
+        // chapters updated since it was cached are not skipped by the next incremental check
+        json = match http_cache::get_with_time(&full_url, 0).await {
+            Ok((text, time)) => {
+                fetched_at = fetched_at.min(time);
+                text
             }
-        };
-        debug!("got response");
-        if !response.status().is_success() {
-            debug!("response is not a success");
-            eprintln!(
-                "Error: get manga Failed to fetch data from the API. Status code: {:?} ({})",
-                response.status(),
-                full_url
-            );
-            return Err(MdownError::StatusError(response.status(), 10811));
-        }
-        json = match response.text().await {
-            Ok(text) => text,
             Err(err) => {
-                return Err(
-                    MdownError::StatusError(
-                        match err.status() {
-                            Some(status) => status,
-                            None => {
-                                return Err(
-                                    MdownError::NotFoundError(
-                                        String::from("StatusCode (get_manga)"),
-                                        10812
-                                    )
-                                );
-                            }
-                        },
-                        10813
-                    )
+                eprintln!(
+                    "Error: get manga Failed to fetch data from the API. {} ({})",
+                    err,
+                    full_url
                 );
+                return Err(MdownError::ChainedError(Box::new(err), 10835));
             }
         };
         if times == 0 {
@@ -946,7 +987,13 @@ pub(crate) async fn get_manga(id: &str, offset: u32) -> Result<(String, usize),
                     offset_temp = data_array.len();
                     if offset_temp >= max_per_session {
                         debug!("data is at or exceeded maximum {}", max_per_session);
-                        json_2 = json;
+                        // Keeps all full pages, not just the last one
+                        json_2 = match join_page(times, &json_2, json) {
+                            Ok(value) => value,
+                            Err(err) => {
+                                return Err(MdownError::ChainedError(Box::new(err), 10845));
+                            }
+                        };
                         times += 1;
                         continue;
                     } else {
@@ -954,7 +1001,7 @@ pub(crate) async fn get_manga(id: &str, offset: u32) -> Result<(String, usize),
                     }
                     if times > 0 {
                         debug!("joining data");
-                        json = match crossfade_data(&json, &json_2) {
+                        json = match join_page(times, &json_2, json) {
                             Ok(value) => value,
                             Err(err) => {
                                 return Err(MdownError::ChainedError(Box::new(err), 10837));
@@ -970,10 +1017,221 @@ pub(crate) async fn get_manga(id: &str, offset: u32) -> Result<(String, usize),
             }
         }
 
+        if offset == 0 {
+            if let Some(time) = chrono::DateTime::from_timestamp(fetched_at, 0) {
+                cache_feed(id, &json, &time.naive_utc().format(FEED_TIME_FORMAT).to_string());
+            }
+        }
         return Ok((json, offset_temp));
     }
 }
 
+/// Format of feed check times; `updatedAtSince` of MangaDex API doesn't accept time zones.
+const FEED_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Cached feeds older than this are fetched whole again, so chapters removed from MangaDex are
+/// dropped from the cache.
+const FEED_MAX_AGE_DAYS: i64 = 30;
+
+/// Stores the whole feed of the manga with `id` in the database.
+fn cache_feed(id: &str, json: &str, checked_at: &str) {
+    let data = match utils::get_json(json) {
+        Ok(value) => value.get("data").and_then(Value::as_array).cloned().unwrap_or_default(),
+        Err(err) => {
+            suspend_error(MdownError::ChainedError(Box::new(err), 10846));
+            return;
+        }
+    };
+    match db::update_feed(id, &data, checked_at, true) {
+        Ok(()) => debug!("cached feed of {} with {} chapters", id, data.len()),
+        Err(err) => suspend_error(MdownError::ChainedError(Box::new(err), 10847)),
+    }
+}
+
+/// Fetches chapters of the manga with `id` updated since the last check and merges them into the
+/// cached feed.
+///
+/// # Returns
+/// * `Ok(Some((String, usize)))` - Merged feed in the same format as `get_manga` returns.
+/// * `Ok(None)` - If the feed is not cached or the cache is too old; the whole feed has to be
+///   fetched.
+///
+/// # Errors
+/// Returns an error if the cache can't be read or written or the request fails.
+async fn get_manga_since(
+    id: &str,
+    checked_at: &str
+) -> Result<Option<(String, usize)>, MdownError> {
+    let since = match db::get_feed_time(id) {
+        Ok(Some(since)) => since,
+        Ok(None) => {
+            return Ok(None);
+        }
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 10848));
+        }
+    };
+    match chrono::NaiveDateTime::parse_from_str(&since, FEED_TIME_FORMAT) {
+        Ok(time) => {
+            if chrono::Utc::now().naive_utc() - time > chrono::Duration::days(FEED_MAX_AGE_DAYS) {
+                debug!("cached feed of {} is too old", id);
+                return Ok(None);
+            }
+        }
+        Err(_err) => {
+            return Ok(None);
+        }
+    }
+    let cached = match db::get_feed(id) {
+        Ok(cached) => cached,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 10849));
+        }
+    };
+    if cached.is_empty() {
+        return Ok(None);
+    }
+
+    let max_per_session = 500;
+    let mut delta = Vec::new();
+    let mut offset = 0;
+    loop {
+        let full_url = format!(
+            "https://api.mangadex.org/manga/{}/feed?limit={}&offset={}&updatedAtSince={}",
+            id,
+            max_per_session,
+            offset,
+            since
+        );
+        debug!("sending request to: {}", full_url);
+        let response = match get_response_client(&full_url).await {
+            Ok(res) => res,
+            Err(err) => {
+                return Err(MdownError::ChainedError(Box::new(err), 10850));
+            }
+        };
+        if !response.status().is_success() {
+            return Err(MdownError::StatusError(response.status(), 10851));
+        }
+        let json = match response.text().await {
+            Ok(text) => text,
+            Err(err) => {
+                return Err(MdownError::NetworkError(err, 10852));
+            }
+        };
+        let data = match utils::get_json(&json) {
+            Ok(value) => value.get("data").and_then(Value::as_array).cloned().unwrap_or_default(),
+            Err(err) => {
+                return Err(MdownError::ChainedError(Box::new(err), 10853));
+            }
+        };
+        let length = data.len();
+        delta.extend(data);
+        if length < max_per_session {
+            break;
+        }
+        offset += max_per_session;
+    }
+
+    // Deltas don't report chapters removed from MangaDex; if the merged feed doesn't match the
+    // whole feed, it is fetched again and replaces the cache
+    let count = delta.len();
+    let data = merge_feed(cached, delta.clone());
+    let total = get_feed_total(id).await?;
+    if data.len() != total {
+        debug!("cached feed of {} has {} chapters instead of {}", id, data.len(), total);
+        return Ok(None);
+    }
+
+    match db::update_feed(id, &delta, checked_at, false) {
+        Ok(()) => (),
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 10854));
+        }
+    }
+    let naive_time_str = chrono::Utc::now().naive_utc().format("%Y-%m-%d %H:%M:%S").to_string();
+    resolute::DATE_FETCHED.lock().push(naive_time_str);
+
+    let message = format!("{} chapters updated since {}   ", count, since);
+    string(3, 0, &message);
+    if
+        *args::ARGS_WEB ||
+        *args::ARGS_GUI ||
+        *args::ARGS_CHECK ||
+        *args::ARGS_UPDATE ||
+        *args::ARGS_LOG
+    {
+        log!(&message);
+    }
+
+    let json =
+        serde_json::json!({
+        "result": "ok",
+        "response": "collection",
+        "data": data,
+        "limit": total,
+        "offset": 0,
+        "total": total
+    });
+    Ok(Some((json.to_string(), total)))
+}
+
+/// Fetches the number of all chapters in the feed of the manga with `id`.
+///
+/// # Errors
+/// Returns an error if the request fails or the response has no total.
+async fn get_feed_total(id: &str) -> Result<usize, MdownError> {
+    let full_url = format!("https://api.mangadex.org/manga/{}/feed?limit=1&offset=0", id);
+    debug!("sending request to: {}", full_url);
+    let response = match get_response_client(&full_url).await {
+        Ok(res) => res,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 10855));
+        }
+    };
+    if !response.status().is_success() {
+        return Err(MdownError::StatusError(response.status(), 10856));
+    }
+    let json = match response.text().await {
+        Ok(text) => text,
+        Err(err) => {
+            return Err(MdownError::NetworkError(err, 10857));
+        }
+    };
+    match utils::get_json(&json) {
+        Ok(value) =>
+            match value.get("total").and_then(Value::as_u64) {
+                Some(total) => Ok(total as usize),
+                None => Err(MdownError::NotFoundError(String::from("feed total"), 10858)),
+            }
+        Err(err) => Err(MdownError::ChainedError(Box::new(err), 10859)),
+    }
+}
+
+/// Replaces chapters of `cached` feed with chapters of `delta` with the same id and appends the
+/// new ones.
+fn merge_feed(mut cached: Vec<Value>, delta: Vec<Value>) -> Vec<Value> {
+    for chapter in delta {
+        let id = chapter.get("id").and_then(Value::as_str).unwrap_or_default().to_string();
+        match cached.iter_mut().find(|item| item.get("id").and_then(Value::as_str) == Some(&id)) {
+            Some(item) => {
+                *item = chapter;
+            }
+            None => cached.push(chapter),
+        }
+    }
+    cached
+}
+
+/// Joins feed `page` fetched with offset `times` pages after the first one with the earlier
+/// `pages`; the first page is returned as is.
+fn join_page(times: u32, pages: &str, page: String) -> Result<String, MdownError> {
+    if times == 0 {
+        return Ok(page);
+    }
+    crossfade_data(&page, pages)
+}
+
 /// Merges two JSON strings by appending the data from the second JSON to the first JSON.
 ///
 /// This function takes two JSON strings, parses them, and combines their "data" arrays. The combined JSON is returned
@@ -1205,6 +1463,82 @@ pub(crate) fn get_arg(arg: &str) -> &str {
     }
 }
 
+// reads authors, artists and publication info of manga
+#[test]
+fn test_get_manga_details() {
+    let data =
+        serde_json::json!({
+        "attributes": {
+            "altTitles": [{ "ja": "進撃の巨人" }, { "en": "AoT" }, { "en-us": "AoT" }],
+            "year": 2009,
+            "status": "completed",
+            "publicationDemographic": null,
+            "originalLanguage": "ja"
+        },
+        "relationships": [
+            { "type": "author", "attributes": { "name": "Isayama Hajime" } },
+            { "type": "artist", "attributes": { "name": "Isayama Hajime" } },
+            { "type": "cover_art", "attributes": { "fileName": "cover.jpg" } }
+        ]
+    });
+
+    let details = get_manga_details(&data);
+
+    assert_eq!(details.authors, vec![String::from("Isayama Hajime")]);
+    assert_eq!(details.artists, vec![String::from("Isayama Hajime")]);
+    assert_eq!(details.alt_titles, vec![String::from("進撃の巨人"), String::from("AoT")]);
+    assert_eq!(details.year, Some(2009));
+    assert_eq!(details.status, "completed");
+    assert_eq!(details.demographic, "");
+    assert_eq!(details.original_language, "ja");
+}
+
+// merges updated chapters into cached feed
+#[test]
+fn test_merge_feed() {
+    let cached = vec![
+        serde_json::json!({ "id": "a", "attributes": { "version": 1 } }),
+        serde_json::json!({ "id": "b", "attributes": { "version": 1 } })
+    ];
+    let delta = vec![
+        serde_json::json!({ "id": "b", "attributes": { "version": 2 } }),
+        serde_json::json!({ "id": "c", "attributes": { "version": 1 } })
+    ];
+
+    let merged = merge_feed(cached, delta);
+
+    assert_eq!(merged.len(), 3);
+    assert_eq!(merged[1]["attributes"]["version"], 2);
+    assert_eq!(merged[2]["id"], "c");
+}
+
+// joins all pages of feed, not just the last full one
+#[test]
+fn test_join_page() {
+    let page = |ids: &[&str]| {
+        let data: Vec<Value> = ids
+            .iter()
+            .map(|id| serde_json::json!({ "id": id }))
+            .collect();
+        serde_json::json!({ "data": data }).to_string()
+    };
+
+    let mut pages = join_page(0, "", page(&["a", "b"])).unwrap();
+    pages = join_page(1, &pages, page(&["c", "d"])).unwrap();
+    let json = join_page(2, &pages, page(&["e"])).unwrap();
+
+    let value = utils::get_json(&json).unwrap();
+    let mut ids: Vec<&str> = value["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|chapter| chapter["id"].as_str())
+        .collect();
+    ids.sort_unstable();
+    assert_eq!(ids, vec!["a", "b", "c", "d", "e"]);
+    assert!(join_page(1, "{}", page(&["a"])).is_err());
+}
+
 // returns english title if exists in title_data
 #[test]
 fn test_get_manga_name_returns_english_title_if_exists() {
@@ -17,10 +17,12 @@ use tracing::{ info, warn };
 
 use crate::{
     args::{ self, ARGS },
-    debug,
     error::{ self, MdownError },
+    gaps,
     getter,
     handle_error,
+    job::{ self, Job },
+    library_db,
     metadata,
     resolute,
     utils,
@@ -32,6 +34,7 @@ lazy_static! {
     pub(crate) static ref CURRENT_CHAPTER: Mutex<String> = Mutex::new(String::new());
     pub(crate) static ref READER_CURRENT_CHAPTER_ID: Mutex<String> = Mutex::new(String::new());
     pub(crate) static ref READER_CHAPTER_PATHS: Mutex<Option<HashMap<String, String>>> = Mutex::new(None);
+    pub(crate) static ref GAPS: Mutex<Option<Vec<metadata::MangaGaps>>> = Mutex::new(None); // None while gaps are being found
 }
 
 include!(concat!(env!("OUT_DIR"), "/loading_gif.rs"));
@@ -184,6 +187,7 @@ impl App {
     /// - "Main": Switches the panel to "main" and enables the heading.
     /// - "Help": Switches the panel to "help" and enables the heading.
     /// - "Reader": Switches the panel to "reader", resets the reader data, and disables the heading.
+    /// - "Gaps": Switches the panel to "gaps" and starts finding missing chapters of the library.
     ///
     /// # Parameters
     /// - `ui: &mut Ui` – The egui UI context used for rendering the menu.
@@ -212,6 +216,12 @@ impl App {
                     self.panel_show_heading = false;
                     self.reader_full_reset();
                 }
+                if ui.button("Gaps").clicked() {
+                    info!("Selected gaps");
+                    self.panel = String::from("gaps");
+                    self.panel_show_heading = true;
+                    find_gaps();
+                }
             });
         });
     }
@@ -1276,8 +1286,8 @@ impl App {
     /// - Displays the downloading interface (`main_downloading`) if a download is active.
     ///
     /// # Actions Performed:
-    /// - If the `DOWNLOADING` lock is not active (no download in progress), it calls `main_config` to render the configuration UI.
-    /// - If the `DOWNLOADING` lock is active (indicating a download is in progress), it calls `main_downloading` to render the downloading UI.
+    /// - If the current job is not downloading (no download in progress), it calls `main_config` to render the configuration UI.
+    /// - If the current job is downloading (indicating a download is in progress), it calls `main_downloading` to render the downloading UI.
     ///
     /// # Example
     /// ```
@@ -1287,7 +1297,7 @@ impl App {
     /// # Notes
     /// - This function acts as a switch, rendering either the configuration or downloading UI based on the current download status.
     fn main(&mut self, ctx: &Context, ui: &mut Ui) {
-        if !*resolute::DOWNLOADING.lock() {
+        if !*job::current().downloading.lock() {
             self.main_config(ui);
         } else {
             self.main_downloading(ctx, ui);
@@ -1300,9 +1310,9 @@ impl App {
     /// including the manga title, chapter name, current download size, and progress indicators.
     ///
     /// # Actions Performed:
-    /// - Displays the manga title and chapter being downloaded (`MANGA_NAME` and `CURRENT_CHAPTER`).
+    /// - Displays the manga title and chapter being downloaded (manga name and current chapter of `job::current`).
     /// - Shows the current download size and the maximum size of the file in megabytes.
-    /// - Displays a progress bar represented by a series of `#` characters, reflecting the number of pages downloaded (`CURRENT_PAGE` and `CURRENT_PAGE_MAX`).
+    /// - Displays a progress bar represented by a series of `#` characters, reflecting the number of pages downloaded (current page and number of pages of the job).
     /// - Calls `main_downloading_end_panel` to show any additional UI elements related to download completion.
     /// - If the download is still ongoing and the manga ID is set, it saves the current manga ID in the `main_done_downloading` variable.
     ///
@@ -1314,18 +1324,19 @@ impl App {
     /// # Notes:
     /// - The function periodically requests repainting of the UI to reflect the progress of the download.
     fn main_downloading(&mut self, ctx: &Context, ui: &mut Ui) {
+        let job = job::current();
         ui.with_layout(Layout::top_down(egui::Align::Center), |ui| {
-            ui.label(format!("Downloading {}", resolute::MANGA_NAME.lock()));
-            ui.label(format!("Chapter: {}", resolute::CURRENT_CHAPTER.lock()));
+            ui.label(format!("Downloading {}", job.manga_name.lock()));
+            ui.label(format!("Chapter: {}", job.current_chapter.lock()));
             ui.label(
                 format!(
                     "[{:.2}mb/{:.2}mb]",
-                    resolute::CURRENT_SIZE.lock(),
-                    resolute::CURRENT_SIZE_MAX.lock()
+                    job.current_size.lock(),
+                    job.current_size_max.lock()
                 )
             );
-            let current_page = resolute::CURRENT_PAGE.lock();
-            let current_page_max = resolute::CURRENT_PAGE_MAX.lock();
+            let current_page = job.current_page.lock();
+            let current_page_max = job.current_page_max.lock();
             let progress = "#".repeat(*current_page as usize);
             let message = format!("Progress: [{}/{}]", current_page, current_page_max);
             ui.label(message);
@@ -1333,8 +1344,8 @@ impl App {
             ui.add_space(5.0);
 
             self.main_downloading_end_panel(ctx, ui);
-            if self.main_done_downloading.is_none() && resolute::MANGA_ID.lock().clone() != "" {
-                self.main_done_downloading = Some(resolute::MANGA_ID.lock().clone());
+            if self.main_done_downloading.is_none() && job.manga_id.lock().clone() != "" {
+                self.main_done_downloading = Some(job.manga_id.lock().clone());
             }
             ctx.request_repaint();
         });
@@ -1347,8 +1358,8 @@ impl App {
     /// If a preview image is available, it is loaded and displayed.
     ///
     /// # Actions Performed:
-    /// - Displays a list of downloaded files stored in `WEB_DOWNLOADED` lock.
-    /// - Shows the scanlation group(s) involved in the download, stored in `SCANLATION_GROUPS` lock.
+    /// - Displays a list of downloaded files stored in the current job.
+    /// - Shows the scanlation group(s) involved in the download, stored in the current job.
     /// - Checks for the presence of a preview image in the cache (`.cache\\preview\\preview.png`).
     /// - If the preview image exists and is valid, it is displayed as a texture in the UI.
     /// - If no preview image exists, attempts to load one from the cache and display it.
@@ -1361,20 +1372,21 @@ impl App {
     /// # Notes:
     /// - The function uses a scrollable area to show all content, ensuring the UI remains scrollable when the content exceeds the available space.
     fn main_downloading_end_panel(&mut self, ctx: &Context, ui: &mut Ui) {
+        let job = job::current();
         ScrollArea::vertical().show(ui, |ui| {
-            if !resolute::WEB_DOWNLOADED.lock().is_empty() {
+            if !job.downloaded.lock().is_empty() {
                 ui.with_layout(Layout::top_down(egui::Align::Center), |ui| {
                     ui.label("Downloaded:");
-                    for i in resolute::WEB_DOWNLOADED.lock().iter() {
+                    for i in job.downloaded.lock().iter() {
                         ui.label(i);
                     }
                 });
             }
 
-            if !resolute::SCANLATION_GROUPS.lock().is_empty() {
+            if !job.scanlation_groups.lock().is_empty() {
                 ui.with_layout(Layout::top_down(egui::Align::Center), |ui| {
                     ui.label("Scanlation group:");
-                    for i in resolute::SCANLATION_GROUPS.lock().iter() {
+                    for i in job.scanlation_groups.lock().iter() {
                         ui.label(i.name.clone());
                     }
                 });
@@ -1383,8 +1395,8 @@ impl App {
             if self.download_texture_handle.is_some() {
                 match std::fs::metadata(".cache\\preview\\preview.png") {
                     Ok(_metadata) => {
-                        if *resolute::CURRENT_CHAPTER.lock() != *CURRENT_CHAPTER.lock() {
-                            *CURRENT_CHAPTER.lock() = resolute::CURRENT_CHAPTER.lock().to_string();
+                        if *job.current_chapter.lock() != *CURRENT_CHAPTER.lock() {
+                            *CURRENT_CHAPTER.lock() = job.current_chapter.lock().to_string();
                             self.download_texture_handle = None;
                         }
                     }
@@ -1444,6 +1456,7 @@ impl App {
     /// - This function also allows for error handling if certain fields cannot be parsed (e.g., max consecutive pages).
     /// - Once the manga is downloaded, the user can select it from the list to enter the manga reader panel.
     fn main_config(&mut self, ui: &mut Ui) {
+        let job = job::current();
         ui.with_layout(Layout::top_down(egui::Align::Center), |ui| {
             ScrollArea::vertical().show(ui, |ui| {
                 ui.label("Set url of manga");
@@ -1505,8 +1518,6 @@ impl App {
                         self.setup_database_offset.clone()
                     );
                     let url = self.setup_url.clone();
-                    *resolute::SAVER.lock() = self.setup_saver;
-                    resolute::SCANLATION_GROUPS.lock().clear();
                     let _ = tokio::spawn(async move {
                         match resolve_download(&url, handle_id).await {
                             Ok(_) => (),
@@ -1533,16 +1544,16 @@ impl App {
                     }
                 }
 
-                if !resolute::WEB_DOWNLOADED.lock().is_empty() {
+                if !job.downloaded.lock().is_empty() {
                     ui.label("Downloaded:");
-                    for i in resolute::WEB_DOWNLOADED.lock().iter() {
+                    for i in job.downloaded.lock().iter() {
                         ui.label(i);
                     }
                 }
 
-                if !resolute::SCANLATION_GROUPS.lock().is_empty() {
+                if !job.scanlation_groups.lock().is_empty() {
                     ui.label("Scanlation group:");
-                    for i in resolute::SCANLATION_GROUPS.lock().iter() {
+                    for i in job.scanlation_groups.lock().iter() {
                         ui.label(i.name.clone());
                     }
                 }
@@ -1581,17 +1592,59 @@ impl App {
         ui.label("with up and down arrows you move pages by 5");
     }
 
+    /// Displays chapters missing between downloaded chapters of every manga, see `gaps::collect`.
+    ///
+    /// Gaps are found in the background by `find_gaps`; until then a loading message is shown.
+    /// "Refresh" finds them again, e.g. after chapters were downloaded.
+    fn gaps(&mut self, ui: &mut Ui) {
+        if ui.button("Refresh").clicked() {
+            find_gaps();
+        }
+        ui.with_layout(Layout::top_down(egui::Align::Center), |ui| {
+            ui.heading("Missing chapters");
+        });
+        let gaps = match GAPS.lock().clone() {
+            Some(gaps) => gaps,
+            None => {
+                ui.label("Finding gaps...");
+                return;
+            }
+        };
+        if gaps.is_empty() {
+            ui.label("No gaps found");
+            return;
+        }
+        ScrollArea::vertical().show(ui, |ui| {
+            for manga in gaps.iter() {
+                ui.collapsing(format!("{} ({})", manga.name, manga.gaps.len()), |ui| {
+                    for gap in manga.gaps.iter() {
+                        let mut line = format!("{}: {}", gap.number, gap.kind.description());
+                        if !gap.languages.is_empty() {
+                            line += &format!(" ({})", gap.languages.join(", "));
+                        }
+                        if gap.fillable {
+                            line += " [fillable]";
+                        }
+                        ui.label(line);
+                    }
+                });
+            }
+        });
+    }
+
     /// Manages the display of the main panel, switching between different sections based on the current panel state.
     ///
     /// This function decides which UI panel to display based on the value of `self.panel`. It can show the following sections:
     /// - **"reader"**: Displays the manga reader interface.
     /// - **"main"**: Displays the main downloader interface.
     /// - **"help"**: Displays the help interface with usage instructions.
+    /// - **"gaps"**: Displays missing chapters of the library.
     ///
     /// # UI Elements:
     /// - If `self.panel` is `"reader"`, the function calls `self.reader(ctx, ui)` to display the reader panel.
     /// - If `self.panel` is `"main"`, the function calls `self.main(ctx, ui)` to display the main downloader panel.
     /// - If `self.panel` is `"help"`, the function calls `self.help(ui)` to display the help panel.
+    /// - If `self.panel` is `"gaps"`, the function calls `self.gaps(ui)` to display the gaps panel.
     ///
     /// # Example:
     /// ```
@@ -1607,6 +1660,8 @@ impl App {
             self.main(ctx, ui);
         } else if self.panel == *"help" {
             self.help(ui);
+        } else if self.panel == *"gaps" {
+            self.gaps(ui);
         }
     }
 
@@ -1778,10 +1833,13 @@ impl eframe::App for App {
 /// - The function does not explicitly handle errors, but logs any failures during the process using `info!` for found entries and file paths.
 fn get_chapter_paths(manga_data: metadata::MangaMetadata) {
     *READER_CHAPTER_PATHS.lock() = Some(HashMap::new());
-    if let Ok(glob_results) = glob(&format!("{}\\*.cbz", &manga_data.mwd[4..])) {
+    if let Ok(glob_results) = glob(&format!("{}\\**\\*.cbz", &manga_data.mwd[4..])) {
         tokio::spawn(async move {
             for entry in glob_results.filter_map(Result::ok) {
                 if let Some(entry_str) = entry.to_str() {
+                    if resolute::is_version(entry_str) {
+                        continue;
+                    }
                     info!("Found entry: {}", entry_str);
                     if let Ok(manga) = resolute::check_for_metadata(entry_str) {
                         if let Some(ref mut value) = *READER_CHAPTER_PATHS.lock() {
@@ -1794,6 +1852,21 @@ fn get_chapter_paths(manga_data: metadata::MangaMetadata) {
     }
 }
 
+/// Finds gaps of all manga in the library in the background and stores them in `GAPS`.
+fn find_gaps() {
+    *GAPS.lock() = None;
+    tokio::spawn(async {
+        let gaps = match gaps::collect(None).await {
+            Ok(gaps) => gaps,
+            Err(err) => {
+                warn!("Failed to find gaps: {}", err);
+                Vec::new()
+            }
+        };
+        *GAPS.lock() = Some(gaps);
+    });
+}
+
 /// Loads an image from the provided byte data, resizes it to fit within the specified available width and height, and returns the texture handle.
 ///
 /// This function accepts image data as a byte slice (`image_data`), attempts to load it into an image format, and resizes it to fit within the given `available_width` and `available_height` while preserving the aspect ratio.
@@ -1867,25 +1940,16 @@ fn load_and_resize_image(
     }
 }
 
-/// Retrieves manga data by reading and parsing a `dat.json` file.
+/// Retrieves manga data from the library index.
 ///
-/// This function attempts to load and parse manga metadata from a JSON file located at the path returned by `getter::get_dat_path()`. It returns the parsed `MangaMetadata` as a `Vec` if successful, or an appropriate error if something goes wrong during the process.
+/// This function loads the library with `library_db::load` and returns its manga.
 ///
 /// # Returns:
-/// - `Ok(Vec<metadata::MangaMetadata>)` if the manga data is successfully retrieved and parsed.
-/// - `Err(MdownError)` if an error occurs while retrieving the path, reading the file, or parsing the JSON.
+/// - `Ok(Vec<metadata::MangaMetadata>)` if the manga data is successfully retrieved.
+/// - `Err(MdownError)` if the library index can't be opened or read.
 ///
 /// # Errors:
-/// - `MdownError::ChainedError` if there is an error when fetching the path (Error code: `14003`).
-/// - `MdownError::IoError` if the `dat.json` file cannot be found or read (Error code: `14000`).
-/// - `MdownError::JsonError` if there is a failure while parsing the JSON data (Error code: `14001`).
-///
-/// # Steps:
-/// 1. Calls `getter::get_dat_path()` to retrieve the path to the `dat.json` file.
-/// 2. Checks if the file exists using `std::fs::metadata`.
-/// 3. If the file exists, it reads the content of the file using `resolute::get_dat_content`.
-/// 4. The content is then parsed into a `metadata::Dat` struct using `serde_json::from_value`.
-/// 5. If successful, the function returns the parsed `Vec<metadata::MangaMetadata>`. If there is an error at any point, an appropriate error is returned.
+/// - `MdownError::ChainedError` if the library index can't be loaded (Error code: `14000`).
 ///
 /// # Example:
 /// ```rust
@@ -1899,37 +1963,10 @@ fn load_and_resize_image(
 ///     }
 /// }
 /// ```
-///
-/// # Notes:
-/// - This function is designed to handle potential errors at multiple points: fetching the file path, reading the file, and parsing the JSON data.
-/// - The error handling is done through the `MdownError` enum, which helps categorize different types of errors (I/O errors, JSON parsing errors, etc.).
-///
-/// # Error Handling:
-/// - If `getter::get_dat_path()` fails, the error is wrapped in `MdownError::ChainedError`.
-/// - If the file does not exist or can't be read, an I/O error is returned with the path and error details.
-/// - If parsing the JSON content fails, a `JsonError` is returned with details about the parsing failure.
 fn get_manga_data() -> Result<Vec<metadata::MangaMetadata>, MdownError> {
-    let dat_path = match getter::get_dat_path() {
-        Ok(path) => path,
-        Err(err) => {
-            return Err(MdownError::ChainedError(Box::new(err), 14003));
-        }
-    };
-    if let Err(err) = std::fs::metadata(&dat_path) {
-        debug!("dat.json not found: {}", err.to_string());
-        return Err(MdownError::IoError(err, dat_path, 14000));
-    }
-
-    let json = match resolute::get_dat_content(dat_path.as_str()) {
-        Ok(value) => value,
-        Err(error) => {
-            return Err(error);
-        }
-    };
-
-    match serde_json::from_value::<metadata::Dat>(json) {
+    match library_db::load() {
         Ok(dat) => Ok(dat.data),
-        Err(err) => Err(MdownError::JsonError(err.to_string(), 14001)),
+        Err(err) => Err(MdownError::ChainedError(Box::new(err), 14000)),
     }
 }
 
@@ -2079,7 +2116,10 @@ async fn resolve_download(url: &str, handle_id: Box<str>) -> Result<String, Mdow
 
     if id != "*" {
         let id = id.as_str();
-        *resolute::MANGA_ID.lock() = id.to_string();
+        // Every download has its own job; the downloading panel shows the latest one
+        let job = Job::new();
+        job::set_current(Arc::clone(&job));
+        *job.manga_id.lock() = id.to_string();
         info!("@{} Found {}", handle_id, id);
         match getter::get_manga_json(id).await {
             Ok(manga_name_json) => {
@@ -2090,7 +2130,7 @@ async fn resolve_download(url: &str, handle_id: Box<str>) -> Result<String, Mdow
                     }
                 };
                 if let Value::Object(obj) = json_value {
-                    resolute::resolve(obj, id).await
+                    resolute::resolve(&job, obj, id).await
                 } else {
                     Err(MdownError::JsonError(String::from("Unexpected JSON value"), 11401))
                 }
@@ -0,0 +1,369 @@
+//! Cache of MangaDex API responses.
+//!
+//! Metadata responses (manga, chapter feeds, statistics, scanlation groups and covers) are stored
+//! in `http_cache` table of the database, keyed by URL. Responses younger than `--cache-ttl` are
+//! used without asking the server; older ones are revalidated with `ETag` and `Last-Modified`. If
+//! the server can't be reached, cached response is used regardless of its age, so the library can
+//! be browsed offline. `--refresh` skips cached responses. Chapter feeds of downloads are always
+//! revalidated, so newly released chapters are not missed. Responses not fetched for
+//! `MAX_AGE` are removed; with `--dry-run` the cache is only read.
+
+use reqwest::{ header, StatusCode };
+use rusqlite::{ Connection, OptionalExtension, params };
+
+use crate::{ args, debug, download, error::{ MdownError, suspend_error }, getter };
+
+/// Cached responses not fetched from the server for this long (30 days, in seconds) are removed.
+const MAX_AGE: i64 = 30 * 24 * 60 * 60;
+
+/// Cached response.
+struct Entry {
+    body: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    fetched_at: i64,
+}
+
+/// Returns body of the response to GET request of `url`, using cached response if possible.
+///
+/// # Errors
+/// * `MdownError::NetworkError` - If the request failed and the response is not cached.
+/// * `MdownError::StatusError` - If the server responded with an error status.
+pub(crate) async fn get(url: &str) -> Result<String, MdownError> {
+    let ttl = args::ARGS.lock().cache_ttl as i64;
+    match get_with_time(url, ttl).await {
+        Ok((body, _fetched_at)) => Ok(body),
+        Err(err) => Err(err),
+    }
+}
+
+/// Same as `get`, but cached response is used without asking the server only if it is younger
+/// than `ttl` seconds; with `0` it is always revalidated.
+///
+/// Returns the body together with the time (unix timestamp) when it was fetched from the server,
+/// which is older than now if cached response was used.
+pub(crate) async fn get_with_time(url: &str, ttl: i64) -> Result<(String, i64), MdownError> {
+    let db_path = match getter::get_db_path() {
+        Ok(path) => path,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12307));
+        }
+    };
+    get_from(&db_path, url, ttl, *args::ARGS_REFRESH, !*args::ARGS_DRY_RUN).await
+}
+
+/// Same as `get_with_time` with the cache in database at `db_path`; `refresh` skips cached
+/// response and the cache is written only if `store` is set.
+async fn get_from(
+    db_path: &str,
+    url: &str,
+    ttl: i64,
+    refresh: bool,
+    store: bool
+) -> Result<(String, i64), MdownError> {
+    let cached = match read(db_path, url) {
+        Ok(entry) => entry,
+        Err(err) => {
+            suspend_error(MdownError::ChainedError(Box::new(err), 12300));
+            None
+        }
+    };
+    let now = chrono::Utc::now().timestamp();
+    if let Some(entry) = &cached {
+        if !refresh && now - entry.fetched_at < ttl {
+            debug!("using cached response of {}", url);
+            return Ok((entry.body.clone(), entry.fetched_at));
+        }
+    }
+
+    let client = match download::get_client() {
+        Ok(client) => client,
+        Err(err) => {
+            return Err(MdownError::NetworkError(err, 12301));
+        }
+    };
+    let mut request = client.get(url);
+    if let Some(entry) = cached.as_ref().filter(|_| !refresh) {
+        if let Some(etag) = &entry.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &entry.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    debug!("sending request to: {}", url);
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(err) => {
+            return match cached {
+                Some(entry) => {
+                    debug!("request failed; using cached response of {}: {}", url, err);
+                    Ok((entry.body, entry.fetched_at))
+                }
+                None => Err(MdownError::NetworkError(err, 12302)),
+            };
+        }
+    };
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        if let Some(entry) = cached {
+            debug!("cached response of {} is not modified", url);
+            if store {
+                if let Err(err) = touch(db_path, url, now) {
+                    suspend_error(MdownError::ChainedError(Box::new(err), 12303));
+                }
+            }
+            return Ok((entry.body, now));
+        }
+    }
+    if !response.status().is_success() {
+        return Err(MdownError::StatusError(response.status(), 12304));
+    }
+
+    let header_value = |name: header::HeaderName| -> Option<String> {
+        response.headers().get(name).and_then(|value| value.to_str().ok()).map(String::from)
+    };
+    let entry_etag = header_value(header::ETAG);
+    let entry_last_modified = header_value(header::LAST_MODIFIED);
+    let body = match response.text().await {
+        Ok(body) => body,
+        Err(err) => {
+            return Err(MdownError::NetworkError(err, 12305));
+        }
+    };
+    let entry = Entry {
+        body,
+        etag: entry_etag,
+        last_modified: entry_last_modified,
+        fetched_at: now,
+    };
+    if store {
+        if let Err(err) = write(db_path, url, &entry) {
+            suspend_error(MdownError::ChainedError(Box::new(err), 12306));
+        }
+    }
+    Ok((entry.body, now))
+}
+
+fn open(db_path: &str) -> Result<Connection, MdownError> {
+    match Connection::open(db_path) {
+        Ok(conn) => Ok(conn),
+        Err(err) => Err(MdownError::DatabaseError(err, 12308)),
+    }
+}
+
+fn read(db_path: &str, url: &str) -> Result<Option<Entry>, MdownError> {
+    let conn = match open(db_path) {
+        Ok(conn) => conn,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12309));
+        }
+    };
+    let entry = conn
+        .query_row(
+            "SELECT body, etag, last_modified, fetched_at FROM http_cache WHERE url = ?1",
+            params![url],
+            |row| {
+                Ok(Entry {
+                    body: row.get(0)?,
+                    etag: row.get(1)?,
+                    last_modified: row.get(2)?,
+                    fetched_at: row.get(3)?,
+                })
+            }
+        )
+        .optional();
+    match entry {
+        Ok(entry) => Ok(entry),
+        Err(err) => Err(MdownError::DatabaseError(err, 12310)),
+    }
+}
+
+/// Stores `entry` and removes responses older than `MAX_AGE`.
+fn write(db_path: &str, url: &str, entry: &Entry) -> Result<(), MdownError> {
+    let conn = match open(db_path) {
+        Ok(conn) => conn,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12311));
+        }
+    };
+    match
+        conn.execute(
+            "INSERT INTO http_cache (url, body, etag, last_modified, fetched_at) VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT(url) DO UPDATE SET body = excluded.body, etag = excluded.etag,
+            last_modified = excluded.last_modified, fetched_at = excluded.fetched_at",
+            params![url, entry.body, entry.etag, entry.last_modified, entry.fetched_at]
+        )
+    {
+        Ok(_) => (),
+        Err(err) => {
+            return Err(MdownError::DatabaseError(err, 12312));
+        }
+    }
+    match
+        conn.execute(
+            "DELETE FROM http_cache WHERE fetched_at < ?1",
+            params![entry.fetched_at - MAX_AGE]
+        )
+    {
+        Ok(removed) => {
+            if removed > 0 {
+                debug!("removed {} old cached responses", removed);
+            }
+            Ok(())
+        }
+        Err(err) => Err(MdownError::DatabaseError(err, 12315)),
+    }
+}
+
+fn touch(db_path: &str, url: &str, fetched_at: i64) -> Result<(), MdownError> {
+    let conn = match open(db_path) {
+        Ok(conn) => conn,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12313));
+        }
+    };
+    match
+        conn.execute(
+            "UPDATE http_cache SET fetched_at = ?2 WHERE url = ?1",
+            params![url, fetched_at]
+        )
+    {
+        Ok(_) => Ok(()),
+        Err(err) => Err(MdownError::DatabaseError(err, 12314)),
+    }
+}
+
+// Creates database with `http_cache` table in temp folder
+#[cfg(test)]
+fn test_db() -> String {
+    let path = std::env::temp_dir().join(
+        format!("mdown_http_cache_{}.db", crate::utils::generate_random_id(8))
+    );
+    let db_path = path.to_string_lossy().to_string();
+    open(&db_path)
+        .unwrap()
+        .execute_batch(
+            "CREATE TABLE http_cache (
+            url TEXT PRIMARY KEY,
+            body TEXT NOT NULL,
+            etag TEXT,
+            last_modified TEXT,
+            fetched_at INTEGER NOT NULL
+        );"
+        )
+        .unwrap();
+    db_path
+}
+
+// Serves `responses` one per connection on local port; returns its url and received requests
+#[cfg(test)]
+fn serve(responses: Vec<&'static str>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+    use std::io::{ Read, Write };
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/manga", listener.local_addr().unwrap());
+    let handle = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let read = stream.read(&mut buffer).unwrap();
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..read]);
+            }
+            requests.push(String::from_utf8_lossy(&request).to_lowercase());
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+        requests
+    });
+    (url, handle)
+}
+
+// Test fresh response is used without request and older one is revalidated
+#[tokio::test]
+async fn test_get_from_fresh_and_not_modified() {
+    let db_path = test_db();
+    let (url, server) = serve(
+        vec![
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 3\r\nConnection: close\r\n\r\none",
+            "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n"
+        ]
+    );
+
+    let (body, fetched_at) = get_from(&db_path, &url, 3600, false, true).await.unwrap();
+    assert_eq!(body, "one");
+    let (body, cached_at) = get_from(&db_path, &url, 3600, false, true).await.unwrap();
+    assert_eq!(body, "one");
+    assert_eq!(cached_at, fetched_at);
+
+    touch(&db_path, &url, 0).unwrap();
+    let (body, revalidated_at) = get_from(&db_path, &url, 3600, false, true).await.unwrap();
+    assert_eq!(body, "one");
+    assert!(revalidated_at > 0);
+    assert_eq!(read(&db_path, &url).unwrap().unwrap().fetched_at, revalidated_at);
+
+    let requests = server.join().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(!requests[0].contains("if-none-match"));
+    assert!(requests[1].contains("if-none-match: \"v1\""));
+
+    std::fs::remove_file(&db_path).unwrap();
+}
+
+// Test cached response is used regardless of age if server can't be reached
+#[tokio::test]
+async fn test_get_from_offline() {
+    let db_path = test_db();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/manga", listener.local_addr().unwrap());
+    drop(listener);
+    let entry = Entry {
+        body: String::from("cached"),
+        etag: None,
+        last_modified: None,
+        fetched_at: chrono::Utc::now().timestamp() - 7200,
+    };
+    write(&db_path, &url, &entry).unwrap();
+
+    let (body, fetched_at) = get_from(&db_path, &url, 0, false, true).await.unwrap();
+    assert_eq!(body, "cached");
+    assert_eq!(fetched_at, entry.fetched_at);
+    assert!(get_from(&db_path, &format!("{}/other", url), 0, false, true).await.is_err());
+
+    std::fs::remove_file(&db_path).unwrap();
+}
+
+// Test response is not cached without store and old responses are removed on write
+#[tokio::test]
+async fn test_get_from_store_and_eviction() {
+    let db_path = test_db();
+    let (url, server) = serve(
+        vec!["HTTP/1.1 200 OK\r\nContent-Length: 3\r\nConnection: close\r\n\r\none"]
+    );
+
+    assert_eq!(get_from(&db_path, &url, 3600, false, false).await.unwrap().0, "one");
+    assert!(read(&db_path, &url).unwrap().is_none());
+    server.join().unwrap();
+
+    let now = chrono::Utc::now().timestamp();
+    let entry = |fetched_at: i64| Entry {
+        body: String::from("body"),
+        etag: None,
+        last_modified: None,
+        fetched_at,
+    };
+    write(&db_path, "old", &entry(now - MAX_AGE - 1)).unwrap();
+    write(&db_path, "recent", &entry(now - MAX_AGE + 60)).unwrap();
+    write(&db_path, "new", &entry(now)).unwrap();
+    assert!(read(&db_path, "old").unwrap().is_none());
+    assert!(read(&db_path, "recent").unwrap().is_some());
+    assert!(read(&db_path, "new").unwrap().is_some());
+
+    std::fs::remove_file(&db_path).unwrap();
+}
@@ -0,0 +1,197 @@
+//! Context of a download job.
+//!
+//! State of the download of one manga is held by a `Job`: its options, i.e. language, chapters to
+//! download and other options given on the command line or by the web API, the manga folder,
+//! downloaded chapters, dates of chapters in the library, covers, filename template and long strip
+//! processing, and progress, i.e. name and id of the manga, the chapter and page being downloaded,
+//! size of the chapter, number of parsed chapters, scanlation groups and downloaded files. The job
+//! is passed through `resolute::resolve`, `download_manga`, `download_chapter` and
+//! `download::download_image`, so progress and logs of every job can be read separately, e.g. of
+//! queued jobs of the web API. Functions which are not given a job, e.g. naming of the manga folder
+//! and logs, use the job of the running task set by `Job::scope`, or the current job shown by the
+//! web, gui and curses window.
+//!
+//! Details of the manga read from mangadex, e.g. `resolute::GENRES`, `resolute::LANGUAGE` and
+//! `resolute::MANGA_DETAILS`, are still global, so only one job can download at a time, see
+//! `DOWNLOAD`.
+
+use chrono::Utc;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{ collections::HashMap, future::Future, sync::Arc };
+use tokio::sync::Mutex as AsyncMutex;
+
+use crate::{
+    metadata::{ ChapterMetadata, CoverMetadata, JobOptions, ScanlationMetadata },
+    utils,
+};
+
+/// Maximum number of log messages kept by a job, older messages are dropped.
+const MAX_LOGS: usize = 1000;
+
+lazy_static! {
+    static ref CURRENT: Mutex<Arc<Job>> = Mutex::new(Job::new());
+
+    /// Held while a job downloads, i.e. a job of the web API, a download of `/manga` or of the
+    /// library, because all of them use global state of `resolute`.
+    pub(crate) static ref DOWNLOAD: AsyncMutex<()> = AsyncMutex::new(());
+}
+
+tokio::task_local! {
+    static TASK_JOB: Arc<Job>;
+}
+
+/// State of the download of one manga.
+#[derive(Debug, Default)]
+pub(crate) struct Job {
+    /// Random id of the job.
+    pub(crate) id: String,
+
+    /// Options of the download.
+    pub(crate) options: JobOptions,
+
+    /// Folder of the manga.
+    pub(crate) mwd: Mutex<String>,
+
+    /// Chapters downloaded (or added to dry run) for the manga.
+    pub(crate) chapters: Mutex<Vec<ChapterMetadata>>,
+
+    /// Ids and update dates of chapters in the library by chapter number; only manga being
+    /// checked have them set.
+    pub(crate) chapter_ids: Mutex<HashMap<String, String>>,
+    pub(crate) chapter_dates: Mutex<HashMap<String, String>>,
+
+    /// Downloaded volume covers of the manga, embedded into chapters.
+    pub(crate) covers: Mutex<Vec<CoverMetadata>>,
+
+    /// Filename template of the manga, empty for built-in naming.
+    pub(crate) template: Mutex<String>,
+
+    /// Whether chapters are stitched and re-sliced before zipping.
+    pub(crate) long_strip: Mutex<bool>,
+
+    pub(crate) manga_name: Mutex<String>,
+    pub(crate) manga_id: Mutex<String>,
+    pub(crate) chapter_id: Mutex<String>,
+
+    /// Folder name of the chapter being downloaded.
+    pub(crate) current_chapter: Mutex<String>,
+    pub(crate) current_page: Mutex<u64>,
+    pub(crate) current_page_max: Mutex<u64>,
+    pub(crate) current_percent: Mutex<f64>,
+
+    /// Downloaded and full size of the chapter being downloaded in megabytes.
+    pub(crate) current_size: Mutex<f64>,
+    pub(crate) current_size_max: Mutex<f64>,
+    pub(crate) current_chapter_parsed: Mutex<u64>,
+    pub(crate) current_chapter_parsed_max: Mutex<u64>,
+    pub(crate) scanlation_groups: Mutex<Vec<ScanlationMetadata>>,
+
+    /// Paths of downloaded chapters.
+    pub(crate) downloaded: Mutex<Vec<String>>,
+    pub(crate) downloading: Mutex<bool>,
+
+    /// Log messages written while the job was the job of the task, with their time.
+    pub(crate) logs: Mutex<Vec<String>>,
+
+    /// Set when the job should stop; downloads stop after the current image and the chapter
+    /// being downloaded is not saved.
+    pub(crate) cancelled: Mutex<bool>,
+}
+
+impl Job {
+    /// Creates a job with options given on the command line.
+    pub(crate) fn new() -> Arc<Job> {
+        Job::with_id(&utils::generate_random_id(12))
+    }
+
+    /// Creates a job with `id`, e.g. handle id of the web page which started the download, and
+    /// options given on the command line.
+    pub(crate) fn with_id(id: &str) -> Arc<Job> {
+        Job::with_options(id, JobOptions::from_args())
+    }
+
+    /// Creates a job with `id` and `options`, e.g. of a job queued by the web API.
+    pub(crate) fn with_options(id: &str, options: JobOptions) -> Arc<Job> {
+        Arc::new(Job { id: id.to_string(), options, ..Default::default() })
+    }
+
+    /// Runs `future` with this job as the job of the task.
+    pub(crate) async fn scope<F: Future>(self: &Arc<Job>, future: F) -> F::Output {
+        TASK_JOB.scope(Arc::clone(self), future).await
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        *self.cancelled.lock()
+    }
+
+    /// Resets progress of the chapter being downloaded.
+    pub(crate) fn reset_chapter(&self) {
+        self.current_chapter.lock().clear();
+        *self.current_page.lock() = 0;
+        *self.current_page_max.lock() = 0;
+        *self.current_percent.lock() = 0.0;
+        *self.current_size.lock() = 0.0;
+        *self.current_size_max.lock() = 0.0;
+    }
+
+    /// Resets progress and state of the manga after it was downloaded; downloaded files and
+    /// scanlation groups are kept for the summary.
+    pub(crate) fn reset(&self) {
+        self.reset_chapter();
+        self.manga_id.lock().clear();
+        *self.current_chapter_parsed.lock() = 0;
+        *self.current_chapter_parsed_max.lock() = 0;
+        self.chapters.lock().clear();
+        self.covers.lock().clear();
+    }
+
+    /// Returns whether chapter `number` was already downloaded (or added to dry run) for the
+    /// manga; uploads of the same number by other scanlation groups are skipped.
+    pub(crate) fn has_chapter(&self, number: &str) -> bool {
+        self.chapters
+            .lock()
+            .iter()
+            .any(|chapter| chapter.number == number)
+    }
+}
+
+/// Returns the job of the running task, or the current job if the task has none.
+pub(crate) fn current() -> Arc<Job> {
+    TASK_JOB.try_with(Arc::clone).unwrap_or_else(|_| CURRENT.lock().clone())
+}
+
+/// Adds `message` to logs of the job of the running task; messages outside of a job are not kept.
+pub(crate) fn log(message: &str) {
+    let _ = TASK_JOB.try_with(|job| {
+        let mut logs = job.logs.lock();
+        if logs.len() >= MAX_LOGS {
+            logs.remove(0);
+        }
+        logs.push(format!("{}  {}", Utc::now().to_rfc3339(), message));
+    });
+}
+
+/// Sets the job shown by the web and gui, which is also the job of tasks without their own job.
+pub(crate) fn set_current(job: Arc<Job>) {
+    *CURRENT.lock() = job;
+}
+
+// Test of job of the task and the current job
+#[tokio::test]
+async fn test_current() {
+    let job = Job::new();
+    *job.manga_name.lock() = String::from("Task");
+    let name = job.scope(async {
+        log("Started");
+        current().manga_name.lock().clone()
+    }).await;
+    assert_eq!(name, "Task");
+    log("Outside");
+    assert_eq!(job.logs.lock().len(), 1);
+    assert!(job.logs.lock()[0].ends_with("  Started"));
+    assert_ne!(current().id, job.id);
+    *job.current_page.lock() = 3;
+    job.reset();
+    assert_eq!(*job.current_page.lock(), 0);
+}
@@ -0,0 +1,341 @@
+//! Queue of download jobs of web mode.
+//!
+//! Jobs are created by the REST API under `/api/jobs` and downloaded one after another by a worker
+//! started with the web server; options of a job are kept in its `Job` context, so the global
+//! arguments of the command line are never changed. Downloads share global state of `resolute`, so
+//! a job and a download of `/manga` never run at once, see `job::DOWNLOAD`. Progress and logs of a job can
+//! be read while other jobs wait in the queue. Only the last `MAX_FINISHED` finished jobs are kept.
+//!
+//! | Method | Path | |
+//! |---|---|---|
+//! | `GET` | `/api/jobs` | Statuses of all jobs |
+//! | `POST` | `/api/jobs` | Queues a job from `JobOptions` |
+//! | `GET` | `/api/jobs/{id}` | Status of the job |
+//! | `GET` | `/api/jobs/{id}/logs` | Log messages of the job |
+//! | `POST`, `DELETE` | `/api/jobs/{id}/cancel`, `/api/jobs/{id}` | Cancels the job |
+//! | `POST` | `/api/jobs/{id}/retry` | Queues the finished job again |
+
+use chrono::Utc;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde_json::{ json, Value };
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+use crate::{
+    error::MdownError,
+    handle_error,
+    job::{ self, Job },
+    log,
+    log_end,
+    metadata::{ JobOptions, JobState, JobStatus },
+    resolute,
+    utils,
+    web,
+};
+
+/// Number of finished jobs kept for their status and logs; older ones are removed.
+const MAX_FINISHED: usize = 100;
+
+lazy_static! {
+    static ref JOBS: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+
+    /// Wakes the worker when a job is queued.
+    static ref QUEUED: Notify = Notify::new();
+}
+
+/// Job in the queue with its context.
+struct Entry {
+    status: JobStatus,
+    job: Arc<Job>,
+}
+
+impl Entry {
+    /// Creates a queued job with `options`.
+    fn new(options: JobOptions, retry_of: Option<String>) -> Entry {
+        let job = Job::with_options(&utils::generate_random_id(12), options.clone());
+        Entry {
+            status: JobStatus {
+                id: job.id.clone(),
+                state: JobState::Queued,
+                options,
+                retry_of,
+                name: String::new(),
+                manga_id: String::new(),
+                chapter: String::new(),
+                page: 0,
+                pages: 0,
+                chapters_parsed: 0,
+                chapters: 0,
+                files: Vec::new(),
+                scanlation_groups: Vec::new(),
+                error: None,
+                created_at: Utc::now().to_rfc3339(),
+                started_at: None,
+                finished_at: None,
+            },
+            job,
+        }
+    }
+
+    /// Returns status of the job with its current progress.
+    fn status(&self) -> JobStatus {
+        let job = &self.job;
+        let mut status = self.status.clone();
+        status.name = job.manga_name.lock().clone();
+        status.manga_id = job.manga_id.lock().clone();
+        status.chapter = job.current_chapter.lock().clone();
+        status.page = *job.current_page.lock();
+        status.pages = *job.current_page_max.lock();
+        status.chapters_parsed = *job.current_chapter_parsed.lock();
+        status.chapters = *job.current_chapter_parsed_max.lock();
+        status.files = job.downloaded.lock().clone();
+        status.scanlation_groups = job.scanlation_groups
+            .lock()
+            .iter()
+            .map(|group| group.name.clone())
+            .collect();
+        status
+    }
+}
+
+/// Queues a job and returns its status.
+fn queue(options: JobOptions, retry_of: Option<String>) -> JobStatus {
+    let entry = Entry::new(options, retry_of);
+    let status = entry.status();
+    let mut jobs = JOBS.lock();
+    jobs.push(entry);
+    prune(&mut jobs);
+    drop(jobs);
+    QUEUED.notify_one();
+    status
+}
+
+/// Starts the worker which downloads queued jobs.
+pub(crate) fn start() {
+    tokio::spawn(async {
+        loop {
+            let download = job::DOWNLOAD.lock().await;
+            match next() {
+                Some(job) => run(job).await,
+                None => {
+                    drop(download);
+                    QUEUED.notified().await;
+                }
+            }
+        }
+    });
+}
+
+/// Marks the oldest queued job as running and returns it.
+fn next() -> Option<Arc<Job>> {
+    let mut jobs = JOBS.lock();
+    let entry = jobs.iter_mut().find(|entry| entry.status.state == JobState::Queued)?;
+    entry.status.state = JobState::Running;
+    entry.status.started_at = Some(Utc::now().to_rfc3339());
+    Some(Arc::clone(&entry.job))
+}
+
+/// Removes the oldest finished jobs, so at most `MAX_FINISHED` of them are kept.
+fn prune(jobs: &mut Vec<Entry>) {
+    let finished = jobs
+        .iter()
+        .filter(|entry| entry.status.state.is_finished())
+        .count();
+    let mut remove = finished.saturating_sub(MAX_FINISHED);
+    jobs.retain(|entry| {
+        if remove > 0 && entry.status.state.is_finished() {
+            remove -= 1;
+            return false;
+        }
+        true
+    });
+}
+
+/// Downloads the job with its options and stores the result in its status; the caller holds
+/// `job::DOWNLOAD`.
+async fn run(job: Arc<Job>) {
+    let url = job.options.url.clone();
+    job::set_current(Arc::clone(&job));
+
+    let handle_id = job.id.clone().into_boxed_str();
+    *resolute::HANDLE_ID.lock() = handle_id.clone();
+    log!(&format!("@{} Job started", handle_id), handle_id.clone());
+    let (state, error) = match web::resolve_web_download(&job, &url).await {
+        _ if job.is_cancelled() => (JobState::Cancelled, None),
+        Ok(response) if response == "!" => {
+            (JobState::Failed, Some(String::from("Didn't find manga")))
+        }
+        Ok(_) => (JobState::Done, None),
+        Err(err) => {
+            handle_error!(&err, String::from("web_job"));
+            (JobState::Failed, Some(err.to_string()))
+        }
+    };
+    log_end(handle_id);
+    *resolute::HANDLE_ID.lock() = String::new().into_boxed_str();
+
+    let mut jobs = JOBS.lock();
+    if let Some(entry) = jobs.iter_mut().find(|entry| entry.status.id == job.id) {
+        entry.status.state = state;
+        entry.status.error = error;
+        entry.status.finished_at = Some(Utc::now().to_rfc3339());
+    }
+    prune(&mut jobs);
+}
+
+/// Handles a request of the REST API and returns its status code and JSON body.
+///
+/// # Parameters
+/// - `method`: Method of the request, e.g. `GET`.
+/// - `path`: Path of the request starting with `/api/`.
+/// - `body`: Body of the request.
+///
+/// # Returns
+/// - `Ok((u16, String))`: Status code and JSON body; errors of the request, e.g. unknown job, are
+///   returned as `{"status": "error", "message": ...}` with their status code.
+/// - `Err(MdownError)`: If serializing of the response fails.
+pub(crate) fn handle(method: &str, path: &str, body: &str) -> Result<(u16, String), MdownError> {
+    let path = path.split('?').next().unwrap_or_default().trim_end_matches('/');
+    let segments: Vec<&str> = match path.strip_prefix("/api/jobs") {
+        Some(rest) => rest.split('/').filter(|segment| !segment.is_empty()).collect(),
+        None => {
+            return Ok(error(404, "Unknown endpoint"));
+        }
+    };
+
+    let value = match (method, segments.as_slice()) {
+        ("GET", []) => {
+            let statuses: Vec<JobStatus> = JOBS.lock().iter().map(Entry::status).collect();
+            (200, json!({ "status": "ok", "jobs": statuses }))
+        }
+        ("POST", []) => {
+            let options: JobOptions = match serde_json::from_str(body) {
+                Ok(options) => options,
+                Err(err) => {
+                    return Ok(error(400, &format!("Invalid job options: {}", err)));
+                }
+            };
+            if options.url.is_empty() {
+                return Ok(error(400, "Missing url"));
+            }
+            log!(&format!("REQUEST Type: job {}", options.url));
+            (201, json!({ "status": "ok", "job": queue(options, None) }))
+        }
+        ("GET", [id]) => {
+            match JOBS.lock().iter().find(|entry| entry.status.id == *id) {
+                Some(entry) => (200, json!({ "status": "ok", "job": entry.status() })),
+                None => {
+                    return Ok(error(404, "Job not found"));
+                }
+            }
+        }
+        ("GET", [id, "logs"]) => {
+            match JOBS.lock().iter().find(|entry| entry.status.id == *id) {
+                Some(entry) => {
+                    let logs = entry.job.logs.lock().clone();
+                    (200, json!({ "status": "ok", "logs": logs }))
+                }
+                None => {
+                    return Ok(error(404, "Job not found"));
+                }
+            }
+        }
+        ("DELETE", [id]) | ("POST", [id, "cancel"]) => {
+            let mut jobs = JOBS.lock();
+            let entry = match jobs.iter_mut().find(|entry| entry.status.id == *id) {
+                Some(entry) => entry,
+                None => {
+                    return Ok(error(404, "Job not found"));
+                }
+            };
+            match entry.status.state {
+                JobState::Queued => {
+                    entry.status.state = JobState::Cancelled;
+                    entry.status.finished_at = Some(Utc::now().to_rfc3339());
+                }
+                JobState::Running => {
+                    *entry.job.cancelled.lock() = true;
+                }
+                _ => {
+                    return Ok(error(409, "Job is already finished"));
+                }
+            }
+            (200, json!({ "status": "ok", "job": entry.status() }))
+        }
+        ("POST", [id, "retry"]) => {
+            let options = match JOBS.lock().iter().find(|entry| entry.status.id == *id) {
+                Some(entry) if entry.status.state.is_finished() => entry.status.options.clone(),
+                Some(_) => {
+                    return Ok(error(409, "Job is not finished"));
+                }
+                None => {
+                    return Ok(error(404, "Job not found"));
+                }
+            };
+            (201, json!({ "status": "ok", "job": queue(options, Some(id.to_string())) }))
+        }
+        (_, [] | [_] | [_, "logs" | "cancel" | "retry"]) => {
+            return Ok(error(405, "Method not allowed"));
+        }
+        _ => {
+            return Ok(error(404, "Unknown endpoint"));
+        }
+    };
+
+    match serde_json::to_string(&value.1) {
+        Ok(body) => Ok((value.0, body)),
+        Err(err) => Err(MdownError::JsonError(err.to_string(), 13000)),
+    }
+}
+
+/// Returns response of a failed request.
+fn error(code: u16, message: &str) -> (u16, String) {
+    let value: Value = json!({ "status": "error", "message": message });
+    (code, value.to_string())
+}
+
+// Test of creating, cancelling and retrying of jobs
+#[test]
+fn test_handle() {
+    let (code, body) = handle("POST", "/api/jobs", r#"{"url": "abc", "lang": "cs"}"#).unwrap();
+    assert_eq!(code, 201);
+    let value: Value = serde_json::from_str(&body).unwrap();
+    let id = value["job"]["id"].as_str().unwrap().to_string();
+    assert_eq!(value["job"]["state"], "queued");
+    assert_eq!(value["job"]["options"]["lang"], "cs");
+    assert_eq!(value["job"]["options"]["max_consecutive"], 40);
+
+    let (code, _) = handle("POST", &format!("/api/jobs/{}/retry", id), "").unwrap();
+    assert_eq!(code, 409);
+    let (code, body) = handle("DELETE", &format!("/api/jobs/{}", id), "").unwrap();
+    assert_eq!(code, 200);
+    assert!(body.contains(r#""state":"cancelled""#));
+    let (code, body) = handle("POST", &format!("/api/jobs/{}/retry/", id), "").unwrap();
+    assert_eq!(code, 201);
+    assert!(body.contains(&format!(r#""retry_of":"{}""#, id)));
+
+    assert_eq!(handle("GET", &format!("/api/jobs/{}/logs", id), "").unwrap().0, 200);
+    assert_eq!(handle("GET", "/api/jobs/unknown", "").unwrap().0, 404);
+    assert_eq!(handle("POST", "/api/jobs", "{}").unwrap().0, 400);
+    assert_eq!(handle("POST", "/api/jobs", "url").unwrap().0, 400);
+    assert_eq!(handle("PUT", "/api/jobs", "").unwrap().0, 405);
+    assert_eq!(handle("GET", "/api/other", "").unwrap().0, 404);
+}
+
+// Test of removing of the oldest finished jobs
+#[test]
+fn test_prune() {
+    let mut jobs: Vec<Entry> = (0..MAX_FINISHED + 3)
+        .map(|_| Entry::new(JobOptions::default(), None))
+        .collect();
+    for entry in jobs.iter_mut().skip(1) {
+        entry.status.state = JobState::Done;
+    }
+    let queued = jobs[0].status.id.clone();
+    let kept = jobs[3].status.id.clone();
+    prune(&mut jobs);
+    assert_eq!(jobs.len(), MAX_FINISHED + 1);
+    assert_eq!(jobs[0].status.id, queued);
+    assert_eq!(jobs[1].status.id, kept);
+}
@@ -0,0 +1,909 @@
+//! Management of the library of downloaded manga.
+//!
+//! `library import` adds folders of `.cbz` files, e.g. made by other downloaders, to the library, so
+//! `database --update` and the gui manage them like manga downloaded by mdown. Chapters are
+//! identified by their `_metadata`, by `ComicInfo.xml` or by their file names, which are matched
+//! against the chapter feed of the manga on mangadex. Matched archives without `_metadata` get one,
+//! because downloaded chapters are recognised by it when the manga is updated.
+//!
+//! `library move` and `library rename` relocate manga folders together with their `mwd` in
+//! the library index. Folders moved outside of mdown are found by `find_moved` in library roots.
+
+use regex::Regex;
+use serde_json::Value;
+use std::{
+    collections::{ BTreeMap, HashMap, HashSet },
+    fs,
+    path::{ Path, PathBuf },
+};
+use walkdir::WalkDir;
+
+use crate::{
+    args,
+    comic_info,
+    db,
+    debug,
+    error::{ MdownError, suspend_error },
+    getter,
+    http_cache,
+    library_db,
+    metadata::{
+        ChapterMetadata,
+        ChapterMetadataIn,
+        ChapterResponse,
+        Dat,
+        MangaMetadata,
+        MangaResponse,
+    },
+    resolute,
+    sanitize,
+    utils,
+    zip_func,
+};
+
+/// Chapter archive found by `import`.
+struct Archive {
+    /// Path of the archive.
+    path: String,
+
+    /// `_metadata` of the archive, if it was downloaded by mdown.
+    metadata: Option<ChapterMetadataIn>,
+
+    /// Id of the chapter from `_metadata` or from `Web` of `ComicInfo.xml`.
+    chapter_id: Option<String>,
+
+    /// Name of the manga from `Series` of `ComicInfo.xml`.
+    series: Option<String>,
+
+    /// Chapter number from `ComicInfo.xml` or the file name.
+    number: Option<String>,
+
+    /// Volume from `ComicInfo.xml` or the file name.
+    volume: Option<String>,
+
+    /// Language from `LanguageISO` of `ComicInfo.xml`.
+    language: Option<String>,
+}
+
+impl Archive {
+    fn read(path: &str) -> Archive {
+        let stem = Path::new(path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let (number, volume) = parse_file_name(&stem);
+        let mut archive = Archive {
+            path: path.to_string(),
+            metadata: None,
+            chapter_id: None,
+            series: None,
+            number,
+            volume,
+            language: None,
+        };
+        if let Ok(metadata) = resolute::check_for_metadata(path) {
+            archive.chapter_id = Some(metadata.id.clone());
+            archive.number = Some(metadata.chapter.clone());
+            archive.metadata = Some(metadata);
+            return archive;
+        }
+        if let Ok(xml) = zip_func::extract_text_from_zip(path, comic_info::FILE_NAME) {
+            let values = comic_info::read(&xml);
+            let value = |name: &str| values.get(name).filter(|value| !value.is_empty()).cloned();
+            archive.chapter_id = value("Web").and_then(|web| {
+                web.rsplit('/')
+                    .find(|part| !part.is_empty())
+                    .filter(|id| utils::is_valid_uuid(id))
+                    .map(String::from)
+            });
+            archive.series = value("Series");
+            if let Some(number) = value("Number") {
+                archive.number = Some(normalize_number(&number));
+            }
+            if let Some(volume) = value("Volume") {
+                archive.volume = Some(normalize_number(&volume));
+            }
+            archive.language = value("LanguageISO");
+        }
+        archive
+    }
+}
+
+/// Imports manga in `path` into the library.
+///
+/// `path` is either a folder with manga folders or a single manga folder, which is recognised by
+/// `.cbz` files directly in it. Manga is identified by `_metadata` of its chapters or by searching
+/// mangadex for `Series` of `ComicInfo.xml` and for the folder name. Manga already in the database
+/// only get chapters which they are missing. With `--dry-run` nothing is written.
+pub(crate) async fn import(path: &str) -> Result<(), MdownError> {
+    let root = Path::new(path);
+    if !root.is_dir() {
+        return Err(MdownError::NotFoundError(format!("Folder {} doesn't exist", path), 12400));
+    }
+    let series = find_series(root);
+    if series.is_empty() {
+        println!("No .cbz files found in {}", path);
+        return Ok(());
+    }
+
+    let mut dat = match library_db::load() {
+        Ok(dat) => dat,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12401));
+        }
+    };
+
+    let dry_run = *args::ARGS_DRY_RUN;
+    let mut imported = 0;
+    for (folder, archives) in series {
+        let folder = folder.to_string_lossy().to_string();
+        match import_series(&folder, &archives, &mut dat, dry_run).await {
+            Ok(true) => {
+                imported += 1;
+            }
+            Ok(false) => (),
+            Err(err) => eprintln!("Error: importing {}: {}", folder, err),
+        }
+    }
+
+    if dry_run {
+        println!("Would import {} manga", imported);
+        return Ok(());
+    }
+    if let Err(err) = library_db::save(&dat) {
+        return Err(MdownError::ChainedError(Box::new(err), 12402));
+    }
+    println!("Imported {} manga", imported);
+    Ok(())
+}
+
+/// Moves folder of manga `id` to `path` and updates its `mwd` in the library.
+///
+/// `id` can also be the name of the manga. If `path` is an existing folder, manga folder is moved
+/// into it. With `--dry-run` changes are only printed.
+pub(crate) fn move_manga(id: &str, path: &str) -> Result<(), MdownError> {
+    let mut dat = match library_db::load() {
+        Ok(dat) => dat,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12424));
+        }
+    };
+    let index = match find_item(&dat, id) {
+        Some(index) => index,
+        None => {
+            return Err(MdownError::NotFoundError(format!("Manga {} in database", id), 12425));
+        }
+    };
+    let source = PathBuf::from(&dat.data[index].mwd);
+    let path = Path::new(path);
+    let target = match source.file_name() {
+        Some(name) if path.is_dir() => path.join(name),
+        _ => path.to_path_buf(),
+    };
+    match relocate(&mut dat, index, &target, None) {
+        Ok(()) => Ok(()),
+        Err(err) => Err(MdownError::ChainedError(Box::new(err), 12426)),
+    }
+}
+
+/// Renames manga `id` to `name` in the library and renames its folder accordingly.
+///
+/// `id` can also be the name of the manga. With `--dry-run` changes are only printed.
+pub(crate) fn rename_manga(id: &str, name: &str) -> Result<(), MdownError> {
+    let mut dat = match library_db::load() {
+        Ok(dat) => dat,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12427));
+        }
+    };
+    let index = match find_item(&dat, id) {
+        Some(index) => index,
+        None => {
+            return Err(MdownError::NotFoundError(format!("Manga {} in database", id), 12428));
+        }
+    };
+    let name = sanitize::manga_name(name);
+    if name.is_empty() {
+        return Err(
+            MdownError::CustomError(
+                String::from("Name is empty after removing characters not allowed in folder names"),
+                String::from("UserError"),
+                12429
+            )
+        );
+    }
+    let source = PathBuf::from(&dat.data[index].mwd);
+    let target = match source.parent() {
+        Some(parent) => parent.join(&name),
+        None => PathBuf::from(&name),
+    };
+    match relocate(&mut dat, index, &target, Some(name)) {
+        Ok(()) => Ok(()),
+        Err(err) => Err(MdownError::ChainedError(Box::new(err), 12430)),
+    }
+}
+
+/// Moves folder of manga at `index` to `target`, optionally renames the manga and saves the library.
+///
+/// If the library can't be saved, the folder is moved back, so files and database always agree.
+fn relocate(
+    dat: &mut Dat,
+    index: usize,
+    target: &Path,
+    name: Option<String>
+) -> Result<(), MdownError> {
+    let item = &mut dat.data[index];
+    let source = PathBuf::from(&item.mwd);
+    if !source.is_dir() {
+        return Err(MdownError::NotFoundError(format!("Folder {}", item.mwd), 12431));
+    }
+    // On case-insensitive file systems a change of case is a rename of the same folder
+    let same_folder = fs::canonicalize(target).ok() == fs::canonicalize(&source).ok();
+    if target.exists() && !same_folder {
+        return Err(
+            MdownError::CustomError(
+                format!("{} already exists", target.display()),
+                String::from("UserError"),
+                12432
+            )
+        );
+    }
+    if *args::ARGS_DRY_RUN {
+        println!("Would move {} -> {}", source.display(), target.display());
+        return Ok(());
+    }
+
+    if let Some(parent) = target.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        if let Err(err) = fs::create_dir_all(parent) {
+            return Err(MdownError::IoError(err, parent.to_string_lossy().to_string(), 12433));
+        }
+    }
+    if let Err(err) = move_folder(&source, target) {
+        return Err(MdownError::ChainedError(Box::new(err), 12434));
+    }
+    let mwd = match fs::canonicalize(target) {
+        Ok(path) => path.to_string_lossy().to_string(),
+        Err(_err) => target.to_string_lossy().to_string(),
+    };
+    let old_mwd = std::mem::replace(&mut item.mwd, mwd.clone());
+    if let Some(name) = name {
+        item.name = name;
+    }
+    if let Err(err) = library_db::save(dat) {
+        if let Err(move_err) = move_folder(target, &source) {
+            eprintln!("Error: moving {} back to {}: {}", mwd, old_mwd, move_err);
+        }
+        return Err(MdownError::ChainedError(Box::new(err), 12435));
+    }
+    println!("{} -> {}", old_mwd, mwd);
+    Ok(())
+}
+
+/// Moves folder `source` to `target`; across drives the folder is copied and then removed.
+fn move_folder(source: &Path, target: &Path) -> Result<(), MdownError> {
+    if fs::rename(source, target).is_ok() {
+        return Ok(());
+    }
+    for entry in WalkDir::new(source).into_iter() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                return Err(MdownError::CustomError(err.to_string(), String::from("WalkDir"), 12436));
+            }
+        };
+        let destination = match entry.path().strip_prefix(source) {
+            Ok(relative) => target.join(relative),
+            Err(_err) => {
+                continue;
+            }
+        };
+        let result = if entry.file_type().is_dir() {
+            fs::create_dir_all(&destination)
+        } else {
+            fs::copy(entry.path(), &destination).map(|_size| ())
+        };
+        if let Err(err) = result {
+            return Err(MdownError::IoError(err, destination.to_string_lossy().to_string(), 12437));
+        }
+    }
+    match remove_dir_all::remove_dir_all(source) {
+        Ok(()) => Ok(()),
+        Err(err) => Err(MdownError::IoError(err, source.to_string_lossy().to_string(), 12438)),
+    }
+}
+
+/// Returns index of manga in `dat` with `id` as its id or name.
+pub(crate) fn find_item(dat: &Dat, id: &str) -> Option<usize> {
+    dat.data
+        .iter()
+        .position(|item| item.id == id)
+        .or_else(|| dat.data.iter().position(|item| item.name == id))
+}
+
+/// Returns folders searched for manga whose folder was moved, set by `settings --library-roots`.
+pub(crate) fn get_roots() -> Result<Vec<String>, MdownError> {
+    match db::read_resource_lone(db::DB_LIBRARY_ROOTS) {
+        Ok(Some(roots)) => Ok(split_roots(&roots)),
+        Ok(None) => Ok(Vec::new()),
+        Err(err) => Err(MdownError::ChainedError(Box::new(err), 12442)),
+    }
+}
+
+/// Sets folders searched for manga whose folder was moved; `roots` are separated by `;` and
+/// empty `roots` remove the setting.
+pub(crate) fn set_roots(roots: &str) -> Result<(), MdownError> {
+    let roots = split_roots(roots).join(";");
+    match db::write_resource_lone(db::DB_LIBRARY_ROOTS, roots.as_bytes(), false) {
+        Ok(_id) => Ok(()),
+        Err(err) => Err(MdownError::ChainedError(Box::new(err), 12443)),
+    }
+}
+
+fn split_roots(roots: &str) -> Vec<String> {
+    roots
+        .split(';')
+        .map(str::trim)
+        .filter(|root| !root.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Returns the new folder of manga `id` whose folder `mwd` doesn't exist anymore.
+///
+/// Folders in library roots and in the parent folder of `mwd` are searched; a folder belongs to
+/// the manga if its chapters have `_metadata` with the manga id. Folder with the old name is
+/// checked first.
+pub(crate) fn find_moved(id: &str, mwd: &str) -> Option<String> {
+    let mut roots: Vec<PathBuf> = match get_roots() {
+        Ok(roots) => roots.iter().map(PathBuf::from).collect(),
+        Err(err) => {
+            suspend_error(MdownError::ChainedError(Box::new(err), 12444));
+            Vec::new()
+        }
+    };
+    let old = Path::new(mwd);
+    if let Some(parent) = old.parent() {
+        roots.push(parent.to_path_buf());
+    }
+    for root in roots {
+        let mut folders: Vec<PathBuf> = match fs::read_dir(&root) {
+            Ok(entries) =>
+                entries
+                    .filter_map(Result::ok)
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_dir())
+                    .collect(),
+            Err(_err) => {
+                continue;
+            }
+        };
+        folders.sort_by_key(|folder| folder.file_name() != old.file_name());
+        for folder in folders {
+            if folder_manga_id(&folder).as_deref() == Some(id) {
+                return match fs::canonicalize(&folder) {
+                    Ok(path) => Some(path.to_string_lossy().to_string()),
+                    Err(_err) => Some(folder.to_string_lossy().to_string()),
+                };
+            }
+        }
+    }
+    None
+}
+
+/// Returns manga id from `_metadata` of the first chapter in `folder` or in its subfolders.
+fn folder_manga_id(folder: &Path) -> Option<String> {
+    WalkDir::new(folder)
+        .max_depth(3)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.')
+        })
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file() && is_cbz(entry.path()))
+        .find_map(|entry| resolute::check_for_metadata(&entry.path().to_string_lossy()).ok())
+        .map(|metadata| metadata.manga_id)
+}
+
+fn is_cbz(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("cbz"))
+}
+
+/// Returns manga folders in `root` with `.cbz` files in them and their subfolders.
+///
+/// Folders starting with `.` (e.g. `.cache`) are skipped.
+fn find_series(root: &Path) -> BTreeMap<PathBuf, Vec<String>> {
+    let single = match fs::read_dir(root) {
+        Ok(entries) => entries.filter_map(Result::ok).any(|entry| is_cbz(&entry.path())),
+        Err(_err) => false,
+    };
+    let mut series: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+    let entries = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.')
+        })
+        .filter_map(Result::ok);
+    for entry in entries {
+        let path = entry.path();
+        if !entry.file_type().is_file() || !is_cbz(path) {
+            continue;
+        }
+        let folder = match path.strip_prefix(root).ok().and_then(|rel| rel.components().next()) {
+            Some(first) if !single && entry.depth() > 1 => root.join(first),
+            _ => root.to_path_buf(),
+        };
+        series.entry(folder).or_default().push(path.to_string_lossy().to_string());
+    }
+    series
+}
+
+/// Imports one manga folder; returns false if the manga or none of its chapters were found.
+async fn import_series(
+    folder: &str,
+    paths: &[String],
+    dat: &mut Dat,
+    dry_run: bool
+) -> Result<bool, MdownError> {
+    let archives: Vec<Archive> = paths
+        .iter()
+        .map(|path| Archive::read(path))
+        .collect();
+
+    let manga_ids = archives
+        .iter()
+        .filter_map(|archive| archive.metadata.as_ref().map(|metadata| metadata.manga_id.clone()));
+    let manga_id = match most_common(manga_ids) {
+        Some(id) => id,
+        None => {
+            let folder_name = Path::new(folder)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let mut titles: Vec<String> = archives
+                .iter()
+                .filter_map(|archive| archive.series.clone())
+                .collect();
+            titles.push(folder_name);
+            let mut seen = HashSet::new();
+            titles.retain(|title| seen.insert(title.clone()));
+            let mut found = None;
+            for title in titles.iter() {
+                match search_manga(title).await {
+                    Ok(Some(id)) => {
+                        found = Some(id);
+                        break;
+                    }
+                    Ok(None) => debug!("no manga found for {}", title),
+                    Err(err) => suspend_error(MdownError::ChainedError(Box::new(err), 12407)),
+                }
+            }
+            match found {
+                Some(id) => id,
+                None => {
+                    println!("{}: no matching manga found on mangadex", folder);
+                    return Ok(false);
+                }
+            }
+        }
+    };
+
+    let manga_json = match getter::get_manga_json(&manga_id).await {
+        Ok(json) => json,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12408));
+        }
+    };
+    let manga_value = match utils::get_json(&manga_json) {
+        Ok(value) => value,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12409));
+        }
+    };
+    let data = match manga_value.get("data") {
+        Some(data) => data,
+        None => {
+            return Err(MdownError::NotFoundError(String::from("data in import_series"), 12410));
+        }
+    };
+    let title_data = match data.get("attributes") {
+        Some(attributes) => attributes,
+        None => {
+            return Err(
+                MdownError::NotFoundError(String::from("attributes in import_series"), 12411)
+            );
+        }
+    };
+    let feed = match get_feed(&manga_id).await {
+        Ok(feed) => feed,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12412));
+        }
+    };
+
+    let languages = archives.iter().filter_map(|archive| archive.language.clone());
+    let language = match most_common(languages) {
+        Some(language) => language,
+        None => args::ARGS.lock().lang.clone(),
+    };
+    let mut used = HashSet::new();
+    let mut matched = Vec::new();
+    let mut unmatched = Vec::new();
+    for archive in archives.iter() {
+        match find_chapter(archive, &feed, &language, &used) {
+            Some(chapter) => {
+                used.insert(chapter.id.clone());
+                matched.push((archive, chapter));
+            }
+            None => unmatched.push(archive.path.clone()),
+        }
+    }
+
+    *resolute::LANGUAGE.lock() = language.clone();
+    let name = getter::get_manga_name(title_data);
+    if matched.is_empty() {
+        println!("{}: no chapters of {} ({}) were found on mangadex", folder, name, manga_id);
+        return Ok(false);
+    }
+    let chapters: Vec<ChapterMetadata> = matched
+        .iter()
+        .map(|(_, chapter)| {
+            ChapterMetadata::new(
+                chapter.attributes.chapter.as_deref().unwrap_or_default(),
+                &chapter.attributes.updatedAt,
+                &chapter.id
+            )
+        })
+        .collect();
+
+    println!(
+        "{} {} ({}) from {}: {} of {} chapters",
+        if dry_run { "Would import" } else { "Importing" },
+        name,
+        manga_id,
+        folder,
+        matched.len(),
+        archives.len()
+    );
+    for path in unmatched.iter() {
+        println!("  not matched: {}", path);
+    }
+    if dry_run {
+        return Ok(true);
+    }
+
+    for (archive, chapter) in matched.iter() {
+        if archive.metadata.is_some() {
+            continue;
+        }
+        if let Err(err) = write_metadata(&archive.path, chapter, &name, &manga_id).await {
+            suspend_error(MdownError::ChainedError(Box::new(err), 12413));
+        }
+    }
+
+    match dat.data.iter_mut().find(|item| item.id == manga_id) {
+        Some(item) => {
+            let mut added = 0;
+            for chapter in chapters {
+                if !item.chapters.iter().any(|existing| existing.id == chapter.id) {
+                    item.chapters.push(chapter);
+                    added += 1;
+                }
+            }
+            println!("  {} is already in database; added {} chapters", item.name, added);
+        }
+        None => {
+            let mwd = match fs::canonicalize(folder) {
+                Ok(path) => path.to_string_lossy().to_string(),
+                Err(err) => {
+                    return Err(MdownError::IoError(err, folder.to_string(), 12414));
+                }
+            };
+            let long_strip = resolute::resolve_theme_genre(title_data);
+            let available_languages = title_data
+                .get("availableTranslatedLanguages")
+                .and_then(Value::as_array)
+                .map(|languages| {
+                    languages
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default();
+            dat.data.push(MangaMetadata {
+                name,
+                id: manga_id.clone(),
+                chapters,
+                cover: fs::metadata(format!("{}\\_cover.png", mwd)).is_ok(),
+                mwd,
+                date: vec![chrono::Utc::now().naive_utc().format("%Y-%m-%d %H:%M:%S").to_string()],
+                available_languages,
+                current_language: language,
+                theme: std::mem::take(&mut *resolute::THEMES.lock()),
+                genre: std::mem::take(&mut *resolute::GENRES.lock()),
+                links: title_data
+                    .get("links")
+                    .and_then(|links| serde_json::from_value(links.clone()).ok())
+                    .unwrap_or_default(),
+                long_strip,
+                template: String::new(),
+                covers: Vec::new(),
+                groups: Vec::new(),
+                details: getter::get_manga_details(data),
+            });
+        }
+    }
+    Ok(true)
+}
+
+/// Returns chapter of `feed` which `archive` contains.
+///
+/// Chapter is found by its id, otherwise by its number in `language`; chapter in the same volume
+/// is preferred. Chapters in `used` were already matched to other archives.
+fn find_chapter<'a>(
+    archive: &Archive,
+    feed: &'a [ChapterResponse],
+    language: &str,
+    used: &HashSet<String>
+) -> Option<&'a ChapterResponse> {
+    if let Some(id) = &archive.chapter_id {
+        if let Some(chapter) = feed.iter().find(|chapter| &chapter.id == id) {
+            return Some(chapter);
+        }
+    }
+    let number = archive.number.as_ref()?;
+    let candidates: Vec<&ChapterResponse> = feed
+        .iter()
+        .filter(|chapter| !used.contains(&chapter.id))
+        .filter(|chapter| chapter.attributes.chapter.as_ref() == Some(number))
+        .filter(|chapter| {
+            language == "*" || chapter.attributes.translatedLanguage.as_deref() == Some(language)
+        })
+        .collect();
+    candidates
+        .iter()
+        .find(|chapter| {
+            archive.volume.is_some() && chapter.attributes.volume == archive.volume
+        })
+        .or(candidates.first())
+        .copied()
+}
+
+/// Returns id of manga on mangadex with `title` as its title or one of its alternative titles.
+async fn search_manga(title: &str) -> Result<Option<String>, MdownError> {
+    let url = match
+        url::Url::parse_with_params(
+            "https://api.mangadex.org/manga",
+            &[
+                ("title", title),
+                ("limit", "10"),
+            ]
+        )
+    {
+        Ok(url) => url,
+        Err(err) => {
+            return Err(MdownError::ConversionError(err.to_string(), 12415));
+        }
+    };
+    let json = match http_cache::get(url.as_str()).await {
+        Ok(json) => json,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12416));
+        }
+    };
+    let value = match utils::get_json(&json) {
+        Ok(value) => value,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12417));
+        }
+    };
+    let wanted = normalize_title(title);
+    for manga in value.get("data").and_then(Value::as_array).into_iter().flatten() {
+        let attributes = &manga["attributes"];
+        let mut titles: Vec<&Value> = attributes["title"]
+            .as_object()
+            .map(|titles| titles.values().collect())
+            .unwrap_or_default();
+        for alt_title in attributes["altTitles"].as_array().into_iter().flatten() {
+            if let Some(alt_title) = alt_title.as_object() {
+                titles.extend(alt_title.values());
+            }
+        }
+        let found = titles
+            .iter()
+            .filter_map(|title| title.as_str())
+            .any(|title| {
+                normalize_title(title) == wanted ||
+                    normalize_title(&sanitize::manga_name(title)) == wanted
+            });
+        if found {
+            return Ok(manga["id"].as_str().map(String::from));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns all chapters of manga `id` in all languages.
+pub(crate) async fn get_feed(id: &str) -> Result<Vec<ChapterResponse>, MdownError> {
+    let mut chapters = Vec::new();
+    loop {
+        // Same url as in `getter::get_manga`, so the cached feed is shared
+        let url = format!(
+            "https://api.mangadex.org/manga/{}/feed?limit={}&offset={}",
+            id,
+            500,
+            chapters.len()
+        );
+        let json = match http_cache::get(&url).await {
+            Ok(json) => json,
+            Err(err) => {
+                return Err(MdownError::ChainedError(Box::new(err), 12418));
+            }
+        };
+        let value = match utils::get_json(&json) {
+            Ok(value) => value,
+            Err(err) => {
+                return Err(MdownError::ChainedError(Box::new(err), 12419));
+            }
+        };
+        let page = match serde_json::from_value::<MangaResponse>(value) {
+            Ok(page) => page,
+            Err(err) => {
+                return Err(MdownError::JsonError(err.to_string(), 12420));
+            }
+        };
+        let count = page.data.len();
+        chapters.extend(page.data);
+        if count == 0 || (chapters.len() as u64) >= page.total {
+            break;
+        }
+    }
+    Ok(chapters)
+}
+
+/// Adds `_metadata` of `chapter` to the archive at `path`.
+async fn write_metadata(
+    path: &str,
+    chapter: &ChapterResponse,
+    manga_name: &str,
+    manga_id: &str
+) -> Result<(), MdownError> {
+    let scanlation = match resolute::resolve_group(chapter).await {
+        Ok(scanlation) => scanlation,
+        Err(err) => {
+            suspend_error(MdownError::ChainedError(Box::new(err), 12421));
+            Default::default()
+        }
+    };
+    let (attributes, _, pages, number, title) = getter::get_metadata(chapter);
+    let metadata = ChapterMetadataIn {
+        name: manga_name.to_string(),
+        id: chapter.id.clone(),
+        manga_id: manga_id.to_string(),
+        saver: args::ARGS.lock().saver,
+        title,
+        pages: pages.to_string(),
+        chapter: number,
+        volume: match attributes.volume.unwrap_or_default().as_str() {
+            "" => String::new(),
+            volume => format!("Vol.{} ", volume),
+        },
+        scanlation,
+    };
+    let json = match serde_json::to_string_pretty(&metadata) {
+        Ok(json) => json,
+        Err(err) => {
+            return Err(MdownError::JsonError(err.to_string(), 12422));
+        }
+    };
+    match zip_func::add_file_to_zip(path, "_metadata", json.as_bytes()) {
+        Ok(()) => Ok(()),
+        Err(err) => Err(MdownError::ChainedError(Box::new(err), 12423)),
+    }
+}
+
+/// Returns chapter number and volume from file name of an archive, e.g. `Vol.2 Ch.12.5 - Title`
+/// or `Title v02 c012`.
+///
+/// If there is no `Ch.`, `Chapter` or `c` before any number, the last number which is not a volume
+/// is used.
+fn parse_file_name(stem: &str) -> (Option<String>, Option<String>) {
+    let number = r"(\d+(?:\.\d+)?)";
+    let (volume_re, chapter_re, any_re) = match
+        (
+            Regex::new(&format!(r"(?i)\b(?:vol(?:ume)?\.?|v)\s*{}", number)),
+            Regex::new(&format!(r"(?i)\b(?:ch(?:apter)?\.?|c)\s*{}", number)),
+            Regex::new(number),
+        )
+    {
+        (Ok(volume_re), Ok(chapter_re), Ok(any_re)) => (volume_re, chapter_re, any_re),
+        _ => {
+            return (None, None);
+        }
+    };
+    let volume_match = volume_re.captures(stem);
+    let volume = volume_match.as_ref().map(|cap| normalize_number(&cap[1]));
+    let chapter = match chapter_re.captures(stem) {
+        Some(cap) => Some(normalize_number(&cap[1])),
+        None => {
+            let rest = match &volume_match {
+                Some(cap) => stem.replacen(&cap[0], "", 1),
+                None => stem.to_string(),
+            };
+            any_re
+                .find_iter(&rest)
+                .last()
+                .map(|found| normalize_number(found.as_str()))
+        }
+    };
+    (chapter, volume)
+}
+
+/// Removes leading zeros of a chapter or volume number, e.g. `012.5` becomes `12.5`.
+fn normalize_number(number: &str) -> String {
+    let number = number.trim();
+    let (integer, fraction) = match number.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (number, None),
+    };
+    let integer = match integer.trim_start_matches('0') {
+        "" => "0",
+        integer => integer,
+    };
+    match fraction {
+        Some(fraction) => format!("{}.{}", integer, fraction),
+        None => integer.to_string(),
+    }
+}
+
+/// Lowercases `title` and leaves only letters and digits, so titles differing in punctuation match.
+fn normalize_title(title: &str) -> String {
+    title
+        .chars()
+        .filter(|ch| ch.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Returns the most common value of `values`; on a tie the one which came first.
+fn most_common(values: impl Iterator<Item = String>) -> Option<String> {
+    let mut counts: HashMap<String, (usize, usize)> = HashMap::new();
+    for (index, value) in values.enumerate() {
+        counts.entry(value).or_insert((0, index)).0 += 1;
+    }
+    counts
+        .into_iter()
+        .max_by(|(_, (count_a, index_a)), (_, (count_b, index_b))| {
+            count_a.cmp(count_b).then(index_b.cmp(index_a))
+        })
+        .map(|(value, _)| value)
+}
+
+// Test of reading chapter numbers and volumes from file names
+#[test]
+fn test_parse_file_name() {
+    let parse = |stem: &str| {
+        let (chapter, volume) = parse_file_name(stem);
+        (chapter.unwrap_or_default(), volume.unwrap_or_default())
+    };
+    assert_eq!(parse("Vol.2 Ch.12.5 - Title"), (String::from("12.5"), String::from("2")));
+    assert_eq!(parse("Kaiju No. 8 v03 c012"), (String::from("12"), String::from("3")));
+    assert_eq!(parse("Chapter 007"), (String::from("7"), String::new()));
+    assert_eq!(parse("Kaiju No. 8 - 045"), (String::from("45"), String::new()));
+    assert_eq!(parse("Volume 1 - 3"), (String::from("3"), String::from("1")));
+}
+
+// Test of matching titles which differ in punctuation and case
+#[test]
+fn test_normalize_title() {
+    assert_eq!(normalize_title("Kaguya-sama: Love Is War"), "kaguyasamaloveiswar");
+    assert_eq!(normalize_title("Ōkami"), "ōkami");
+    assert_ne!(normalize_title("One Piece"), normalize_title("One Punch"));
+}
+
+// Test of splitting library roots and skipping empty ones
+#[test]
+fn test_split_roots() {
+    assert_eq!(split_roots(" D:\\manga ;;E:\\manga;"), vec!["D:\\manga", "E:\\manga"]);
+    assert!(split_roots("").is_empty());
+}
@@ -0,0 +1,675 @@
+//! Library index stored in the database.
+//!
+//! Manga, their chapters, tags, links and scanlation groups are stored in normalised tables of
+//! `resources.db`. The schema is created by versioned migrations; `PRAGMA user_version` holds the
+//! number of applied ones. Every change of the library is written in a single transaction, so a
+//! crash never leaves the index half-written.
+//!
+//! Server and cli may change the library at the same time, so saving writes only the manga that
+//! differ from the database and removes only the ones this process has loaded before; a manga added
+//! by another process in the meantime is kept.
+//!
+//! `dat.json` written by older versions is imported when the index is opened for the first time and
+//! renamed to `dat.json.bak`. JSON in the same format is still available as an export, see
+//! `export_json`, and backups are made from it.
+
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use rusqlite::{ Connection, OptionalExtension, Transaction, TransactionBehavior, params };
+use serde_json::{ Map, Value };
+use std::{ collections::{ HashMap, HashSet }, fs, time::Duration };
+
+use crate::{
+    debug,
+    error::MdownError,
+    getter,
+    metadata::{ ChapterMetadata, CoverMetadata, Dat, MangaMetadata, TagMetadata },
+    resolute,
+    version_manager::get_current_version,
+};
+
+/// Schema migrations in order; a migration is never changed once released, new one is added
+/// instead.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE library_info (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE manga (
+        id TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        mwd TEXT NOT NULL,
+        cover INTEGER NOT NULL,
+        current_language TEXT NOT NULL,
+        long_strip INTEGER NOT NULL,
+        template TEXT NOT NULL,
+        year INTEGER,
+        status TEXT NOT NULL,
+        demographic TEXT NOT NULL,
+        original_language TEXT NOT NULL
+    );
+    CREATE TABLE chapters (
+        manga_id TEXT NOT NULL REFERENCES manga(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        id TEXT NOT NULL,
+        number TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        PRIMARY KEY (manga_id, position)
+    );
+    CREATE INDEX chapters_id ON chapters(id);
+    CREATE TABLE tags (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL
+    );
+    CREATE TABLE manga_tags (
+        manga_id TEXT NOT NULL REFERENCES manga(id) ON DELETE CASCADE,
+        kind TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        tag_id TEXT NOT NULL REFERENCES tags(id),
+        PRIMARY KEY (manga_id, kind, position)
+    );
+    CREATE TABLE links (
+        manga_id TEXT NOT NULL REFERENCES manga(id) ON DELETE CASCADE,
+        site TEXT NOT NULL,
+        url TEXT NOT NULL,
+        PRIMARY KEY (manga_id, site)
+    );
+    CREATE TABLE manga_values (
+        manga_id TEXT NOT NULL REFERENCES manga(id) ON DELETE CASCADE,
+        kind TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (manga_id, kind, position)
+    );
+    CREATE TABLE covers (
+        manga_id TEXT NOT NULL REFERENCES manga(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        volume TEXT NOT NULL,
+        locale TEXT NOT NULL,
+        file TEXT NOT NULL,
+        PRIMARY KEY (manga_id, position)
+    );
+    CREATE TABLE scanlation_groups (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        website TEXT NOT NULL
+    );",
+    "ALTER TABLE chapters ADD COLUMN removed INTEGER NOT NULL DEFAULT 0;",
+];
+
+/// Kinds of list values of manga stored in `manga_values`.
+const DATE: &str = "date";
+const LANGUAGE: &str = "language";
+const AUTHOR: &str = "author";
+const ARTIST: &str = "artist";
+const ALT_TITLE: &str = "alt_title";
+const GROUP: &str = "group";
+
+/// Kinds of tags stored in `manga_tags`.
+const THEME: &str = "theme";
+const GENRE: &str = "genre";
+
+lazy_static! {
+    /// Ids of manga this process has read from the library; only these are removed when missing on save.
+    static ref LOADED: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+/// Opens the database and applies missing migrations; `dat.json` of older versions is imported
+/// the first time.
+fn open() -> Result<Connection, MdownError> {
+    let db_path = match getter::get_db_path() {
+        Ok(path) => path,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12500));
+        }
+    };
+    let mut conn = match Connection::open(&db_path) {
+        Ok(conn) => conn,
+        Err(err) => {
+            return Err(MdownError::DatabaseError(err, 12501));
+        }
+    };
+    // Server and cli may use the library at the same time
+    let result = conn
+        .busy_timeout(Duration::from_secs(10))
+        .and_then(|()| conn.pragma_update(None, "foreign_keys", "ON"));
+    if let Err(err) = result {
+        return Err(MdownError::DatabaseError(err, 12502));
+    }
+    if let Err(err) = migrate(&mut conn) {
+        return Err(MdownError::ChainedError(Box::new(err), 12503));
+    }
+    Ok(conn)
+}
+
+/// Applies migrations newer than `user_version` of the database, each in its own transaction.
+fn migrate(conn: &mut Connection) -> Result<(), MdownError> {
+    let version = match conn.pragma_query_value(None, "user_version", |row| row.get::<_, usize>(0)) {
+        Ok(version) => version,
+        Err(err) => {
+            return Err(MdownError::DatabaseError(err, 12504));
+        }
+    };
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        debug!("migrating library to version {}", index + 1);
+        let result = conn.transaction().and_then(|tx| {
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", index + 1)?;
+            tx.commit()
+        });
+        if let Err(err) = result {
+            return Err(MdownError::DatabaseError(err, 12505));
+        }
+    }
+
+    let initialised = conn
+        .query_row("SELECT value FROM library_info WHERE key = 'version'", [], |row|
+            row.get::<_, String>(0)
+        )
+        .optional();
+    match initialised {
+        Ok(Some(_version)) => Ok(()),
+        Ok(None) => import_dat_json(conn),
+        Err(err) => Err(MdownError::DatabaseError(err, 12506)),
+    }
+}
+
+/// Imports `dat.json` written by older versions into the empty index and renames it to
+/// `dat.json.bak`; without `dat.json` an empty library is created.
+fn import_dat_json(conn: &mut Connection) -> Result<(), MdownError> {
+    let dat_path = match getter::get_dat_path() {
+        Ok(path) => path,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12507));
+        }
+    };
+    let exists = fs::metadata(&dat_path).is_ok();
+    let mut dat = if exists {
+        let json = match resolute::get_dat_content(&dat_path) {
+            Ok(json) => json,
+            Err(err) => {
+                return Err(MdownError::ChainedError(Box::new(err), 12508));
+            }
+        };
+        match serde_json::from_value::<Dat>(json) {
+            Ok(dat) => dat,
+            Err(err) => {
+                return Err(MdownError::JsonError(err.to_string(), 12509));
+            }
+        }
+    } else {
+        Dat { data: Vec::new(), version: get_current_version() }
+    };
+
+    // dat.json was never checked for duplicates, first entry is kept like in resolute::resolve_dat
+    let mut seen = HashSet::new();
+    dat.data.retain(|item| {
+        let first = seen.insert(item.id.clone());
+        if !first {
+            println!("Skipping duplicate entry of {} ({}) in dat.json", item.name, item.id);
+        }
+        first
+    });
+
+    if let Err(err) = write(conn, &dat, None) {
+        return Err(MdownError::ChainedError(Box::new(err), 12510));
+    }
+    if exists {
+        let backup_path = format!("{}.bak", dat_path);
+        if let Err(err) = fs::rename(&dat_path, &backup_path) {
+            return Err(MdownError::IoError(err, dat_path, 12511));
+        }
+        println!("Library index moved from dat.json to the database, old file is {}", backup_path);
+    }
+    Ok(())
+}
+
+/// Reads the whole library.
+///
+/// # Errors
+/// - `MdownError::ChainedError(12500)`, `MdownError::DatabaseError(12501..=12506)`: If the database
+///   can't be opened or migrated.
+/// - `MdownError::DatabaseError(12512)`: If the index can't be read.
+pub(crate) fn load() -> Result<Dat, MdownError> {
+    let conn = open()?;
+    match read(&conn) {
+        Ok(dat) => {
+            LOADED.lock().extend(dat.data.iter().map(|item| item.id.clone()));
+            Ok(dat)
+        }
+        Err(err) => Err(MdownError::DatabaseError(err, 12512)),
+    }
+}
+
+/// Writes the manga of `dat` that changed and removes the loaded ones it no longer contains, in a
+/// single transaction.
+///
+/// # Errors
+/// - `MdownError::ChainedError(12500)`, `MdownError::DatabaseError(12501..=12506)`: If the database
+///   can't be opened or migrated.
+/// - `MdownError::DatabaseError(12513)`: If the index can't be written, e.g. if two entries have
+///   the same id; the library is left unchanged.
+pub(crate) fn save(dat: &Dat) -> Result<(), MdownError> {
+    let mut conn = open()?;
+    let mut loaded = LOADED.lock();
+    write(&mut conn, dat, Some(&loaded))?;
+    *loaded = dat.data
+        .iter()
+        .map(|item| item.id.clone())
+        .collect();
+    Ok(())
+}
+
+/// Replaces the whole library with `dat` in a single transaction, e.g. when a backup is restored.
+///
+/// # Errors
+/// Same as `save`.
+pub(crate) fn replace(dat: &Dat) -> Result<(), MdownError> {
+    let mut conn = open()?;
+    write(&mut conn, dat, None)?;
+    *LOADED.lock() = dat.data
+        .iter()
+        .map(|item| item.id.clone())
+        .collect();
+    Ok(())
+}
+
+/// Removes all manga from the library.
+pub(crate) fn clear() -> Result<(), MdownError> {
+    let mut conn = open()?;
+    let result = conn.transaction().and_then(|tx| {
+        tx.execute("DELETE FROM manga", [])?;
+        tx.commit()
+    });
+    match result {
+        Ok(()) => Ok(()),
+        Err(err) => Err(MdownError::DatabaseError(err, 12514)),
+    }
+}
+
+/// Returns the library in the format of `dat.json`.
+pub(crate) fn export_json() -> Result<String, MdownError> {
+    let dat = load()?;
+    match serde_json::to_string_pretty(&dat) {
+        Ok(json) => Ok(format!("{}\n", json)),
+        Err(err) => Err(MdownError::JsonError(err.to_string(), 12515)),
+    }
+}
+
+/// Remembers name and website of scanlation group `id`.
+pub(crate) fn save_group(id: &str, name: &str, website: &str) -> Result<(), MdownError> {
+    let conn = open()?;
+    match
+        conn.execute(
+            "INSERT INTO scanlation_groups (id, name, website) VALUES (?1, ?2, ?3)
+            ON CONFLICT(id) DO UPDATE SET name = excluded.name, website = excluded.website",
+            params![id, name, website]
+        )
+    {
+        Ok(_rows) => Ok(()),
+        Err(err) => Err(MdownError::DatabaseError(err, 12516)),
+    }
+}
+
+/// Returns name and website of scanlation group `id` if it was resolved before.
+pub(crate) fn get_group(id: &str) -> Result<Option<(String, String)>, MdownError> {
+    let conn = open()?;
+    match
+        conn
+            .query_row(
+                "SELECT name, website FROM scanlation_groups WHERE id = ?1",
+                params![id],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            )
+            .optional()
+    {
+        Ok(group) => Ok(group),
+        Err(err) => Err(MdownError::DatabaseError(err, 12517)),
+    }
+}
+
+/// Writes `dat` to the library. Manga missing from `dat` are removed if their id is in `loaded`,
+/// or all of them if `loaded` is `None`.
+fn write(
+    conn: &mut Connection,
+    dat: &Dat,
+    loaded: Option<&HashSet<String>>
+) -> Result<(), MdownError> {
+    // Immediate transaction holds the write lock from reading the stored manga until commit
+    let result = conn.transaction_with_behavior(TransactionBehavior::Immediate).and_then(|tx| {
+        let stored = read(&tx)?
+            .data.into_iter()
+            .map(|item| (item.id.clone(), item))
+            .collect::<HashMap<_, _>>();
+        let mut ids = HashSet::new();
+        for (position, item) in dat.data.iter().enumerate() {
+            if !ids.insert(item.id.as_str()) {
+                // Second entry with the same id is rejected by the primary key
+                insert_manga(&tx, position, item)?;
+            } else if stored.get(&item.id) == Some(item) {
+                tx.execute(
+                    "UPDATE manga SET position = ?2 WHERE id = ?1",
+                    params![item.id, position]
+                )?;
+            } else {
+                tx.execute("DELETE FROM manga WHERE id = ?1", params![item.id])?;
+                insert_manga(&tx, position, item)?;
+            }
+        }
+        for id in stored.keys() {
+            let removed = loaded.is_none_or(|loaded| loaded.contains(id));
+            if removed && !ids.contains(id.as_str()) {
+                tx.execute("DELETE FROM manga WHERE id = ?1", params![id])?;
+            }
+        }
+        tx.execute("DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM manga_tags)", [])?;
+        tx.execute(
+            "INSERT INTO library_info (key, value) VALUES ('version', ?1)
+            ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![dat.version]
+        )?;
+        tx.commit()
+    });
+    match result {
+        Ok(()) => Ok(()),
+        Err(err) => Err(MdownError::DatabaseError(err, 12513)),
+    }
+}
+
+fn insert_manga(tx: &Transaction, position: usize, item: &MangaMetadata) -> rusqlite::Result<()> {
+    let details = &item.details;
+    tx.prepare_cached(
+        "INSERT INTO manga (id, position, name, mwd, cover, current_language, long_strip, template,
+        year, status, demographic, original_language)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"
+    )?.execute(
+        params![
+            item.id,
+            position,
+            item.name,
+            item.mwd,
+            item.cover,
+            item.current_language,
+            item.long_strip,
+            item.template,
+            details.year,
+            details.status,
+            details.demographic,
+            details.original_language
+        ]
+    )?;
+
+    let mut chapter_stmt = tx.prepare_cached(
+        "INSERT INTO chapters (manga_id, position, id, number, updated_at, removed)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)"
+    )?;
+    for (position, chapter) in item.chapters.iter().enumerate() {
+        chapter_stmt.execute(
+            params![
+                item.id,
+                position,
+                chapter.id,
+                chapter.number,
+                chapter.updated_at,
+                chapter.removed
+            ]
+        )?;
+    }
+
+    let mut tag_stmt = tx.prepare_cached(
+        "INSERT INTO tags (id, name) VALUES (?1, ?2)
+        ON CONFLICT(id) DO UPDATE SET name = excluded.name"
+    )?;
+    let mut manga_tag_stmt = tx.prepare_cached(
+        "INSERT INTO manga_tags (manga_id, kind, position, tag_id) VALUES (?1, ?2, ?3, ?4)"
+    )?;
+    for (kind, tags) in [
+        (THEME, &item.theme),
+        (GENRE, &item.genre),
+    ] {
+        for (position, tag) in tags.iter().enumerate() {
+            tag_stmt.execute(params![tag.id, tag.name])?;
+            manga_tag_stmt.execute(params![item.id, kind, position, tag.id])?;
+        }
+    }
+
+    let mut value_stmt = tx.prepare_cached(
+        "INSERT INTO manga_values (manga_id, kind, position, value) VALUES (?1, ?2, ?3, ?4)"
+    )?;
+    for (kind, values) in [
+        (DATE, &item.date),
+        (LANGUAGE, &item.available_languages),
+        (AUTHOR, &details.authors),
+        (ARTIST, &details.artists),
+        (ALT_TITLE, &details.alt_titles),
+        (GROUP, &item.groups),
+    ] {
+        for (position, value) in values.iter().enumerate() {
+            value_stmt.execute(params![item.id, kind, position, value])?;
+        }
+    }
+
+    // Links are stored by their key in `LinksMetadata`, so new sites don't need a migration
+    if let Ok(Value::Object(links)) = serde_json::to_value(&item.links) {
+        let mut link_stmt = tx.prepare_cached(
+            "INSERT INTO links (manga_id, site, url) VALUES (?1, ?2, ?3)"
+        )?;
+        for (site, url) in links.iter() {
+            if let Some(url) = url.as_str() {
+                link_stmt.execute(params![item.id, site, url])?;
+            }
+        }
+    }
+
+    let mut cover_stmt = tx.prepare_cached(
+        "INSERT INTO covers (manga_id, position, volume, locale, file) VALUES (?1, ?2, ?3, ?4, ?5)"
+    )?;
+    for (position, cover) in item.covers.iter().enumerate() {
+        cover_stmt.execute(params![item.id, position, cover.volume, cover.locale, cover.file])?;
+    }
+    Ok(())
+}
+
+fn read(conn: &Connection) -> rusqlite::Result<Dat> {
+    let version = conn
+        .query_row("SELECT value FROM library_info WHERE key = 'version'", [], |row| row.get(0))
+        .optional()?
+        .unwrap_or_else(get_current_version);
+
+    let mut stmt = conn.prepare(
+        "SELECT id, name, mwd, cover, current_language, long_strip, template, year, status,
+        demographic, original_language FROM manga ORDER BY position"
+    )?;
+    let mut data = stmt
+        .query_map([], |row| {
+            let mut item = MangaMetadata {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                chapters: Vec::new(),
+                mwd: row.get(2)?,
+                cover: row.get(3)?,
+                date: Vec::new(),
+                available_languages: Vec::new(),
+                current_language: row.get(4)?,
+                theme: Vec::new(),
+                genre: Vec::new(),
+                links: Default::default(),
+                long_strip: row.get(5)?,
+                template: row.get(6)?,
+                covers: Vec::new(),
+                groups: Vec::new(),
+                details: Default::default(),
+            };
+            item.details.year = row.get(7)?;
+            item.details.status = row.get(8)?;
+            item.details.demographic = row.get(9)?;
+            item.details.original_language = row.get(10)?;
+            Ok(item)
+        })?
+        .collect::<rusqlite::Result<Vec<MangaMetadata>>>()?;
+
+    let mut chapter_stmt = conn.prepare(
+        "SELECT id, number, updated_at, removed FROM chapters WHERE manga_id = ?1 ORDER BY position"
+    )?;
+    let mut tag_stmt = conn.prepare(
+        "SELECT tags.id, tags.name FROM manga_tags JOIN tags ON tags.id = manga_tags.tag_id
+        WHERE manga_tags.manga_id = ?1 AND manga_tags.kind = ?2 ORDER BY manga_tags.position"
+    )?;
+    let mut value_stmt = conn.prepare(
+        "SELECT value FROM manga_values WHERE manga_id = ?1 AND kind = ?2 ORDER BY position"
+    )?;
+    let mut link_stmt = conn.prepare("SELECT site, url FROM links WHERE manga_id = ?1")?;
+    let mut cover_stmt = conn.prepare(
+        "SELECT volume, locale, file FROM covers WHERE manga_id = ?1 ORDER BY position"
+    )?;
+    for item in data.iter_mut() {
+        item.chapters = chapter_stmt
+            .query_map(params![item.id], |row| {
+                Ok(ChapterMetadata {
+                    id: row.get(0)?,
+                    number: row.get(1)?,
+                    updated_at: row.get(2)?,
+                    removed: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        for (kind, tags) in [
+            (THEME, &mut item.theme),
+            (GENRE, &mut item.genre),
+        ] {
+            *tags = tag_stmt
+                .query_map(params![item.id, kind], |row| {
+                    Ok(TagMetadata { id: row.get(0)?, name: row.get(1)? })
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+        }
+
+        for (kind, values) in [
+            (DATE, &mut item.date),
+            (LANGUAGE, &mut item.available_languages),
+            (AUTHOR, &mut item.details.authors),
+            (ARTIST, &mut item.details.artists),
+            (ALT_TITLE, &mut item.details.alt_titles),
+            (GROUP, &mut item.groups),
+        ] {
+            *values = value_stmt
+                .query_map(params![item.id, kind], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+        }
+
+        let links = link_stmt
+            .query_map(params![item.id], |row| {
+                Ok((row.get::<_, String>(0)?, Value::String(row.get(1)?)))
+            })?
+            .collect::<rusqlite::Result<Map<String, Value>>>()?;
+        if let Ok(links) = serde_json::from_value(Value::Object(links)) {
+            item.links = links;
+        }
+
+        item.covers = cover_stmt
+            .query_map(params![item.id], |row| {
+                Ok(CoverMetadata { volume: row.get(0)?, locale: row.get(1)?, file: row.get(2)? })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+    }
+    Ok(Dat { data, version })
+}
+
+// Tests that the library is read back the same as it was written
+#[test]
+fn test_write_read() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(MIGRATIONS.join("\n").as_str()).unwrap();
+    let mut item = MangaMetadata {
+        name: String::from("Name"),
+        id: String::from("id"),
+        chapters: vec![
+            ChapterMetadata::new("2", "2024-01-02T00:00:00", "b"),
+            ChapterMetadata::new("1", "2024-01-01T00:00:00", "a")
+        ],
+        mwd: String::from("C:\\manga\\Name"),
+        cover: true,
+        date: vec![String::from("2024-01-01")],
+        available_languages: vec![String::from("en"), String::from("ja")],
+        current_language: String::from("en"),
+        theme: vec![TagMetadata::new("Isekai", "t1")],
+        genre: vec![TagMetadata::new("Action", "g1"), TagMetadata::new("Drama", "g2")],
+        links: Default::default(),
+        long_strip: false,
+        template: String::new(),
+        covers: vec![CoverMetadata {
+            volume: String::from("1"),
+            locale: String::from("ja"),
+            file: String::from("covers\\1.jpg"),
+        }],
+        groups: vec![String::from("group")],
+        details: Default::default(),
+    };
+    item.links.mal = Some(String::from("123"));
+    item.chapters[1].removed = true;
+    item.details.authors = vec![String::from("Author")];
+    item.details.year = Some(2020);
+    let mut other = item.clone();
+    other.id = String::from("other");
+    other.genre.truncate(1);
+    let dat = Dat { data: vec![other, item], version: String::from("0.15.7") };
+    write(&mut conn, &dat, None).unwrap();
+    assert_eq!(read(&conn).unwrap(), dat);
+
+    // Duplicate ids are rejected and the library stays unchanged
+    let mut duplicate = dat.clone();
+    duplicate.data.push(dat.data[0].clone());
+    assert!(write(&mut conn, &duplicate, None).is_err());
+    assert_eq!(read(&conn).unwrap(), dat);
+}
+
+// Tests that saving keeps manga added by another writer and removes only the loaded ones
+#[test]
+fn test_write_concurrent() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(MIGRATIONS.join("\n").as_str()).unwrap();
+    let item = |id: &str| MangaMetadata {
+        name: id.to_string(),
+        id: id.to_string(),
+        chapters: vec![ChapterMetadata::new("1", "2024-01-01T00:00:00", "a")],
+        mwd: String::new(),
+        cover: false,
+        date: Vec::new(),
+        available_languages: Vec::new(),
+        current_language: String::from("en"),
+        theme: Vec::new(),
+        genre: vec![TagMetadata::new("Action", "g1")],
+        links: Default::default(),
+        long_strip: false,
+        template: String::new(),
+        covers: Vec::new(),
+        groups: Vec::new(),
+        details: Default::default(),
+    };
+    let dat = Dat { data: vec![item("a"), item("b")], version: String::from("0.15.7") };
+    write(&mut conn, &dat, None).unwrap();
+    let loaded = ["a", "b"].map(String::from).into_iter().collect::<HashSet<_>>();
+
+    // Another writer adds "c" after this one loaded the library
+    let mut other = read(&conn).unwrap();
+    other.data.push(item("c"));
+    write(&mut conn, &other, None).unwrap();
+
+    // This writer removes "a" and changes "b"
+    let mut changed = dat.clone();
+    changed.data.remove(0);
+    changed.data[0].name = String::from("B");
+    write(&mut conn, &changed, Some(&loaded)).unwrap();
+    let names = read(&conn)
+        .unwrap()
+        .data.into_iter()
+        .map(|item| item.name)
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["B", "c"]);
+
+    // Replacing removes every manga missing from the new library
+    write(&mut conn, &changed, None).unwrap();
+    assert_eq!(read(&conn).unwrap(), changed);
+}
@@ -20,6 +20,7 @@ macro_rules! log {
     ($message:expr) => {
         {
             tracing::info!("@{}  {}", $crate::resolute::HANDLE_ID.lock(), $message);
+            $crate::job::log($message);
             $crate::resolute::LOGS.lock().push($crate::metadata::Log::new($message));
         }
     };
@@ -73,36 +74,36 @@ macro_rules! debug {
 }
 
 #[macro_export]
-/// Retrieves the current saver setting, with optional inversion.
+/// Retrieves the saver setting of a job, with optional inversion.
 ///
-/// This macro determines the current data saver setting based on a locked state. It can optionally invert the result based on the provided boolean flag.
+/// This macro determines the data saver setting from the `saver` option of a job. It can optionally invert the result based on the provided boolean flag.
 ///
 /// # Parameters
 ///
-/// - `()` : No additional parameters. Returns the current saver setting.
-/// - `($invert:expr)` : A boolean flag to invert the saver setting if true.
+/// - `($saver:expr)` : The `saver` option of the job. Returns the saver setting.
+/// - `($saver:expr, $invert:expr)` : A boolean flag to invert the saver setting if true.
 ///
 /// # Examples
 ///
 /// ```rust
-/// let saver = get_saver!();
-/// let inverted_saver = get_saver!(true);
+/// let saver = get_saver!(job.options.saver);
+/// let inverted_saver = get_saver!(job.options.saver, true);
 /// ```
 macro_rules! get_saver {
-    () => {
-        match *$crate::resolute::SAVER.lock() {
+    ($saver:expr) => {
+        match $saver {
             true => $crate::metadata::Saver::dataSaver,
             false => $crate::metadata::Saver::data,
         }
     };
-    ($invert:expr) => {
+    ($saver:expr, $invert:expr) => {
         if $invert {
-            match *$crate::resolute::SAVER.lock() {
+            match $saver {
                 true => $crate::metadata::Saver::data,
                 false => $crate::metadata::Saver::dataSaver,
             }
         } else  {
-            match *$crate::resolute::SAVER.lock()  {
+            match $saver  {
                 true => $crate::metadata::Saver::dataSaver,
                 false => $crate::metadata::Saver::data,
             }
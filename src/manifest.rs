@@ -0,0 +1,445 @@
+//! Portable manifest of the library.
+//!
+//! `library export` writes tracked manga as JSON or CSV without absolute paths: folders are stored
+//! relative to the library root they are in. `library import-manifest` recreates the tracking on
+//! another machine, with folders under `--root`, and with `--download` downloads the chapters.
+//!
+//! CSV has one row per manga; lists are separated by `;`, chapters are written as `number:id` and
+//! links as `site=url`.
+
+use serde_json::{ Map, Value };
+use std::{ collections::HashMap, fs, path::{ Component, Path, PathBuf } };
+
+use crate::{
+    args,
+    db,
+    error::MdownError,
+    library,
+    library_db,
+    metadata::{ ChapterMetadata, Dat, LinksMetadata, MangaMetadata, Manifest, ManifestManga },
+    sanitize,
+    utils,
+    version_manager::get_current_version,
+};
+
+/// Columns of the CSV manifest.
+const CSV_HEADER: [&str; 9] = [
+    "id",
+    "name",
+    "language",
+    "folder",
+    "chapters",
+    "groups",
+    "links",
+    "long_strip",
+    "template",
+];
+
+/// Writes manifest of the library in `format` (`json` or `csv`) into `output`, or prints it.
+pub(crate) fn export(format: &str, output: Option<&str>) -> Result<(), MdownError> {
+    let dat = match library_db::load() {
+        Ok(dat) => dat,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12600));
+        }
+    };
+    let roots = match library::get_roots() {
+        Ok(roots) => roots,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12601));
+        }
+    };
+    let manifest = to_manifest(&dat, &roots);
+    let content = match format.to_ascii_lowercase().as_str() {
+        "json" =>
+            match serde_json::to_string_pretty(&manifest) {
+                Ok(json) => format!("{}\n", json),
+                Err(err) => {
+                    return Err(MdownError::JsonError(err.to_string(), 12602));
+                }
+            }
+        "csv" => to_csv(&manifest),
+        _ => {
+            return Err(
+                MdownError::CustomError(
+                    format!("Unknown manifest format {}; use json or csv", format),
+                    String::from("UserError"),
+                    12603
+                )
+            );
+        }
+    };
+    match output {
+        Some(output) => {
+            if let Err(err) = fs::write(output, content) {
+                return Err(MdownError::IoError(err, output.to_string(), 12604));
+            }
+            println!("Exported {} manga to {}", manifest.manga.len(), output);
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+/// Adds manga from manifest at `path` to the library, with folders under `root`.
+///
+/// Manifest is read as CSV if `path` ends with `.csv`, otherwise as JSON. Without `root` the first
+/// library root is used, or the current folder. Manga already in the library are skipped. With
+/// `--dry-run` nothing is written.
+///
+/// # Returns
+/// Ids of the imported manga; empty with `--dry-run`.
+pub(crate) fn import(path: &str, root: Option<&str>) -> Result<Vec<String>, MdownError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) => {
+            return Err(MdownError::IoError(err, path.to_string(), 12605));
+        }
+    };
+    let manifest = if path.to_ascii_lowercase().ends_with(".csv") {
+        match from_csv(&content) {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                return Err(MdownError::ChainedError(Box::new(err), 12606));
+            }
+        }
+    } else {
+        match serde_json::from_str::<Manifest>(&content) {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                return Err(MdownError::JsonError(err.to_string(), 12607));
+            }
+        }
+    };
+    let root = match root {
+        Some(root) => PathBuf::from(root),
+        None =>
+            match library::get_roots() {
+                Ok(roots) if !roots.is_empty() => PathBuf::from(&roots[0]),
+                Ok(_) => PathBuf::from("."),
+                Err(err) => {
+                    return Err(MdownError::ChainedError(Box::new(err), 12608));
+                }
+            }
+    };
+    let mut dat = match library_db::load() {
+        Ok(dat) => dat,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12609));
+        }
+    };
+
+    let dry_run = *args::ARGS_DRY_RUN;
+    let mut imported = 0;
+    let mut ids = Vec::new();
+    for manga in manifest.manga {
+        if !utils::is_valid_uuid(&manga.id) {
+            eprintln!("Error: {} is not a valid manga id, skipping", manga.id);
+            continue;
+        }
+        if dat.data.iter().any(|item| item.id == manga.id) {
+            println!("{} is already in library", manga.name);
+            continue;
+        }
+        let folder = root.join(folder_path(&manga));
+        if dry_run {
+            println!("Would import {} into {}", manga.name, folder.display());
+            imported += 1;
+            continue;
+        }
+        if let Err(err) = fs::create_dir_all(&folder) {
+            return Err(MdownError::IoError(err, folder.to_string_lossy().to_string(), 12610));
+        }
+        let mwd = match fs::canonicalize(&folder) {
+            Ok(path) => path.to_string_lossy().to_string(),
+            Err(_err) => folder.to_string_lossy().to_string(),
+        };
+        println!("{} -> {}", manga.name, mwd);
+        ids.push(manga.id.clone());
+        dat.data.push(MangaMetadata {
+            name: manga.name,
+            id: manga.id,
+            chapters: manga.chapters,
+            mwd,
+            cover: false,
+            date: vec![chrono::Utc::now().naive_utc().format("%Y-%m-%d %H:%M:%S").to_string()],
+            available_languages: vec![manga.language.clone()],
+            current_language: manga.language,
+            theme: Vec::new(),
+            genre: Vec::new(),
+            links: manga.links,
+            long_strip: manga.long_strip,
+            template: manga.template,
+            covers: Vec::new(),
+            groups: manga.groups,
+            details: Default::default(),
+        });
+        imported += 1;
+    }
+
+    if dry_run {
+        println!("Would import {} manga", imported);
+        return Ok(Vec::new());
+    }
+    if let Err(err) = library_db::save(&dat) {
+        return Err(MdownError::ChainedError(Box::new(err), 12611));
+    }
+    println!("Imported {} manga", imported);
+    Ok(ids)
+}
+
+fn to_manifest(dat: &Dat, roots: &[String]) -> Manifest {
+    let manga = dat.data
+        .iter()
+        .map(|item| ManifestManga {
+            id: item.id.clone(),
+            name: item.name.clone(),
+            language: item.current_language.clone(),
+            folder: relative_folder(&item.mwd, roots),
+            chapters: item.chapters.clone(),
+            groups: if item.groups.is_empty() {
+                chapter_groups(item)
+            } else {
+                item.groups.clone()
+            },
+            links: item.links.clone(),
+            long_strip: item.long_strip,
+            template: item.template.clone(),
+        })
+        .collect();
+    Manifest { version: get_current_version(), manga }
+}
+
+/// Returns `mwd` relative to the library root it is in, or just its name.
+fn relative_folder(mwd: &str, roots: &[String]) -> String {
+    let mwd = mwd.replace('\\', "/");
+    for root in roots.iter() {
+        let root = root.replace('\\', "/");
+        let root = root.trim_end_matches('/');
+        if let Some(relative) = mwd.strip_prefix(root).and_then(|rest| rest.strip_prefix('/')) {
+            if !relative.is_empty() {
+                return relative.trim_end_matches('/').to_string();
+            }
+        }
+    }
+    mwd.trim_end_matches('/').rsplit('/').next().unwrap_or_default().to_string()
+}
+
+/// Returns path of manga folder from the manifest; parts which would leave the root are dropped.
+fn folder_path(manga: &ManifestManga) -> PathBuf {
+    let mut path = PathBuf::new();
+    for part in Path::new(&manga.folder.replace('\\', "/")).components() {
+        if let Component::Normal(part) = part {
+            let part = sanitize::manga_name(&part.to_string_lossy());
+            if !part.is_empty() {
+                path.push(part);
+            }
+        }
+    }
+    if path.as_os_str().is_empty() {
+        path.push(sanitize::manga_name(&manga.name));
+    }
+    path
+}
+
+/// Returns ids of scanlation groups of downloaded chapters from the cached feed, most common first.
+fn chapter_groups(item: &MangaMetadata) -> Vec<String> {
+    let feed = db::get_feed(&item.id).unwrap_or_default();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for chapter in feed.iter() {
+        let id = chapter.get("id").and_then(Value::as_str).unwrap_or_default();
+        if !item.chapters.iter().any(|downloaded| downloaded.id == id) {
+            continue;
+        }
+        let relationships = chapter.get("relationships").and_then(Value::as_array);
+        for relationship in relationships.into_iter().flatten() {
+            if relationship.get("type").and_then(Value::as_str) == Some("scanlation_group") {
+                if let Some(group) = relationship.get("id").and_then(Value::as_str) {
+                    *counts.entry(group.to_string()).or_default() += 1;
+                }
+            }
+        }
+    }
+    let mut groups: Vec<(String, usize)> = counts.into_iter().collect();
+    groups.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    groups
+        .into_iter()
+        .map(|(group, _count)| group)
+        .collect()
+}
+
+fn to_csv(manifest: &Manifest) -> String {
+    let mut csv = format!("{}\n", CSV_HEADER.join(","));
+    for manga in manifest.manga.iter() {
+        let chapters: Vec<String> = manga.chapters
+            .iter()
+            .map(|chapter| format!("{}:{}", chapter.number, chapter.id))
+            .collect();
+        let links: Vec<String> = match serde_json::to_value(&manga.links) {
+            Ok(Value::Object(links)) =>
+                links
+                    .iter()
+                    .filter_map(|(site, url)| url.as_str().map(|url| format!("{}={}", site, url)))
+                    .collect(),
+            _ => Vec::new(),
+        };
+        let row = [
+            manga.id.clone(),
+            manga.name.clone(),
+            manga.language.clone(),
+            manga.folder.clone(),
+            chapters.join(";"),
+            manga.groups.join(";"),
+            links.join(";"),
+            manga.long_strip.to_string(),
+            manga.template.clone(),
+        ];
+        let row: Vec<String> = row
+            .iter()
+            .map(|field| csv_field(field))
+            .collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn from_csv(csv: &str) -> Result<Manifest, MdownError> {
+    let mut rows = parse_csv(csv).into_iter();
+    let header = match rows.next() {
+        Some(header) => header,
+        None => {
+            return Err(MdownError::NotFoundError(String::from("header of manifest"), 12612));
+        }
+    };
+    let column = |name: &str| header.iter().position(|column| column.trim() == name);
+    let id_column = match column("id") {
+        Some(index) => index,
+        None => {
+            return Err(MdownError::NotFoundError(String::from("id column of manifest"), 12613));
+        }
+    };
+    let columns: Vec<Option<usize>> = CSV_HEADER.iter()
+        .map(|name| column(name))
+        .collect();
+
+    let mut manga = Vec::new();
+    for row in rows {
+        let field = |index: usize| -> &str {
+            columns[index]
+                .and_then(|column| row.get(column))
+                .map(|value| value.trim())
+                .unwrap_or_default()
+        };
+        if row.get(id_column).is_none_or(|id| id.trim().is_empty()) {
+            continue;
+        }
+        let list = |value: &str| -> Vec<String> {
+            value
+                .split(';')
+                .map(str::trim)
+                .filter(|part| !part.is_empty())
+                .map(String::from)
+                .collect()
+        };
+        let chapters = list(field(4))
+            .iter()
+            .map(|chapter| {
+                let (number, id) = chapter.split_once(':').unwrap_or((chapter, ""));
+                ChapterMetadata::new(number, "", id)
+            })
+            .collect();
+        let links: Map<String, Value> = list(field(6))
+            .iter()
+            .filter_map(|link| link.split_once('='))
+            .map(|(site, url)| (site.to_string(), Value::String(url.to_string())))
+            .collect();
+        manga.push(ManifestManga {
+            id: field(0).to_string(),
+            name: field(1).to_string(),
+            language: field(2).to_string(),
+            folder: field(3).to_string(),
+            chapters,
+            groups: list(field(5)),
+            links: serde_json::from_value::<LinksMetadata>(Value::Object(links)).unwrap_or_default(),
+            long_strip: field(7).eq_ignore_ascii_case("true"),
+            template: field(8).to_string(),
+        });
+    }
+    Ok(Manifest { version: get_current_version(), manga })
+}
+
+/// Quotes `value` if it contains a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Splits `csv` into rows of fields; quoted fields may contain separators and line breaks.
+fn parse_csv(csv: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = csv.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => {
+                quoted = false;
+            }
+            '"' if field.is_empty() => {
+                quoted = true;
+            }
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted => (),
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+// Test of writing CSV manifest and reading it back
+#[test]
+fn test_csv() {
+    let mut manga = ManifestManga {
+        id: String::from("a1b2c3d4-0000-0000-0000-000000000000"),
+        name: String::from("Name, with \"quotes\""),
+        language: String::from("en"),
+        folder: String::from("shounen/Name"),
+        chapters: vec![
+            ChapterMetadata::new("1", "", "c1"),
+            ChapterMetadata::new("1.5", "", "c2")
+        ],
+        groups: vec![String::from("g1"), String::from("g2")],
+        long_strip: true,
+        ..Default::default()
+    };
+    manga.links.mal = Some(String::from("123"));
+    let manifest = Manifest { version: get_current_version(), manga: vec![manga] };
+    assert_eq!(from_csv(&to_csv(&manifest)).unwrap(), manifest);
+}
+
+// Test of folders relative to library roots
+#[test]
+fn test_relative_folder() {
+    let roots = vec![String::from("D:\\manga\\"), String::from("/home/user/manga")];
+    assert_eq!(relative_folder("D:\\manga\\shounen\\Name", &roots), "shounen/Name");
+    assert_eq!(relative_folder("/home/user/manga/Name", &roots), "Name");
+    assert_eq!(relative_folder("C:\\other\\Name", &roots), "Name");
+    assert_eq!(relative_folder("D:\\manga2\\Name", &roots), "Name");
+}
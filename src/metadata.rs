@@ -2,7 +2,7 @@ use chrono::Utc;
 use serde::{ Deserialize, Serialize };
 use std::collections::BTreeMap;
 
-use crate::resolute;
+use crate::{ args::ARGS, job, resolute };
 
 /// Represents the settings for the application, such as folder paths, status flags, and optional features.
 ///
@@ -14,6 +14,7 @@ use crate::resolute;
 /// - `folder`: A `String` representing the folder path where files are saved.
 /// - `stat`: A `bool` indicating whether to display status updates. Defaults to `false` if not set.
 /// - `backup`: A `bool` indicating whether to enable backup functionality. Defaults to `false` if not set.
+/// - `library_roots`: A `Vec<String>` of folders searched for manga whose folder was moved.
 /// - `music`: An optional setting that is only included when the "music" feature is enabled. It holds an `Option<String>`
 ///   which may represent a music-related configuration or path.
 ///
@@ -31,6 +32,12 @@ pub(crate) struct Settings {
     /// Whether to enable backup functionality.
     pub(crate) backup: bool,
 
+    /// Default filename template, empty if built-in naming is used.
+    pub(crate) template: String,
+
+    /// Folders searched for manga whose folder was moved.
+    pub(crate) library_roots: Vec<String>,
+
     /// An optional music setting, available only when the "music" feature is enabled.
     #[cfg(feature = "music")]
     pub(crate) music: Option<Option<String>>,
@@ -59,9 +66,12 @@ pub(crate) struct ChapterMetadata {
 
     /// A unique identifier for the chapter.
     pub(crate) id: String,
+
+    /// Whether the chapter was removed from mangadex; the archive is kept in the library.
+    #[serde(default)]
+    pub(crate) removed: bool,
 }
 
-#[cfg(feature = "gui")]
 impl ChapterMetadata {
     /// Parses the chapter number into a vector of integers.
     ///
@@ -78,7 +88,10 @@ impl ChapterMetadata {
             .filter_map(|part| part.parse().ok())
             .collect()
     }
+}
 
+#[cfg(feature = "gui")]
+impl ChapterMetadata {
     /// Retrieves the next chapter from a list of chapters.
     ///
     /// This method finds the index of the current chapter in the provided list and attempts to return the next chapter.
@@ -155,6 +168,7 @@ impl ChapterMetadata {
             updated_at: updated_at.to_owned(),
             number: number.to_owned(),
             id: id.to_owned(),
+            removed: false,
         }
     }
 }
@@ -211,6 +225,48 @@ pub(crate) struct ChapterMetadataIn {
     pub(crate) scanlation: ScanlationMetadata,
 }
 
+/// Describes a chapter that would be downloaded, reported by `--dry-run`.
+///
+/// # Fields
+/// - `id`: A `String` with the ID of the chapter.
+/// - `number`: A `String` with the chapter number.
+/// - `volume`: A `String` with the volume number; empty if chapter is not in any volume.
+/// - `title`: A `String` with the title of the chapter.
+/// - `group`: A `String` with the name of the scanlation group.
+/// - `language`: A `String` with the translated language of the chapter.
+/// - `pages`: A `u64` with the number of pages.
+/// - `size`: A `u64` with the estimated size of all pages in bytes.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub(crate) struct DryRunChapter {
+    pub(crate) id: String,
+    pub(crate) number: String,
+    pub(crate) volume: String,
+    pub(crate) title: String,
+    pub(crate) group: String,
+    pub(crate) language: String,
+    pub(crate) pages: u64,
+    pub(crate) size: u64,
+}
+
+/// Result of `--dry-run`; list of chapters that would be downloaded and their totals.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub(crate) struct DryRunMetadata {
+    /// The name of the manga.
+    pub(crate) name: String,
+
+    /// The ID of the manga.
+    pub(crate) id: String,
+
+    /// Chapters that would be downloaded, in order of downloading.
+    pub(crate) chapters: Vec<DryRunChapter>,
+
+    /// Total number of pages.
+    pub(crate) pages: u64,
+
+    /// Estimated total size in bytes.
+    pub(crate) size: u64,
+}
+
 /// Contains metadata about the scanlation group.
 ///
 /// This struct holds information about the scanlation group responsible for the translation and
@@ -425,7 +481,7 @@ impl Log {
     ///
     /// A `Log` instance with the current time and provided message.
     pub(crate) fn new(message: &str) -> Log {
-        let name = resolute::CURRENT_CHAPTER.lock().clone();
+        let name = job::current().current_chapter.lock().clone();
         let handle_id = match resolute::HANDLE_ID.try_lock() {
             Some(handle) => handle.to_string(),
             None => String::new(),
@@ -477,13 +533,12 @@ impl Log {
     /// # Returns
     ///
     /// A `Log` instance with the current time, provided message, and handle ID.
-    #[cfg(feature = "web")]
     pub(crate) fn new_with_handle_id(message: &str, handle_id: Box<str>) -> Log {
         Log {
             handle_id: handle_id.into_string(),
             message: message.to_owned(),
             time: Utc::now().to_rfc3339(),
-            name: resolute::CURRENT_CHAPTER.lock().clone(),
+            name: job::current().current_chapter.lock().clone(),
         }
     }
 }
@@ -583,6 +638,12 @@ pub(crate) struct Dat {
 /// - `theme`: A `Vec<TagMetadata>` representing the themes of the manga (e.g., drama, comedy, etc.).
 /// - `genre`: A `Vec<TagMetadata>` representing the genres of the manga (e.g., action, romance, etc.).
 /// - `links`: A `LinksMetadata` struct that contains various URLs or external links related to the manga.
+/// - `long_strip`: A `bool` indicating whether chapters are re-sliced as a long strip (webtoon) before zipping.
+/// - `template`: A `String` with the filename template used for the manga's chapters.
+/// - `covers`: A `Vec<CoverMetadata>` listing downloaded volume covers.
+/// - `groups`: A `Vec<String>` with ids of preferred scanlation groups, e.g. imported from a manifest.
+/// - `details`: A `MangaDetails` struct with authors, artists, alternative titles and publication info; its fields
+///   are stored directly in the entry.
 ///
 /// # Notes
 /// This struct is essential for representing all metadata related to a specific manga, including its chapters, themes, genres,
@@ -622,6 +683,438 @@ pub(crate) struct MangaMetadata {
     /// Links and external resources related to the manga.
     #[serde(default)]
     pub(crate) links: LinksMetadata,
+
+    /// Whether chapters are stitched and re-sliced as a long strip before zipping.
+    #[serde(default)]
+    pub(crate) long_strip: bool,
+
+    /// Filename template used for the manga, empty if the default one is used.
+    #[serde(default)]
+    pub(crate) template: String,
+
+    /// Downloaded volume covers, empty if only the main cover was downloaded.
+    #[serde(default)]
+    pub(crate) covers: Vec<CoverMetadata>,
+
+    /// Ids of preferred scanlation groups, most preferred first.
+    #[serde(default)]
+    pub(crate) groups: Vec<String>,
+
+    /// Authors, artists and publication info of the manga.
+    #[serde(flatten)]
+    pub(crate) details: MangaDetails,
+}
+
+/// Describes who made the manga and how it is published.
+///
+/// Entries written by older versions don't have these fields; they are filled in by
+/// `database --check` or `database --update`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub(crate) struct MangaDetails {
+    /// Names of the authors (writers).
+    pub(crate) authors: Vec<String>,
+
+    /// Names of the artists.
+    pub(crate) artists: Vec<String>,
+
+    /// Alternative titles in all languages, e.g. the original title.
+    pub(crate) alt_titles: Vec<String>,
+
+    /// Year of the first publication.
+    pub(crate) year: Option<u64>,
+
+    /// Publication status, e.g. `ongoing` or `completed`.
+    pub(crate) status: String,
+
+    /// Target demographic, e.g. `shounen`; empty if not set.
+    pub(crate) demographic: String,
+
+    /// Original language of the manga, e.g. `ja`.
+    pub(crate) original_language: String,
+}
+
+impl MangaDetails {
+    /// Returns true if the details were not fetched yet.
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == MangaDetails::default()
+    }
+}
+
+/// Describes a downloaded cover art of a volume.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub(crate) struct CoverMetadata {
+    /// Volume of the cover, empty if the cover doesn't belong to any volume.
+    pub(crate) volume: String,
+
+    /// Locale of the cover, e.g. `ja` or `en`.
+    pub(crate) locale: String,
+
+    /// Path of the cover relative to the manga folder.
+    pub(crate) file: String,
+}
+
+/// Portable list of tracked manga written by `library export`.
+///
+/// Unlike `Dat`, it doesn't contain absolute paths, so it can be imported on another machine by
+/// `library import-manifest`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct Manifest {
+    /// Version of mdown which wrote the manifest.
+    pub(crate) version: String,
+
+    /// Tracked manga.
+    pub(crate) manga: Vec<ManifestManga>,
+}
+
+/// Manga in `Manifest`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(default)]
+pub(crate) struct ManifestManga {
+    /// Id of the manga on mangadex.
+    pub(crate) id: String,
+
+    /// Name of the manga.
+    pub(crate) name: String,
+
+    /// Language of downloaded chapters.
+    pub(crate) language: String,
+
+    /// Folder of the manga relative to a library root, with `/` as separator.
+    pub(crate) folder: String,
+
+    /// Downloaded chapters.
+    pub(crate) chapters: Vec<ChapterMetadata>,
+
+    /// Ids of scanlation groups of downloaded chapters, most common first.
+    pub(crate) groups: Vec<String>,
+
+    /// Links to other sites.
+    pub(crate) links: LinksMetadata,
+
+    /// Whether chapters are re-sliced as a long strip.
+    pub(crate) long_strip: bool,
+
+    /// Filename template of the manga, empty if the default one is used.
+    pub(crate) template: String,
+}
+
+/// Statistics of the library reported by `library stats`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub(crate) struct LibraryStats {
+    /// Number of manga.
+    pub(crate) manga: usize,
+
+    /// Number of chapters in the library.
+    pub(crate) chapters: usize,
+
+    /// Number of pages of downloaded chapters.
+    pub(crate) pages: u64,
+
+    /// Size of manga folders in bytes.
+    pub(crate) size: u64,
+
+    /// Manga and chapters by language of the manga.
+    pub(crate) languages: Vec<StatsEntry>,
+
+    /// Manga and chapters by scanlation group of downloaded chapters.
+    pub(crate) groups: Vec<StatsEntry>,
+
+    /// Manga and chapters by genre.
+    pub(crate) genres: Vec<StatsEntry>,
+
+    /// Manga and chapters by theme.
+    pub(crate) themes: Vec<StatsEntry>,
+
+    /// Manga and chapters by month of the first download.
+    pub(crate) dates: Vec<StatsEntry>,
+
+    /// Manga with the largest folders.
+    pub(crate) largest: Vec<MangaStats>,
+
+    /// Manga with the most chapters which are not downloaded.
+    pub(crate) most_missing: Vec<MangaStats>,
+
+    /// All manga.
+    pub(crate) series: Vec<MangaStats>,
+}
+
+/// Number of manga and chapters in a group of `LibraryStats`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub(crate) struct StatsEntry {
+    /// Name of the group, e.g. language or genre.
+    pub(crate) name: String,
+
+    /// Number of manga in the group.
+    pub(crate) manga: usize,
+
+    /// Number of chapters in the group.
+    pub(crate) chapters: usize,
+}
+
+/// Statistics of a single manga in `LibraryStats`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub(crate) struct MangaStats {
+    /// Id of the manga.
+    pub(crate) id: String,
+
+    /// Name of the manga.
+    pub(crate) name: String,
+
+    /// Language of the manga.
+    pub(crate) language: String,
+
+    /// Number of chapters in the library.
+    pub(crate) chapters: usize,
+
+    /// Number of pages of downloaded chapters.
+    pub(crate) pages: u64,
+
+    /// Size of the manga folder in bytes.
+    pub(crate) size: u64,
+
+    /// Number of chapters in the cached feed which are not downloaded; `None` if the feed of the
+    /// manga is not cached.
+    pub(crate) missing: Option<usize>,
+}
+
+/// Missing chapters of a manga reported by `library gaps`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub(crate) struct MangaGaps {
+    /// Id of the manga.
+    pub(crate) id: String,
+
+    /// Name of the manga.
+    pub(crate) name: String,
+
+    /// Language of the manga.
+    pub(crate) language: String,
+
+    /// Whole chapter numbers between the first and the last downloaded chapter which are not
+    /// downloaded.
+    pub(crate) gaps: Vec<Gap>,
+}
+
+/// A chapter number missing in a manga, see `MangaGaps`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct Gap {
+    /// Chapter number.
+    pub(crate) number: i32,
+
+    /// Why the chapter is missing.
+    pub(crate) kind: GapKind,
+
+    /// Languages in which the chapter is uploaded, if it is not uploaded in the language of the
+    /// manga.
+    pub(crate) languages: Vec<String>,
+
+    /// Whether the chapter can be downloaded in the language of the manga.
+    pub(crate) fillable: bool,
+}
+
+/// Reason why a chapter of a `Gap` is missing.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum GapKind {
+    /// Chapter is not uploaded to mangadex in any language.
+    NotUploaded,
+
+    /// Chapter is uploaded only in other languages.
+    OtherLanguage,
+
+    /// Chapter is uploaded in the language of the manga, but was not downloaded, e.g. because it
+    /// was skipped by `--chapter` or is hosted on an external site.
+    Filtered,
+
+    /// Chapter was in the cached chapter feed, but is no longer on mangadex.
+    Deleted,
+}
+
+impl GapKind {
+    /// Returns description of the reason shown to the user.
+    pub(crate) fn description(&self) -> &'static str {
+        match self {
+            GapKind::NotUploaded => "not yet uploaded",
+            GapKind::OtherLanguage => "available in another language",
+            GapKind::Filtered => "uploaded, but filtered out",
+            GapKind::Deleted => "deleted upstream",
+        }
+    }
+}
+
+/// Progress event printed as a JSON line by `--output json`, see `events`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(crate) enum Event {
+    /// Manga was found; `chapters` is the number of chapters in its feed.
+    MangaResolved {
+        id: String,
+        name: String,
+        language: String,
+        chapters: usize,
+    },
+
+    /// Chapter with id `id` and number `chapter` is not downloaded because of `reason`.
+    ChapterSkipped {
+        id: String,
+        chapter: String,
+        reason: String,
+    },
+
+    /// Download of a chapter started.
+    ChapterStarted {
+        id: String,
+        chapter: String,
+        volume: String,
+        language: String,
+        pages: usize,
+    },
+
+    /// Page `page` of `pages` of a chapter was downloaded.
+    PageFinished {
+        id: String,
+        chapter: String,
+        page: usize,
+        pages: usize,
+    },
+
+    /// Chapter was packed into the archive at `path`.
+    ChapterZipped {
+        id: String,
+        chapter: String,
+        path: String,
+    },
+
+    /// Error with its `MdownError::code`; `context` tells where it happened.
+    Error {
+        code: i32,
+        message: String,
+        context: Option<String>,
+    },
+
+    /// Line of output which is not a progress step, e.g. result of `database --check`.
+    Message {
+        text: String,
+    },
+
+    /// Totals of the run; `elapsed` is the number of seconds since the first event.
+    Summary {
+        manga: usize,
+        downloaded: usize,
+        skipped: usize,
+        pages: usize,
+        errors: usize,
+        elapsed: f64,
+    },
+}
+
+/// Options of a download job, given on the command line or by the REST API of web mode; missing
+/// options have the same defaults as command-line arguments.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub(crate) struct JobOptions {
+    /// Url or id of the manga.
+    pub(crate) url: String,
+    pub(crate) lang: String,
+    pub(crate) title: String,
+    pub(crate) folder: String,
+    pub(crate) volume: String,
+    pub(crate) chapter: String,
+    pub(crate) saver: bool,
+    pub(crate) stat: bool,
+    pub(crate) max_consecutive: usize,
+    pub(crate) force: bool,
+    pub(crate) offset: String,
+    pub(crate) database_offset: String,
+}
+
+impl Default for JobOptions {
+    fn default() -> Self {
+        JobOptions {
+            url: String::new(),
+            lang: String::from("en"),
+            title: String::from("*"),
+            folder: String::from("."),
+            volume: String::from("*"),
+            chapter: String::from("*"),
+            saver: false,
+            stat: false,
+            max_consecutive: 40,
+            force: false,
+            offset: String::from("0"),
+            database_offset: String::from("0"),
+        }
+    }
+}
+
+impl JobOptions {
+    /// Returns options given on the command line.
+    pub(crate) fn from_args() -> JobOptions {
+        let args = ARGS.lock();
+        JobOptions {
+            url: args.url.clone(),
+            lang: args.lang.clone(),
+            title: args.title.clone(),
+            folder: args.folder.clone(),
+            volume: args.volume.clone(),
+            chapter: args.chapter.clone(),
+            saver: args.saver,
+            stat: args.stat,
+            max_consecutive: args.max_consecutive,
+            force: args.force,
+            offset: args.offset.clone(),
+            database_offset: args.database_offset.clone(),
+        }
+    }
+}
+
+/// State of a download job of web mode.
+#[cfg(feature = "web")]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum JobState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+#[cfg(feature = "web")]
+impl JobState {
+    /// Returns whether the job will not change anymore.
+    pub(crate) fn is_finished(&self) -> bool {
+        matches!(self, JobState::Done | JobState::Failed | JobState::Cancelled)
+    }
+}
+
+/// Status of a download job returned by the REST API of web mode.
+#[cfg(feature = "web")]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct JobStatus {
+    pub(crate) id: String,
+    pub(crate) state: JobState,
+    pub(crate) options: JobOptions,
+
+    /// Id of the job this job retries.
+    pub(crate) retry_of: Option<String>,
+    pub(crate) name: String,
+    pub(crate) manga_id: String,
+
+    /// Chapter being downloaded and its downloaded and total pages.
+    pub(crate) chapter: String,
+    pub(crate) page: u64,
+    pub(crate) pages: u64,
+
+    /// Parsed and total chapters of the manga.
+    pub(crate) chapters_parsed: u64,
+    pub(crate) chapters: u64,
+    pub(crate) files: Vec<String>,
+    pub(crate) scanlation_groups: Vec<String>,
+    pub(crate) error: Option<String>,
+    pub(crate) created_at: String,
+    pub(crate) started_at: Option<String>,
+    pub(crate) finished_at: Option<String>,
 }
 
 /// Contains metadata for links.
@@ -840,6 +1333,44 @@ pub(crate) struct ChapterAttrResponse {
     pub(crate) version: u64,
 }
 
+/// Contains one page of cover arts returned by the `/cover` endpoint.
+///
+/// # Fields
+/// - `data`: A `Vec<CoverResponse>` with the cover arts on this page.
+/// - `total`: A `usize` indicating the total number of cover arts of the manga.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct CoverList {
+    /// The cover arts on this page.
+    pub(crate) data: Vec<CoverResponse>,
+
+    /// The total number of cover arts.
+    pub(crate) total: usize,
+}
+
+/// Contains a single cover art in the API response.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct CoverResponse {
+    /// The unique identifier of the cover art.
+    pub(crate) id: String,
+
+    /// The attributes of the cover art.
+    pub(crate) attributes: CoverAttrResponse,
+}
+
+/// Contains attributes of a cover art in the API response.
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub(crate) struct CoverAttrResponse {
+    /// The volume of the cover, `None` if it doesn't belong to any volume.
+    pub(crate) volume: Option<String>,
+
+    /// The name of the image file on the uploads server.
+    pub(crate) fileName: String,
+
+    /// The locale of the cover, e.g. `ja`.
+    pub(crate) locale: Option<String>,
+}
+
 /// Contains data about a chapter, including image URLs.
 ///
 /// This struct represents the data related to a specific manga chapter, primarily focusing on the chapter's image URLs.
@@ -0,0 +1,445 @@
+//! OPDS 1.2 catalog of the library in server mode.
+//!
+//! E-reader apps, e.g. KOReader, Chunky or Panels, browse the library from `/opds`: series, series
+//! by genre and by language are navigation feeds built from the library, every series is an
+//! acquisition feed of its chapters with links to their `.cbz` archives and covers. Chapters also
+//! have OPDS-PSE links, so apps which support page streaming can read pages without downloading
+//! the whole archive. Series can be searched by name through the OpenSearch description.
+//!
+//! Clients which authenticate by `?token=` in the url can't send it on their own, so every link of
+//! the catalog carries the token of the request.
+//!
+//! - `/opds`: Root navigation feed.
+//! - `/opds/series`, `/opds/series/<manga id>`: All series and chapters of a series.
+//! - `/opds/genres`, `/opds/genres/<genre>`: Genres and series of a genre.
+//! - `/opds/languages`, `/opds/languages/<language>`: Languages and series in a language.
+//! - `/opds/search?q=<text>`, `/opds/search.xml`: Search and its OpenSearch description.
+//! - `/opds/download/<manga id>/<chapter id>/<file name>`: Archive of a chapter.
+//! - `/opds/page/<manga id>/<chapter id>/<page>`: Page of a chapter for OPDS-PSE, the first page
+//!   is `0`.
+
+use chrono::Utc;
+use percent_encoding::{ NON_ALPHANUMERIC, percent_decode_str, percent_encode };
+use std::collections::{ BTreeMap, HashMap };
+
+use crate::{
+    comic_info::escape,
+    error::MdownError,
+    library_db,
+    metadata::MangaMetadata,
+    reader::{ self, Chapter },
+    server,
+    zip_func,
+};
+
+const NAVIGATION: &str = "application/atom+xml;profile=opds-catalog;kind=navigation";
+const ACQUISITION: &str = "application/atom+xml;profile=opds-catalog;kind=acquisition";
+const OPENSEARCH: &str = "application/opensearchdescription+xml";
+const CBZ: &str = "application/vnd.comicbook+zip";
+
+/// Returns response of a request of the catalog as its content type and content.
+///
+/// # Parameters
+/// - `path`: Path of the request without query, e.g. `/opds/series`.
+/// - `query`: Query parameters of the request.
+///
+/// # Returns
+/// - `Ok((&str, Vec<u8>))`: Content type and content of the response.
+/// - `Err(MdownError)`: If the series, chapter or page is not found or can't be read.
+pub(crate) fn handle(
+    path: &str,
+    query: &HashMap<String, String>
+) -> Result<(&'static str, Vec<u8>), MdownError> {
+    let segments: Vec<String> = path
+        .trim_start_matches("/opds")
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(decode)
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    let token = query
+        .get("token")
+        .map(String::as_str)
+        .filter(|token| !token.is_empty());
+
+    let (kind, xml) = match segments.as_slice() {
+        [] => (NAVIGATION, root(token)),
+        ["series"] => {
+            let feed = series_feed("series", "All series", "/opds/series", token, |_| true)?;
+            (NAVIGATION, feed)
+        }
+        ["series", id] => (ACQUISITION, chapters_feed(id, token)?),
+        ["genres"] => (NAVIGATION, groups_feed("genres", "Genres", genres, token)?),
+        ["genres", genre] => {
+            let href = format!("/opds/genres/{}", encode(genre));
+            let feed = series_feed(&format!("genres:{}", genre), genre, &href, token, |manga| {
+                genres(manga).contains(&genre.to_string())
+            })?;
+            (NAVIGATION, feed)
+        }
+        ["languages"] => (NAVIGATION, groups_feed("languages", "Languages", languages, token)?),
+        ["languages", language] => {
+            let href = format!("/opds/languages/{}", encode(language));
+            let id = format!("languages:{}", language);
+            let feed = series_feed(&id, language, &href, token, |manga| {
+                languages(manga).contains(&language.to_string())
+            })?;
+            (NAVIGATION, feed)
+        }
+        ["search.xml"] => (OPENSEARCH, opensearch(token)),
+        ["search"] => {
+            let text = decode(&query.get("q").cloned().unwrap_or_default().replace('+', " "));
+            let text = text.to_lowercase();
+            let href = format!("/opds/search?q={}", encode(&text));
+            let title = format!("Search: {}", text);
+            let feed = series_feed(&format!("search:{}", text), &title, &href, token, |manga| {
+                manga.name.to_lowercase().contains(&text)
+            })?;
+            (NAVIGATION, feed)
+        }
+        ["download", id, chapter_id, _file_name] => {
+            let chapter = match reader::find_chapter(id, chapter_id) {
+                Ok(chapter) => chapter,
+                Err(err) => {
+                    return Err(MdownError::ChainedError(Box::new(err), 13200));
+                }
+            };
+            return match std::fs::read(&chapter.path) {
+                Ok(content) => Ok((CBZ, content)),
+                Err(err) => Err(MdownError::IoError(err, chapter.path, 13201)),
+            };
+        }
+        ["page", id, chapter_id, page] => {
+            let page = match page.parse::<usize>() {
+                Ok(page) => page,
+                Err(err) => {
+                    return Err(MdownError::ConversionError(err.to_string(), 13202));
+                }
+            };
+            let chapter = match reader::find_chapter(id, chapter_id) {
+                Ok(chapter) => chapter,
+                Err(err) => {
+                    return Err(MdownError::ChainedError(Box::new(err), 13203));
+                }
+            };
+            return match zip_func::extract_image_from_zip_gui(&chapter.path, page + 1) {
+                Ok(content) => Ok((reader::image_type(&content), content)),
+                Err(err) => Err(MdownError::ChainedError(Box::new(err), 13204)),
+            };
+        }
+        _ => {
+            return Err(MdownError::NotFoundError(path.to_string(), 13205));
+        }
+    };
+    Ok((kind, xml.into_bytes()))
+}
+
+fn decode(text: &str) -> String {
+    percent_decode_str(text).decode_utf8_lossy().to_string()
+}
+
+fn encode(text: &str) -> String {
+    percent_encode(text.as_bytes(), NON_ALPHANUMERIC).to_string()
+}
+
+fn load() -> Result<Vec<MangaMetadata>, MdownError> {
+    match library_db::load() {
+        Ok(dat) => {
+            Ok(
+                dat.data
+                    .into_iter()
+                    .filter(|manga| server::within_roots(reader::folder(&manga.mwd)))
+                    .collect()
+            )
+        }
+        Err(err) => Err(MdownError::ChainedError(Box::new(err), 13206)),
+    }
+}
+
+fn genres(manga: &MangaMetadata) -> Vec<String> {
+    manga.genre
+        .iter()
+        .map(|tag| tag.name.clone())
+        .collect()
+}
+
+fn languages(manga: &MangaMetadata) -> Vec<String> {
+    vec![manga.current_language.clone()]
+}
+
+/// Returns escaped `href` with `token` of the request added to its query.
+fn link(href: &str, token: Option<&str>) -> String {
+    match token {
+        Some(token) => {
+            let separator = if href.contains('?') { '&' } else { '?' };
+            escape(&format!("{}{}token={}", href, separator, encode(token)))
+        }
+        None => escape(href),
+    }
+}
+
+/// Returns Atom feed with `entries`; `id` is appended to the urn of the catalog.
+fn feed(
+    id: &str,
+    title: &str,
+    href: &str,
+    kind: &str,
+    entries: &[String],
+    token: Option<&str>
+) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\" xmlns:opds=\"http://opds-spec.org/2010/catalog\" xmlns:pse=\"http://vaemendis.net/opds-pse/ns\">\n<id>urn:mdown:{}</id>\n<title>{}</title>\n<updated>{}</updated>\n<author><name>mdown</name></author>\n<link rel=\"self\" href=\"{}\" type=\"{}\"/>\n<link rel=\"start\" href=\"{}\" type=\"{}\"/>\n<link rel=\"search\" href=\"{}\" type=\"{}\"/>\n{}</feed>\n",
+        escape(id),
+        escape(title),
+        Utc::now().to_rfc3339(),
+        link(href, token),
+        kind,
+        link("/opds", token),
+        NAVIGATION,
+        link("/opds/search.xml", token),
+        OPENSEARCH,
+        entries.concat()
+    )
+}
+
+/// Returns entry linking to a navigation or acquisition feed; `links` are added to the entry.
+fn entry(
+    id: &str,
+    title: &str,
+    content: &str,
+    href: &str,
+    kind: &str,
+    links: &str,
+    token: Option<&str>
+) -> String {
+    format!(
+        "<entry>\n<title>{}</title>\n<id>urn:mdown:{}</id>\n<updated>{}</updated>\n<content type=\"text\">{}</content>\n<link rel=\"subsection\" href=\"{}\" type=\"{}\"/>\n{}</entry>\n",
+        escape(title),
+        escape(id),
+        Utc::now().to_rfc3339(),
+        escape(content),
+        link(href, token),
+        kind,
+        links
+    )
+}
+
+/// Returns links to the cover of the series, if it was downloaded.
+fn cover_links(manga_id: &str, cover: bool, token: Option<&str>) -> String {
+    if !cover {
+        return String::new();
+    }
+    let href = link(&format!("/__reader__/cover?id={}", manga_id), token);
+    format!(
+        "<link rel=\"http://opds-spec.org/image\" href=\"{}\" type=\"image/png\"/>\n<link rel=\"http://opds-spec.org/image/thumbnail\" href=\"{}\" type=\"image/png\"/>\n",
+        href,
+        href
+    )
+}
+
+fn root(token: Option<&str>) -> String {
+    let entries = [
+        entry(
+            "series",
+            "All series",
+            "Every series in the library",
+            "/opds/series",
+            NAVIGATION,
+            "",
+            token
+        ),
+        entry(
+            "genres",
+            "By genre",
+            "Series grouped by genre",
+            "/opds/genres",
+            NAVIGATION,
+            "",
+            token
+        ),
+        entry(
+            "languages",
+            "By language",
+            "Series grouped by language",
+            "/opds/languages",
+            NAVIGATION,
+            "",
+            token
+        ),
+    ];
+    feed("root", "mdown", "/opds", NAVIGATION, &entries, token)
+}
+
+/// Returns feed of series sorted by name for which `filter` returns `true`.
+fn series_feed(
+    id: &str,
+    title: &str,
+    href: &str,
+    token: Option<&str>,
+    filter: impl Fn(&MangaMetadata) -> bool
+) -> Result<String, MdownError> {
+    let mut series: Vec<MangaMetadata> = load()?.into_iter().filter(filter).collect();
+    series.sort_by_key(|manga| manga.name.to_lowercase());
+    let entries: Vec<String> = series
+        .iter()
+        .map(|manga| series_entry(manga, token))
+        .collect();
+    Ok(feed(id, title, href, NAVIGATION, &entries, token))
+}
+
+fn series_entry(manga: &MangaMetadata, token: Option<&str>) -> String {
+    let content = format!("{} chapters; {}", manga.chapters.len(), genres(manga).join(", "));
+    entry(
+        &format!("series:{}", manga.id),
+        &manga.name,
+        &content,
+        &format!("/opds/series/{}", manga.id),
+        ACQUISITION,
+        &cover_links(&manga.id, manga.cover, token),
+        token
+    )
+}
+
+/// Returns feed of values of series returned by `values`, e.g. genres, with number of series.
+fn groups_feed(
+    name: &str,
+    title: &str,
+    values: fn(&MangaMetadata) -> Vec<String>,
+    token: Option<&str>
+) -> Result<String, MdownError> {
+    let mut groups: BTreeMap<String, usize> = BTreeMap::new();
+    for manga in load()?.iter() {
+        for value in values(manga) {
+            if !value.is_empty() {
+                *groups.entry(value).or_default() += 1;
+            }
+        }
+    }
+    let entries: Vec<String> = groups
+        .iter()
+        .map(|(value, count)| {
+            entry(
+                &format!("{}:{}", name, value),
+                value,
+                &format!("{} series", count),
+                &format!("/opds/{}/{}", name, encode(value)),
+                NAVIGATION,
+                "",
+                token
+            )
+        })
+        .collect();
+    Ok(feed(name, title, &format!("/opds/{}", name), NAVIGATION, &entries, token))
+}
+
+fn chapters_feed(id: &str, token: Option<&str>) -> Result<String, MdownError> {
+    let manga = match reader::find_manga(id) {
+        Ok(manga) => manga,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 13207));
+        }
+    };
+    let chapters = match reader::chapters(id) {
+        Ok(chapters) => chapters,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 13208));
+        }
+    };
+    let entries: Vec<String> = chapters
+        .iter()
+        .map(|chapter| chapter_entry(&manga, chapter, token))
+        .collect();
+    Ok(
+        feed(
+            &format!("series:{}", manga.id),
+            &manga.name,
+            &format!("/opds/series/{}", manga.id),
+            ACQUISITION,
+            &entries,
+            token
+        )
+    )
+}
+
+/// Returns entry of a chapter with its acquisition link, OPDS-PSE link and cover.
+fn chapter_entry(manga: &MangaMetadata, chapter: &Chapter, token: Option<&str>) -> String {
+    let mut title = String::new();
+    if !chapter.volume.is_empty() {
+        title.push_str(&format!("Vol.{} ", chapter.volume));
+    }
+    title.push_str(&format!("Ch.{}", chapter.chapter));
+    if !chapter.title.is_empty() {
+        title.push_str(&format!(" - {}", chapter.title));
+    }
+    let updated = manga.chapters
+        .iter()
+        .find(|item| item.id == chapter.id)
+        .map(|item| item.updated_at.clone())
+        .unwrap_or_else(|| Utc::now().to_rfc3339());
+    let file_name = chapter.path.rsplit(['\\', '/']).next().unwrap_or_default();
+    format!(
+        "<entry>\n<title>{}</title>\n<id>urn:mdown:chapter:{}</id>\n<updated>{}</updated>\n<link rel=\"http://opds-spec.org/acquisition\" href=\"{}\" type=\"{}\"/>\n<link rel=\"http://vaemendis.net/opds-pse/stream\" href=\"{}\" type=\"image/jpeg\" pse:count=\"{}\"/>\n{}</entry>\n",
+        escape(&title),
+        escape(&chapter.id),
+        escape(&updated),
+        link(&format!("/opds/download/{}/{}/{}", manga.id, chapter.id, encode(file_name)), token),
+        CBZ,
+        link(&format!("/opds/page/{}/{}/{{pageNumber}}", manga.id, chapter.id), token),
+        chapter.pages,
+        cover_links(&manga.id, manga.cover, token)
+    )
+}
+
+fn opensearch(token: Option<&str>) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<OpenSearchDescription xmlns=\"http://a9.com/-/spec/opensearch/1.1/\">\n<ShortName>mdown</ShortName>\n<Description>Search series in the library</Description>\n<InputEncoding>UTF-8</InputEncoding>\n<OutputEncoding>UTF-8</OutputEncoding>\n<Url type=\"{}\" template=\"{}\"/>\n</OpenSearchDescription>\n",
+        NAVIGATION,
+        link("/opds/search?q={searchTerms}", token)
+    )
+}
+
+// Test of entry of a chapter
+#[test]
+fn test_chapter_entry() {
+    let manga: MangaMetadata = serde_json::from_value(
+        serde_json::json!({
+            "name": "Tom & Jerry",
+            "id": "m1",
+            "chapters": [{ "updated_at": "2024-01-01T00:00:00+00:00", "number": "1", "id": "c1" }],
+            "mwd": "",
+            "cover": true,
+            "date": [],
+            "available_languages": [],
+            "current_language": "en",
+            "theme": [],
+            "genre": [{ "name": "Comedy", "id": "g1" }],
+        })
+    ).unwrap();
+    let chapter = Chapter {
+        id: String::from("c1"),
+        chapter: String::from("1"),
+        volume: String::new(),
+        title: String::from("Cat & Mouse"),
+        pages: 12,
+        path: String::from("Tom & Jerry\\Ch.1 - Cat & Mouse.cbz"),
+    };
+    let entry = chapter_entry(&manga, &chapter, None);
+    assert!(entry.contains("<title>Ch.1 - Cat &amp; Mouse</title>"));
+    assert!(entry.contains("<updated>2024-01-01T00:00:00+00:00</updated>"));
+    assert!(entry.contains("href=\"/opds/download/m1/c1/Ch%2E1%20%2D%20Cat%20%26%20Mouse%2Ecbz\""));
+    assert!(entry.contains("href=\"/opds/page/m1/c1/{pageNumber}\" type=\"image/jpeg\" pse:count=\"12\""));
+    assert!(entry.contains("rel=\"http://opds-spec.org/image/thumbnail\""));
+    assert!(series_entry(&manga, None).contains("<content type=\"text\">1 chapters; Comedy</content>"));
+
+    // Every link carries the token of the request
+    let entry = chapter_entry(&manga, &chapter, Some("abc"));
+    assert!(entry.contains("href=\"/opds/download/m1/c1/Ch%2E1%20%2D%20Cat%20%26%20Mouse%2Ecbz?token=abc\""));
+    assert!(entry.contains("href=\"/opds/page/m1/c1/{pageNumber}?token=abc\""));
+    assert!(entry.contains("href=\"/__reader__/cover?id=m1&amp;token=abc\""));
+    assert!(series_entry(&manga, Some("abc")).contains("href=\"/opds/series/m1?token=abc\""));
+    let root = root(Some("abc"));
+    assert!(root.contains("href=\"/opds/genres?token=abc\""));
+    assert!(root.contains("rel=\"start\" href=\"/opds?token=abc\""));
+    assert!(root.contains("rel=\"search\" href=\"/opds/search.xml?token=abc\""));
+    assert!(opensearch(Some("abc")).contains("template=\"/opds/search?q={searchTerms}&amp;token=abc\""));
+}
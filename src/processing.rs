@@ -0,0 +1,639 @@
+//! Page post-processing for downloaded chapters.
+//!
+//! Everything in this module works on a chapter's cache folder (`.cache\NAME\`) after all images
+//! were downloaded and before the folder is converted into a `.cbz` file. Processors rewrite the
+//! chapter's pages in place and keep the `pages` field of the `_metadata` file in sync with the
+//! number of images that end up in the archive.
+
+use image::{ codecs::jpeg::JpegEncoder, imageops::{ self, FilterType }, RgbImage };
+use std::{ fs::{ self, File }, io::{ BufWriter, Write }, path::PathBuf };
+
+use crate::{
+    args,
+    debug,
+    error::MdownError,
+    job::Job,
+    resolute,
+    utils,
+    zip_func::extract_page_number,
+};
+
+/// Maximum difference in luminance for a row of pixels to be considered a gutter.
+const GUTTER_TOLERANCE: u8 = 12;
+
+/// Quality of JPEG files written by processors.
+const JPEG_QUALITY: u8 = 90;
+
+/// Chapter pages loaded into memory, stitched vertically into one virtual strip.
+///
+/// Pages are not copied into one big image; rows are looked up in the page they belong to, so
+/// stitching a chapter does not need twice the memory of the decoded pages.
+struct Strip {
+    /// Decoded pages, all scaled to the same width.
+    pages: Vec<RgbImage>,
+
+    /// Y coordinate at which each page starts in the strip.
+    offsets: Vec<u32>,
+
+    /// Width of the strip.
+    width: u32,
+
+    /// Total height of the strip.
+    height: u32,
+}
+
+impl Strip {
+    fn new(pages: Vec<RgbImage>) -> Strip {
+        let width = pages
+            .iter()
+            .map(|page| page.width())
+            .max()
+            .unwrap_or_default();
+        let pages: Vec<RgbImage> = pages
+            .into_iter()
+            .map(|page| {
+                if page.width() == width || page.width() == 0 {
+                    page
+                } else {
+                    let height = (
+                        ((page.height() as u64) * (width as u64)) /
+                        (page.width() as u64)
+                    ).max(1) as u32;
+                    imageops::resize(&page, width, height, FilterType::Lanczos3)
+                }
+            })
+            .collect();
+        let mut offsets = Vec::with_capacity(pages.len());
+        let mut height = 0;
+        for page in pages.iter() {
+            offsets.push(height);
+            height += page.height();
+        }
+        Strip { pages, offsets, width, height }
+    }
+
+    /// Returns raw RGB data of row `y` of the strip.
+    fn row(&self, y: u32) -> &[u8] {
+        let index = self.offsets.partition_point(|&offset| offset <= y) - 1;
+        let local = (y - self.offsets[index]) as usize;
+        let row_len = (self.width as usize) * 3;
+        &self.pages[index].as_raw()[local * row_len..(local + 1) * row_len]
+    }
+
+    /// Returns true if row `y` has (nearly) uniform colour across the whole width.
+    fn is_gutter(&self, y: u32) -> bool {
+        let (mut min, mut max) = (u8::MAX, u8::MIN);
+        for pixel in self.row(y).chunks_exact(3) {
+            let luma = luminance(pixel);
+            min = min.min(luma);
+            max = max.max(luma);
+            if max - min > GUTTER_TOLERANCE {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Finds where the page starting at `start` should end.
+    ///
+    /// Searches outward from `start + target` for the nearest gutter row, but no closer to `start`
+    /// than half of the `target` and no further than one and a half of it. If no gutter is found,
+    /// the strip is cut at exactly `target` pixels. Leftover smaller than a quarter of `target` is
+    /// appended to the last page instead of making a tiny page of its own.
+    fn find_cut(&self, start: u32, target: u32) -> u32 {
+        if self.height - start <= target + target / 4 {
+            return self.height;
+        }
+        let ideal = start + target;
+        let low = start + target / 2;
+        let high = (start + target + target / 2).min(self.height - 1);
+        for distance in 0..=target / 2 {
+            if ideal + distance <= high && self.is_gutter(ideal + distance) {
+                return ideal + distance;
+            }
+            if ideal - distance >= low && self.is_gutter(ideal - distance) {
+                return ideal - distance;
+            }
+        }
+        ideal
+    }
+
+    /// Copies rows `start..end` of the strip into a new image.
+    fn crop(&self, start: u32, end: u32) -> RgbImage {
+        let mut page = RgbImage::new(self.width, end - start);
+        let row_len = (self.width as usize) * 3;
+        let buffer: &mut [u8] = &mut page;
+        for (i, y) in (start..end).enumerate() {
+            buffer[i * row_len..(i + 1) * row_len].copy_from_slice(self.row(y));
+        }
+        page
+    }
+}
+
+/// Returns true if any processor is enabled for the manga of `job`.
+pub(crate) fn is_enabled(job: &Job) -> bool {
+    *job.long_strip.lock() || *args::ARGS_SPLIT_SPREADS || args::ARGS_TRIM_BORDERS.is_some()
+}
+
+/// Runs enabled processors on the chapter in `folder`.
+///
+/// Long strip chapters are only re-sliced (see [`long_strip`]), since spreads and borders have no
+/// meaning for them; other chapters go through [`pages`].
+///
+/// # Parameters
+/// - `job`: Job downloading the chapter.
+/// - `folder`: Cache folder of the chapter with a trailing separator (`.cache\NAME\`).
+///
+/// # Returns
+/// - `Ok(usize)`: Number of pages the chapter has after processing.
+/// - `Err(MdownError)`: If any of the processors failed.
+pub(crate) fn process_chapter(job: &Job, folder: &str) -> Result<usize, MdownError> {
+    if *job.long_strip.lock() {
+        let height = args::ARGS.lock().webtoon_height;
+        return match long_strip(folder, height) {
+            Ok(count) => Ok(count),
+            Err(err) => Err(MdownError::ChainedError(Box::new(err), 11914)),
+        };
+    }
+    let right_to_left = resolute::ORIGINAL_LANGUAGE.lock().as_str() == "ja";
+    match pages(folder, *args::ARGS_SPLIT_SPREADS, right_to_left, *args::ARGS_TRIM_BORDERS) {
+        Ok(count) => Ok(count),
+        Err(err) => Err(MdownError::ChainedError(Box::new(err), 11915)),
+    }
+}
+
+/// Stitches all pages of a long strip chapter vertically and re-slices them into pages of
+/// `target_height` pixels, cutting at whitespace gutters between panels where possible.
+///
+/// # Parameters
+/// - `folder`: Cache folder of the chapter with a trailing separator (`.cache\NAME\`).
+/// - `target_height`: Desired height of the resulting pages in pixels.
+///
+/// # Returns
+/// - `Ok(usize)`: Number of pages the chapter has after re-slicing.
+/// - `Err(MdownError)`: If pages could not be read, decoded, written or `_metadata` could not be updated.
+///
+/// # Notes
+/// - Pages narrower than the widest page are scaled to its width before stitching.
+/// - Original images are removed and replaced by JPEG files named after the first original page,
+///   numbered from 1, so the page number can still be extracted from the file name.
+pub(crate) fn long_strip(folder: &str, target_height: u32) -> Result<usize, MdownError> {
+    let files = match get_pages(folder) {
+        Ok(files) => files,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 11900));
+        }
+    };
+    if files.is_empty() || target_height == 0 {
+        return Ok(files.len());
+    }
+    let prefix = get_prefix(&files[0].1);
+
+    let mut pages = Vec::with_capacity(files.len());
+    for (_, path) in files.iter() {
+        pages.push(match load_page(path) {
+            Ok(page) => page,
+            Err(err) => {
+                return Err(MdownError::ChainedError(Box::new(err), 11901));
+            }
+        });
+    }
+    let strip = Strip::new(pages);
+    debug!("stitched {} pages into strip {}x{}", files.len(), strip.width, strip.height);
+    if strip.height == 0 {
+        return Ok(files.len());
+    }
+
+    let mut cuts = vec![0];
+    let mut start = 0;
+    while start < strip.height {
+        start = strip.find_cut(start, target_height);
+        cuts.push(start);
+    }
+    debug!("long strip cut at {:?}", cuts);
+
+    // Slices are first written to temporary names, so the original pages are kept if writing fails
+    let mut slices = Vec::with_capacity(cuts.len() - 1);
+    for (index, window) in cuts.windows(2).enumerate() {
+        let page = strip.crop(window[0], window[1]);
+        let temp = format!("{}_page_{}.tmp", folder, index + 1);
+        if let Err(err) = save_page(&page, &temp) {
+            for temp in slices.iter().chain(std::iter::once(&temp)) {
+                let _ = fs::remove_file(temp);
+            }
+            return Err(MdownError::ChainedError(Box::new(err), 11903));
+        }
+        slices.push(temp);
+    }
+
+    for (_, path) in files.iter() {
+        if let Err(err) = fs::remove_file(path) {
+            return Err(MdownError::IoError(err, path.to_string_lossy().to_string(), 11902));
+        }
+    }
+    for (index, temp) in slices.iter().enumerate() {
+        let name = format!("{}{} - {}.jpg", folder, prefix, index + 1);
+        if let Err(err) = fs::rename(temp, &name) {
+            return Err(MdownError::IoError(err, name, 11925));
+        }
+    }
+    let count = slices.len();
+
+    match utils::update_metadata_pages(folder, |_pages| count) {
+        Ok(()) => Ok(count),
+        Err(err) => Err(MdownError::ChainedError(Box::new(err), 11904)),
+    }
+}
+
+/// Trims borders of pages and splits double-page spreads of the chapter in `folder`.
+///
+/// # Parameters
+/// - `folder`: Cache folder of the chapter with a trailing separator (`.cache\NAME\`).
+/// - `split_spreads`: Whether landscape pages should be split into two pages.
+/// - `right_to_left`: Whether the right half of a spread is read first.
+/// - `trim_tolerance`: Tolerance of border colour; `None` disables trimming.
+///
+/// # Returns
+/// - `Ok(usize)`: Number of pages the chapter has after processing.
+/// - `Err(MdownError)`: If pages could not be read, decoded, written or `_metadata` could not be updated.
+///
+/// # Notes
+/// - Pages are renumbered from 1 in reading order, so page number extracted from file name and
+///   `pages` in `_metadata` stay consistent after splitting.
+/// - Only pages that were changed are re-encoded (as JPEG); the rest are just renamed.
+pub(crate) fn pages(
+    folder: &str,
+    split_spreads: bool,
+    right_to_left: bool,
+    trim_tolerance: Option<u8>
+) -> Result<usize, MdownError> {
+    let files = match get_pages(folder) {
+        Ok(files) => files,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 11916));
+        }
+    };
+    if files.is_empty() {
+        return Ok(0);
+    }
+    let prefix = get_prefix(&files[0].1);
+
+    // Pages are first written to temporary names, so new page numbers can't collide with original files
+    let mut processed: Vec<(String, String)> = Vec::with_capacity(files.len());
+    for (_, path) in files.iter() {
+        let path_str = path.to_string_lossy().to_string();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or("jpg")
+            .to_string();
+
+        let is_spread = match image::image_dimensions(path) {
+            Ok((width, height)) => split_spreads && width > height,
+            Err(err) => {
+                return Err(
+                    MdownError::CustomError(
+                        format!("{} ({})", err, path_str),
+                        String::from("ImageError"),
+                        11917
+                    )
+                );
+            }
+        };
+
+        if !is_spread && trim_tolerance.is_none() {
+            let temp = format!("{}_page_{}.tmp", folder, processed.len() + 1);
+            if let Err(err) = fs::rename(path, &temp) {
+                return Err(MdownError::IoError(err, path_str, 11918));
+            }
+            processed.push((temp, extension));
+            continue;
+        }
+
+        let mut page = match load_page(path) {
+            Ok(page) => page,
+            Err(err) => {
+                return Err(MdownError::ChainedError(Box::new(err), 11919));
+            }
+        };
+        let mut changed = false;
+        if let Some(tolerance) = trim_tolerance {
+            if let Some(trimmed) = trim_borders(&page, tolerance) {
+                page = trimmed;
+                changed = true;
+            }
+        }
+        let outputs = if is_spread {
+            debug!("splitting spread {}", path_str);
+            split_spread(&page, right_to_left).to_vec()
+        } else if changed {
+            vec![page]
+        } else {
+            vec![]
+        };
+
+        if outputs.is_empty() {
+            let temp = format!("{}_page_{}.tmp", folder, processed.len() + 1);
+            if let Err(err) = fs::rename(path, &temp) {
+                return Err(MdownError::IoError(err, path_str, 11920));
+            }
+            processed.push((temp, extension));
+            continue;
+        }
+        for output in outputs.iter() {
+            let temp = format!("{}_page_{}.tmp", folder, processed.len() + 1);
+            match save_page(output, &temp) {
+                Ok(()) => (),
+                Err(err) => {
+                    return Err(MdownError::ChainedError(Box::new(err), 11921));
+                }
+            }
+            processed.push((temp, String::from("jpg")));
+        }
+        if let Err(err) = fs::remove_file(path) {
+            return Err(MdownError::IoError(err, path_str, 11922));
+        }
+    }
+
+    for (index, (temp, extension)) in processed.iter().enumerate() {
+        let name = format!("{}{} - {}.{}", folder, prefix, index + 1, extension);
+        if let Err(err) = fs::rename(temp, &name) {
+            return Err(MdownError::IoError(err, name, 11923));
+        }
+    }
+    let count = processed.len();
+
+    match utils::update_metadata_pages(folder, |_pages| count) {
+        Ok(()) => Ok(count),
+        Err(err) => Err(MdownError::ChainedError(Box::new(err), 11924)),
+    }
+}
+
+/// Splits a double-page spread in half; halves are returned in reading order.
+fn split_spread(page: &RgbImage, right_to_left: bool) -> [RgbImage; 2] {
+    let half = page.width() / 2;
+    let left = imageops::crop_imm(page, 0, 0, half, page.height()).to_image();
+    let right = imageops::crop_imm(page, half, 0, page.width() - half, page.height()).to_image();
+    if right_to_left {
+        [right, left]
+    } else {
+        [left, right]
+    }
+}
+
+/// Removes uniform white or black borders from `page`.
+///
+/// Colour of the border is taken from the top left pixel; if it is neither white nor black within
+/// `tolerance`, page is left as it is.
+///
+/// # Returns
+/// - `Some(RgbImage)`: The trimmed page.
+/// - `None`: If there was nothing to trim or the whole page is a single colour.
+fn trim_borders(page: &RgbImage, tolerance: u8) -> Option<RgbImage> {
+    let (width, height) = page.dimensions();
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let corner = luminance(&page.get_pixel(0, 0).0);
+    let border = if corner >= u8::MAX - tolerance {
+        u8::MAX
+    } else if corner <= tolerance {
+        u8::MIN
+    } else {
+        return None;
+    };
+    let is_border = |x: u32, y: u32| luminance(&page.get_pixel(x, y).0).abs_diff(border) <= tolerance;
+
+    let top = match (0..height).find(|&y| !(0..width).all(|x| is_border(x, y))) {
+        Some(top) => top,
+        None => {
+            return None;
+        }
+    };
+    let bottom = (top..height).rev().find(|&y| !(0..width).all(|x| is_border(x, y)))? + 1;
+    let left = (0..width).find(|&x| !(top..bottom).all(|y| is_border(x, y)))?;
+    let right = (left..width).rev().find(|&x| !(top..bottom).all(|y| is_border(x, y)))? + 1;
+
+    if top == 0 && left == 0 && bottom == height && right == width {
+        return None;
+    }
+    Some(imageops::crop_imm(page, left, top, right - left, bottom - top).to_image())
+}
+
+/// Returns image files in `folder` sorted by their page number.
+fn get_pages(folder: &str) -> Result<Vec<(usize, PathBuf)>, MdownError> {
+    let entries = match fs::read_dir(folder) {
+        Ok(entries) => entries,
+        Err(err) => {
+            return Err(MdownError::IoError(err, folder.to_string(), 11905));
+        }
+    };
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name.to_string(),
+            None => {
+                continue;
+            }
+        };
+        let extension = match name.rsplit_once('.') {
+            Some((_, extension)) => extension.to_lowercase(),
+            None => {
+                continue;
+            }
+        };
+        if !matches!(extension.as_str(), "jpg" | "jpeg" | "png" | "gif" | "bmp" | "webp") {
+            continue;
+        }
+        if let Some(page) = extract_page_number(&name) {
+            files.push((page, path));
+        }
+    }
+    files.sort_by_key(|(page, _)| *page);
+    Ok(files)
+}
+
+/// Returns file name of a page without extension and page number, e.g. `NAME - Ch.1` for `NAME - Ch.1 - 3.jpg`.
+fn get_prefix(path: &std::path::Path) -> String {
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+    match stem.rsplit_once(" - ") {
+        Some((prefix, _)) => prefix.to_string(),
+        None => stem.trim_end_matches(|c: char| c.is_ascii_digit()).to_string(),
+    }
+}
+
+fn load_page(path: &std::path::Path) -> Result<RgbImage, MdownError> {
+    match image::open(path) {
+        Ok(image) => Ok(image.to_rgb8()),
+        Err(err) =>
+            Err(
+                MdownError::CustomError(
+                    format!("{} ({})", err, path.to_string_lossy()),
+                    String::from("ImageError"),
+                    11906
+                )
+            ),
+    }
+}
+
+fn save_page(page: &RgbImage, path: &str) -> Result<(), MdownError> {
+    let file = match File::create(path) {
+        Ok(file) => file,
+        Err(err) => {
+            return Err(MdownError::IoError(err, path.to_string(), 11907));
+        }
+    };
+    let mut writer = BufWriter::new(file);
+    match JpegEncoder::new_with_quality(&mut writer, JPEG_QUALITY).encode_image(page) {
+        Ok(()) => (),
+        Err(err) => {
+            return Err(
+                MdownError::CustomError(
+                    format!("{} ({})", err, path),
+                    String::from("ImageError"),
+                    11908
+                )
+            );
+        }
+    }
+    match writer.flush() {
+        Ok(()) => Ok(()),
+        Err(err) => Err(MdownError::IoError(err, path.to_string(), 11909)),
+    }
+}
+
+#[inline]
+fn luminance(pixel: &[u8]) -> u8 {
+    (((pixel[0] as u32) * 299 + (pixel[1] as u32) * 587 + (pixel[2] as u32) * 114) / 1000) as u8
+}
+
+// Test for trimming white border and keeping pages without uniform border untouched
+#[test]
+fn test_trim_borders() {
+    let mut page = RgbImage::from_pixel(20, 30, image::Rgb([250, 250, 250]));
+    for y in 5..25 {
+        for x in 3..15 {
+            page.put_pixel(x, y, image::Rgb([20, 20, 20]));
+        }
+    }
+    let trimmed = trim_borders(&page, 16).unwrap();
+    assert_eq!(trimmed.dimensions(), (12, 20));
+    assert!(trim_borders(&trimmed, 16).is_none());
+    assert!(trim_borders(&RgbImage::from_pixel(5, 5, image::Rgb([0, 0, 0])), 16).is_none());
+}
+
+// Test for splitting spread in right-to-left and left-to-right reading order
+#[test]
+fn test_split_spread() {
+    let mut page = RgbImage::from_pixel(10, 4, image::Rgb([0, 0, 0]));
+    for y in 0..4 {
+        for x in 5..10 {
+            page.put_pixel(x, y, image::Rgb([255, 255, 255]));
+        }
+    }
+    let [first, second] = split_spread(&page, true);
+    assert_eq!(first.get_pixel(0, 0).0, [255, 255, 255]);
+    assert_eq!(second.get_pixel(0, 0).0, [0, 0, 0]);
+    let [first, second] = split_spread(&page, false);
+    assert_eq!(first.get_pixel(0, 0).0, [0, 0, 0]);
+    assert_eq!(second.dimensions(), (5, 4));
+}
+
+// Test for cutting long strip at gutter closest to target height
+#[test]
+fn test_strip_find_cut_gutter() {
+    let mut page = RgbImage::from_pixel(4, 300, image::Rgb([0, 0, 0]));
+    for y in 0..300 {
+        page.put_pixel(y % 4, y, image::Rgb([255, 255, 255]));
+    }
+    for y in 110..115 {
+        for x in 0..4 {
+            page.put_pixel(x, y, image::Rgb([255, 255, 255]));
+        }
+    }
+    let strip = Strip::new(vec![page]);
+    assert_eq!(strip.find_cut(0, 100), 110);
+    assert_eq!(strip.find_cut(200, 100), 300);
+}
+
+// Test for hard cut when there is no gutter and for scaling pages to common width
+#[test]
+fn test_strip_find_cut_no_gutter() {
+    let mut page = RgbImage::from_pixel(4, 200, image::Rgb([0, 0, 0]));
+    for y in 0..200 {
+        page.put_pixel(y % 4, y, image::Rgb([255, 255, 255]));
+    }
+    let narrow = RgbImage::from_pixel(2, 50, image::Rgb([0, 0, 0]));
+    let strip = Strip::new(vec![page, narrow]);
+    assert_eq!(strip.width, 4);
+    assert_eq!(strip.height, 300);
+    assert_eq!(strip.find_cut(0, 100), 100);
+    assert_eq!(strip.crop(0, 100).height(), 100);
+}
+
+// Creates chapter folder with two long strip pages and `_metadata` in temp folder
+#[cfg(test)]
+fn long_strip_folder() -> (PathBuf, String) {
+    let dir = std::env::temp_dir().join(format!("mdown_strip_{}", utils::generate_random_id(8)));
+    fs::create_dir_all(&dir).unwrap();
+    for page in 1..=2 {
+        RgbImage::from_pixel(4, 150, image::Rgb([0, 0, 0]))
+            .save(dir.join(format!("Manga - Ch.1 - {}.png", page)))
+            .unwrap();
+    }
+    let metadata = crate::metadata::ChapterMetadataIn {
+        pages: String::from("2"),
+        ..Default::default()
+    };
+    fs::write(dir.join("_metadata"), serde_json::to_string(&metadata).unwrap()).unwrap();
+    let folder = format!("{}{}", dir.to_string_lossy(), std::path::MAIN_SEPARATOR);
+    (dir, folder)
+}
+
+// Test for re-slicing long strip into pages of target height
+#[test]
+fn test_long_strip() {
+    let (dir, folder) = long_strip_folder();
+
+    assert_eq!(long_strip(&folder, 100).unwrap(), 3);
+
+    let pages: Vec<usize> = get_pages(&folder)
+        .unwrap()
+        .into_iter()
+        .map(|(page, _)| page)
+        .collect();
+    assert_eq!(pages, vec![1, 2, 3]);
+    assert!(!dir.join("Manga - Ch.1 - 1.png").exists());
+    assert!(dir.join("Manga - Ch.1 - 3.jpg").exists());
+    assert!(!dir.join("_page_1.tmp").exists());
+    let content = fs::read_to_string(dir.join("_metadata")).unwrap();
+    assert!(content.contains("\"pages\": \"3\""));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// Test for keeping original pages when a slice can't be written
+#[test]
+fn test_long_strip_write_failure() {
+    let (dir, folder) = long_strip_folder();
+    // Directory in place of the second slice makes writing it fail
+    fs::create_dir(dir.join("_page_2.tmp")).unwrap();
+
+    assert!(long_strip(&folder, 100).is_err());
+
+    assert!(dir.join("Manga - Ch.1 - 1.png").exists());
+    assert!(dir.join("Manga - Ch.1 - 2.png").exists());
+    assert!(!dir.join("_page_1.tmp").exists());
+    assert!(!dir.join("Manga - Ch.1 - 1.jpg").exists());
+    let content = fs::read_to_string(dir.join("_metadata")).unwrap();
+    assert!(content.contains("\"pages\":\"2\""));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,317 @@
+//! Reader of downloaded manga in server mode.
+//!
+//! The reader app served on `/reader` lists series of the library with their covers, then chapters
+//! of a series, and pages through a chapter left to right, right to left or as a long strip. Pages
+//! are read straight from the `.cbz` archives of the library, so nothing is extracted to disk.
+//!
+//! - `/__reader__/series`: Series of the library.
+//! - `/__reader__/chapters?id=<manga id>`: Chapters of a series sorted by chapter number.
+//! - `/__reader__/cover?id=<manga id>`: Cover of a series.
+//! - `/__reader__/page?id=<manga id>&chapter=<chapter id>&page=<page>`: Page of a chapter, the
+//!   first page is `1`.
+//!
+//! Only series whose folder, and chapters and covers whose files, are inside library roots are
+//! served, like files of the file manager, see `server::within_roots`.
+
+use glob::glob;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::{ json, Value };
+use std::{ collections::HashMap, fs::{ self, File }, io::Read };
+
+use crate::{
+    args,
+    error::MdownError,
+    library_db,
+    log,
+    metadata::MangaMetadata,
+    resolute,
+    server,
+    zip_func,
+};
+
+lazy_static! {
+    /// Chapters of series found by `chapters`, so pages don't scan the folder of the series again.
+    static ref CHAPTERS: Mutex<HashMap<String, Vec<Chapter>>> = Mutex::new(HashMap::new());
+}
+
+/// Chapter of a series found in the library.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct Chapter {
+    pub(crate) id: String,
+    pub(crate) chapter: String,
+    pub(crate) volume: String,
+    pub(crate) title: String,
+    pub(crate) pages: usize,
+
+    /// Path of the archive of the chapter.
+    #[serde(skip)]
+    pub(crate) path: String,
+}
+
+/// Returns response of a request of the reader as its content type and content.
+///
+/// # Parameters
+/// - `path`: Path of the request without query, e.g. `/__reader__/page`.
+/// - `query`: Query parameters of the request.
+///
+/// # Returns
+/// - `Ok((&str, Vec<u8>))`: Content type and content of the response.
+/// - `Err(MdownError)`: If the series, chapter or page is not found or can't be read.
+pub(crate) fn handle(
+    path: &str,
+    query: &HashMap<String, String>
+) -> Result<(&'static str, Vec<u8>), MdownError> {
+    let id = query.get("id").cloned().unwrap_or_default();
+    match path {
+        "/reader" | "/reader/" => Ok(("text/html", get_html().into_bytes())),
+        "/__reader__/series" => json_content(series()?),
+        "/__reader__/chapters" => {
+            let chapters = match chapters(&id) {
+                Ok(chapters) => chapters,
+                Err(err) => {
+                    return Err(MdownError::ChainedError(Box::new(err), 13100));
+                }
+            };
+            json_content(json!({ "chapters": chapters }))
+        }
+        "/__reader__/cover" => {
+            let manga = find_manga(&id)?;
+            let cover = format!("{}\\_cover.png", folder(&manga.mwd));
+            if !server::within_roots(&cover) {
+                return Err(forbidden(&cover, 13113));
+            }
+            match fs::read(&cover) {
+                Ok(content) => Ok((image_type(&content), content)),
+                Err(err) => Err(MdownError::IoError(err, cover, 13101)),
+            }
+        }
+        "/__reader__/page" => {
+            let page = match query.get("page").map(|page| page.parse::<usize>()) {
+                Some(Ok(page)) if page > 0 => page,
+                _ => {
+                    return Err(MdownError::NotFoundError(String::from("Page"), 13102));
+                }
+            };
+            let chapter_id = query.get("chapter").cloned().unwrap_or_default();
+            let path = match find_chapter(&id, &chapter_id) {
+                Ok(chapter) => chapter.path,
+                Err(err) => {
+                    return Err(MdownError::ChainedError(Box::new(err), 13103));
+                }
+            };
+            match zip_func::extract_image_from_zip_gui(&path, page) {
+                Ok(content) => Ok((image_type(&content), content)),
+                Err(err) => Err(MdownError::ChainedError(Box::new(err), 13104)),
+            }
+        }
+        _ => Err(MdownError::NotFoundError(path.to_string(), 13105)),
+    }
+}
+
+fn json_content(value: Value) -> Result<(&'static str, Vec<u8>), MdownError> {
+    match serde_json::to_vec(&value) {
+        Ok(content) => Ok(("application/json", content)),
+        Err(err) => Err(MdownError::JsonError(err.to_string(), 13106)),
+    }
+}
+
+/// Logs a request of `path` outside library roots and returns its error; the server responds to
+/// it with `403 FORBIDDEN`, see `is_forbidden`.
+fn forbidden(path: &str, code: u32) -> MdownError {
+    log!(&format!("[server] 403 Forbidden: {}", path));
+    MdownError::CustomError(path.to_string(), String::from("Forbidden"), code)
+}
+
+/// Returns whether `err` (or the error it was chained from) was made by `forbidden`.
+pub(crate) fn is_forbidden(err: &MdownError) -> bool {
+    match err {
+        MdownError::CustomError(_path, name, _code) => name == "Forbidden",
+        MdownError::ChainedError(err, _code) => is_forbidden(err),
+        _ => false,
+    }
+}
+
+/// Returns folder of the manga without the verbatim prefix of `mwd`.
+pub(crate) fn folder(mwd: &str) -> &str {
+    mwd.strip_prefix("\\\\?\\").unwrap_or(mwd)
+}
+
+pub(crate) fn find_manga(id: &str) -> Result<MangaMetadata, MdownError> {
+    let dat = match library_db::load() {
+        Ok(dat) => dat,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 13107));
+        }
+    };
+    match dat.data.into_iter().find(|manga| manga.id == id) {
+        Some(manga) if server::within_roots(folder(&manga.mwd)) => Ok(manga),
+        Some(manga) => Err(forbidden(folder(&manga.mwd), 13112)),
+        None => Err(MdownError::NotFoundError(format!("Manga {}", id), 13108)),
+    }
+}
+
+/// Returns series of the library sorted by name.
+fn series() -> Result<Value, MdownError> {
+    let dat = match library_db::load() {
+        Ok(dat) => dat,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 13109));
+        }
+    };
+    let mut series: Vec<Value> = dat.data
+        .iter()
+        .filter(|manga| server::within_roots(folder(&manga.mwd)))
+        .map(|manga| {
+            json!({
+                "id": manga.id,
+                "name": manga.name,
+                "cover": manga.cover,
+                "chapters": manga.chapters.len(),
+                "genre": manga.genre.iter().map(|tag| tag.name.clone()).collect::<Vec<String>>(),
+            })
+        })
+        .collect();
+    series.sort_by_key(|manga| manga["name"].as_str().unwrap_or_default().to_lowercase());
+    Ok(json!({ "series": series }))
+}
+
+/// Finds chapters of the series with `id` in its folder; old versions of replaced chapters are
+/// skipped.
+pub(crate) fn chapters(id: &str) -> Result<Vec<Chapter>, MdownError> {
+    let manga = find_manga(id)?;
+    let pattern = format!("{}\\**\\*.cbz", folder(&manga.mwd));
+    let paths = match glob(&pattern) {
+        Ok(paths) => paths,
+        Err(err) => {
+            return Err(MdownError::CustomError(err.to_string(), String::from("Glob"), 13110));
+        }
+    };
+
+    let mut chapters = Vec::new();
+    for path in paths.filter_map(Result::ok) {
+        let path = path.to_string_lossy().to_string();
+        if resolute::is_version(&path) {
+            continue;
+        }
+        // Archive can be a symlink leading out of library roots
+        if !server::within_roots(&path) {
+            forbidden(&path, 13114);
+            continue;
+        }
+        let metadata = match resolute::check_for_metadata(&path) {
+            Ok(metadata) if metadata.manga_id == id || metadata.manga_id.is_empty() => metadata,
+            _ => {
+                continue;
+            }
+        };
+        let pages = zip_func::extract_image_len_from_zip_gui(&path).unwrap_or_default();
+        chapters.push(Chapter {
+            id: metadata.id,
+            chapter: metadata.chapter,
+            volume: metadata.volume,
+            title: metadata.title,
+            pages,
+            path,
+        });
+    }
+    sort_chapters(&mut chapters);
+    CHAPTERS.lock().insert(id.to_string(), chapters.clone());
+    Ok(chapters)
+}
+
+/// Sorts chapters by chapter number, chapters with the same number by volume; chapters which aren't
+/// numbers, e.g. oneshots, are last.
+fn sort_chapters(chapters: &mut [Chapter]) {
+    let number = |value: &str| value.parse::<f64>().unwrap_or(f64::MAX);
+    chapters.sort_by(|a, b| {
+        number(&a.chapter)
+            .total_cmp(&number(&b.chapter))
+            .then_with(|| number(&a.volume).total_cmp(&number(&b.volume)))
+    });
+}
+
+/// Returns chapter with `chapter_id` of the series with `id`; chapters are found again if the
+/// series wasn't listed yet or the chapter was downloaded later.
+pub(crate) fn find_chapter(id: &str, chapter_id: &str) -> Result<Chapter, MdownError> {
+    let cached = CHAPTERS.lock()
+        .get(id)
+        .and_then(|chapters| chapters.iter().find(|chapter| chapter.id == chapter_id).cloned());
+    if let Some(chapter) = cached {
+        // Archive could be replaced by a symlink since it was listed
+        if !server::within_roots(&chapter.path) {
+            return Err(forbidden(&chapter.path, 13115));
+        }
+        return Ok(chapter);
+    }
+    match chapters(id)?.into_iter().find(|chapter| chapter.id == chapter_id) {
+        Some(chapter) => Ok(chapter),
+        None => Err(MdownError::NotFoundError(format!("Chapter {}", chapter_id), 13111)),
+    }
+}
+
+/// Returns content type of an image by its signature; unknown images are sent as `image/jpeg`.
+pub(crate) fn image_type(content: &[u8]) -> &'static str {
+    match content {
+        [0x89, b'P', b'N', b'G', ..] => "image/png",
+        [b'G', b'I', b'F', ..] => "image/gif",
+        [b'B', b'M', ..] => "image/bmp",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "image/webp",
+        _ => "image/jpeg",
+    }
+}
+
+/// Retrieves the HTML content of the reader app; in development mode it is read from `reader.html`.
+fn get_html() -> String {
+    if *args::ARGS_DEV {
+        let mut contents = String::new();
+        if let Ok(mut file) = File::open("reader.html") {
+            if file.read_to_string(&mut contents).is_ok() {
+                return contents;
+            }
+        }
+    }
+    String::from(
+        "<!DOCTYPE html><html lang=\"en\"><head><meta charset=\"UTF-8\"><meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\"><title>mdown reader</title><style>body {font-family: Arial, sans-serif;background-color: #121212;color: #fff;margin: 0;padding: 0;}a {color: inherit;text-decoration: none;}header {display: flex;align-items: center;gap: 12px;padding: 10px 16px;background-color: #1e1e1e;position: sticky;top: 0;z-index: 5;}header h1 {font-size: 20px;margin: 0;flex: 1;white-space: nowrap;overflow: hidden;text-overflow: ellipsis;}button, select {background-color: #333;color: #fff;border: 1px solid #555;border-radius: 4px;padding: 6px 10px;font-size: 15px;}.grid {display: grid;grid-template-columns: repeat(auto-fill, minmax(150px, 1fr));gap: 16px;padding: 16px;}.card {background-color: #272727;border-radius: 8px;overflow: hidden;cursor: pointer;}.card img {width: 100%;aspect-ratio: 2 / 3;object-fit: cover;background-color: #333;display: block;}.card p {margin: 8px;font-size: 14px;}.card small {display: block;margin: 0 8px 8px;color: #aaa;}.chapters {list-style: none;margin: 0;padding: 16px;max-width: 800px;margin: auto;}.chapters li {padding: 12px;border-bottom: 1px solid #333;cursor: pointer;}.chapters li:hover {background-color: #272727;}.chapters small {color: #aaa;margin-left: 8px;}#page {height: calc(100vh - 52px);display: flex;align-items: center;justify-content: center;user-select: none;touch-action: pan-y pinch-zoom;}#page img {max-width: 100%;max-height: 100%;object-fit: contain;}#strip img {display: block;width: 100%;max-width: 900px;margin: 0 auto;min-height: 200px;}#counter {position: fixed;bottom: 12px;left: 50%;transform: translateX(-50%);background-color: rgba(0, 0, 0, 0.7);padding: 4px 12px;border-radius: 8px;}.hidden {display: none !important;}</style></head><body><header><button id=\"back\" class=\"hidden\" onclick=\"goBack()\">&#x2190;</button><h1 id=\"title\">Library</h1><select id=\"mode\" class=\"hidden\" onchange=\"setMode(this.value)\"><option value=\"ltr\">Left to right</option><option value=\"rtl\">Right to left</option><option value=\"strip\">Long strip</option></select></header><div id=\"series\" class=\"grid\"></div><ul id=\"chapters\" class=\"chapters hidden\"></ul><div id=\"page\" class=\"hidden\"><img id=\"image\" alt=\"\"></div><div id=\"strip\" class=\"hidden\"></div><div id=\"counter\" class=\"hidden\"></div><script>let library = [];let chapters = [];let state = {series: null, chapter: null, page: 0};let mode = localStorage.getItem(\'mode\') || \'ltr\';let touchX = null;document.getElementById(\'mode\').value = mode; function show(view) {[\'series\', \'chapters\', \'page\', \'strip\', \'counter\'].forEach(id => document.getElementById(id).classList.add(\'hidden\'));view.forEach(id => document.getElementById(id).classList.remove(\'hidden\'));document.getElementById(\'back\').classList.toggle(\'hidden\', view.includes(\'series\'));document.getElementById(\'mode\').classList.toggle(\'hidden\', !view.includes(\'counter\'));} function pageUrl(chapter, page) {return `/__reader__/page?id=${state.series.id}&chapter=${chapter.id}&page=${page + 1}`;} function escapeHtml(text) {const element = document.createElement(\'span\');element.textContent = text;return element.innerHTML;} async function getJson(url) {const response = await fetch(url);if (!response.ok) {throw new Error(`Request failed: ${response.status}`);}return response.json();} async function route() {const parts = location.hash.replace(/^#\\/?/, \'\').split(\'/\').filter(part => part.length > 0);if (library.length === 0) {library = (await getJson(\'/__reader__/series\')).series;}if (parts.length === 0) {renderSeries();return;}const series = library.find(item => item.id === parts[0]);if (!series) {location.hash = \'\';return;}if (!state.series || state.series.id !== series.id) {state.series = series;chapters = (await getJson(`/__reader__/chapters?id=${series.id}`)).chapters;}if (parts.length === 1) {renderChapters();return;}const chapter = chapters.find(item => item.id === parts[1]);if (!chapter) {location.hash = `#/${series.id}`;return;}state.chapter = chapter;state.page = Math.min(Math.max(parseInt(parts[2] || \'1\', 10) - 1, 0), Math.max(chapter.pages - 1, 0));renderReader();} function renderSeries() {state.series = null;document.getElementById(\'title\').textContent = \'Library\';document.getElementById(\'series\').innerHTML = library.map(item => `<a class=\"card\" href=\"#/${item.id}\"><img loading=\"lazy\" src=\"${item.cover ? `/__reader__/cover?id=${item.id}` : \'/__get__?path=error_404\'}\" alt=\"\"><p>${escapeHtml(item.name)}</p><small>${item.chapters} chapters</small></a>`).join(\'\');show([\'series\']);} function chapterName(chapter) {const volume = chapter.volume ? `Vol.${chapter.volume} ` : \'\';const title = chapter.title ? ` - ${chapter.title}` : \'\';return `${volume}Ch.${chapter.chapter}${title}`;} function renderChapters() {document.getElementById(\'title\').textContent = state.series.name;document.getElementById(\'chapters\').innerHTML = chapters.map(chapter => `<li onclick=\"location.hash = \'#/${state.series.id}/${chapter.id}/1\'\">${escapeHtml(chapterName(chapter))}<small>${chapter.pages} pages</small></li>`).join(\'\');show([\'chapters\']);window.scrollTo(0, 0);} function renderReader() {document.getElementById(\'title\').textContent = `${state.series.name} - ${chapterName(state.chapter)}`;if (mode === \'strip\') {const strip = document.getElementById(\'strip\');if (strip.dataset.chapter !== state.chapter.id) {strip.dataset.chapter = state.chapter.id;strip.innerHTML = Array.from({length: state.chapter.pages}, (_, page) => `<img loading=\"lazy\" src=\"${pageUrl(state.chapter, page)}\" alt=\"\">`).join(\'\');strip.insertAdjacentHTML(\'beforeend\', \'<p style=\"text-align: center\"><button onclick=\"nextChapter(1)\">Next chapter</button></p>\');window.scrollTo(0, 0);}show([\'strip\', \'counter\']);updateCounter();return;}document.getElementById(\'strip\').dataset.chapter = \'\';document.getElementById(\'image\').src = pageUrl(state.chapter, state.page);show([\'page\', \'counter\']);updateCounter();preload();} function updateCounter() {document.getElementById(\'counter\').textContent = mode === \'strip\' ? `${state.chapter.pages} pages` : `${state.page + 1}/${state.chapter.pages}`;} function preload() {for (let page = state.page + 1; page <= state.page + 3 && page < state.chapter.pages; page++) {new Image().src = pageUrl(state.chapter, page);}} function turn(step) {const page = state.page + step;if (page < 0) {nextChapter(-1);} else if (page >= state.chapter.pages) {nextChapter(1);} else {location.replace(`#/${state.series.id}/${state.chapter.id}/${page + 1}`);}} function nextChapter(step) {const index = chapters.findIndex(chapter => chapter.id === state.chapter.id) + step;if (index < 0 || index >= chapters.length) {location.hash = `#/${state.series.id}`;return;}location.hash = `#/${state.series.id}/${chapters[index].id}/1`;} function setMode(value) {mode = value;localStorage.setItem(\'mode\', mode);renderReader();} function goBack() {if (state.chapter && !document.getElementById(\'counter\').classList.contains(\'hidden\')) {state.chapter = null;location.hash = `#/${state.series.id}`;} else {location.hash = \'\';}} function direction() {return mode === \'rtl\' ? -1 : 1;} document.addEventListener(\'keydown\', event => {if (document.getElementById(\'counter\').classList.contains(\'hidden\')) {return;}if (event.key === \'Escape\') {goBack();} else if (mode === \'strip\') {if (event.key === \'n\') {nextChapter(1);} else if (event.key === \'p\') {nextChapter(-1);}} else if (event.key === \'ArrowRight\') {turn(direction());} else if (event.key === \'ArrowLeft\') {turn(-direction());} else if (event.key === \' \' || event.key === \'PageDown\' || event.key === \'ArrowDown\') {event.preventDefault();turn(1);} else if (event.key === \'PageUp\' || event.key === \'ArrowUp\') {turn(-1);}}); document.getElementById(\'page\').addEventListener(\'click\', event => {const left = event.clientX < window.innerWidth / 2;turn(left ? -direction() : direction());}); document.getElementById(\'page\').addEventListener(\'touchstart\', event => {touchX = event.changedTouches[0].clientX;}, {passive: true}); document.getElementById(\'page\').addEventListener(\'touchend\', event => {if (touchX === null) {return;}const distance = event.changedTouches[0].clientX - touchX;touchX = null;if (Math.abs(distance) > 50) {event.preventDefault();turn(distance < 0 ? direction() : -direction());}}); window.addEventListener(\'hashchange\', () => route().catch(error => console.error(\'Error:\', error)));route().catch(error => console.error(\'Error:\', error));</script></body></html>"
+    )
+}
+
+// Test of content types of images
+#[test]
+fn test_image_type() {
+    assert_eq!(image_type(b"\x89PNG\r\n"), "image/png");
+    assert_eq!(image_type(b"\xff\xd8\xff\xe0"), "image/jpeg");
+    assert_eq!(image_type(b"GIF89a"), "image/gif");
+    assert_eq!(image_type(b"RIFF\x00\x00\x00\x00WEBPVP8 "), "image/webp");
+    assert_eq!(image_type(b""), "image/jpeg");
+}
+
+// Test of order of chapters
+#[test]
+fn test_sort_chapters() {
+    let chapter = |chapter: &str, volume: &str| Chapter {
+        id: chapter.to_string(),
+        chapter: chapter.to_string(),
+        volume: volume.to_string(),
+        title: String::new(),
+        pages: 0,
+        path: String::new(),
+    };
+    let mut chapters = vec![chapter("10", "2"), chapter("2.5", "1"), chapter("2", "1")];
+    sort_chapters(&mut chapters);
+    let order: Vec<&str> = chapters
+        .iter()
+        .map(|chapter| chapter.id.as_str())
+        .collect();
+    assert_eq!(order, ["2", "2.5", "10"]);
+}
+
+// Test of recognizing paths outside library roots in chained errors
+#[test]
+fn test_is_forbidden() {
+    let err = forbidden("/outside/Ch.1.cbz", 13115);
+    assert!(is_forbidden(&err));
+    assert!(is_forbidden(&MdownError::ChainedError(Box::new(err), 13205)));
+    assert!(!is_forbidden(&MdownError::NotFoundError(String::from("chapter"), 13105)));
+}
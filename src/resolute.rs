@@ -1,28 +1,37 @@
 use crossterm::event::{ self, Event, KeyCode };
+use glob::glob;
 use lazy_static::lazy_static;
 use parking_lot::Mutex;
 use remove_dir_all::remove_dir_all;
 use semver::{ BuildMetadata, Prerelease, Version };
 use serde_json::{ Map, Value };
 use std::{
-    collections::HashMap,
+    collections::{ HashMap, HashSet },
     fs::{ self, File, OpenOptions },
     io::{ BufRead, Read, Write },
+    path::{ Path, PathBuf },
     sync::Arc,
 };
 
 use crate::{
     args::{ self, ARGS },
+    db,
     debug,
     download,
     download_manga,
     error::{ MdownError, suspend_error, SUSPENDED },
+    events,
     getter::{ self, get_folder_name, get_manga, get_manga_name, get_scanlation_group },
     handle_error,
+    http_cache,
+    job::{ self, Job },
+    library,
+    library_db,
     log,
     log_end,
     MAXPOINTS,
-    metadata::{ self, ChapterMetadata, Dat, Log, MangaMetadata, TagMetadata },
+    metadata::{ self, ChapterMetadata, Log, MangaMetadata, TagMetadata },
+    sanitize,
     string,
     utils::{ self, clear_screen, input },
     version_manager::{ check_ver, get_current_version },
@@ -33,41 +42,27 @@ use crate::{
 use crate::metadata::MusicStage;
 
 lazy_static! {
-    pub(crate) static ref SCANLATION_GROUPS: Mutex<Vec<metadata::ScanlationMetadata>> = Mutex::new(Vec::new()); // ID, name
-    pub(crate) static ref WEB_DOWNLOADED: Mutex<Vec<String>> = Mutex::new(Vec::new()); // filenames
-    pub(crate) static ref MANGA_NAME: Mutex<String> = Mutex::new(String::new());
-    pub(crate) static ref MANGA_ID: Mutex<String> = Mutex::new(String::new());
-    pub(crate) static ref CHAPTER_ID: Mutex<String> = Mutex::new(String::new());
     pub(crate) static ref LOGS: Mutex<Vec<Log>> = Mutex::new(Vec::new());
     pub(crate) static ref HANDLE_ID: Mutex<Box<str>> = Mutex::new(String::new().into_boxed_str()); // handle id
     pub(crate) static ref HANDLE_ID_END: Mutex<Vec<Box<str>>> = Mutex::new(Vec::new()); // handle id to end
-    pub(crate) static ref CHAPTERS: Mutex<Vec<ChapterMetadata>> = Mutex::new(Vec::new()); // chapter metadata
     pub(crate) static ref CHAPTERS_TO_REMOVE: Mutex<Vec<ChapterMetadata>> = Mutex::new(Vec::new()); // chapters to remove from database
-    pub(crate) static ref MWD: Mutex<String> = Mutex::new(String::new());
     pub(crate) static ref TO_DOWNLOAD: Mutex<Vec<String>> = Mutex::new(Vec::new()); // chapter number to download
     pub(crate) static ref TO_DOWNLOAD_DATE: Mutex<Vec<String>> = Mutex::new(Vec::new()); // chapter number to download because of date
-    pub(crate) static ref CURRENT_CHAPTER: Mutex<String> = Mutex::new(String::new()); // filename.get_folder_name()
-    pub(crate) static ref CURRENT_PAGE: Mutex<u64> = Mutex::new(0);
-    pub(crate) static ref CURRENT_PAGE_MAX: Mutex<u64> = Mutex::new(0);
-    pub(crate) static ref CURRENT_PERCENT: Mutex<f64> = Mutex::new(0.0);
-    pub(crate) static ref CURRENT_SIZE: Mutex<f64> = Mutex::new(0.0);
-    pub(crate) static ref CURRENT_SIZE_MAX: Mutex<f64> = Mutex::new(0.0);
-    pub(crate) static ref CURRENT_CHAPTER_PARSED: Mutex<u64> = Mutex::new(0);
-    pub(crate) static ref CURRENT_CHAPTER_PARSED_MAX: Mutex<u64> = Mutex::new(0);
     pub(crate) static ref CURRENT_LINKS: Mutex<metadata::LinksMetadata> = Mutex::new(metadata::LinksMetadata::default());
-    pub(crate) static ref DOWNLOADING: Mutex<bool> = Mutex::new(false);
     pub(crate) static ref COVER: Mutex<bool> = Mutex::new(false);
     pub(crate) static ref ENDED: Mutex<bool> = Mutex::new(false); // end variable for handlers
     pub(crate) static ref FINAL_END: Mutex<bool> = Mutex::new(false); // if true at the end it will use std::process::exit(0)
-    pub(crate) static ref SAVER: Mutex<bool> = Mutex::new(ARGS.lock().saver);
     pub(crate) static ref DATE_FETCHED: Mutex<Vec<String>> = Mutex::new(Vec::new()); // date of fetching data in format %Y-%m-%d %H:%M:%S
     pub(crate) static ref LANGUAGES: Mutex<Vec<String>> = Mutex::new(Vec::new()); // vec of all available languages
     pub(crate) static ref LANGUAGE: Mutex<String> = Mutex::new(String::new()); // current language
-    pub(crate) static ref CHAPTER_IDS: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new()); // chapter number, id from mangadex database
-    pub(crate) static ref CHAPTER_DATES: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new()); // chapter number, time from mangadex database
     pub(crate) static ref FIXED_DATES: Mutex<Vec<String>> = Mutex::new(Vec::new()); // vec of chapter number which have been fixed
+    pub(crate) static ref REPLACED: Mutex<Vec<String>> = Mutex::new(Vec::new()); // chapter number replaced on mangadex whose old archive was moved to VERSIONS_FOLDER
     pub(crate) static ref GENRES: Mutex<Vec<TagMetadata>> = Mutex::new(Vec::new());
     pub(crate) static ref THEMES: Mutex<Vec<TagMetadata>> = Mutex::new(Vec::new());
+    pub(crate) static ref ORIGINAL_LANGUAGE: Mutex<String> = Mutex::new(String::new()); // original language of manga e.g. 'ja'
+    pub(crate) static ref MANGA_DETAILS: Mutex<metadata::MangaDetails> = Mutex::new(metadata::MangaDetails::default()); // authors, artists and publication info of current manga
+    pub(crate) static ref CHAPTER_FILTER: Mutex<HashMap<String, Vec<String>>> = Mutex::new(HashMap::new()); // manga id, chapter numbers to download (empty for all chapters); other manga are skipped when checking, empty for all manga
+    pub(crate) static ref DRY_RUN: Mutex<Vec<metadata::DryRunChapter>> = Mutex::new(Vec::new()); // chapters which would be downloaded
     pub(crate) static ref INITSCR_INIT: Mutex<bool> = Mutex::new(false);
 }
 
@@ -77,16 +72,12 @@ lazy_static! {
     pub(crate) static ref MUSIC_END: Mutex<bool> = Mutex::new(false);
 }
 pub(crate) fn args_delete() -> Result<(), MdownError> {
-    let path = match getter::get_dat_path() {
-        Ok(path) => path,
+    match library_db::clear() {
+        Ok(()) => Ok(()),
         Err(err) => {
             handle_error!(&err, String::from("program"));
-            return Err(MdownError::ChainedError(Box::new(err), 10254));
+            Err(MdownError::ChainedError(Box::new(err), 10254))
         }
-    };
-    match fs::remove_file(&path) {
-        Ok(()) => Ok(()),
-        Err(err) => Err(MdownError::IoError(err, path, 10200)),
     }
 }
 
@@ -281,25 +272,7 @@ pub(crate) async fn show_log() -> Result<(), MdownError> {
 }
 
 pub(crate) async fn show() -> Result<(), MdownError> {
-    let dat_path = match getter::get_dat_path() {
-        Ok(path) => path,
-        Err(err) => {
-            return Err(MdownError::ChainedError(Box::new(err), 10258));
-        }
-    };
-    if let Err(err) = fs::metadata(&dat_path) {
-        debug!("dat.json not found: {}", err.to_string());
-        return Err(MdownError::IoError(err, dat_path, 10212));
-    }
-
-    let json = match get_dat_content(dat_path.as_str()) {
-        Ok(value) => value,
-        Err(error) => {
-            return Err(error);
-        }
-    };
-
-    match serde_json::from_value::<Dat>(json) {
+    match library_db::load() {
         Ok(mut dat) => {
             let mut not_orig = false;
             let version = match Version::parse(&dat.version) {
@@ -445,7 +418,9 @@ pub(crate) async fn show() -> Result<(), MdownError> {
                 let cover = fs::metadata(format!("{}\\_cover.png", mwd)).is_ok();
                 let chapters: Vec<String> = item.chapters
                     .iter()
-                    .map(|d| d.number.clone())
+                    .map(|d| {
+                        if d.removed { format!("{} (removed upstream)", d.number) } else { d.number.clone() }
+                    })
                     .collect();
 
                 let mut chapter_str = String::new();
@@ -455,9 +430,36 @@ pub(crate) async fn show() -> Result<(), MdownError> {
                 }
                 chapter_str = chapter_str.trim_end_matches(", ").to_string();
 
+                let details = &item.details;
+
                 println!("Manga name: {}", manga_name);
+                if !details.alt_titles.is_empty() {
+                    println!("Alternative titles: {}", details.alt_titles.join(", "));
+                }
                 println!("MWD: {}", mwd);
                 println!("ID: {}", id);
+                if details.is_empty() {
+                    println!("Details: not fetched yet; run database --details to fill them");
+                } else {
+                    if !details.authors.is_empty() {
+                        println!("Authors: {}", details.authors.join(", "));
+                    }
+                    if !details.artists.is_empty() {
+                        println!("Artists: {}", details.artists.join(", "));
+                    }
+                    if let Some(year) = details.year {
+                        println!("Year: {}", year);
+                    }
+                    if !details.status.is_empty() {
+                        println!("Status: {}", details.status);
+                    }
+                    if !details.demographic.is_empty() {
+                        println!("Demographic: {}", details.demographic);
+                    }
+                    if !details.original_language.is_empty() {
+                        println!("Original language: {}", details.original_language);
+                    }
+                }
                 println!("Database fetched: {}", date_str);
                 if !genres.is_empty() {
                     println!("Genres: {}", genre_str);
@@ -497,19 +499,21 @@ pub(crate) async fn show() -> Result<(), MdownError> {
 
                 if args::ARGS_SHOW_ALL.is_some() {
                     let mut chapters = vec![];
-                    if let Ok(entries) = fs::read_dir(&mwd) {
-                        for entry in entries.flatten() {
-                            let file_name = entry.file_name();
-                            if let Some(name) = file_name.to_str() {
-                                if name.ends_with(".cbz") {
-                                    chapters.push(name.to_string());
+                    // Chapters can be in subfolders created by filename templates; glob doesn't
+                    // accept verbatim paths, so `\\?\` prefix of canonicalized mwd is removed
+                    let folder = mwd.strip_prefix("\\\\?\\").unwrap_or(&mwd);
+                    if let Ok(entries) = glob(&format!("{}\\**\\*.cbz", folder)) {
+                        for entry in entries.filter_map(Result::ok) {
+                            if let Some(path) = entry.to_str() {
+                                if is_version(path) {
+                                    continue;
                                 }
+                                chapters.push(path.to_string());
                             }
                         }
                     }
                     if !chapters.is_empty() {
-                        for entry in chapters {
-                            let path = format!("{}\\{}", mwd, entry);
+                        for path in chapters {
                             let obj = match check_for_metadata(&path) {
                                 Ok(metadata) => metadata,
                                 Err(err) => {
@@ -556,14 +560,197 @@ pub(crate) async fn show() -> Result<(), MdownError> {
             }
         }
         Err(err) => {
-            return Err(MdownError::JsonError(err.to_string(), 10213));
+            return Err(MdownError::ChainedError(Box::new(err), 10213));
+        }
+    }
+
+    Ok(())
+}
+
+/// Renames manga folders and chapters in `dat.json` to names made by `sanitize`.
+///
+/// `name` and `mwd` of renamed manga are updated in `dat.json`. If a chapter's new name is taken by
+/// a different file, chapter id is appended, so the result does not depend on the order of files.
+/// With `--dry-run` changes are only printed. Renames which fail are reported and skipped; the
+/// rest is still saved, so `mwd` of every renamed folder is stored before an error is returned.
+pub(crate) fn sanitize_library() -> Result<(), MdownError> {
+    let mut dat = match library_db::load() {
+        Ok(dat) => dat,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 10277));
+        }
+    };
+    let dry_run = *args::ARGS_DRY_RUN;
+    let mut renamed = 0;
+    // Renames which failed are reported and the rest is still saved, so renamed folders match `mwd`
+    let mut failed = 0;
+    for item in dat.data.iter_mut() {
+        let name = sanitize::manga_name(&item.name);
+        if !name.is_empty() && name != item.name {
+            println!("{} -> {}", item.name, name);
+            item.name = name;
+        }
+
+        let mut mwd = item.mwd.clone();
+        let folder = Path::new(&item.mwd);
+        if
+            let (Some(parent), Some(folder_name)) = (
+                folder.parent(),
+                folder.file_name().and_then(|name| name.to_str()),
+            )
+        {
+            let new_name = sanitize::name(folder_name);
+            if !new_name.is_empty() && new_name != folder_name {
+                let new_mwd = parent.join(&new_name).to_string_lossy().to_string();
+                println!("{} -> {}", item.mwd, new_mwd);
+                let result = if dry_run { Ok(()) } else { fs::rename(&item.mwd, &new_mwd) };
+                match result {
+                    Ok(()) => {
+                        if !dry_run {
+                            mwd = new_mwd.clone();
+                        }
+                        item.mwd = new_mwd;
+                        renamed += 1;
+                    }
+                    Err(err) => {
+                        suspend_error(MdownError::IoError(err, item.mwd.clone(), 10279));
+                        failed += 1;
+                    }
+                }
+            }
+        }
+
+        // glob doesn't accept verbatim paths, so `\\?\` prefix of canonicalized mwd is removed
+        let folder = mwd.strip_prefix("\\\\?\\").unwrap_or(&mwd);
+        let entries: Vec<PathBuf> = match glob(&format!("{}\\**\\*.cbz", folder)) {
+            Ok(entries) => entries.filter_map(Result::ok).collect(),
+            Err(_err) => Vec::new(),
+        };
+        for path in entries {
+            let stem = path.file_stem().and_then(|stem| stem.to_str());
+            let (dir, stem) = match (path.parent(), stem) {
+                (Some(dir), Some(stem)) => (dir, stem),
+                _ => {
+                    continue;
+                }
+            };
+            let new_stem = sanitize::fit_path(
+                &dir.to_string_lossy(),
+                &sanitize::name(stem),
+                ".cbz"
+            );
+            if new_stem.is_empty() || new_stem == stem {
+                continue;
+            }
+            let mut target = dir.join(format!("{}.cbz", new_stem));
+            if target.exists() {
+                let id = match check_for_metadata(&path.to_string_lossy()) {
+                    Ok(metadata) => metadata.id,
+                    Err(err) => {
+                        suspend_error(MdownError::ChainedError(Box::new(err), 10280));
+                        continue;
+                    }
+                };
+                target = dir.join(format!("{}.cbz", sanitize::with_id(&new_stem, &id)));
+                if target.exists() {
+                    eprintln!("Error: {} already exists, skipping", target.display());
+                    continue;
+                }
+            }
+            println!("{} -> {}", path.display(), target.display());
+            if !dry_run {
+                if let Err(err) = fs::rename(&path, &target) {
+                    suspend_error(
+                        MdownError::IoError(err, path.to_string_lossy().to_string(), 10281)
+                    );
+                    failed += 1;
+                    continue;
+                }
+            }
+            renamed += 1;
+        }
+    }
+
+    if dry_run {
+        println!("Would rename {} files and folders", renamed);
+        return Ok(());
+    }
+
+    if let Err(err) = library_db::save(&dat) {
+        return Err(MdownError::ChainedError(Box::new(err), 10282));
+    }
+    println!("Renamed {} files and folders", renamed);
+    if failed > 0 {
+        return Err(
+            MdownError::CustomError(
+                format!("{} files and folders could not be renamed", failed),
+                String::from("Sanitize"),
+                10292
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Fetches authors, artists, alternative titles and publication info of manga in `dat.json` which
+/// don't have them, e.g. downloaded before they were stored; chapters are not checked.
+///
+/// Manga which can't be fetched are reported and skipped. With `--dry-run` only names of manga
+/// without details are printed.
+pub(crate) async fn fill_details() -> Result<(), MdownError> {
+    let mut dat = match library_db::load() {
+        Ok(dat) => dat,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 10289));
         }
+    };
+    let dry_run = *args::ARGS_DRY_RUN;
+    let mut filled = 0;
+    for item in dat.data.iter_mut().filter(|item| item.details.is_empty()) {
+        if dry_run {
+            println!("{} ({})", item.name, item.id);
+            filled += 1;
+            continue;
+        }
+        let json = match getter::get_manga_json(&item.id).await {
+            Ok(json) => json,
+            Err(err) => {
+                eprintln!("Error: failed to fetch details of {}: {}", item.name, err);
+                continue;
+            }
+        };
+        let details = match utils::get_json(&json) {
+            Ok(value) =>
+                match value.get("data") {
+                    Some(data) => getter::get_manga_details(data),
+                    None => {
+                        eprintln!("Error: {} has no data", item.name);
+                        continue;
+                    }
+                }
+            Err(err) => {
+                suspend_error(MdownError::ChainedError(Box::new(err), 10290));
+                continue;
+            }
+        };
+        println!("{}: details fetched", item.name);
+        item.details = details;
+        filled += 1;
     }
 
+    if dry_run {
+        println!("Would fetch details of {} manga", filled);
+        return Ok(());
+    }
+
+    if let Err(err) = library_db::save(&dat) {
+        return Err(MdownError::ChainedError(Box::new(err), 10291));
+    }
+    println!("Fetched details of {} manga", filled);
     Ok(())
 }
 
-pub(crate) fn check_for_metadata_saver(file_path: &str) -> Result<bool, MdownError> {
+pub(crate) fn check_for_metadata_saver(file_path: &str, current: bool) -> Result<bool, MdownError> {
     // Returns true if cbz file saver is different than the current one
     let obj = match check_for_metadata(file_path) {
         Ok(metadata) => metadata,
@@ -572,7 +759,7 @@ pub(crate) fn check_for_metadata_saver(file_path: &str) -> Result<bool, MdownErr
         }
     };
     let saver = obj.saver;
-    if *SAVER.lock() != saver {
+    if current != saver {
         return Ok(true);
     }
     Ok(false)
@@ -588,26 +775,9 @@ pub(crate) fn check_for_metadata(
 }
 
 pub(crate) async fn resolve_check() -> Result<(), MdownError> {
-    let dat_path = match getter::get_dat_path() {
-        Ok(path) => path,
-        Err(err) => {
-            return Err(MdownError::ChainedError(Box::new(err), 10261));
-        }
-    };
-    match fs::metadata(&dat_path) {
-        Ok(_metadata) => (),
-        Err(err) => {
-            return Err(MdownError::IoError(err, dat_path, 10214));
-        }
-    }
-    let mut json = match get_dat_content(dat_path.as_str()) {
-        Ok(value) => value,
-        Err(error) => {
-            return Err(error);
-        }
-    };
-
-    json = match serde_json::from_value::<Dat>(json) {
+    // Manga are checked one after another with options of the command line
+    let job = job::current();
+    let dat = match library_db::load() {
         Ok(mut dat) => {
             let data = &mut dat.data;
             let mut iter: i32 = -1;
@@ -618,8 +788,14 @@ pub(crate) async fn resolve_check() -> Result<(), MdownError> {
                     *MUSIC_STAGE.lock() = MusicStage::Init;
                 }
                 iter += 1;
+                {
+                    let filter = CHAPTER_FILTER.lock();
+                    if !filter.is_empty() && !filter.contains_key(&item.id) {
+                        continue;
+                    }
+                }
                 let manga_name = item.name.clone();
-                println!("Checking {}\r", manga_name);
+                events::message(&format!("Checking {}\r", manga_name));
                 let past_mwd = match std::env::current_dir() {
                     Ok(m) =>
                         (
@@ -639,11 +815,19 @@ pub(crate) async fn resolve_check() -> Result<(), MdownError> {
                         return Err(MdownError::IoError(err, String::new(), 10216));
                     }
                 };
-                let mwd: String = item.mwd.clone();
+                let mut mwd: String = item.mwd.clone();
 
                 *LANGUAGE.lock() = item.current_language.clone();
+                if std::fs::metadata(&mwd).is_err() {
+                    // Folder could have been moved to a different library root
+                    if let Some(new_mwd) = library::find_moved(&item.id, &mwd) {
+                        events::message(&format!("{} moved to {}", &manga_name, new_mwd));
+                        item.mwd = new_mwd.clone();
+                        mwd = new_mwd;
+                    }
+                }
                 if std::env::set_current_dir(&mwd).is_err() {
-                    println!("{} not found; deleting from database", &manga_name);
+                    events::message(&format!("{} not found; deleting from database", &manga_name));
                     to_remove.push(iter);
                     continue;
                 }
@@ -724,8 +908,8 @@ pub(crate) async fn resolve_check() -> Result<(), MdownError> {
                             let chapters_temp = item.chapters.clone();
 
                             {
-                                let mut chapter_da = CHAPTER_DATES.lock();
-                                let mut chapter_id = CHAPTER_IDS.lock();
+                                let mut chapter_da = job.chapter_dates.lock();
+                                let mut chapter_id = job.chapter_ids.lock();
                                 for i in chapters_temp.iter() {
                                     let number = i.number.clone();
                                     let date = i.updated_at.clone();
@@ -755,8 +939,27 @@ pub(crate) async fn resolve_check() -> Result<(), MdownError> {
                                     }
                                 };
                             }
-                            *MANGA_NAME.lock() = get_manga_name(title_data);
-                            match resolve_manga(&id, false).await {
+                            if *args::ARGS_UPDATE {
+                                resolve_all_covers(&job, &id, ".").await;
+                            }
+                            *job.long_strip.lock() = resolve_theme_genre(title_data);
+                            *ORIGINAL_LANGUAGE.lock() = title_data
+                                .get("originalLanguage")
+                                .and_then(Value::as_str)
+                                .unwrap_or_default()
+                                .to_string();
+                            if let Some(data) = obj.get("data") {
+                                let details = getter::get_manga_details(data);
+                                item.details = details.clone();
+                                *MANGA_DETAILS.lock() = details;
+                            }
+                            if item.long_strip {
+                                *job.long_strip.lock() = true;
+                            }
+                            item.long_strip = *job.long_strip.lock();
+                            resolve_template(&job, &id);
+                            *job.manga_name.lock() = get_manga_name(title_data);
+                            match resolve_manga(&job, &id, false).await {
                                 Ok(()) => (),
                                 Err(err) => {
                                     handle_error!(&err, String::from("manga"));
@@ -770,16 +973,17 @@ pub(crate) async fn resolve_check() -> Result<(), MdownError> {
                 }
                 if *args::ARGS_UPDATE {
                     item.cover = if !cover { *COVER.lock() } else { true };
+                    let covers = std::mem::take(&mut *job.covers.lock());
+                    if !covers.is_empty() {
+                        item.covers = covers;
+                    }
                 }
                 let mut chapters_temp = item.chapters.clone();
                 {
                     let chapters_remove = CHAPTERS_TO_REMOVE.lock();
                     for i in chapters_remove.iter() {
                         chapters_temp.retain(|value| {
-                            let number = value.number.clone();
-                            let date = value.updated_at.clone();
-                            let id = value.id.clone();
-                            ChapterMetadata { number, updated_at: date, id } != *i
+                            ChapterMetadata::new(&value.number, &value.updated_at, &value.id) != *i
                         });
                     }
                 }
@@ -788,54 +992,78 @@ pub(crate) async fn resolve_check() -> Result<(), MdownError> {
                     let number = i.number.clone();
                     let date = i.updated_at.clone();
                     let id = i.id.clone();
-                    chapters.push(ChapterMetadata { number, updated_at: date, id });
+                    chapters.push(ChapterMetadata { number, updated_at: date, id, removed: i.removed });
                 }
 
-                for i in CHAPTERS.lock().iter() {
-                    if !chapters.contains(i) {
+                for i in job.chapters.lock().iter() {
+                    if
+                        !chapters
+                            .iter()
+                            .any(|chapter| {
+                                ChapterMetadata::new(
+                                    &chapter.number,
+                                    &chapter.updated_at,
+                                    &chapter.id
+                                ) == *i
+                            })
+                    {
                         chapters.push(i.clone());
                     }
                 }
                 item.chapters = chapters;
+                let removed = flag_removed(&id, &mut item.chapters);
 
                 if item.chapters.is_empty() && !cover {
-                    println!("{} not found; deleting from database", &manga_name);
+                    events::message(&format!("{} not found; deleting from database", &manga_name));
                     to_remove.push(iter);
                     continue;
                 }
 
                 if *args::ARGS_CHECK {
-                    println!("Checked  {} ({})", &manga_name, item.id);
+                    events::message(&format!("Checked  {} ({})", &manga_name, item.id));
                     let to_dow;
                     if !TO_DOWNLOAD.lock().is_empty() || !TO_DOWNLOAD_DATE.lock().is_empty() {
                         to_dow = true;
-                        println!("Chapters available");
+                        events::message("Chapters available");
                         for chapter in TO_DOWNLOAD.lock().iter() {
-                            println!(" {}", chapter);
+                            events::message(&format!(" {}", chapter));
                         }
                         for chapter in TO_DOWNLOAD_DATE.lock().iter() {
-                            println!(" {} (OUTDATED CHAPTER)", chapter);
+                            events::message(&format!(" {} (OUTDATED CHAPTER)", chapter));
                         }
                     } else if !FIXED_DATES.lock().is_empty() {
                         to_dow = false;
-                        println!("Chapters ERROR");
+                        events::message("Chapters ERROR");
                         for date in FIXED_DATES.lock().iter() {
-                            println!(" {} (CORRUPT DATE) (FIXED)", date);
+                            events::message(&format!(" {} (CORRUPT DATE) (FIXED)", date));
                         }
                     } else {
                         to_dow = false;
                     }
                     if !to_dow {
-                        println!("Up to-date");
+                        events::message("Up to-date");
                     }
                     if !cover {
-                        println!("Cover is not downloaded");
+                        events::message("Cover is not downloaded");
                     }
                 }
-                CHAPTERS.lock().clear();
+                if !REPLACED.lock().is_empty() {
+                    events::message(&format!("Chapters replaced upstream; old versions moved to {}", VERSIONS_FOLDER));
+                    for chapter in REPLACED.lock().iter() {
+                        events::message(&format!(" {}", chapter));
+                    }
+                }
+                if !removed.is_empty() {
+                    events::message("Chapters removed upstream; kept locally");
+                    for chapter in removed.iter() {
+                        events::message(&format!(" {}", chapter));
+                    }
+                }
+                job.chapters.lock().clear();
                 TO_DOWNLOAD.lock().clear();
                 TO_DOWNLOAD_DATE.lock().clear();
                 FIXED_DATES.lock().clear();
+                REPLACED.lock().clear();
             }
             #[cfg(feature = "music")]
             {
@@ -845,83 +1073,31 @@ pub(crate) async fn resolve_check() -> Result<(), MdownError> {
             for &index in to_remove.iter().rev() {
                 data.remove(index as usize);
             }
-            match serde_json::to_value(dat) {
-                Ok(value) => value,
-                Err(err) => {
-                    return Err(MdownError::JsonError(err.to_string(), 10219));
-                }
-            }
+            dat
         }
         Err(err) => {
-            return Err(MdownError::JsonError(err.to_string(), 10220));
+            return Err(MdownError::ChainedError(Box::new(err), 10220));
         }
     };
 
-    let mut file = match File::create(&dat_path) {
-        Ok(path) => path,
-        Err(err) => {
-            return Err(MdownError::IoError(err, dat_path, 10221));
-        }
-    };
-
-    let json_string = match serde_json::to_string_pretty(&json) {
-        Ok(value) => value,
-        Err(err) => {
-            return Err(MdownError::JsonError(err.to_string(), 10222));
-        }
-    };
-
-    if let Err(err) = writeln!(file, "{}", json_string) {
-        return Err(MdownError::IoError(err, dat_path, 10223));
+    if let Err(err) = library_db::save(&dat) {
+        return Err(MdownError::ChainedError(Box::new(err), 10221));
     }
     Ok(())
 }
 
-pub(crate) fn resolve_dat() -> Result<(), MdownError> {
-    let dat_path = match getter::get_dat_path() {
-        Ok(path) => path,
-        Err(err) => {
-            return Err(MdownError::ChainedError(Box::new(err), 10263));
-        }
-    };
-    if fs::metadata(&dat_path).is_err() {
-        let mut file = match fs::File::create(&dat_path) {
-            Ok(file) => file,
-            Err(err) => {
-                return Err(MdownError::IoError(err, dat_path, 10224));
-            }
-        };
-
-        let content = format!(
-            "{{\n  \"data\": [],\n  \"version\": \"{}\"\n}}",
-            get_current_version()
-        );
-
-        match file.write_all(content.as_bytes()) {
-            Ok(()) => (),
-            Err(_err) => (),
-        };
-    }
-    let mut json = match get_dat_content(dat_path.as_str()) {
-        Ok(value) => value,
-        Err(err) => {
-            return Err(MdownError::ChainedError(Box::new(err), 10264));
-        }
-    };
-
-    json = match serde_json::from_value::<Dat>(json.clone()) {
+pub(crate) fn resolve_dat(job: &Job) -> Result<(), MdownError> {
+    let dat = match library_db::load() {
         Ok(mut dat) => {
             let data = &mut dat.data;
 
-            let manga_names: Vec<String> = data
-                .iter()
-                .map(|item| item.name.clone())
-                .collect();
-            if data.is_empty() || !manga_names.contains(&MANGA_NAME.lock().clone()) {
-                let mwd = format!("{}", MWD.lock());
+            // Manga is found by id, so renamed manga is still found
+            let manga_id = job.manga_id.lock().clone();
+            if !data.iter().any(|item| item.id == manga_id) {
+                let mwd = job.mwd.lock().clone();
                 let cover = COVER.lock();
                 let mut chapters = Vec::new();
-                let chapters_data: Vec<ChapterMetadata> = CHAPTERS.lock().clone();
+                let chapters_data: Vec<ChapterMetadata> = job.chapters.lock().clone();
                 for i in chapters_data.iter() {
                     chapters.push(match serde_json::to_value(i) {
                         Ok(v) => v,
@@ -951,8 +1127,8 @@ pub(crate) fn resolve_dat() -> Result<(), MdownError> {
                     });
                 }
                 let manga_data = MangaMetadata {
-                    name: MANGA_NAME.lock().clone(),
-                    id: MANGA_ID.lock().clone(),
+                    name: job.manga_name.lock().clone(),
+                    id: manga_id.clone(),
                     chapters: chapters_data,
                     mwd,
                     cover: *cover,
@@ -962,13 +1138,32 @@ pub(crate) fn resolve_dat() -> Result<(), MdownError> {
                     theme: themes_data,
                     genre: genres_data,
                     links: CURRENT_LINKS.lock().clone(),
+                    long_strip: *job.long_strip.lock(),
+                    template: ARGS.lock().template.clone(),
+                    covers: job.covers.lock().clone(),
+                    groups: Vec::new(),
+                    details: MANGA_DETAILS.lock().clone(),
                 };
 
                 data.push(manga_data);
             } else {
                 for chap_data in data.iter_mut() {
-                    let name = &chap_data.name;
-                    if name == MANGA_NAME.lock().as_str() {
+                    if chap_data.id == manga_id {
+                        if *job.long_strip.lock() {
+                            chap_data.long_strip = true;
+                        }
+                        let template = ARGS.lock().template.clone();
+                        if !template.is_empty() {
+                            chap_data.template = template;
+                        }
+                        let covers = job.covers.lock().clone();
+                        if !covers.is_empty() {
+                            chap_data.covers = covers;
+                        }
+                        let details = MANGA_DETAILS.lock().clone();
+                        if !details.is_empty() {
+                            chap_data.details = details;
+                        }
                         let existing_chapters = &mut chap_data.chapters;
 
                         let mut existing_chapters_temp = Vec::new();
@@ -978,7 +1173,7 @@ pub(crate) fn resolve_dat() -> Result<(), MdownError> {
                             existing_chapters_temp.push(number);
                         }
 
-                        let mut new_chapters: Vec<_> = CHAPTERS.lock()
+                        let mut new_chapters: Vec<_> = job.chapters.lock()
                             .iter()
                             .filter(|&chapter| {
                                 let number = chapter.number.clone();
@@ -1001,34 +1196,15 @@ pub(crate) fn resolve_dat() -> Result<(), MdownError> {
                     }
                 }
             }
-            match serde_json::to_value(dat) {
-                Ok(json) => json,
-                Err(err) => {
-                    return Err(MdownError::JsonError(err.to_string(), 10228));
-                }
-            }
-        }
-        Err(err) => {
-            return Err(MdownError::JsonError(err.to_string(), 10229));
+            dat
         }
-    };
-
-    let mut file = match File::create(&dat_path) {
-        Ok(file) => file,
-        Err(err) => {
-            return Err(MdownError::IoError(err, dat_path, 10230));
-        }
-    };
-
-    let json_string = match serde_json::to_string_pretty(&json) {
-        Ok(value) => value,
         Err(err) => {
-            return Err(MdownError::JsonError(err.to_string(), 10231));
+            return Err(MdownError::ChainedError(Box::new(err), 10229));
         }
     };
 
-    if let Err(err) = writeln!(file, "{}", json_string) {
-        return Err(MdownError::JsonError(err.to_string(), 10232));
+    if let Err(err) = library_db::save(&dat) {
+        return Err(MdownError::ChainedError(Box::new(err), 10230));
     }
     Ok(())
 }
@@ -1048,10 +1224,63 @@ pub(crate) fn get_dat_content(dat_path: &str) -> Result<Value, MdownError> {
     utils::get_json(&contents)
 }
 
-pub(crate) async fn resolve(obj: Map<String, Value>, id: &str) -> Result<String, MdownError> {
+pub(crate) async fn resolve(
+    job: &Arc<Job>,
+    obj: Map<String, Value>,
+    id: &str
+) -> Result<String, MdownError> {
+    job.scope(resolve_job(job, obj, id)).await
+}
+
+/// Finds id of the manga in `url`, e.g. a link to mangadex or the id itself, and downloads the
+/// manga in `job`.
+///
+/// # Returns
+/// - `Ok(String)`: Name of the manga, or `!` if `url` has no id or the manga couldn't be fetched.
+/// - `Err(MdownError)`: If the manga can't be parsed or downloaded.
+pub(crate) async fn resolve_url(job: &Arc<Job>, url: &str) -> Result<String, MdownError> {
+    let handle_id = HANDLE_ID.lock().clone();
+    let id;
+    if let Some(id_temp) = utils::resolve_regex(url) {
+        id = id_temp.as_str();
+    } else if utils::is_valid_uuid(url) {
+        id = url;
+    } else {
+        log!(&format!("@{} Didn't find any id", handle_id), handle_id);
+        return Ok(String::from("!"));
+    }
+    *job.manga_id.lock() = id.to_string();
+    log!(&format!("@{} Found {}", handle_id, id), handle_id);
+    let manga_name_json = match getter::get_manga_json(id).await {
+        Ok(value) => value,
+        Err(_err) => {
+            return Ok(String::from("!"));
+        }
+    };
+    let json_value = match utils::get_json(&manga_name_json) {
+        Ok(value) => value,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 10292));
+        }
+    };
+    match json_value {
+        Value::Object(obj) =>
+            match resolve(job, obj, id).await {
+                Ok(value) => Ok(value),
+                Err(err) => Err(MdownError::ChainedError(Box::new(err), 10293)),
+            }
+        _ => Err(MdownError::JsonError(String::from("Could not parse manga json"), 10294)),
+    }
+}
+
+async fn resolve_job(
+    job: &Arc<Job>,
+    obj: Map<String, Value>,
+    id: &str
+) -> Result<String, MdownError> {
     let handle_id = utils::generate_random_id(16);
     *HANDLE_ID.lock() = handle_id.clone();
-    debug!("handle id set to {}", handle_id);
+    debug!("handle id set to {} in job {}", handle_id, job.id);
     let data = match obj.get("data") {
         Some(value) => value,
         None => {
@@ -1067,78 +1296,91 @@ pub(crate) async fn resolve(obj: Map<String, Value>, id: &str) -> Result<String,
         }
     };
 
-    let manga_name = if ARGS.lock().title == "*" {
+    let manga_name = if job.options.title == "*" {
         debug!("manga name using functions");
         get_manga_name(title_data)
     } else {
         debug!("manga name is user defined");
-        ARGS.lock().title.to_string()
+        job.options.title.to_string()
     };
     debug!("manga name set to {}", manga_name);
-    *MANGA_NAME.lock() = manga_name.clone();
+    *job.manga_name.lock() = manga_name.clone();
+    // Folder template can use authors and other details
+    *MANGA_DETAILS.lock() = getter::get_manga_details(data);
     let folder = get_folder_name();
 
+    *LANGUAGE.lock() = job.options.lang.clone();
     match resolve_language(title_data) {
         Ok(()) => (),
         Err(err) => {
             return Err(MdownError::ChainedError(Box::new(err), 10265));
         }
     }
-    *DOWNLOADING.lock() = true;
+    *job.downloading.lock() = true;
 
     // If folder is already present, assume it will be rewritten
     let was_rewritten = fs::metadata(folder).is_ok();
 
     debug!("is there folder with same name: {}", was_rewritten);
 
-    if !was_rewritten {
-        match fs::create_dir(folder) {
-            Ok(()) => (),
-            Err(err) => {
-                if err.raw_os_error().unwrap_or_default() != 183 {
-                    eprintln!("Error: creating directory {} {}", &folder, err);
+    // Dry run must not write anything, so folder is not created and nothing is saved in it
+    if *args::ARGS_DRY_RUN {
+        *job.mwd.lock() = folder.to_string();
+    } else {
+        if !was_rewritten {
+            match fs::create_dir_all(folder) {
+                Ok(()) => (),
+                Err(err) => {
+                    if err.raw_os_error().unwrap_or_default() != 183 {
+                        eprintln!("Error: creating directory {} {}", &folder, err);
+                    }
                 }
             }
+            debug!("created directory {}", folder);
         }
-        debug!("created directory {}", folder);
-    }
-    *MWD.lock() = match std::fs::canonicalize(folder) {
-        Ok(value) =>
-            match value.to_str() {
-                Some(value) => {
-                    debug!("mwd set to {}", value);
-                    value.to_string()
-                }
-                None => {
-                    return Err(
-                        MdownError::ConversionError(
-                            String::from("Value is not a valid unicode"),
-                            10240
-                        )
-                    );
+        *job.mwd.lock() = match std::fs::canonicalize(folder) {
+            Ok(value) =>
+                match value.to_str() {
+                    Some(value) => {
+                        debug!("mwd set to {}", value);
+                        value.to_string()
+                    }
+                    None => {
+                        return Err(
+                            MdownError::ConversionError(
+                                String::from("Value is not a valid unicode"),
+                                10240
+                            )
+                        );
+                    }
                 }
+            Err(err) => {
+                return Err(MdownError::IoError(err, folder.to_string(), 10241));
             }
-        Err(err) => {
-            return Err(MdownError::IoError(err, folder.to_string(), 10241));
-        }
-    };
+        };
 
-    match resolve_description(folder, title_data) {
-        Ok(()) => (),
-        Err(err) => {
-            return Err(MdownError::ChainedError(Box::new(err), 10266));
+        match resolve_description(job, folder, title_data) {
+            Ok(()) => (),
+            Err(err) => {
+                return Err(MdownError::ChainedError(Box::new(err), 10266));
+            }
         }
     }
 
-    resolve_theme_genre(title_data);
+    *job.long_strip.lock() = resolve_theme_genre(title_data);
+    resolve_long_strip(job, id);
+    resolve_template(job, id);
 
-    resolve_cover(&data, id, folder).await;
+    if !*args::ARGS_DRY_RUN {
+        resolve_cover(&data, id, folder).await;
+        resolve_all_covers(job, id, folder).await;
 
-    if ARGS.lock().stat {
-        debug!("starting downloading stat");
-        match download::download_stat(id, &manga_name).await {
-            Ok(()) => debug!("stat downloaded successfully"),
-            Err(err) => handle_error!(&err, String::from("statistics")),
+        if job.options.stat {
+            debug!("starting downloading stat");
+            match download::download_stat(id, &manga_name).await {
+                Ok(()) => debug!("stat downloaded successfully"),
+                Err(err) => handle_error!(&err, String::from("statistics")),
+            }
         }
     }
 
@@ -1215,7 +1457,7 @@ pub(crate) async fn resolve(obj: Map<String, Value>, id: &str) -> Result<String,
         langs_final
     };
 
-    match resolve_manga(id, was_rewritten).await {
+    match resolve_manga(job, id, was_rewritten).await {
         Ok(()) => (),
         Err(err) => {
             handle_error!(&err, String::from("program"));
@@ -1231,21 +1473,13 @@ pub(crate) async fn resolve(obj: Map<String, Value>, id: &str) -> Result<String,
     {
         log!("Downloaded manga");
     }
-    *DOWNLOADING.lock() = false;
+    *job.downloading.lock() = false;
     #[cfg(feature = "music")]
     {
         *MUSIC_STAGE.lock() = MusicStage::End;
     }
-    CHAPTERS.lock().clear();
-    MANGA_ID.lock().clear();
-    CURRENT_CHAPTER.lock().clear();
-    *CURRENT_PAGE.lock() = 0;
-    *CURRENT_PAGE_MAX.lock() = 0;
-    *CURRENT_PERCENT.lock() = 0.0;
-    *CURRENT_SIZE.lock() = 0.0;
-    *CURRENT_SIZE_MAX.lock() = 0.0;
-    *CURRENT_CHAPTER_PARSED.lock() = 0;
-    *CURRENT_CHAPTER_PARSED_MAX.lock() = 0;
+    *MANGA_DETAILS.lock() = metadata::MangaDetails::default();
+    job.reset();
     *CURRENT_LINKS.lock() = metadata::LinksMetadata::default();
     debug!("global variables reset");
     Ok(manga_name)
@@ -1289,16 +1523,19 @@ async fn resolve_cover(data: &serde_json::Value, id: &str, folder: &str) {
     }
 }
 
-fn resolve_theme_genre(title_data: &Value) {
+/// Sets themes and genres of the manga from its `title_data` and returns whether its chapters
+/// should be processed as long strip.
+pub(crate) fn resolve_theme_genre(title_data: &Value) -> bool {
     let tags_attributes = match title_data.get("tags").and_then(Value::as_array) {
         Some(value) => value,
         None => {
-            return;
+            return *args::ARGS_WEBTOON;
         }
     };
 
     let mut theme: Vec<TagMetadata> = vec![];
     let mut genre: Vec<TagMetadata> = vec![];
+    let mut long_strip = *args::ARGS_WEBTOON;
 
     for tag in tags_attributes.iter() {
         let id = tag.get("id").and_then(Value::as_str).unwrap_or_default();
@@ -1320,6 +1557,10 @@ fn resolve_theme_genre(title_data: &Value) {
                         debug!("manga genre: {:?}", name);
                         genre.push(TagMetadata::new(name, id));
                     }
+                    "format" if name == "Long Strip" => {
+                        debug!("manga format: {:?}", name);
+                        long_strip = true;
+                    }
                     _ => (),
                 }
             }
@@ -1328,15 +1569,80 @@ fn resolve_theme_genre(title_data: &Value) {
 
     *GENRES.lock() = genre;
     *THEMES.lock() = theme;
+    long_strip
+}
+
+/// Sets filename template of the manga with `id`.
+///
+/// Template from `--template` is used first, then the one remembered for the manga in the
+/// database and then the default one from settings.
+fn resolve_template(job: &Job, id: &str) {
+    let template = ARGS.lock().template.clone();
+    if !template.is_empty() {
+        *job.template.lock() = template;
+        return;
+    }
+    if let Ok(dat) = library_db::load() {
+        let item = dat.data.iter().find(|item| item.id == id && !item.template.is_empty());
+        if let Some(item) = item {
+            debug!("template from database: {}", item.template);
+            *job.template.lock() = item.template.clone();
+            return;
+        }
+    }
+    *job.template.lock() = match db::get_template() {
+        Ok(template) => template.unwrap_or_default(),
+        Err(err) => {
+            suspend_error(MdownError::ChainedError(Box::new(err), 10275));
+            String::new()
+        }
+    };
 }
 
-fn resolve_description(folder: &str, title_data: &serde_json::Value) -> Result<(), MdownError> {
+/// Downloads covers of all volumes if `--all-covers` is set or if they were already downloaded
+/// for the manga with `id`; covers are kept in `job` for embedding into chapters.
+async fn resolve_all_covers(job: &Job, id: &str, folder: &str) {
+    let remembered = match library_db::load() {
+        Ok(dat) => dat.data.iter().any(|item| item.id == id && !item.covers.is_empty()),
+        Err(_err) => false,
+    };
+    if !*args::ARGS_ALL_COVERS && !remembered {
+        return;
+    }
+    debug!("starting downloading volume covers");
+    *job.covers.lock() = match download::download_covers(id, folder).await {
+        Ok(covers) => covers,
+        Err(err) => {
+            suspend_error(MdownError::ChainedError(Box::new(err), 10285));
+            Vec::new()
+        }
+    };
+}
+
+/// Enables long strip processing if it was enabled for the manga with `id` in the database.
+fn resolve_long_strip(job: &Job, id: &str) {
+    if *job.long_strip.lock() {
+        return;
+    }
+    if let Ok(dat) = library_db::load() {
+        if dat.data.iter().any(|item| item.id == id && item.long_strip) {
+            debug!("long strip processing enabled in database");
+            *job.long_strip.lock() = true;
+        }
+    }
+}
+
+fn resolve_description(
+    job: &Job,
+    folder: &str,
+    title_data: &serde_json::Value
+) -> Result<(), MdownError> {
     let desc = title_data
         .get("description")
         .and_then(|description| description.get("en"))
         .and_then(Value::as_str)
         .unwrap_or_default();
-    let manga_folder = if *args::ARGS_UPDATE { MWD.lock().clone() } else { folder.to_string() };
+    let manga_folder = if *args::ARGS_UPDATE { job.mwd.lock().clone() } else { folder.to_string() };
 
     let file_name = if *args::ARGS_UPDATE {
         String::from("_description.txt")
@@ -1370,6 +1676,7 @@ fn resolve_language(title_data: &Value) -> Result<(), MdownError> {
             );
         }
     };
+    *ORIGINAL_LANGUAGE.lock() = orig_lang.to_string();
     let languages = match title_data.get("availableTranslatedLanguages").and_then(Value::as_array) {
         Some(value) => value,
         None => {
@@ -1449,7 +1756,97 @@ pub(crate) async fn resolve_group(
     Ok(scan)
 }
 
-pub(crate) fn parse_scanlation_file() -> Result<(), MdownError> {
+/// Adds chapter to the list of chapters which would be downloaded in dry run.
+///
+/// Size of the chapter is estimated by HEAD requests of all its images on the at-home server;
+/// nothing is downloaded or written.
+pub(crate) async fn resolve_dry_run(
+    job: &Job,
+    id: &str,
+    array_item: &metadata::ChapterResponse,
+    title: &str,
+    scanlation: &metadata::ScanlationMetadata
+) -> Result<(), MdownError> {
+    let (chapter_attr, language, pages, number, _) = getter::get_metadata(array_item);
+    let json = match getter::get_chapter(id).await {
+        Ok(json) => json,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 10272));
+        }
+    };
+    let json_value = match utils::get_json(&json) {
+        Ok(value) => value,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 10273));
+        }
+    };
+    let obj = match serde_json::from_value::<metadata::ChapterData>(json_value) {
+        Ok(value) => value,
+        Err(err) => {
+            return Err(MdownError::JsonError(err.to_string(), 10274));
+        }
+    };
+    let (mut saver, mut images) = match job.options.saver {
+        true => ("data-saver", obj.chapter.dataSaver.clone().unwrap_or_default()),
+        false => ("data", obj.chapter.data.clone()),
+    };
+    if images.is_empty() {
+        (saver, images) = match job.options.saver {
+            true => ("data", obj.chapter.data),
+            false => ("data-saver", obj.chapter.dataSaver.unwrap_or_default()),
+        };
+    }
+
+    let base_url: Arc<str> = Arc::from(obj.baseUrl.as_str());
+    let hash: Arc<str> = Arc::from(obj.chapter.hash.as_str());
+    let saver: Arc<str> = Arc::from(saver);
+    let max_consecutive = job.options.max_consecutive.max(1);
+    let mut size = 0;
+    for batch in images.chunks(max_consecutive) {
+        let tasks = batch.iter().map(|image| {
+            download::get_image_size(
+                Arc::clone(&base_url),
+                Arc::clone(&hash),
+                Arc::from(image.trim_matches('"')),
+                Arc::clone(&saver)
+            )
+        });
+        for result in futures::future::join_all(tasks).await {
+            match result {
+                Ok(image_size) => {
+                    size += image_size;
+                }
+                Err(err) => suspend_error(err),
+            }
+        }
+    }
+    debug!("estimated size of chapter {}: {}", number, size);
+
+    add_dry_run(
+        job,
+        metadata::DryRunChapter {
+            id: id.to_string(),
+            number,
+            volume: chapter_attr.volume.unwrap_or_default(),
+            title: title.to_string(),
+            group: scanlation.name.clone(),
+            language,
+            pages,
+            size,
+        },
+        &chapter_attr.updatedAt
+    );
+    Ok(())
+}
+
+/// Adds `chapter` to dry run and marks its number as downloaded like a real run does, so other
+/// uploads of the same number are skipped, see `Job::has_chapter`.
+fn add_dry_run(job: &Job, chapter: metadata::DryRunChapter, updated_at: &str) {
+    job.chapters.lock().push(ChapterMetadata::new(&chapter.number, updated_at, &chapter.id));
+    DRY_RUN.lock().push(chapter);
+}
+
+pub(crate) fn parse_scanlation_file(job: &Job) -> Result<(), MdownError> {
     let file_name = if *args::ARGS_UPDATE {
         String::from("_scanlation_groups.txt")
     } else {
@@ -1466,7 +1863,7 @@ pub(crate) fn parse_scanlation_file() -> Result<(), MdownError> {
     for line in reader.lines() {
         let line = line.unwrap();
         if let Some((name, website)) = parse_line(&line) {
-            SCANLATION_GROUPS.lock().push(metadata::ScanlationMetadata {
+            job.scanlation_groups.lock().push(metadata::ScanlationMetadata {
                 name: name.to_string(),
                 website: website.to_string(),
             });
@@ -1486,6 +1883,7 @@ fn parse_line(line: &str) -> Option<(&str, &str)> {
 }
 
 pub(crate) fn get_scanlation_group_to_file(
+    job: &Job,
     scanlation: &metadata::ScanlationMetadata
 ) -> Result<(), MdownError> {
     let name = &scanlation.name;
@@ -1493,8 +1891,8 @@ pub(crate) fn get_scanlation_group_to_file(
     if name == "None" {
         return Ok(());
     }
-    if name != "Unknown" && !SCANLATION_GROUPS.lock().contains(scanlation) {
-        SCANLATION_GROUPS.lock().push(scanlation.clone());
+    if name != "Unknown" && !job.scanlation_groups.lock().contains(scanlation) {
+        job.scanlation_groups.lock().push(scanlation.clone());
     }
 
     let file_name = if *args::ARGS_UPDATE {
@@ -1527,87 +1925,82 @@ pub(crate) async fn resolve_group_metadata(id: &str) -> Result<(String, String),
     let base_url = "https://api.mangadex.org/group/";
     let full_url = format!("{}{}", base_url, id);
 
-    debug!("sending request to: {}", full_url);
-
-    let response = match download::get_response_client(&full_url).await {
-        Ok(res) => res,
+    // Groups are cached, so the same group is not fetched again for every chapter
+    let json = match http_cache::get(&full_url).await {
+        Ok(json) => json,
         Err(err) => {
+            // Groups of the library are remembered even if the response is not cached anymore
+            if let Ok(Some(group)) = library_db::get_group(id) {
+                return Ok(group);
+            }
             return Err(MdownError::ChainedError(Box::new(err), 10268));
         }
     };
-    if response.status().is_success() {
-        let json = match response.text().await {
-            Ok(json) => json,
-            Err(err) => {
-                return Err(MdownError::JsonError(err.to_string(), 10248));
-            }
-        };
-        let json_value = match utils::get_json(&json) {
-            Ok(value) => value,
-            Err(err) => {
-                return Err(MdownError::ChainedError(Box::new(err), 10269));
-            }
-        };
-        match json_value {
-            Value::Object(obj) => {
-                let data = match obj.get("data") {
-                    Some(value) => value,
-                    None => {
-                        return Err(
-                            MdownError::NotFoundError(
-                                "data in resolve_group_metadata".to_string(),
-                                10249
-                            )
-                        );
-                    }
-                };
-                let attr = match data.get("attributes") {
-                    Some(value) => value,
-                    None => {
-                        return Err(
-                            MdownError::NotFoundError(
-                                "attributes in resolve_group_metadata".to_string(),
-                                10250
-                            )
-                        );
-                    }
-                };
-                let name = match attr.get("name").and_then(Value::as_str) {
-                    Some(name) => name.to_string(),
-                    None => {
-                        return Ok((String::from("Unknown"), String::new()));
-                    }
-                };
-                let website = attr
-                    .get("website")
-                    .and_then(Value::as_str)
-                    .unwrap_or("None")
-                    .to_owned();
-                return Ok((name, website));
-            }
-            _ => {
-                return Ok((String::from("Unknown"), String::new()));
+    let json_value = match utils::get_json(&json) {
+        Ok(value) => value,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 10269));
+        }
+    };
+    match json_value {
+        Value::Object(obj) => {
+            let data = match obj.get("data") {
+                Some(value) => value,
+                None => {
+                    return Err(
+                        MdownError::NotFoundError(
+                            "data in resolve_group_metadata".to_string(),
+                            10249
+                        )
+                    );
+                }
+            };
+            let attr = match data.get("attributes") {
+                Some(value) => value,
+                None => {
+                    return Err(
+                        MdownError::NotFoundError(
+                            "attributes in resolve_group_metadata".to_string(),
+                            10250
+                        )
+                    );
+                }
+            };
+            let name = match attr.get("name").and_then(Value::as_str) {
+                Some(name) => name.to_string(),
+                None => {
+                    return Ok((String::from("Unknown"), String::new()));
+                }
+            };
+            let website = attr
+                .get("website")
+                .and_then(Value::as_str)
+                .unwrap_or("None")
+                .to_owned();
+            if let Err(err) = library_db::save_group(id, &name, &website) {
+                suspend_error(MdownError::ChainedError(Box::new(err), 10286));
             }
+            Ok((name, website))
         }
+        _ => Ok((String::from("Unknown"), String::new())),
     }
-    Err(MdownError::NetworkError(response.error_for_status().unwrap_err(), 10251))
 }
 
-async fn resolve_manga(id: &str, was_rewritten: bool) -> Result<(), MdownError> {
+async fn resolve_manga(job: &Arc<Job>, id: &str, was_rewritten: bool) -> Result<(), MdownError> {
     debug!("\nresolve_manga");
-    let going_offset: u32 = match ARGS.lock().database_offset.as_str().parse() {
+    let going_offset: u32 = match job.options.database_offset.as_str().parse() {
         Ok(offset) => offset,
         Err(err) => {
             return Err(MdownError::ConversionError(err.to_string(), 10252));
         }
     };
-    let arg_force = ARGS.lock().force;
+    let arg_force = job.options.force;
     let downloaded: &mut Vec<String> = &mut vec![];
-    *MANGA_ID.lock() = id.to_owned();
+    *job.manga_id.lock() = id.to_owned();
     match get_manga(id, going_offset).await {
         Ok((json, _offset)) => {
             clear_screen(1);
-            let downloaded_temp = match download_manga(json, arg_force).await {
+            let downloaded_temp = match download_manga(job, json, arg_force).await {
                 Ok(value) => value,
                 Err(err) => {
                     return Err(MdownError::ChainedError(Box::new(err), 10270));
@@ -1632,7 +2025,7 @@ async fn resolve_manga(id: &str, was_rewritten: bool) -> Result<(), MdownError>
             for _ in 0..downloaded.len() {
                 resolve_move(&mut moves, downloaded, 2, 1 + moved_by);
             }
-        } else if !was_rewritten {
+        } else if !was_rewritten && !*args::ARGS_DRY_RUN {
             match remove_dir_all(get_folder_name()) {
                 Ok(()) => (),
                 Err(err) => eprintln!("Error: remove directory {}", err),
@@ -1673,9 +2066,194 @@ pub(crate) fn title(mut title: String) -> String {
     title
 }
 
+/// Folder next to chapter archives with old versions of chapters replaced on mangadex.
+pub(crate) const VERSIONS_FOLDER: &str = ".versions";
+
+/// Returns whether chapter `number` with `id` was replaced on mangadex, i.e. it was updated at
+/// `updated_at`, after the version in the library; only manga being checked have dates set.
+pub(crate) fn is_replaced(job: &Job, number: &str, id: &str, updated_at: &str) -> bool {
+    if job.chapter_ids.lock().get(number).map(String::as_str) != Some(id) {
+        return false;
+    }
+    let current = match job.chapter_dates.lock().get(number) {
+        Some(date) => date.clone(),
+        None => {
+            return false;
+        }
+    };
+    match
+        (
+            chrono::DateTime::parse_from_rfc3339(&current),
+            chrono::DateTime::parse_from_rfc3339(updated_at),
+        )
+    {
+        (Ok(current), Ok(updated_at)) => current < updated_at,
+        _ => false,
+    }
+}
+
+/// Returns whether `path` is in `VERSIONS_FOLDER`; old versions are not chapters of the library.
+pub(crate) fn is_version(path: &str) -> bool {
+    path.split(['\\', '/']).any(|part| part == VERSIONS_FOLDER)
+}
+
+/// Moves archive of a replaced chapter at `path` to `VERSIONS_FOLDER`, so it is not overwritten
+/// by the new version; `updated_at` of the old version is added to its name. It is called only
+/// after the new version was zipped, so a failed download never loses the chapter.
+pub(crate) fn keep_version(path: &str, updated_at: &str) -> Result<String, MdownError> {
+    let path = Path::new(path);
+    let folder = match path.parent() {
+        Some(parent) => parent.join(VERSIONS_FOLDER),
+        None => PathBuf::from(VERSIONS_FOLDER),
+    };
+    if let Err(err) = fs::create_dir_all(&folder) {
+        return Err(MdownError::IoError(err, folder.to_string_lossy().to_string(), 10287));
+    }
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let date = updated_at.get(..19).unwrap_or(updated_at).replace(':', "-");
+    let target = folder.join(format!("{} ({}).cbz", stem, date));
+    match fs::rename(path, &target) {
+        Ok(()) => Ok(target.to_string_lossy().to_string()),
+        Err(err) => Err(MdownError::IoError(err, path.to_string_lossy().to_string(), 10288)),
+    }
+}
+
+/// Flags chapters of manga `id` which are no longer in its cached feed as removed; they are kept
+/// in the library. Returns numbers of chapters removed since the last check. Nothing is flagged if
+/// the feed is not cached. A feed that no longer matches MangaDex is fetched whole again by
+/// `getter::get_manga`, so chapters removed upstream are flagged by the next check.
+fn flag_removed(id: &str, chapters: &mut [ChapterMetadata]) -> Vec<String> {
+    let feed = db::get_feed(id).unwrap_or_default();
+    mark_removed(&feed, chapters)
+}
+
+/// Flags `chapters` which are not in `feed` as removed and returns numbers of the newly removed.
+fn mark_removed(feed: &[Value], chapters: &mut [ChapterMetadata]) -> Vec<String> {
+    if feed.is_empty() {
+        return Vec::new();
+    }
+    let ids: HashSet<&str> = feed
+        .iter()
+        .filter_map(|chapter| chapter.get("id").and_then(Value::as_str))
+        .collect();
+    let mut removed = Vec::new();
+    for chapter in chapters.iter_mut() {
+        if chapter.id.is_empty() {
+            continue;
+        }
+        let is_removed = !ids.contains(chapter.id.as_str());
+        if is_removed && !chapter.removed {
+            removed.push(chapter.number.clone());
+        }
+        chapter.removed = is_removed;
+    }
+    removed
+}
+
 pub(crate) fn resolve_skip(arg: &str, with: &str) -> bool {
     if arg == "*" || arg == with {
         return false;
     }
     true
 }
+
+// Test chapter is replaced only if it has the same id and a newer date
+#[test]
+fn test_is_replaced() {
+    let job = Job::with_options("test", Default::default());
+    job.chapter_ids.lock().insert(String::from("1"), String::from("id"));
+    job.chapter_dates.lock().insert(String::from("1"), String::from("2024-01-01T00:00:00+00:00"));
+
+    assert!(is_replaced(&job, "1", "id", "2024-02-01T00:00:00+00:00"));
+    assert!(!is_replaced(&job, "1", "id", "2024-01-01T00:00:00+00:00"));
+    assert!(!is_replaced(&job, "1", "other", "2024-02-01T00:00:00+00:00"));
+    assert!(!is_replaced(&job, "1", "id", "invalid"));
+    assert!(!is_replaced(&job, "2", "id", "2024-02-01T00:00:00+00:00"));
+}
+
+// Test paths in versions folder are recognized
+#[test]
+fn test_is_version() {
+    assert!(is_version("manga/.versions/Ch.1 (2024-01-01T00-00-00).cbz"));
+    assert!(is_version("manga\\.versions\\Ch.1.cbz"));
+    assert!(!is_version("manga/Ch.1.cbz"));
+    assert!(!is_version("manga/.versions_old/Ch.1.cbz"));
+}
+
+// Test replaced chapter is moved to versions folder with its date
+#[test]
+fn test_keep_version() {
+    let dir = std::env::temp_dir().join(format!("mdown_version_{}", utils::generate_random_id(8)));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("Ch.1.cbz");
+    fs::write(&path, b"old").unwrap();
+
+    let target = keep_version(&path.to_string_lossy(), "2024-01-01T12:30:00+00:00").unwrap();
+
+    let expected = dir.join(VERSIONS_FOLDER).join("Ch.1 (2024-01-01T12-30-00).cbz");
+    assert_eq!(PathBuf::from(&target), expected);
+    assert!(!path.exists());
+    assert_eq!(fs::read(&expected).unwrap(), b"old");
+    assert!(keep_version(&path.to_string_lossy(), "2024-01-01T12:30:00+00:00").is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// Test chapters missing from feed are flagged and reported once
+#[test]
+fn test_mark_removed() {
+    let chapter = |number: &str, id: &str| ChapterMetadata {
+        updated_at: String::new(),
+        number: number.to_string(),
+        id: id.to_string(),
+        removed: false,
+    };
+    let mut chapters = vec![chapter("1", "a"), chapter("2", "b"), chapter("3", "")];
+    let feed = vec![serde_json::json!({ "id": "a" })];
+
+    assert!(mark_removed(&[], &mut chapters).is_empty());
+    assert!(chapters.iter().all(|chapter| !chapter.removed));
+
+    assert_eq!(mark_removed(&feed, &mut chapters), vec![String::from("2")]);
+    assert!(!chapters[0].removed);
+    assert!(chapters[1].removed);
+    assert!(!chapters[2].removed);
+
+    assert!(mark_removed(&feed, &mut chapters).is_empty());
+    assert!(chapters[1].removed);
+
+    let feed = vec![serde_json::json!({ "id": "a" }), serde_json::json!({ "id": "b" })];
+    assert!(mark_removed(&feed, &mut chapters).is_empty());
+    assert!(!chapters[1].removed);
+}
+
+// Test dry run lists only the first upload of a chapter number, as a real run downloads it
+#[test]
+fn test_add_dry_run_same_number() {
+    let chapter = |id: &str, group: &str| metadata::DryRunChapter {
+        id: id.to_string(),
+        number: String::from("test_dry_run_7"),
+        group: group.to_string(),
+        ..Default::default()
+    };
+    let uploads = vec![chapter("a", "First group"), chapter("b", "Second group")];
+
+    let job = Job::with_options("test", Default::default());
+    for upload in uploads {
+        if !job.has_chapter(&upload.number) {
+            add_dry_run(&job, upload, "2024-01-01T00:00:00+00:00");
+        }
+    }
+
+    let listed: Vec<String> = DRY_RUN.lock()
+        .iter()
+        .filter(|chapter| chapter.number == "test_dry_run_7")
+        .map(|chapter| chapter.group.clone())
+        .collect();
+    assert_eq!(listed, vec![String::from("First group")]);
+    assert!(job.has_chapter("test_dry_run_7"));
+    DRY_RUN.lock().retain(|chapter| chapter.number != "test_dry_run_7");
+}
@@ -0,0 +1,190 @@
+//! Sanitising of file and folder names.
+//!
+//! All names written by the program go through `name`, so the same title results in the same file
+//! name on every platform. Names are cut only at grapheme boundaries, so multi-byte titles
+//! (Japanese, Korean, accented Latin, emoji) are never split in the middle of a character.
+
+use std::path::Path;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Characters which are not allowed in file names on at least one platform.
+const RESERVED_CHARS: [char; 9] = ['<', '>', ':', '|', '?', '*', '/', '\\', '"'];
+
+/// Device names reserved by Windows; they are reserved with any extension too, e.g. `CON.cbz`.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON",
+    "PRN",
+    "AUX",
+    "NUL",
+    "COM1",
+    "COM2",
+    "COM3",
+    "COM4",
+    "COM5",
+    "COM6",
+    "COM7",
+    "COM8",
+    "COM9",
+    "LPT1",
+    "LPT2",
+    "LPT3",
+    "LPT4",
+    "LPT5",
+    "LPT6",
+    "LPT7",
+    "LPT8",
+    "LPT9",
+];
+
+/// Maximum length of a single file or folder name in bytes; most file systems allow 255.
+pub(crate) const MAX_NAME: usize = 255;
+
+/// Maximum length of a full path in UTF-16 units; `MAX_PATH` of Windows without long paths.
+pub(crate) const MAX_PATH: usize = 260;
+
+/// Maximum length of a manga name in graphemes, longer names are cut and end with `__`.
+pub(crate) const MAX_MANGA_NAME: usize = 70;
+
+/// Makes `name` usable as a file or folder name on all platforms.
+///
+/// Reserved and control characters are removed, trailing dots and spaces (which Windows drops)
+/// are trimmed, reserved device names get `_` appended and the name is cut to `MAX_NAME` bytes.
+pub(crate) fn name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .filter(|ch| !RESERVED_CHARS.contains(ch) && !ch.is_control())
+        .collect();
+    let name = truncate_bytes(name.trim(), MAX_NAME);
+    let name = name.trim_end_matches(['.', ' ']).trim_start();
+    let stem = match name.split_once('.') {
+        Some((stem, _)) => stem,
+        None => name,
+    };
+    if RESERVED_NAMES.contains(&stem.trim_end().to_uppercase().as_str()) {
+        // Appended after the stem, so `CON.cbz` becomes `CON_.cbz`
+        return format!("{}_{}", stem, &name[stem.len()..]);
+    }
+    name.to_string()
+}
+
+/// Makes manga title usable as a folder name; titles longer than `MAX_MANGA_NAME` graphemes are
+/// cut and end with `__`.
+pub(crate) fn manga_name(title: &str) -> String {
+    let title = title.trim();
+    if title.graphemes(true).count() > MAX_MANGA_NAME {
+        name(&format!("{}__", truncate(title, MAX_MANGA_NAME)))
+    } else {
+        name(title)
+    }
+}
+
+/// Returns first `max` graphemes of `name`.
+pub(crate) fn truncate(name: &str, max: usize) -> &str {
+    match name.grapheme_indices(true).nth(max) {
+        Some((index, _)) => &name[..index],
+        None => name,
+    }
+}
+
+/// Returns longest prefix of `name` with at most `max` bytes which ends at a grapheme boundary.
+pub(crate) fn truncate_bytes(name: &str, max: usize) -> &str {
+    if name.len() <= max {
+        return name;
+    }
+    let mut end = 0;
+    for (index, grapheme) in name.grapheme_indices(true) {
+        if index + grapheme.len() > max {
+            break;
+        }
+        end = index + grapheme.len();
+    }
+    &name[..end]
+}
+
+/// Cuts `name` so that `dir\name{suffix}` fits into `MAX_PATH`.
+pub(crate) fn fit_path(dir: &str, name: &str, suffix: &str) -> String {
+    fit(name, MAX_PATH.saturating_sub(path_length(dir) + 1 + utf16_length(suffix)))
+}
+
+/// Cuts `name` at grapheme boundary to at most `available` UTF-16 units.
+///
+/// Name is never cut to less than one grapheme; if even that doesn't fit, the path is left for
+/// the file system to refuse.
+pub(crate) fn fit(name: &str, available: usize) -> String {
+    if utf16_length(name) <= available {
+        return name.to_string();
+    }
+    let mut end = 0;
+    let mut length = 0;
+    for (index, grapheme) in name.grapheme_indices(true) {
+        length += utf16_length(grapheme);
+        if length > available && end != 0 {
+            break;
+        }
+        end = index + grapheme.len();
+    }
+    name[..end].trim_end_matches(['.', ' ']).to_string()
+}
+
+/// Returns length of the absolute path of `dir`; relative `dir` is resolved against current
+/// working directory.
+pub(crate) fn path_length(dir: &str) -> usize {
+    if Path::new(dir).is_absolute() {
+        return utf16_length(dir);
+    }
+    match std::env::current_dir() {
+        Ok(cwd) => utf16_length(&cwd.to_string_lossy()) + 1 + utf16_length(dir),
+        Err(_err) => utf16_length(dir),
+    }
+}
+
+/// Returns name for a chapter which would overwrite a different chapter with the same `name`.
+///
+/// Start of the chapter id is appended, so the result doesn't depend on the order in which
+/// chapters were downloaded.
+pub(crate) fn with_id(name: &str, id: &str) -> String {
+    format!("{} [{}]", name, truncate(id, 8))
+}
+
+fn utf16_length(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+// Test of removing reserved characters and names
+#[test]
+fn test_name_reserved() {
+    assert_eq!(name("Re:Zero?"), "ReZero");
+    assert_eq!(name("Title..."), "Title");
+    assert_eq!(name("con"), "con_");
+    assert_eq!(name("CON.cbz"), "CON_.cbz");
+    assert_eq!(name("Console"), "Console");
+    assert_eq!(name("a\u{0}b\nc"), "abc");
+}
+
+// Test of cutting multi-byte names at grapheme boundaries
+#[test]
+fn test_truncate_graphemes() {
+    let title = "進撃の巨人".repeat(20);
+    let name = manga_name(&title);
+    assert_eq!(name.graphemes(true).count(), MAX_MANGA_NAME + 2);
+    assert!(name.ends_with("__"));
+    assert_eq!(truncate_bytes("éé", 3), "é");
+    assert_eq!(truncate_bytes("e\u{301}e\u{301}", 4), "e\u{301}");
+    assert_eq!(fit_path("/", &"ä".repeat(300), ".cbz").encode_utf16().count(), MAX_PATH - 6);
+}
+
+// Given a valid filename with no special characters, it should return the same filename
+#[test]
+fn should_return_same_filename() {
+    let filename = "test.txt";
+    let result = name(filename);
+    assert_eq!(result, "test.txt");
+}
+
+// Given a filename with only special characters, it should return an empty string
+#[test]
+fn should_return_empty_string() {
+    let filename = "<>:|?*/\\\"";
+    let result = name(filename);
+    assert_eq!(result, "");
+}
@@ -1,30 +1,145 @@
 use if_addrs::get_if_addrs;
+use lazy_static::lazy_static;
 use serde_json::{ Value, json };
 use std::{
     fs::{ self, File },
     io::{ self, BufRead, BufReader, Read, Write },
     net::{ TcpListener, TcpStream },
-    path::Path,
+    path::{ Component, Path, PathBuf },
     thread,
 };
+use walkdir::WalkDir;
 include!(concat!(env!("OUT_DIR"), "/error_404_jpg.rs"));
 
 use crate::{
     args,
+    auth::{ self, Role },
     error::MdownError,
     getter::get_query,
     handle_error,
+    library,
     log,
+    opds,
+    reader,
     utils,
     version_manager::get_current_version,
     zip_func,
 };
 
+lazy_static! {
+    /// Canonical folders which can be accessed through the server, see `get_roots`.
+    static ref ROOTS: Vec<PathBuf> = get_roots();
+}
+
+/// Reason why a requested path isn't served.
+#[derive(Debug, PartialEq)]
+enum Denied {
+    /// The path doesn't exist.
+    NotFound,
+    /// The path is outside of library roots, e.g. `..`, an absolute path or a symlink leading out.
+    Forbidden,
+}
+
+/// Returns canonical folders set by `settings --library-roots`, or the working directory if the
+/// setting is empty; roots which don't exist are left out.
+fn get_roots() -> Vec<PathBuf> {
+    let roots = match library::get_roots() {
+        Ok(roots) => roots,
+        Err(err) => {
+            handle_error!(&MdownError::ChainedError(Box::new(err), 11243), String::from("server_roots"));
+            Vec::new()
+        }
+    };
+    if roots.is_empty() {
+        return fs::canonicalize(".").into_iter().collect();
+    }
+    roots
+        .iter()
+        .filter_map(|root| fs::canonicalize(root).ok())
+        .collect()
+}
+
+/// Resolves `path` of a request to a canonical path inside one of `roots`.
+///
+/// Relative paths are relative to the first root. `..` and symlinks are resolved before the path
+/// is compared with roots, so neither can lead out of them; path which doesn't exist is
+/// `NotFound` only if it would be inside a root.
+fn resolve(roots: &[PathBuf], path: &str) -> Result<PathBuf, Denied> {
+    let root = match roots.first() {
+        Some(root) => root,
+        None => {
+            return Err(Denied::Forbidden);
+        }
+    };
+    // Absolute `path` replaces the root
+    let path = root.join(path);
+    match fs::canonicalize(&path) {
+        Ok(path) if is_within(roots, &path) => Ok(path),
+        Ok(_) => Err(Denied::Forbidden),
+        Err(_err) if is_within(roots, &normalize(&path)) => Err(Denied::NotFound),
+        Err(_err) => Err(Denied::Forbidden),
+    }
+}
+
+/// Returns whether `path` of the library, e.g. a chapter archive, is inside library roots after
+/// symlinks are resolved.
+pub(crate) fn within_roots(path: &str) -> bool {
+    resolve(&ROOTS, path).is_ok()
+}
+
+fn is_within(roots: &[PathBuf], path: &Path) -> bool {
+    roots.iter().any(|root| path.starts_with(root))
+}
+
+/// Removes `.` and `..` from `path` without accessing the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Returns whether a file in folder `dir` leads out of `roots` through a symlink.
+fn escapes(roots: &[PathBuf], dir: &Path) -> bool {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .any(|entry| {
+            match fs::canonicalize(entry.path()) {
+                Ok(path) => !is_within(roots, &path),
+                Err(_err) => entry.path_is_symlink(),
+            }
+        })
+}
+
+/// Sends response to a request of a path which isn't served; forbidden requests are logged.
+fn deny(stream: &mut TcpStream, denied: Denied, path: &str) -> Result<(), MdownError> {
+    let response = match denied {
+        Denied::NotFound => "HTTP/1.1 404 NOT FOUND\r\nContent-Length: 0\r\n\r\n",
+        Denied::Forbidden => {
+            log!(&format!("[server] 403 Forbidden: {}", path));
+            "HTTP/1.1 403 FORBIDDEN\r\nContent-Length: 0\r\n\r\n"
+        }
+    };
+    match stream.write_all(response.as_bytes()) {
+        Ok(()) => Ok(()),
+        Err(err) => Err(MdownError::IoError(err, String::new(), 11244)),
+    }
+}
+
 /// Retrieves the content of a directory and returns it as a JSON object.
 ///
 /// # Parameters
 ///
-/// - `path: &str` - The path of the directory to scan. The path is URL-decoded before being used.
+/// - `roots: &[PathBuf]` - Library roots; entries leading out of them through symlinks are left out.
+/// - `path: &Path` - The path of the directory to scan, resolved by `resolve`.
 ///
 /// # Returns
 ///
@@ -35,40 +150,39 @@ use crate::{
 ///   - `"type"` (`"file"` or `"directory"`): Indicates whether the entry is a file or a directory.
 ///   - `"content"` (`Object`): If the entry is a directory, contains its nested contents.
 /// - `Err(MdownError)` - Returns an error in the following cases:
-///   - `ConversionError`: If decoding the input path fails (error code `11200`, `11203`).
+///   - `ConversionError`: If a file name isn't valid UTF-8 (error code `11203`).
 ///   - `IoError`: If reading the directory (`11201`), accessing an entry (`11202`),
 ///     retrieving metadata (`11204`), or fetching the modification time (`11205`) fails.
 ///   - `NotFoundError`: If `file_info` cannot be converted to a mutable object (`11206`).
 ///
 /// # Behavior
 ///
-/// - Decodes the URL-encoded path before use.
 /// - Iterates over entries in the specified directory.
 /// - Gathers metadata for each entry (size, modification time, type).
 /// - Recursively retrieves content for subdirectories.
 /// - Returns a structured JSON representation of the directory tree.
-fn get_directory_content(path: &str) -> Result<Value, MdownError> {
+fn get_directory_content(roots: &[PathBuf], path: &Path) -> Result<Value, MdownError> {
     let mut result = serde_json::Map::new();
-    let decoded_str = match percent_encoding::percent_decode_str(path).decode_utf8() {
-        Ok(decoded_str) => decoded_str.to_string(),
-        Err(err) => {
-            return Err(MdownError::ConversionError(err.to_string(), 11200));
-        }
-    };
 
-    let dir = match fs::read_dir(&decoded_str) {
+    let dir = match fs::read_dir(path) {
         Ok(dir) => dir,
         Err(err) => {
-            return Err(MdownError::IoError(err, decoded_str, 11201));
+            return Err(MdownError::IoError(err, path.to_string_lossy().to_string(), 11201));
         }
     };
     for entry in dir {
         let entry = match entry {
             Ok(entry) => entry,
             Err(err) => {
-                return Err(MdownError::IoError(err, decoded_str, 11202));
+                return Err(MdownError::IoError(err, path.to_string_lossy().to_string(), 11202));
             }
         };
+        match fs::canonicalize(entry.path()) {
+            Ok(path) if is_within(roots, &path) => (),
+            _ => {
+                continue;
+            }
+        }
         let file_name = match entry.file_name().into_string() {
             Ok(file_name) => file_name,
             Err(_err) => {
@@ -100,7 +214,7 @@ fn get_directory_content(path: &str) -> Result<Value, MdownError> {
         });
 
         if metadata.is_dir() {
-            if let Ok(sub_dir_content) = get_directory_content(&entry.path().to_string_lossy()) {
+            if let Ok(sub_dir_content) = get_directory_content(roots, &entry.path()) {
                 match file_info.as_object_mut() {
                     Some(value) => value.insert("content".to_string(), sub_dir_content),
                     None => {
@@ -144,6 +258,8 @@ fn get_directory_content(path: &str) -> Result<Value, MdownError> {
 /// - `/__download__?path=<dir>`: Creates and sends a `.zip` archive of the specified directory.
 /// - `/__version__`: Returns the current application version as plain text.
 /// - `/__get__?path=<resource>`: Returns predefined resources (e.g., `error_404` image).
+/// - `/reader` and `/__reader__/...`: Reader app and its series, chapters and pages, see `reader`.
+/// - `/opds/...`: OPDS catalog of the library, see `opds`.
 /// - `/` (Root path): Returns the main HTML page.
 /// - Any other path:
 ///   - If it's a file, serves it as a downloadable attachment.
@@ -152,6 +268,8 @@ fn get_directory_content(path: &str) -> Result<Value, MdownError> {
 /// # Behavior
 ///
 /// - Reads the request line and extracts the request path.
+/// - Rejects requests of users without permission, see `auth`; `/__download__` requires `admin`.
+/// - Serves only files inside library roots, see `resolve`; other paths get `403 FORBIDDEN`.
 /// - Decodes percent-encoded URLs before processing.
 /// - Handles file system operations securely (reading, writing, compressing).
 /// - Sends appropriate HTTP responses based on the request.
@@ -166,6 +284,24 @@ fn handle_client(stream: TcpStream) -> Result<(), MdownError> {
             return Err(MdownError::IoError(err, String::new(), 11207));
         }
     }
+    // Headers are needed only for authentication
+    let mut request = request_line.clone();
+    loop {
+        let mut line = String::new();
+        match stream.read_line(&mut line) {
+            Ok(0) => {
+                break;
+            }
+            Ok(_n) => (),
+            Err(err) => {
+                return Err(MdownError::IoError(err, String::new(), 11239));
+            }
+        }
+        if line.trim().is_empty() {
+            break;
+        }
+        request.push_str(&line);
+    }
 
     let parts: Vec<&str> = request_line.split_whitespace().collect();
     let path = request_line.split_whitespace().nth(1).unwrap_or("/");
@@ -188,6 +324,14 @@ fn handle_client(stream: TcpStream) -> Result<(), MdownError> {
             }
         }
     }
+    // Zipping writes to the working directory, so it needs admin
+    let role = if path.starts_with("/__download__") { Role::Admin } else { Role::Read };
+    if let Some(response) = auth::reject(&request, role) {
+        return match stream.get_mut().write_all(response.as_bytes()) {
+            Ok(()) => Ok(()),
+            Err(err) => Err(MdownError::IoError(err, String::new(), 11240)),
+        };
+    }
     if parts.len() >= 2 {
         let query_params = get_query(parts);
         if path.starts_with("/__search__") {
@@ -199,7 +343,19 @@ fn handle_client(stream: TcpStream) -> Result<(), MdownError> {
             } else {
                 String::from(".")
             };
-            let json_response = match get_directory_content(&file_path) {
+            let decoded_str = match percent_encoding::percent_decode_str(&file_path).decode_utf8() {
+                Ok(decoded_str) => decoded_str.to_string(),
+                Err(err) => {
+                    return Err(MdownError::ConversionError(err.to_string(), 11200));
+                }
+            };
+            let dir = match resolve(&ROOTS, &decoded_str) {
+                Ok(dir) => dir,
+                Err(denied) => {
+                    return deny(stream.get_mut(), denied, &decoded_str);
+                }
+            };
+            let json_response = match get_directory_content(&ROOTS, &dir) {
                 Ok(value) => value,
                 Err(err) => {
                     return Err(MdownError::JsonError(err.to_string(), 11208));
@@ -225,18 +381,24 @@ fn handle_client(stream: TcpStream) -> Result<(), MdownError> {
             };
         } else if path.starts_with("/__preview__?") {
             let file_path = match query_params.get("path").cloned() {
-                Some(value) => format!(".\\{}", value),
+                Some(value) => value,
                 None => {
                     return Ok(());
                 }
             };
 
             let decoded_str = match percent_encoding::percent_decode_str(&file_path).decode_utf8() {
-                Ok(decoded_str) => decoded_str.to_string().replace("./", "").replace("/", ""),
+                Ok(decoded_str) => decoded_str.to_string(),
                 Err(err) => {
                     return Err(MdownError::ConversionError(err.to_string(), 11211));
                 }
             };
+            let decoded_str = match resolve(&ROOTS, &decoded_str) {
+                Ok(file) => file.to_string_lossy().to_string(),
+                Err(denied) => {
+                    return deny(stream.get_mut(), denied, &decoded_str);
+                }
+            };
 
             let contents = if decoded_str.ends_with(".cbz") {
                 match zip_func::extract_image_from_zip(&decoded_str) {
@@ -281,7 +443,7 @@ fn handle_client(stream: TcpStream) -> Result<(), MdownError> {
                 }
             };
 
-            let mut decoded_str = match
+            let decoded_str = match
                 percent_encoding::percent_decode_str(&file_path).decode_utf8()
             {
                 Ok(decoded_str) => decoded_str.to_string(),
@@ -289,19 +451,28 @@ fn handle_client(stream: TcpStream) -> Result<(), MdownError> {
                     return Err(MdownError::ConversionError(err.to_string(), 11215));
                 }
             };
-
-            if decoded_str.ends_with('/') {
-                decoded_str.pop();
+            let dir = match resolve(&ROOTS, &decoded_str) {
+                Ok(dir) if dir.is_dir() => dir,
+                Ok(_) => {
+                    return deny(stream.get_mut(), Denied::NotFound, &decoded_str);
+                }
+                Err(denied) => {
+                    return deny(stream.get_mut(), denied, &decoded_str);
+                }
+            };
+            // All files of the folder are read into the archive, so none may lead out of roots
+            if escapes(&ROOTS, &dir) {
+                return deny(stream.get_mut(), Denied::Forbidden, &decoded_str);
             }
 
-            let dst_file = match decoded_str.split('/').last() {
-                Some(value) => format!("{}.zip", value),
+            let dst_file = match dir.file_name() {
+                Some(value) => format!("{}.zip", value.to_string_lossy()),
                 None => {
                     return Ok(());
                 }
             };
 
-            zip_func::to_zip(&decoded_str, &dst_file);
+            zip_func::to_zip(&dir.to_string_lossy(), &dst_file);
 
             let contents = match fs::read(&dst_file) {
                 Ok(contents) => contents,
@@ -375,6 +546,50 @@ fn handle_client(stream: TcpStream) -> Result<(), MdownError> {
                     return Err(MdownError::IoError(err, String::new(), 11223));
                 }
             }
+        } else if
+            path.starts_with("/reader") ||
+            path.starts_with("/__reader__/") ||
+            path.starts_with("/opds")
+        {
+            let route = path.split('?').next().unwrap_or_default();
+            let result = if route.starts_with("/opds") {
+                opds::handle(route, &query_params)
+            } else {
+                reader::handle(route, &query_params)
+            };
+            let (status, content_type, content) = match result {
+                Ok((content_type, content)) => ("200 OK", content_type, content),
+                Err(err) => {
+                    let status = if reader::is_forbidden(&err) {
+                        "403 FORBIDDEN"
+                    } else {
+                        "404 NOT FOUND"
+                    };
+                    handle_error!(&err, String::from(if route.starts_with("/opds") { "opds" } else { "reader" }));
+                    (status, "text/plain", Vec::new())
+                }
+            };
+            // Pages and covers don't change, so the browser can keep preloaded pages
+            let cache = if content_type.starts_with("image/") { "max-age=3600" } else { "no-cache" };
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: {}\r\nCache-Control: {}\r\nContent-Length: {}\r\n\r\n",
+                status,
+                content_type,
+                cache,
+                content.len()
+            );
+            match stream.get_mut().write_all(response.as_bytes()) {
+                Ok(_n) => (),
+                Err(err) => {
+                    return Err(MdownError::IoError(err, String::new(), 11237));
+                }
+            }
+            match stream.get_mut().write_all(&content) {
+                Ok(_n) => (),
+                Err(err) => {
+                    return Err(MdownError::IoError(err, String::new(), 11238));
+                }
+            }
         } else if path == "/" {
             let html = get_html();
             let response = format!(
@@ -395,8 +610,14 @@ fn handle_client(stream: TcpStream) -> Result<(), MdownError> {
                     return Err(MdownError::ConversionError(err.to_string(), 11225));
                 }
             };
-            let file_path = format!(".{}", decoded_str);
-            if Path::new(&file_path).is_file() {
+            let file_path = match resolve(&ROOTS, decoded_str.trim_start_matches('/')) {
+                Ok(file_path) => Some(file_path),
+                Err(Denied::NotFound) => None,
+                Err(denied) => {
+                    return deny(stream.get_mut(), denied, &decoded_str);
+                }
+            };
+            if let Some(file_path) = file_path.filter(|file_path| file_path.is_file()) {
                 let contents = match fs::read(&file_path) {
                     Ok(contents) => contents,
                     Err(err) => {
@@ -404,8 +625,8 @@ fn handle_client(stream: TcpStream) -> Result<(), MdownError> {
                     }
                 };
                 let mut response = String::new();
-                let filename = match file_path.split("/").last() {
-                    Some(value) => value.to_owned(),
+                let filename = match file_path.file_name() {
+                    Some(value) => value.to_string_lossy().to_string(),
                     None => format!("{}.cbz", utils::generate_random_id(16)),
                 };
                 response.push_str("HTTP/1.1 200 OK\r\n");
@@ -490,53 +711,24 @@ fn get_html() -> String {
 }
 
 pub(crate) fn start() -> Result<(), MdownError> {
-    let mut ips = vec![];
-    if let Ok(interfaces) = get_if_addrs() {
-        for (times, interface) in interfaces.iter().enumerate() {
-            println!("{}) {}", times + 1, interface.ip());
-            ips.push(interface.ip().to_string());
-        }
-    } else {
-        println!("Unable to retrieve interface addresses");
-    }
-
-    print!("> ");
-    match io::stdout().flush() {
-        Ok(_) => (),
-        Err(err) => {
-            return Err(MdownError::IoError(err, String::new(), 11230));
+    let ip_address = match args::ARGS_BIND.as_ref() {
+        Some(address) => address.clone(),
+        None => {
+            match choose_interface() {
+                Ok(address) => address,
+                Err(err) => {
+                    return Err(MdownError::ChainedError(Box::new(err), 11241));
+                }
+            }
         }
-    }
-
-    let mut input = String::new();
-
-    match io::stdin().read_line(&mut input) {
-        Ok(_) => (),
+    };
+    match auth::load(&ip_address) {
+        Ok(()) => (),
         Err(err) => {
-            return Err(MdownError::IoError(err, String::new(), 11231));
+            return Err(MdownError::ChainedError(Box::new(err), 11242));
         }
     }
 
-    let number: usize = match input.trim().parse() {
-        Ok(value) => value,
-        Err(err) => {
-            return Err(MdownError::ConversionError(err.to_string(), 11232));
-        }
-    };
-
-    let ip_address = match ips.get(number - 1) {
-        Some(value) => value,
-        None => {
-            return Err(
-                MdownError::CustomError(
-                    String::from("Invalid IP address"),
-                    String::from("IP_address"),
-                    11233
-                )
-            );
-        }
-    };
-
     let handler = ctrlc::set_handler(|| {
         log!("[user] Ctrl+C received! Exiting...");
         log!("[web] Closing server");
@@ -570,6 +762,8 @@ pub(crate) fn start() -> Result<(), MdownError> {
         }
     };
     println!("Server listening on {}:80 ...", ip_address);
+    println!("Reader on http://{}:80/reader", ip_address);
+    println!("OPDS catalog on http://{}:80/opds", ip_address);
 
     let url = format!("http://{}:80/", ip_address);
     if let Err(err) = webbrowser::open(&url) {
@@ -586,3 +780,104 @@ pub(crate) fn start() -> Result<(), MdownError> {
 
     Ok(())
 }
+
+/// Prints addresses of network interfaces and returns the one chosen by the user.
+fn choose_interface() -> Result<String, MdownError> {
+    let mut ips = vec![];
+    if let Ok(interfaces) = get_if_addrs() {
+        for (times, interface) in interfaces.iter().enumerate() {
+            println!("{}) {}", times + 1, interface.ip());
+            ips.push(interface.ip().to_string());
+        }
+    } else {
+        println!("Unable to retrieve interface addresses");
+    }
+
+    print!("> ");
+    match io::stdout().flush() {
+        Ok(_) => (),
+        Err(err) => {
+            return Err(MdownError::IoError(err, String::new(), 11230));
+        }
+    }
+
+    let mut input = String::new();
+
+    match io::stdin().read_line(&mut input) {
+        Ok(_) => (),
+        Err(err) => {
+            return Err(MdownError::IoError(err, String::new(), 11231));
+        }
+    }
+
+    let number: usize = match input.trim().parse() {
+        Ok(value) => value,
+        Err(err) => {
+            return Err(MdownError::ConversionError(err.to_string(), 11232));
+        }
+    };
+
+    match ips.get(number - 1) {
+        Some(value) => Ok(value.clone()),
+        None => {
+            Err(
+                MdownError::CustomError(
+                    String::from("Invalid IP address"),
+                    String::from("IP_address"),
+                    11233
+                )
+            )
+        }
+    }
+}
+
+// Test of resolving of paths of requests, including traversal, absolute paths and symlinks
+#[test]
+fn test_resolve() {
+    let base = std::env::temp_dir().join(format!("mdown_server_{}", utils::generate_random_id(8)));
+    let root = base.join("library");
+    let outside = base.join("outside");
+    fs::create_dir_all(root.join("manga")).unwrap();
+    fs::create_dir_all(&outside).unwrap();
+    fs::write(root.join("manga").join("page.png"), b"page").unwrap();
+    fs::write(outside.join("secret.txt"), b"secret").unwrap();
+    let roots = vec![fs::canonicalize(&root).unwrap()];
+    let secret = fs::canonicalize(outside.join("secret.txt")).unwrap();
+
+    assert_eq!(resolve(&roots, "."), Ok(roots[0].clone()));
+    assert_eq!(resolve(&roots, ""), Ok(roots[0].clone()));
+    assert_eq!(resolve(&roots, "manga/page.png"), Ok(roots[0].join("manga").join("page.png")));
+    assert_eq!(resolve(&roots, "./manga/../manga/page.png"), Ok(roots[0].join("manga").join("page.png")));
+    assert_eq!(resolve(&roots, "missing.png"), Err(Denied::NotFound));
+    assert_eq!(resolve(&roots, "manga/../missing.png"), Err(Denied::NotFound));
+    assert_eq!(resolve(&roots, ".."), Err(Denied::Forbidden));
+    assert_eq!(resolve(&roots, "../outside/secret.txt"), Err(Denied::Forbidden));
+    assert_eq!(resolve(&roots, "manga/../../outside/secret.txt"), Err(Denied::Forbidden));
+    assert_eq!(resolve(&roots, "../outside/missing.txt"), Err(Denied::Forbidden));
+    assert_eq!(resolve(&roots, "../library_other"), Err(Denied::Forbidden));
+    assert_eq!(resolve(&roots, &secret.to_string_lossy()), Err(Denied::Forbidden));
+    assert_eq!(resolve(&roots, &base.to_string_lossy()), Err(Denied::Forbidden));
+    assert_eq!(resolve(&[], "."), Err(Denied::Forbidden));
+
+    // Files in other roots are accessible by absolute path
+    let both = vec![roots[0].clone(), fs::canonicalize(&outside).unwrap()];
+    assert_eq!(resolve(&both, &secret.to_string_lossy()), Ok(secret.clone()));
+    assert!(!escapes(&roots, &roots[0]));
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+        std::os::unix::fs::symlink(&secret, root.join("manga").join("secret.txt")).unwrap();
+        assert_eq!(resolve(&roots, "link"), Err(Denied::Forbidden));
+        assert_eq!(resolve(&roots, "link/secret.txt"), Err(Denied::Forbidden));
+        assert_eq!(resolve(&roots, "manga/secret.txt"), Err(Denied::Forbidden));
+        assert!(escapes(&roots, &roots[0].join("manga")));
+
+        let content = get_directory_content(&roots, &roots[0]).unwrap();
+        assert!(content.get("link").is_none());
+        assert!(content["manga"]["content"].get("page.png").is_some());
+        assert!(content["manga"]["content"].get("secret.txt").is_none());
+    }
+
+    fs::remove_dir_all(&base).unwrap();
+}
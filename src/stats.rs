@@ -0,0 +1,348 @@
+//! Statistics of the library.
+//!
+//! `library stats` reports totals and numbers of every manga: chapters, pages and disk usage, with
+//! breakdowns by language, scanlation group, genre, theme and month of the first download. Pages
+//! and scanlation groups are read from `_metadata` of downloaded chapters; missing chapters are
+//! counted from the cached chapter feed, so no request is sent to mangadex.
+
+use serde_json::Value;
+use std::{ collections::{ BTreeSet, HashMap }, path::Path };
+use walkdir::WalkDir;
+
+use crate::{
+    db,
+    error::MdownError,
+    library_db,
+    metadata::{ Dat, LibraryStats, MangaMetadata, MangaStats, StatsEntry },
+    resolute,
+};
+
+/// Prints statistics of the library in `format` (`table`, `json` or `markdown`); lists of the
+/// largest series and of series with the most missing chapters have at most `top` entries.
+pub(crate) fn show(format: &str, top: usize) -> Result<(), MdownError> {
+    let dat = match library_db::load() {
+        Ok(dat) => dat,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12700));
+        }
+    };
+    let stats = collect(&dat, top);
+    let output = match format.to_ascii_lowercase().as_str() {
+        "table" => render(&stats, false),
+        "markdown" | "md" => render(&stats, true),
+        "json" =>
+            match serde_json::to_string_pretty(&stats) {
+                Ok(json) => json,
+                Err(err) => {
+                    return Err(MdownError::JsonError(err.to_string(), 12701));
+                }
+            }
+        _ => {
+            return Err(
+                MdownError::CustomError(
+                    format!("Unknown format {}; use table, json or markdown", format),
+                    String::from("UserError"),
+                    12702
+                )
+            );
+        }
+    };
+    println!("{}", output);
+    Ok(())
+}
+
+/// Chapter archives of a manga folder.
+#[derive(Default)]
+struct FolderStats {
+    /// Size of all files in the folder.
+    size: u64,
+
+    /// Pages of downloaded chapters.
+    pages: u64,
+
+    /// Number of downloaded chapters by scanlation group.
+    groups: HashMap<String, usize>,
+}
+
+fn read_folder(mwd: &str) -> FolderStats {
+    let mut stats = FolderStats::default();
+    for entry in WalkDir::new(mwd).into_iter().filter_map(Result::ok) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        stats.size += entry
+            .metadata()
+            .map(|metadata| metadata.len())
+            .unwrap_or_default();
+        if !is_cbz(entry.path()) || resolute::is_version(&entry.path().to_string_lossy()) {
+            continue;
+        }
+        if let Ok(metadata) = resolute::check_for_metadata(&entry.path().to_string_lossy()) {
+            stats.pages += metadata.pages.parse::<u64>().unwrap_or_default();
+            let group = if metadata.scanlation.name.is_empty() {
+                String::from("Unknown")
+            } else {
+                metadata.scanlation.name
+            };
+            *stats.groups.entry(group).or_default() += 1;
+        }
+    }
+    stats
+}
+
+fn is_cbz(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("cbz"))
+}
+
+/// Returns number of chapters in the cached feed of `item` in its language which are not in the
+/// library, or `None` if the feed is not cached.
+fn missing_chapters(item: &MangaMetadata) -> Option<usize> {
+    let feed = db::get_feed(&item.id).unwrap_or_default();
+    if feed.is_empty() {
+        return None;
+    }
+    let available: BTreeSet<&str> = feed
+        .iter()
+        .filter_map(|chapter| chapter.get("attributes"))
+        .filter(|attributes| {
+            item.current_language == "*" ||
+                attributes.get("translatedLanguage").and_then(Value::as_str) ==
+                    Some(item.current_language.as_str())
+        })
+        .filter_map(|attributes| attributes.get("chapter").and_then(Value::as_str))
+        .collect();
+    let downloaded: BTreeSet<&str> = item.chapters
+        .iter()
+        .map(|chapter| chapter.number.as_str())
+        .collect();
+    Some(available.difference(&downloaded).count())
+}
+
+fn collect(dat: &Dat, top: usize) -> LibraryStats {
+    let mut stats = LibraryStats { manga: dat.data.len(), ..Default::default() };
+    let mut languages = HashMap::new();
+    let mut groups = HashMap::new();
+    let mut genres = HashMap::new();
+    let mut themes = HashMap::new();
+    let mut dates = HashMap::new();
+    for item in dat.data.iter() {
+        let folder = read_folder(&item.mwd);
+        let chapters = item.chapters.len();
+        stats.chapters += chapters;
+        stats.pages += folder.pages;
+        stats.size += folder.size;
+
+        add(&mut languages, &item.current_language, chapters);
+        for (group, count) in folder.groups.iter() {
+            add(&mut groups, group, *count);
+        }
+        for tag in item.genre.iter() {
+            add(&mut genres, &tag.name, chapters);
+        }
+        for tag in item.theme.iter() {
+            add(&mut themes, &tag.name, chapters);
+        }
+        let month = item.date
+            .first()
+            .and_then(|date| date.get(..7))
+            .unwrap_or("Unknown");
+        add(&mut dates, month, chapters);
+
+        stats.series.push(MangaStats {
+            id: item.id.clone(),
+            name: item.name.clone(),
+            language: item.current_language.clone(),
+            chapters,
+            pages: folder.pages,
+            size: folder.size,
+            missing: missing_chapters(item),
+        });
+    }
+    stats.languages = sorted(languages);
+    stats.groups = sorted(groups);
+    stats.genres = sorted(genres);
+    stats.themes = sorted(themes);
+    stats.dates = sorted(dates);
+    // Months are listed in order, not by number of manga
+    stats.dates.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut largest = stats.series.clone();
+    largest.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    largest.truncate(top);
+    stats.largest = largest;
+
+    let mut most_missing: Vec<MangaStats> = stats.series
+        .iter()
+        .filter(|series| series.missing.unwrap_or_default() > 0)
+        .cloned()
+        .collect();
+    most_missing.sort_by(|a, b| b.missing.cmp(&a.missing).then_with(|| a.name.cmp(&b.name)));
+    most_missing.truncate(top);
+    stats.most_missing = most_missing;
+    stats
+}
+
+/// Adds a manga with `chapters` chapters to group `name` of a breakdown.
+fn add(entries: &mut HashMap<String, StatsEntry>, name: &str, chapters: usize) {
+    let name = if name.is_empty() { "Unknown" } else { name };
+    let entry = entries.entry(name.to_string()).or_insert_with(|| StatsEntry {
+        name: name.to_string(),
+        ..Default::default()
+    });
+    entry.manga += 1;
+    entry.chapters += chapters;
+}
+
+/// Returns entries of a breakdown, the largest first.
+fn sorted(entries: HashMap<String, StatsEntry>) -> Vec<StatsEntry> {
+    let mut entries: Vec<StatsEntry> = entries.into_values().collect();
+    entries.sort_by(|a, b| {
+        b.manga
+            .cmp(&a.manga)
+            .then_with(|| b.chapters.cmp(&a.chapters))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    entries
+}
+
+/// Renders `stats` as terminal tables, or as Markdown if `markdown` is set.
+fn render(stats: &LibraryStats, markdown: bool) -> String {
+    let mut output = if markdown {
+        String::from("# Library statistics\n\n")
+    } else {
+        String::new()
+    };
+    let totals = [
+        ("Manga", stats.manga.to_string()),
+        ("Chapters", stats.chapters.to_string()),
+        ("Pages", stats.pages.to_string()),
+        ("Disk usage", bytefmt::format(stats.size)),
+    ];
+    for (name, value) in totals.iter() {
+        if markdown {
+            output += &format!("- {}: {}\n", name, value);
+        } else {
+            output += &format!("{}: {}\n", name, value);
+        }
+    }
+
+    let breakdowns = [
+        ("By language", "Language", &stats.languages),
+        ("By scanlation group", "Group", &stats.groups),
+        ("By genre", "Genre", &stats.genres),
+        ("By theme", "Theme", &stats.themes),
+        ("By download date", "Month", &stats.dates),
+    ];
+    for (title, column, entries) in breakdowns {
+        let rows: Vec<Vec<String>> = entries
+            .iter()
+            .map(|entry| vec![entry.name.clone(), entry.manga.to_string(), entry.chapters.to_string()])
+            .collect();
+        output += &section(title, &[column, "Manga", "Chapters"], &rows, markdown);
+    }
+
+    let rows: Vec<Vec<String>> = stats.largest
+        .iter()
+        .map(|series| vec![series.name.clone(), bytefmt::format(series.size), series.chapters.to_string()])
+        .collect();
+    output += &section("Largest series", &["Name", "Size", "Chapters"], &rows, markdown);
+
+    let rows: Vec<Vec<String>> = stats.most_missing
+        .iter()
+        .map(|series| vec![series.name.clone(), missing(series), series.chapters.to_string()])
+        .collect();
+    output += &section("Most missing chapters", &["Name", "Missing", "Chapters"], &rows, markdown);
+
+    let rows: Vec<Vec<String>> = stats.series
+        .iter()
+        .map(|series| {
+            vec![
+                series.name.clone(),
+                series.language.clone(),
+                series.chapters.to_string(),
+                series.pages.to_string(),
+                bytefmt::format(series.size),
+                missing(series)
+            ]
+        })
+        .collect();
+    output += &section(
+        "Series",
+        &["Name", "Language", "Chapters", "Pages", "Size", "Missing"],
+        &rows,
+        markdown
+    );
+    output.trim_end().to_string()
+}
+
+fn missing(series: &MangaStats) -> String {
+    match series.missing {
+        Some(missing) => missing.to_string(),
+        None => String::from("?"),
+    }
+}
+
+fn section(title: &str, header: &[&str], rows: &[Vec<String>], markdown: bool) -> String {
+    if rows.is_empty() {
+        return String::new();
+    }
+    if markdown {
+        let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+        let mut output = format!("\n## {}\n\n", title);
+        output += &line(header.iter().map(|cell| cell.to_string()).collect());
+        output += &line(header.iter().map(|_cell| String::from("---")).collect());
+        for row in rows.iter() {
+            output += &line(row.iter().map(|cell| cell.replace('|', "\\|")).collect());
+        }
+        return output;
+    }
+    let mut widths: Vec<usize> = header
+        .iter()
+        .map(|cell| cell.chars().count())
+        .collect();
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<String>| {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        format!("{}\n", cells.join("  ").trim_end())
+    };
+    let mut output = format!("\n{}\n", title);
+    output += &line(header.iter().map(|cell| cell.to_uppercase()).collect());
+    for row in rows.iter() {
+        output += &line(row.clone());
+    }
+    output
+}
+
+// Test of breakdowns sorted by number of manga and chapters
+#[test]
+fn test_sorted() {
+    let mut entries = HashMap::new();
+    add(&mut entries, "en", 10);
+    add(&mut entries, "ja", 5);
+    add(&mut entries, "ja", 1);
+    add(&mut entries, "", 2);
+    let names: Vec<String> = sorted(entries)
+        .into_iter()
+        .map(|entry| entry.name)
+        .collect();
+    assert_eq!(names, vec!["ja", "en", "Unknown"]);
+}
+
+// Test of aligned terminal table and Markdown table
+#[test]
+fn test_section() {
+    let rows = vec![vec![String::from("en"), String::from("2")]];
+    assert_eq!(section("By language", &["Language", "Manga"], &rows, false), "\nBy language\nLANGUAGE  MANGA\nen        2\n");
+    assert_eq!(
+        section("By language", &["Language", "Manga"], &rows, true),
+        "\n## By language\n\n| Language | Manga |\n| --- | --- |\n| en | 2 |\n"
+    );
+}
@@ -0,0 +1,292 @@
+//! Filename and folder templates.
+//!
+//! Templates decide where downloaded chapters are saved. A template is plain text with fields in
+//! braces, e.g. `{manga}/{volume:02}/{manga} c{chapter:03.1} [{group}]`. `/` (or `\`) separates
+//! folders and every folder and file name is cleaned with `sanitize::name`; folders which
+//! end up empty (e.g. `{volume}` of a chapter without volume) are left out. `{{` and `}}` are
+//! literal braces.
+//!
+//! Fields accept a spec after `:`. For numbers `0N` pads the integer part with zeroes to `N` digits
+//! and `.M` cuts the fractional part to at most `M` digits, so chapter `5.25` with `{chapter:03.1}`
+//! becomes `005.2` and chapter `5` becomes `005`. Spec of `{date}` is a `chrono` format string,
+//! e.g. `{date:%Y-%m}`.
+//!
+//! `{author}`, `{artist}`, `{year}`, `{status}` and `{demographic}` describe the manga and can be
+//! used in folder templates too, e.g. `library/{author}/{manga}`.
+//!
+//! Chapters are never looked up by their file name. Existing files are identified by `_metadata`
+//! inside the archive, so changing a template does not cause chapters to be downloaded again.
+
+use chrono::{ format::{ Item, StrftimeItems }, DateTime };
+use std::fmt::Write;
+
+use crate::{ error::MdownError, sanitize };
+
+/// Template keyword which selects the built-in naming.
+pub(crate) const DEFAULT: &str = "default";
+
+/// Fields which can be used in templates.
+pub(crate) const FIELDS: [&str; 15] = [
+    "manga",
+    "manga_id",
+    "author",
+    "artist",
+    "year",
+    "status",
+    "demographic",
+    "volume",
+    "chapter",
+    "chapter_id",
+    "title",
+    "group",
+    "group_id",
+    "language",
+    "date",
+];
+
+/// Part of a parsed template.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Text(String),
+    Field(String, String),
+}
+
+/// Returns true if `template` should be used instead of the built-in naming.
+pub(crate) fn is_custom(template: &str) -> bool {
+    !template.is_empty() && template != DEFAULT
+}
+
+/// Checks that `template` is well formed and uses only known fields.
+pub(crate) fn validate(template: &str) -> Result<(), MdownError> {
+    if !is_custom(template) {
+        return Ok(());
+    }
+    match parse(template) {
+        Ok(_tokens) => Ok(()),
+        Err(err) => Err(MdownError::ChainedError(Box::new(err), 12000)),
+    }
+}
+
+/// Renders `template` into a relative path, segments are joined with `\`.
+///
+/// `field` returns the value of the field with given name; unknown fields are rejected while
+/// parsing, so `None` is rendered as empty string.
+pub(crate) fn render<F>(template: &str, field: F) -> Result<String, MdownError>
+    where F: Fn(&str) -> Option<String>
+{
+    let tokens = match parse(template) {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 12001));
+        }
+    };
+    let mut rendered = String::new();
+    for token in tokens.iter() {
+        match token {
+            Token::Text(text) => rendered.push_str(text),
+            Token::Field(name, spec) => {
+                let value = field(name).unwrap_or_default();
+                // Separators from values must not create new folders
+                let value = value.replace(['/', '\\'], "");
+                rendered.push_str(&format_value(name, &value, spec));
+            }
+        }
+    }
+    let segments: Vec<String> = rendered
+        .split(['/', '\\'])
+        .map(sanitize::name)
+        .filter(|segment| !segment.is_empty() && segment != "." && segment != "..")
+        .collect();
+    if segments.is_empty() {
+        return Err(
+            MdownError::CustomError(
+                format!("template '{}' rendered to empty path", template),
+                String::from("TemplateError"),
+                12002
+            )
+        );
+    }
+    Ok(segments.join("\\"))
+}
+
+fn parse(template: &str) -> Result<Vec<Token>, MdownError> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut inner = String::new();
+                let mut closed = false;
+                for ch in chars.by_ref() {
+                    if ch == '}' {
+                        closed = true;
+                        break;
+                    }
+                    inner.push(ch);
+                }
+                if !closed {
+                    return Err(
+                        MdownError::CustomError(
+                            format!("unclosed field in template '{}'", template),
+                            String::from("TemplateError"),
+                            12003
+                        )
+                    );
+                }
+                let (name, spec) = match inner.split_once(':') {
+                    Some((name, spec)) => (name.trim(), spec),
+                    None => (inner.trim(), ""),
+                };
+                if !FIELDS.contains(&name) {
+                    return Err(
+                        MdownError::CustomError(
+                            format!(
+                                "unknown field '{}' in template; known fields are {}",
+                                name,
+                                FIELDS.join(", ")
+                            ),
+                            String::from("TemplateError"),
+                            12004
+                        )
+                    );
+                }
+                let valid = if name == "date" {
+                    !StrftimeItems::new(spec).any(|item| item == Item::Error)
+                } else {
+                    spec.is_empty() || parse_spec(spec).is_some()
+                };
+                if !valid {
+                    return Err(
+                        MdownError::CustomError(
+                            format!("invalid spec '{}' of field '{}' in template", spec, name),
+                            String::from("TemplateError"),
+                            12005
+                        )
+                    );
+                }
+                if !text.is_empty() {
+                    tokens.push(Token::Text(std::mem::take(&mut text)));
+                }
+                tokens.push(Token::Field(name.to_string(), spec.to_string()));
+            }
+            '}' => {
+                return Err(
+                    MdownError::CustomError(
+                        format!("unexpected '}}' in template '{}'", template),
+                        String::from("TemplateError"),
+                        12006
+                    )
+                );
+            }
+            ch => text.push(ch),
+        }
+    }
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+    Ok(tokens)
+}
+
+/// Parses number spec `0N.M` into width and precision.
+fn parse_spec(spec: &str) -> Option<(usize, Option<usize>)> {
+    let (width, precision) = match spec.split_once('.') {
+        Some((width, precision)) => (width, Some(precision)),
+        None => (spec, None),
+    };
+    let width = if width.is_empty() { 0 } else { width.parse().ok()? };
+    let precision = match precision {
+        Some(precision) => Some(precision.parse().ok()?),
+        None => None,
+    };
+    Some((width, precision))
+}
+
+fn format_value(name: &str, value: &str, spec: &str) -> String {
+    if spec.is_empty() || value.is_empty() {
+        return value.to_string();
+    }
+    if name == "date" {
+        return match DateTime::parse_from_rfc3339(value) {
+            Ok(date) => {
+                let mut formatted = String::new();
+                match write!(formatted, "{}", date.format(spec)) {
+                    Ok(()) => formatted,
+                    Err(_err) => value.to_string(),
+                }
+            }
+            Err(_err) => value.to_string(),
+        };
+    }
+    let (width, precision) = match parse_spec(spec) {
+        Some(spec) => spec,
+        None => {
+            return value.to_string();
+        }
+    };
+    let (integer, fraction) = match value.split_once('.') {
+        Some((integer, fraction)) => (integer, fraction),
+        None => (value, ""),
+    };
+    if !integer.chars().all(|ch| ch.is_ascii_digit()) || !fraction.is_ascii() {
+        return value.to_string();
+    }
+    let fraction = match precision {
+        Some(precision) => &fraction[..fraction.len().min(precision)],
+        None => fraction,
+    };
+    if fraction.is_empty() {
+        format!("{:0>width$}", integer, width = width)
+    } else {
+        format!("{:0>width$}.{}", integer, fraction, width = width)
+    }
+}
+
+// Test of padding and folders in templates
+#[test]
+fn test_render() {
+    let field = |name: &str| -> Option<String> {
+        match name {
+            "manga" => Some(String::from("Manga: Name")),
+            "volume" => Some(String::from("3")),
+            "chapter" => Some(String::from("5.25")),
+            "group" => Some(String::from("Group/Name")),
+            "date" => Some(String::from("2024-08-31T00:00:00+00:00")),
+            _ => None,
+        }
+    };
+    assert_eq!(
+        render("{manga}/{volume:02}/{manga} c{chapter:03.1} [{group}]", field).unwrap(),
+        "Manga Name\\03\\Manga Name c005.2 [GroupName]"
+    );
+    assert_eq!(render("{manga}/{title}/{{{date:%Y}}}", field).unwrap(), "Manga Name\\{2024}");
+}
+
+// Test of number formatting with values that aren't plain numbers
+#[test]
+fn test_format_value() {
+    assert_eq!(format_value("chapter", "12.345", "03.1"), "012.3");
+    assert_eq!(format_value("chapter", "12", "03.1"), "012");
+    assert_eq!(format_value("chapter", "12.日本", "03.1"), "12.日本");
+    assert_eq!(format_value("chapter", "日.5", "03.1"), "日.5");
+    assert_eq!(format_value("chapter", "Extra", "03"), "Extra");
+}
+
+// Test of template validation
+#[test]
+fn test_validate() {
+    assert!(validate("{manga} - {chapter:03}").is_ok());
+    assert!(validate(DEFAULT).is_ok());
+    assert!(validate("{manga").is_err());
+    assert!(validate("{unknown}").is_err());
+    assert!(validate("{chapter:x}").is_err());
+    assert!(validate("{date:%Q}").is_err());
+}
@@ -22,11 +22,15 @@ use crate::{
     error::{ MdownError, suspend_error },
     getter,
     IS_END,
+    job::{ self, Job },
+    library_db,
     log,
     MAXPOINTS,
     metadata,
-    resolute::{ self, resolve_move, CURRENT_PERCENT, CURRENT_SIZE, CURRENT_SIZE_MAX },
+    resolute::{ self, resolve_move },
+    sanitize,
     string,
+    template,
     version_manager::get_current_version,
 };
 
@@ -157,14 +161,15 @@ pub(crate) fn log_handler() {
                     );
                 }
                 16 => {
-                    let manga_name = resolute::MANGA_NAME.lock().clone();
+                    let job = job::current();
+                    let manga_name = job.manga_name.lock().clone();
                     // If downloading ended will not discard id
-                    let manga_id = if *resolute::DOWNLOADING.lock() {
-                        resolute::MANGA_ID.lock().clone()
+                    let manga_id = if *job.downloading.lock() {
+                        job.manga_id.lock().clone()
                     } else {
                         current_id.clone()
                     };
-                    let mwd = resolute::MWD.lock().clone();
+                    let mwd = job.mwd.lock().clone();
                     data.logs.insert(
                         handle_id.to_string(),
                         metadata::LogsMetadata::new(
@@ -386,12 +391,11 @@ fn clear_line(i: u32) {
     string(i, 0, &" ".repeat(MAXPOINTS.max_x as usize));
 }
 
-#[inline]
-pub(crate) fn process_filename(filename: &str) -> String {
-    filename.replace(['<', '>', ':', '|', '?', '*', '/', '\\', '"'], "")
-}
-
-pub(crate) async fn wait_for_end(file_path: &str, images_length: usize) -> Result<(), MdownError> {
+pub(crate) async fn wait_for_end(
+    job: &Job,
+    file_path: &str,
+    images_length: usize
+) -> Result<(), MdownError> {
     let full_path = format!(".cache\\{}.lock", file_path);
     let mut full_size = 0.0;
     let start = Instant::now();
@@ -452,9 +456,9 @@ pub(crate) async fn wait_for_end(file_path: &str, images_length: usize) -> Resul
             }
         }
         let percent = if full_size == 0.0 { 0.0 } else { (100.0 / full_size) * size };
-        *CURRENT_PERCENT.lock() = percent;
-        *CURRENT_SIZE.lock() = size;
-        *CURRENT_SIZE_MAX.lock() = full_size;
+        *job.current_percent.lock() = percent;
+        *job.current_size.lock() = size;
+        *job.current_size_max.lock() = full_size;
         string(
             4,
             MAXPOINTS.max_x - 60,
@@ -552,8 +556,36 @@ pub(crate) fn get_json(manga_name_json: &str) -> Result<Value, MdownError> {
 }
 
 pub(crate) async fn search() -> Result<String, MdownError> {
+    let title = args::ARGS.lock().search.clone();
+
+    // Limit is set to 1
+    let manga_array = match search_manga(&title, 1).await {
+        Ok(value) => value,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 10455));
+        }
+    };
+    let manga_ids: Vec<&str> = manga_array
+        .iter()
+        .map(|manga| &manga["id"])
+        .filter_map(|id| id.as_str())
+        .collect();
+
+    debug!("manga_ids: {:?}", manga_ids);
+
+    match manga_ids.first() {
+        Some(id) => Ok(id.to_string()),
+        None =>
+            Err(
+                MdownError::NotFoundError(String::from("manga_id in manga_ids in main.rs"), 10414)
+            ),
+    }
+}
+
+/// Searches mangadex for manga matching `title` and returns data of at most `limit` of them with
+/// their cover art.
+pub(crate) async fn search_manga(title: &str, limit: usize) -> Result<Vec<Value>, MdownError> {
     let base_url = "https://api.mangadex.org";
-    let title = &args::ARGS.lock().search.clone();
 
     let client = match download::get_client() {
         Ok(client) => client,
@@ -566,13 +598,12 @@ pub(crate) async fn search() -> Result<String, MdownError> {
 
     debug!("sending request to: {}", full_url);
 
-    // Limit is set to 1
-
     let response = match
         client
             .get(&full_url)
             .query(&[("title", title)])
-            .query(&[("limit", 1)])
+            .query(&[("limit", limit)])
+            .query(&[("includes[]", "cover_art")])
             .send().await
     {
         Ok(response) => response,
@@ -582,50 +613,31 @@ pub(crate) async fn search() -> Result<String, MdownError> {
     };
 
     if response.status().is_success() {
-        let manga_data: serde_json::Value = match response.json().await {
+        let mut manga_data: serde_json::Value = match response.json().await {
             Ok(value) => value,
             Err(err) => {
                 return Err(MdownError::JsonError(err.to_string(), 10411));
             }
         };
 
-        let data = match manga_data.get("data") {
-            Some(data) => data,
+        let data = match manga_data.get_mut("data") {
+            Some(data) => data.take(),
             None => {
                 return Err(
                     MdownError::NotFoundError(String::from("data in manga_data in main.rs"), 10412)
                 );
             }
         };
-        let manga_array = match data.as_array() {
-            Some(data) => data,
-            None => {
-                return Err(
+        match data {
+            Value::Array(manga_array) => Ok(manga_array),
+            _ =>
+                Err(
                     MdownError::ConversionError(
                         String::from("manga_data to array in main.rs"),
                         10413
                     )
-                );
-            }
-        };
-        let manga_ids: Vec<&str> = manga_array
-            .iter()
-            .map(|manga| &manga["id"])
-            .filter_map(|id| id.as_str())
-            .collect();
-
-        debug!("manga_ids: {:?}", manga_ids);
-
-        return match manga_ids.first() {
-            Some(id) => Ok(id.to_string()),
-            None =>
-                Err(
-                    MdownError::NotFoundError(
-                        String::from("manga_id in manga_ids in main.rs"),
-                        10414
-                    )
                 ),
-        };
+        }
     } else {
         Err(MdownError::StatusError(response.status(), 10415))
     }
@@ -945,19 +957,6 @@ pub(crate) fn backup_choose() -> Result<(), MdownError> {
             return Err(MdownError::ChainedError(Box::new(err), 10438));
         }
     };
-    let dat_file = match getter::get_dat_path() {
-        Ok(exe_path) => exe_path,
-        Err(err) => {
-            return Err(MdownError::ChainedError(Box::new(err), 10439));
-        }
-    };
-    let exe_dir = match getter::get_exe_path() {
-        Ok(exe_path) => exe_path,
-        Err(err) => {
-            return Err(MdownError::ChainedError(Box::new(err), 10440));
-        }
-    };
-
     let (dats, dats_filename) = match get_backup_dat(&backup_dir) {
         Ok((dats, dats_filename)) => (dats, dats_filename),
         Err(err) => {
@@ -990,7 +989,6 @@ pub(crate) fn backup_choose() -> Result<(), MdownError> {
     if vstup > 0 && vstup <= dats.len() {
         let filename = &dats_filename[vstup - 1];
         let file_path = format!("{}\\{}", backup_dir, filename);
-        let backup_file_path = format!("{}\\{}", exe_dir, "dat.json.tmp");
 
         let vstup = match
             input(&format!("Are you sure you want to choose \"{}\"? (y/N) > ", filename))
@@ -1001,23 +999,21 @@ pub(crate) fn backup_choose() -> Result<(), MdownError> {
             }
         };
         if vstup == "y" {
-            match fs::copy(&dat_file, &backup_file_path) {
-                Ok(_) => (),
-                Err(err) => {
-                    return Err(MdownError::IoError(err, dat_file, 10421));
-                }
-            }
-            match fs::copy(&file_path, &dat_file) {
-                Ok(_) => (),
+            let json = match resolute::get_dat_content(&file_path) {
+                Ok(json) => json,
                 Err(err) => {
-                    return Err(MdownError::IoError(err, dat_file, 10422));
+                    return Err(MdownError::ChainedError(Box::new(err), 10421));
                 }
-            }
-            match fs::remove_file(&backup_file_path) {
-                Ok(_) => (),
+            };
+            let dat = match serde_json::from_value::<metadata::Dat>(json) {
+                Ok(dat) => dat,
                 Err(err) => {
-                    return Err(MdownError::IoError(err, dat_file, 10423));
+                    return Err(MdownError::JsonError(err.to_string(), 10422));
                 }
+            };
+            // Library is replaced in a single transaction, so it is never left half-restored
+            if let Err(err) = library_db::replace(&dat) {
+                return Err(MdownError::ChainedError(Box::new(err), 10423));
             }
             println!("Backup successful");
         } else {
@@ -1063,8 +1059,9 @@ pub(crate) fn backup_handler(force: bool) -> Result<(), MdownError> {
 
     if latest_date < &day_before || dats.is_empty() || force {
         debug!("creating backup");
-        let source_file = match getter::get_dat_path() {
-            Ok(source) => source,
+        // Backups are JSON exports of the library index
+        let source = match library_db::export_json() {
+            Ok(json) => json,
             Err(err) => {
                 return Err(MdownError::ChainedError(Box::new(err), 10446));
             }
@@ -1075,12 +1072,7 @@ pub(crate) fn backup_handler(force: bool) -> Result<(), MdownError> {
                 Some(date) => date,
                 None => "",
             };
-            let source_sha = match calculate_sha256(&source_file) {
-                Ok(hash) => hash,
-                Err(err) => {
-                    return Err(MdownError::ChainedError(Box::new(err), 10447));
-                }
-            };
+            let source_sha = format!("{:x}", Sha256::digest(source.as_bytes()));
 
             let latest_sha = match calculate_sha256(&format!("{}\\{}", backup_dir, current_file)) {
                 Ok(hash) => hash,
@@ -1098,12 +1090,12 @@ pub(crate) fn backup_handler(force: bool) -> Result<(), MdownError> {
 
         let destination_file = format!("{}\\dat_{}.json", backup_dir, date_name);
 
-        match fs::copy(&source_file, &destination_file) {
-            Ok(_) => {
+        match fs::write(&destination_file, source) {
+            Ok(()) => {
                 debug!("Copied successfully");
             }
             Err(err) => {
-                return Err(MdownError::IoError(err, source_file, 10425));
+                return Err(MdownError::IoError(err, destination_file, 10425));
             }
         }
     } else {
@@ -1113,10 +1105,126 @@ pub(crate) fn backup_handler(force: bool) -> Result<(), MdownError> {
     Ok(())
 }
 
+pub(crate) fn print_dry_run(manga_name: &str, id: &str) -> Result<(), MdownError> {
+    if manga_name == "!" {
+        println!("Manga was not found; see --help");
+        return Ok(());
+    }
+    let dry_run = collect_dry_run(manga_name, id, resolute::DRY_RUN.lock().clone());
+    println!("{}", format_dry_run(&dry_run, *args::ARGS_DRY_RUN_JSON)?);
+    Ok(())
+}
+
+/// Returns dry run result of manga with `chapters` which would be downloaded and their totals.
+fn collect_dry_run(
+    manga_name: &str,
+    id: &str,
+    chapters: Vec<metadata::DryRunChapter>
+) -> metadata::DryRunMetadata {
+    metadata::DryRunMetadata {
+        name: manga_name.to_string(),
+        id: id.to_string(),
+        pages: chapters
+            .iter()
+            .map(|chapter| chapter.pages)
+            .sum(),
+        size: chapters
+            .iter()
+            .map(|chapter| chapter.size)
+            .sum(),
+        chapters,
+    }
+}
+
+/// Formats result of `--dry-run` as text or, with `json`, as JSON.
+fn format_dry_run(dry_run: &metadata::DryRunMetadata, json: bool) -> Result<String, MdownError> {
+    if json {
+        return match serde_json::to_string_pretty(dry_run) {
+            Ok(json) => Ok(json),
+            Err(err) => Err(MdownError::JsonError(err.to_string(), 10449)),
+        };
+    }
+
+    let mut lines = vec![format!("{} ({})", dry_run.name, dry_run.id)];
+    if dry_run.chapters.is_empty() {
+        lines.push(String::from("Nothing to download"));
+        return Ok(lines.join("\n"));
+    }
+    for chapter in dry_run.chapters.iter() {
+        let vol = match chapter.volume.as_str() {
+            "" => String::new(),
+            value => format!("Vol.{} ", value),
+        };
+        let title = match chapter.title.as_str() {
+            "" => String::new(),
+            value => format!(" - {}", value),
+        };
+        lines.push(
+            format!(
+                " {}Ch.{}{} [{}] ({}) {} pages, {}",
+                vol,
+                chapter.number,
+                title,
+                chapter.group,
+                chapter.language,
+                chapter.pages,
+                bytefmt::format(chapter.size)
+            )
+        );
+    }
+    lines.push(
+        format!(
+            "Would download {} chapters, {} pages, estimated size {}",
+            dry_run.chapters.len(),
+            dry_run.pages,
+            bytefmt::format(dry_run.size)
+        )
+    );
+    Ok(lines.join("\n"))
+}
+
+/// Rewrites `pages` in `_metadata` of the chapter in `folder` to the value returned by `pages` for
+/// the current number of pages.
+pub(crate) fn update_metadata_pages(
+    folder: &str,
+    pages: impl FnOnce(usize) -> usize
+) -> Result<(), MdownError> {
+    let path = format!("{}_metadata", folder);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) => {
+            return Err(MdownError::IoError(err, path, 10451));
+        }
+    };
+    let mut metadata = match serde_json::from_str::<metadata::ChapterMetadataIn>(&content) {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            return Err(MdownError::JsonError(err.to_string(), 10452));
+        }
+    };
+    metadata.pages = pages(metadata.pages.parse().unwrap_or_default()).to_string();
+    let json = match serde_json::to_string_pretty(&metadata) {
+        Ok(json) => json,
+        Err(err) => {
+            return Err(MdownError::JsonError(err.to_string(), 10453));
+        }
+    };
+    match fs::write(&path, json) {
+        Ok(()) => Ok(()),
+        Err(err) => Err(MdownError::IoError(err, path, 10454)),
+    }
+}
+
 pub(crate) fn show_settings(settings: metadata::Settings) {
     println!("folder: {}", settings.folder);
     println!("stat: {}", settings.stat);
     println!("backup: {}", settings.backup);
+    println!("template: {}", if settings.template.is_empty() {
+        template::DEFAULT
+    } else {
+        &settings.template
+    });
+    println!("library roots: {}", settings.library_roots.join("; "));
 }
 
 pub(crate) fn is_directory_empty(path: &str) -> bool {
@@ -1137,19 +1245,29 @@ pub(crate) fn is_directory_empty(path: &str) -> bool {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub(crate) struct FileName {
     pub(crate) manga_name: String,
     pub(crate) vol: String,
     pub(crate) chapter_num: String,
     pub(crate) title: String,
     pub(crate) folder: String,
+    pub(crate) manga_id: String,
+    pub(crate) volume: String,
+    pub(crate) chapter_id: String,
+    pub(crate) group: String,
+    pub(crate) group_id: String,
+    pub(crate) language: String,
+    pub(crate) date: String,
+    pub(crate) template: String,
+    /// Set if file name would overwrite a different chapter; chapter id is appended to it.
+    pub(crate) unique: bool,
 }
 
 impl FileName {
     pub(crate) fn get_folder_name(&self) -> String {
-        if !self.title.is_empty() {
-            process_filename(
+        let name = if !self.title.is_empty() {
+            sanitize::name(
                 &format!(
                     "{} - {}Ch.{} - {}",
                     self.manga_name,
@@ -1159,23 +1277,87 @@ impl FileName {
                 )
             )
         } else {
-            process_filename(&format!("{} - {}Ch.{}", self.manga_name, self.vol, self.chapter_num))
+            sanitize::name(&format!("{} - {}Ch.{}", self.manga_name, self.vol, self.chapter_num))
+        };
+        // Pages are saved in `.cache\NAME\NAME - PAGE.jpg`, so name has to fit in the path twice
+        let reserved = sanitize::path_length(".cache") + " - 9999.jpg".len() + 2;
+        sanitize::fit(&name, sanitize::MAX_PATH.saturating_sub(reserved) / 2)
+    }
+    /// Name of the `.cbz` file without extension, relative to the manga folder; with custom
+    /// template it may contain folders separated by `\`.
+    pub(crate) fn get_file_name(&self) -> String {
+        let name = if template::is_custom(&self.template) {
+            match
+                template::render(&self.template, |field| {
+                    Some(
+                        (
+                            match field {
+                                "manga" => &self.manga_name,
+                                "manga_id" => &self.manga_id,
+                                "volume" => &self.volume,
+                                "chapter" => &self.chapter_num,
+                                "chapter_id" => &self.chapter_id,
+                                "title" => &self.title,
+                                "group" => &self.group,
+                                "group_id" => &self.group_id,
+                                "language" => &self.language,
+                                "date" => &self.date,
+                                field => {
+                                    return getter::get_details_field(field);
+                                }
+                            }
+                        ).to_string()
+                    )
+                })
+            {
+                Ok(name) => name,
+                Err(err) => {
+                    suspend_error(MdownError::ChainedError(Box::new(err), 10450));
+                    self.get_folder_name()
+                }
+            }
+        } else {
+            self.get_folder_name()
+        };
+        let (folders, file) = match name.rsplit_once('\\') {
+            Some((folders, file)) => (Some(folders), file),
+            None => (None, name.as_str()),
+        };
+        let base = if *args::ARGS_UPDATE { "." } else { self.folder.as_str() };
+        let dir = match folders {
+            Some(folders) => format!("{}\\{}", base, folders),
+            None => base.to_string(),
+        };
+        let file = if self.unique {
+            let suffix = format!("{}.cbz", sanitize::with_id("", &self.chapter_id));
+            sanitize::with_id(&sanitize::fit_path(&dir, file, &suffix), &self.chapter_id)
+        } else {
+            sanitize::fit_path(&dir, file, ".cbz")
+        };
+        match folders {
+            Some(folders) => format!("{}\\{}", folders, file),
+            None => file,
+        }
+    }
+    /// Marks file name as unique if `.cbz` file with the same name belongs to a different chapter.
+    pub(crate) fn resolve_collision(&mut self) {
+        self.unique = false;
+        if let Ok(metadata) = resolute::check_for_metadata(&self.get_file_w_folder()) {
+            if metadata.id != self.chapter_id {
+                debug!("file name is used by chapter {}, appending id", metadata.id);
+                self.unique = true;
+            }
         }
     }
     pub(crate) fn get_file_w_folder(&self) -> String {
         if *args::ARGS_UPDATE {
-            format!("{}.cbz", process_filename(&self.get_folder_name()))
+            format!("{}.cbz", self.get_file_name())
         } else {
-            format!("{}\\{}.cbz", self.folder, process_filename(&self.get_folder_name()))
+            format!("{}\\{}.cbz", self.folder, self.get_file_name())
         }
     }
     pub(crate) fn get_file_w_folder_w_cwd(&self) -> String {
-        format!(
-            "{}{}\\{}.cbz",
-            *args::ARGS_CWD,
-            self.folder,
-            process_filename(&self.get_folder_name())
-        )
+        format!("{}{}\\{}.cbz", *args::ARGS_CWD, self.folder, self.get_file_name())
     }
     pub(crate) fn get_folder_w_end(&self) -> String {
         format!(".cache\\{}\\", self.get_folder_name())
@@ -1276,6 +1458,37 @@ pub(crate) fn debug_print<T>(item: T, file: &str) -> Result<(), MdownError> wher
     Ok(())
 }
 
+/// Returns cover of `volume` from `covers`.
+///
+/// Cover in `language` of the chapter is preferred, then cover in `original` language of the
+/// manga, then any cover of the volume. Volumes are compared as numbers, so `01` matches `1`.
+pub(crate) fn find_cover<'a>(
+    covers: &'a [metadata::CoverMetadata],
+    volume: &str,
+    language: &str,
+    original: &str
+) -> Option<&'a metadata::CoverMetadata> {
+    if volume.is_empty() {
+        return None;
+    }
+    let matching: Vec<&metadata::CoverMetadata> = covers
+        .iter()
+        .filter(|cover| {
+            cover.volume == volume ||
+                matches!(
+                    (cover.volume.parse::<f64>(), volume.parse::<f64>()),
+                    (Ok(a), Ok(b)) if a == b
+                )
+        })
+        .collect();
+    matching
+        .iter()
+        .find(|cover| cover.locale == language)
+        .or_else(|| matching.iter().find(|cover| cover.locale == original))
+        .or_else(|| matching.first())
+        .copied()
+}
+
 pub(crate) fn generate_random_id(length: usize) -> Box<str> {
     let rng = rand::rng();
     let id: String = rng.sample_iter(&Alphanumeric).take(length).map(char::from).collect();
@@ -1330,18 +1543,91 @@ fn test_resolve_regex_mangadex_url_with_query_parameters() {
     assert!(result.is_some());
 }
 
-// Given a valid filename with no special characters, it should return the same filename
+// Test of choosing volume cover by volume and locale
 #[test]
-fn should_return_same_filename() {
-    let filename = "test.txt";
-    let result = process_filename(filename);
-    assert_eq!(result, "test.txt");
+fn test_find_cover() {
+    let cover = |volume: &str, locale: &str| metadata::CoverMetadata {
+        volume: volume.to_string(),
+        locale: locale.to_string(),
+        file: format!("_covers\\Vol.{} ({}).jpg", volume, locale),
+    };
+    let covers = vec![cover("1", "ja"), cover("1", "en"), cover("2", "ko")];
+    assert_eq!(find_cover(&covers, "1", "en", "ja"), Some(&covers[1]));
+    assert_eq!(find_cover(&covers, "01", "de", "ja"), Some(&covers[0]));
+    assert_eq!(find_cover(&covers, "2", "en", "ja"), Some(&covers[2]));
+    assert_eq!(find_cover(&covers, "3", "en", "ja"), None);
+    assert_eq!(find_cover(&covers, "", "en", "ja"), None);
 }
 
-// Given a filename with only special characters, it should return an empty string
+// Test of dry run totals and output over prepared chapters
 #[test]
-fn should_return_empty_string() {
-    let filename = "<>:|?*/\\\"";
-    let result = process_filename(filename);
-    assert_eq!(result, "");
+fn test_dry_run() {
+    let chapter = |number: &str, volume: &str, title: &str, pages: u64, size: u64| {
+        metadata::DryRunChapter {
+            id: format!("id{}", number),
+            number: number.to_string(),
+            volume: volume.to_string(),
+            title: title.to_string(),
+            group: String::from("Group"),
+            language: String::from("en"),
+            pages,
+            size,
+        }
+    };
+    let chapters = vec![
+        chapter("1", "1", "Start", 20, 2_000_000),
+        chapter("2", "", "", 15, 1_500_000)
+    ];
+
+    let dry_run = collect_dry_run("Manga", "mangaid", chapters);
+    assert_eq!(dry_run.chapters.len(), 2);
+    assert_eq!(dry_run.pages, 35);
+    assert_eq!(dry_run.size, 3_500_000);
+
+    let text = format_dry_run(&dry_run, false).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "Manga (mangaid)");
+    assert!(lines[1].starts_with(" Vol.1 Ch.1 - Start [Group] (en) 20 pages, "));
+    assert!(lines[2].starts_with(" Ch.2 [Group] (en) 15 pages, "));
+    assert_eq!(
+        lines[3],
+        format!(
+            "Would download 2 chapters, 35 pages, estimated size {}",
+            bytefmt::format(3_500_000)
+        )
+    );
+
+    let json = format_dry_run(&dry_run, true).unwrap();
+    let parsed: metadata::DryRunMetadata = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, dry_run);
+    let value = get_json(&json).unwrap();
+    assert_eq!(value["pages"], 35);
+    assert_eq!(value["size"], 3_500_000);
+    assert_eq!(value["chapters"][1]["number"], "2");
+
+    let empty = format_dry_run(&collect_dry_run("Manga", "mangaid", Vec::new()), false).unwrap();
+    assert_eq!(empty, "Manga (mangaid)\nNothing to download");
+}
+
+// Test of counting a page added to the chapter in `_metadata`
+#[test]
+fn test_update_metadata_pages() {
+    let dir = std::env::temp_dir().join(format!("mdown_metadata_{}", generate_random_id(8)));
+    fs::create_dir_all(&dir).unwrap();
+    let folder = format!("{}{}", dir.to_string_lossy(), std::path::MAIN_SEPARATOR);
+    let metadata = metadata::ChapterMetadataIn {
+        pages: String::from("12"),
+        ..Default::default()
+    };
+    fs::write(dir.join("_metadata"), serde_json::to_string(&metadata).unwrap()).unwrap();
+
+    update_metadata_pages(&folder, |pages| pages + 1).unwrap();
+
+    let content = fs::read_to_string(dir.join("_metadata")).unwrap();
+    let updated: metadata::ChapterMetadataIn = serde_json::from_str(&content).unwrap();
+    assert_eq!(updated.pages, "13");
+    assert!(update_metadata_pages("missing_folder\\", |pages| pages).is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
 }
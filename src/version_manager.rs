@@ -1,7 +1,7 @@
 use bytes::BytesMut;
 use chrono::{ NaiveDateTime, Local };
 use semver::{ BuildMetadata, Prerelease, Version, VersionReq };
-use std::{ fs::{ File, write }, io::Write, process::Command };
+use std::{ fs::write, process::Command };
 use sha2::{ Digest, Sha256 };
 
 use crate::{
@@ -9,7 +9,8 @@ use crate::{
     download,
     debug,
     error::MdownError,
-    getter::{ get_dat_path, get_exe_path, get_exe_file_path, get_exe_name },
+    getter::{ get_exe_path, get_exe_file_path, get_exe_name },
+    library_db,
     metadata::Dat,
 };
 
@@ -20,7 +21,7 @@ pub const DB_VERSION: &str = "0000";
 /// This function compares the given `version` with the `current_version` of the application
 /// and updates the `Dat` object if the version needs to be changed.
 /// It uses semantic versioning and checks if the provided `version` is less than the current version.
-/// If the version is out of date, it updates the `Dat` object and writes the changes to disk.
+/// If the version is out of date, it updates the `Dat` object and saves it into the library index.
 ///
 /// # Arguments
 /// * `dat` - A mutable reference to the `Dat` object that stores version information.
@@ -32,8 +33,7 @@ pub const DB_VERSION: &str = "0000";
 ///   or an error if something goes wrong (such as file I/O or JSON serialization).
 ///
 /// # Errors
-/// * `MdownError::IoError` - If there is an issue reading or writing to the file.
-/// * `MdownError::JsonError` - If JSON serialization of the `Dat` object fails.
+/// * `MdownError::ChainedError` - If the library index can't be saved.
 ///
 /// # Example
 /// ```rust
@@ -65,36 +65,8 @@ pub(crate) fn check_ver(
             println!("Changing to version: {}", version_to_change_to);
             dat.version = version_to_change_to.clone();
 
-            let dat_path = match get_dat_path() {
-                Ok(path) => path,
-                Err(err) => {
-                    return Err(MdownError::ChainedError(Box::new(err), 11621));
-                }
-            };
-
-            let mut file = match File::create(&dat_path) {
-                Ok(path) => path,
-                Err(err) => {
-                    return Err(MdownError::IoError(err, dat_path, 11600));
-                }
-            };
-
-            let json = match serde_json::to_value::<Dat>(dat.clone()) {
-                Ok(value) => value,
-                Err(err) => {
-                    return Err(MdownError::JsonError(err.to_string(), 11601));
-                }
-            };
-
-            let json_string = match serde_json::to_string_pretty(&json) {
-                Ok(value) => value,
-                Err(err) => {
-                    return Err(MdownError::JsonError(err.to_string(), 11602));
-                }
-            };
-
-            if let Err(err) = writeln!(file, "{}", json_string) {
-                return Err(MdownError::IoError(err, dat_path, 11603));
+            if let Err(err) = library_db::save(dat) {
+                return Err(MdownError::ChainedError(Box::new(err), 11600));
             }
 
             version = match Version::parse(&get_current_version()) {
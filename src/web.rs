@@ -1,30 +1,29 @@
 use lazy_static::lazy_static;
 use percent_encoding::{ NON_ALPHANUMERIC, percent_decode_str, percent_encode };
-use serde_json::{ json, Value };
-use std::{ collections::HashMap, fs::File, io::{ Read, Write }, net::TcpListener };
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{ Read, Write },
+    net::TcpListener,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::broadcast;
 
 use crate::{
     args,
     db,
     error::MdownError,
+    events,
     getter,
     handle_error,
+    auth::{ self, Role },
+    job::{ self, Job },
+    jobs,
     log,
     log_end,
-    resolute::{
-        self,
-        CURRENT_CHAPTER,
-        CURRENT_CHAPTER_PARSED,
-        CURRENT_CHAPTER_PARSED_MAX,
-        CURRENT_PAGE,
-        CURRENT_PAGE_MAX,
-        CURRENT_PERCENT,
-        CURRENT_SIZE,
-        CURRENT_SIZE_MAX,
-        MANGA_NAME,
-        SCANLATION_GROUPS,
-        WEB_DOWNLOADED,
-    },
+    resolute,
     utils,
     version_manager::get_current_version,
     zip_func,
@@ -72,6 +71,9 @@ lazy_static! {
 
 include!(concat!(env!("OUT_DIR"), "/error_404_jpg.rs"));
 
+/// Time after which a comment is sent to clients of the event stream if there was no event.
+const EVENTS_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
 /// Decodes a percent-encoded URL string.
 ///
 /// # Parameters
@@ -109,6 +111,7 @@ pub(crate) fn encode(url: &str) -> String {
 /// Resolves and downloads manga information based on a given URL.
 ///
 /// # Parameters
+/// - `job`: Job holding progress of the download.
 /// - `url`: A string slice representing the manga URL or identifier.
 ///
 /// # Returns
@@ -116,9 +119,7 @@ pub(crate) fn encode(url: &str) -> String {
 /// - `Err(MdownError)`: If an error occurs during resolution or data retrieval.
 ///
 /// # Behavior
-/// - Extracts the manga ID from the given URL using regex or UUID validation.
-/// - Fetches the manga JSON data from an external source.
-/// - Parses and processes the manga metadata.
+/// - Downloads the manga with `resolute::resolve_url`.
 /// - Returns a JSON object with:
 ///     - `"status": "ok"`
 ///     - `"name"`: Manga title
@@ -134,49 +135,20 @@ pub(crate) fn encode(url: &str) -> String {
 ///   "scanlation_groups": ["Group A", "Group B"]
 /// }
 /// ```
-async fn resolve_web_download(url: &str) -> Result<String, MdownError> {
-    let handle_id = resolute::HANDLE_ID.lock().clone();
-    let mut manga_name = String::from("!");
-    let id;
-    if let Some(id_temp) = utils::resolve_regex(url) {
-        id = id_temp.as_str();
-    } else if utils::is_valid_uuid(url) {
-        id = url;
-    } else {
-        log!(&format!("@{} Didn't find any id", handle_id), handle_id);
-        return Ok(String::from("!"));
-    }
-    *resolute::MANGA_ID.lock() = id.to_string();
-    log!(&format!("@{} Found {}", handle_id, id), handle_id);
-    if let Ok(manga_name_json) = getter::get_manga_json(id).await {
-        let json_value = match utils::get_json(&manga_name_json) {
-            Ok(value) => value,
-            Err(err) => {
-                return Err(MdownError::ChainedError(Box::new(err), 11312));
-            }
-        };
-        match json_value {
-            Value::Object(obj) => {
-                manga_name = match resolute::resolve(obj, id).await {
-                    Ok(value) => value,
-                    Err(err) => {
-                        return Err(MdownError::ChainedError(Box::new(err), 11313));
-                    }
-                };
-            }
-            _ => {
-                return Err(
-                    MdownError::JsonError(String::from("Could not parse manga json"), 11300)
-                );
-            }
+pub(crate) async fn resolve_web_download(job: &Arc<Job>, url: &str) -> Result<String, MdownError> {
+    let manga_name = match resolute::resolve_url(job, url).await {
+        Ok(value) => value,
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 11313));
         }
-    }
+    };
+    events::summary();
 
     if manga_name.eq("!") {
         Ok(String::from("!"))
     } else {
-        let downloaded_files = WEB_DOWNLOADED.lock().clone();
-        let scanlation = SCANLATION_GROUPS.lock().clone();
+        let downloaded_files = job.downloaded.lock().clone();
+        let scanlation = job.scanlation_groups.lock().clone();
 
         let response_map: HashMap<&str, serde_json::Value> = [
             ("status", serde_json::Value::String("ok".to_string())),
@@ -221,8 +193,12 @@ async fn resolve_web_download(url: &str) -> Result<String, MdownError> {
 ///
 /// # Behavior
 /// - Reads the request from the client.
+/// - Rejects requests of users without permission, see `auth`; downloads, `/end` and changes of
+///   jobs require `admin`.
 /// - Parses the request path and handles different endpoints:
+///     - `/api/jobs...` → REST API of download jobs, see `jobs`.
 ///     - `/manga?url=...` → Handles manga downloads.
+///     - `/__events__` → Streams events of downloads as Server-Sent Events.
 ///     - `/__get__?path=...` → Serves static resources.
 ///     - `/__confetti__` → Extracts and serves images from a ZIP archive.
 ///     - `/manga-result?id=...` → Retrieves download progress.
@@ -253,18 +229,15 @@ async fn resolve_web_download(url: &str) -> Result<String, MdownError> {
 /// }
 /// ```
 async fn handle_client(mut stream: std::net::TcpStream) -> Result<(), MdownError> {
-    let mut buffer = [0; 1024];
-    match stream.read(&mut buffer) {
-        Ok(_n) => (),
+    let request = match read_request(&mut stream) {
+        Ok(request) => request,
         Err(err) => {
             return Err(MdownError::IoError(err, String::new(), 11302));
         }
-    }
+    };
 
     let mut end = false;
 
-    let request = String::from_utf8_lossy(&buffer[..]);
-
     let url_param = "url=";
 
     let parts: Vec<&str> = request.split_whitespace().collect();
@@ -276,9 +249,42 @@ async fn handle_client(mut stream: std::net::TcpStream) -> Result<(), MdownError
         }
     };
 
+    // Reading needs `read`, downloading and stopping the server need `admin`
+    let role = if
+        path.starts_with("/manga?") ||
+        path.starts_with("/end") ||
+        (path.starts_with("/api/") && parts[0] != "GET")
+    {
+        Role::Admin
+    } else {
+        Role::Read
+    };
+    if let Some(response) = auth::reject(&request, role) {
+        return match stream.write_all(response.as_bytes()) {
+            Ok(()) => Ok(()),
+            Err(err) => Err(MdownError::IoError(err, String::new(), 11316)),
+        };
+    }
+
     if parts.len() >= 2 {
         let response;
-        if path.starts_with("/manga?") && path.contains(url_param) {
+        if path.starts_with("/api/") {
+            let body = match request.split_once("\r\n\r\n") {
+                Some((_, body)) => body,
+                None => "",
+            };
+            response = match jobs::handle(parts[0], path, body) {
+                Ok((code, json)) => json_response(code, &json),
+                Err(err) => {
+                    handle_error!(&err, String::from("web_api"));
+                    json_response(500, r#"{"status":"error","message":"Internal error"}"#)
+                }
+            };
+            match stream.write_all(response.as_bytes()) {
+                Ok(()) => (),
+                Err(_err) => (),
+            }
+        } else if path.starts_with("/manga?") && path.contains(url_param) {
             log!("REQUEST RECEIVED");
             log!("REQUEST Type: download");
 
@@ -290,8 +296,14 @@ async fn handle_client(mut stream: std::net::TcpStream) -> Result<(), MdownError
                 };
                 let decoded_url = decode(&manga_url);
 
+                // Waits for a running job of the queue
+                let _download = job::DOWNLOAD.lock().await;
                 *resolute::HANDLE_ID.lock() = handle_id.clone();
-                let json = match resolve_web_download(&decoded_url).await {
+                // Every download has its own job with handle id of the page, so the page can find
+                // its events; the progress endpoint shows the latest job
+                let job = Job::with_id(&handle_id);
+                job::set_current(Arc::clone(&job));
+                let json = match resolve_web_download(&job, &decoded_url).await {
                     Ok(response) =>
                         format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{}", response),
 
@@ -316,6 +328,10 @@ async fn handle_client(mut stream: std::net::TcpStream) -> Result<(), MdownError
                 Ok(()) => (),
                 Err(_err) => (),
             }
+        } else if path.starts_with("/__events__") {
+            log!("REQUEST Type: events");
+            stream_events(stream).await;
+            return Ok(());
         } else if path.starts_with("/__get__?") {
             log!("REQUEST Type: GET");
             let query_params = getter::get_query(parts);
@@ -473,6 +489,88 @@ async fn handle_client(mut stream: std::net::TcpStream) -> Result<(), MdownError
     Ok(())
 }
 
+/// Sends events of downloads to the client as Server-Sent Events until it disconnects.
+///
+/// Every event is one `data:` line with the JSON object printed by `--output json` and `job`, id of
+/// its job; a comment is sent when there was no event for a while to find out closed connections.
+async fn stream_events(mut stream: std::net::TcpStream) {
+    let mut receiver = events::subscribe();
+    let headers =
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n";
+    if stream.write_all(headers.as_bytes()).is_err() {
+        return;
+    }
+    loop {
+        let message = match tokio::time::timeout(EVENTS_KEEP_ALIVE, receiver.recv()).await {
+            Ok(Ok(event)) => format!("data: {}\n\n", event),
+            Ok(Err(broadcast::error::RecvError::Lagged(_))) => {
+                continue;
+            }
+            Ok(Err(broadcast::error::RecvError::Closed)) => {
+                return;
+            }
+            Err(_elapsed) => String::from(": keep-alive\n\n"),
+        };
+        if stream.write_all(message.as_bytes()).is_err() || stream.flush().is_err() {
+            return;
+        }
+    }
+}
+
+/// Reads a request from the client; the body is read by its `Content-Length` header.
+fn read_request(stream: &mut std::net::TcpStream) -> std::io::Result<String> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    loop {
+        let n = stream.read(&mut buffer)?;
+        request.extend_from_slice(&buffer[..n]);
+        let end = match request.windows(4).position(|window| window == b"\r\n\r\n") {
+            Some(end) => end + 4,
+            None if n == 0 => {
+                break;
+            }
+            None => {
+                continue;
+            }
+        };
+        let head = String::from_utf8_lossy(&request[..end]).to_lowercase();
+        let length = head
+            .lines()
+            .find_map(|line| line.strip_prefix("content-length:"))
+            .and_then(|length| length.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+        while request.len() < end + length {
+            let n = stream.read(&mut buffer)?;
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buffer[..n]);
+        }
+        break;
+    }
+    Ok(String::from_utf8_lossy(&request).to_string())
+}
+
+/// Returns HTTP response with JSON `body` and status `code`.
+fn json_response(code: u16, body: &str) -> String {
+    let reason = match code {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        _ => "Internal Server Error",
+    };
+    format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        code,
+        reason,
+        body.len(),
+        body
+    )
+}
+
 /// Parses an incoming request and generates an appropriate HTTP response.
 ///
 /// # Parameters
@@ -518,30 +616,37 @@ fn parse_request(url: String) -> Result<String, MdownError> {
         let html = get_html();
         Ok(format!("{}{}", "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n", html))
     } else if url == *"progress" {
-        let downloaded_files = WEB_DOWNLOADED.lock().clone();
-        let scanlation = SCANLATION_GROUPS.lock().clone();
+        let job = job::current();
+        let downloaded_files = job.downloaded.lock().clone();
+        let scanlation = job.scanlation_groups.lock().clone();
         let response_map: HashMap<&str, serde_json::Value> = [
             ("status", serde_json::Value::String("ok".to_string())),
-            ("name", serde_json::Value::String(MANGA_NAME.lock().to_string())),
-            ("current", serde_json::Value::String(CURRENT_CHAPTER.lock().to_string())),
-            ("current_page", serde_json::Value::String(CURRENT_PAGE.lock().to_string())),
-            ("current_page_max", serde_json::Value::String(CURRENT_PAGE_MAX.lock().to_string())),
+            ("name", serde_json::Value::String(job.manga_name.lock().to_string())),
+            ("current", serde_json::Value::String(job.current_chapter.lock().to_string())),
+            ("current_page", serde_json::Value::String(job.current_page.lock().to_string())),
+            (
+                "current_page_max",
+                serde_json::Value::String(job.current_page_max.lock().to_string()),
+            ),
             (
                 "current_percent",
-                serde_json::Value::String(format!("{:.2}", CURRENT_PERCENT.lock())),
+                serde_json::Value::String(format!("{:.2}", job.current_percent.lock())),
+            ),
+            (
+                "current_size",
+                serde_json::Value::String(format!("{:.2}", job.current_size.lock())),
             ),
-            ("current_size", serde_json::Value::String(format!("{:.2}", CURRENT_SIZE.lock()))),
             (
                 "current_size_max",
-                serde_json::Value::String(format!("{:.2}", CURRENT_SIZE_MAX.lock())),
+                serde_json::Value::String(format!("{:.2}", job.current_size_max.lock())),
             ),
             (
                 "current_chapter_parsed",
-                serde_json::Value::String(CURRENT_CHAPTER_PARSED.lock().to_string()),
+                serde_json::Value::String(job.current_chapter_parsed.lock().to_string()),
             ),
             (
                 "current_chapter_parsed_max",
-                serde_json::Value::String(CURRENT_CHAPTER_PARSED_MAX.lock().to_string()),
+                serde_json::Value::String(job.current_chapter_parsed_max.lock().to_string()),
             ),
             (
                 "files",
@@ -617,7 +722,7 @@ fn get_html() -> String {
         contents
     } else {
         String::from(
-            "<!DOCTYPE html><html lang=\"en\"><head><meta charset=\"UTF-8\"><meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\"><title>Mdown</title><style>body {font-family: Arial, sans-serif;background-color: #121212;color: #fff;margin: 0;padding: 0;box-sizing: border-box;transition: background-color 0.5s;}body.dark-mode {background-color: #fff;color: #121212;}.title {margin-left: 44vw;color: inherit;display: flex;align-items: center;}.mangaForm {max-width: 400px;margin: 20px auto;background-color: #272727;padding: 20px;border-radius: 8px;box-shadow: 0 0 10px rgba(0, 0, 0, 0.1);}.mangaForm.dark-mode {color: #FFF;background-color: #FFF;}.urlInput {display: block;margin-bottom: 8px;color: #fff;}.urlInput.dark-mode {color: #000;}input {width: 100%;padding: 10px;margin-bottom: 16px;box-sizing: border-box;border: 1px solid #555;border-radius: 4px;background-color: #333;color: #fff;}.exit-button {background-color: #FFF;color: #000;padding: 10px 15px;border: none;border-radius: 50%;cursor: pointer;position: fixed;top: 20px;left: 20px;font-size: 20px;}.dark-mode-toggle {background-color: #FFF;color: #000;padding: 10px 15px;border: none;border-radius: 50%;cursor: pointer;position: fixed;top: 20px;right: 20px;font-size: 20px;}.dark-mode-toggle:hover {background-color: grey;}.download {background-color: #4caf50;color: #fff;padding: 10px 15px;border: none;border-radius: 4px;cursor: pointer;}.download:hover {background-color: #45a049;}#resultMessage {margin: 20px auto;max-width: 600px;background-color: #272727;padding: 50px;border-radius: 8px;box-shadow: 0 0 10px rgba(0, 0, 0, 0.1);}ul {list-style-type: none;padding: 0;}li {margin-bottom: 8px;}#result {color: #FFF;}#resultEnd {margin: 20px auto;max-width: 600px;background-color: #272727;padding: 50px;border-radius: 8px;box-shadow: 0 0 10px rgba(0, 0, 0, 0.1);animation: popUp 1s ease-out;display: none;transform: scale(0);opacity: 0;}#resultEnd.dark-mode {color: #000}#resultEnd.visible {display: block;position: absolute;z-index: 10;top: 30%;left: 40vw;color: #FFF;animation: popUp 1s ease-out forwards;}@keyframes popUp {0% {transform: scale(0);opacity: 0;}95% {transform: scale(4);opacity: 1;}100% {transform: scale(2);opacity: 1;}}#imageContainer {position: fixed;top: 0;left: 0;width: 100%;height: 100%;pointer-events: none;overflow: hidden;}.flying-image {position: absolute;animation: fly 200s linear infinite;max-width: 20vw;animation-direction: alternate;animation-timing-function: ease-in-out;}@keyframes fly {0% {transform: translateX(-100vw) rotate(-20deg);}100% {transform: translateX(200vw) rotate(20deg);}}#version {margin-left: 5px;}</style></head><body><button type=\"button\" onclick=\"exitApp()\" class=\"exit-button\" id=\"exitButton\">Exit</button>    <button type=\"button\" onclick=\"toggleDarkMode()\" class=\"dark-mode-toggle\" id=\"darkModeToggle\">&#x2600;</button>    <h1 class=\"title\">mdown <p id=\"version\"></p></h1><form class=\"mangaForm\"><label class=\"urlInput\" for=\"urlInput\">Enter Manga URL:</label><input type=\"text\" id=\"urlInput\" name=\"url\" required><button type=\"button\" class=\"download\" onclick=\"downloadManga()\">Download</button></form><div id=\"resultMessage\"></div><div id=\"resultEnd\"></div><div id=\"imageContainer\"></div><audio id=\"downloadedMusic\" src=\"__get__?path=rambling_pleat\" loop></audio><audio id=\"downloadMusic\" src=\"__get__?path=system_haven\" loop></audio><script>fetch(\'__version__\').then(response => {if (!response.ok) {throw new Error(\'Network response was not ok\');}return response.text();}).then(text => {document.getElementById(\'version\').textContent = `v${text}`;}).catch(error => {console.error(\'There was a problem fetching the text:\', error);});function delay(time) {return new Promise(resolve => setTimeout(resolve, time));}let id = \"\";let isPostRequestInProgress = false;let isPostRequestInProgress_tmp = true;let images = [];let times = 0;let end = false;function sleep(ms) {return new Promise(resolve => setTimeout(resolve, ms));}function clickHandler(event) {end = true;const resultEndDiv = document.getElementById(\'resultEnd\');resultEndDiv.classList.remove(\'visible\');const downloadedMusic = document.getElementById(\'downloadedMusic\');downloadedMusic.pause();downloadedMusic.currentTime = 0;const imageContainer = document.getElementById(\'imageContainer\');imageContainer.innerHTML = \'\';}function createFlyingImage() {const imageContainer = document.getElementById(\'imageContainer\');const img = document.createElement(\'img\');console.log(images.length);var randomIndex = Math.floor(Math.random() * images.length);var randomImage = images[randomIndex];img.src = \"data:image/png;base64,\" + images[randomIndex];img.classList.add(\'flying-image\');img.style.zIndex = Math.random() >= 0.5 ? \"1\" : \"20\";const initialPosition = \"0vw\";img.style.left = initialPosition;img.style.top = `${(Math.random() * 100) - 25}vh`;img.style.animationDuration = `${5 + Math.random() * 20}s`;imageContainer.appendChild(img);img.addEventListener(\'animationiteration\', () => {const newInitialPosition = initialPosition === \'-100vw\' ? \'200vw\' : \'-100vw\';img.style.left = newInitialPosition;});}async function get_confetti() {try {const response = await fetch(\'__confetti__\');if (!response.ok) {throw new Error(\'Network response was not ok\');}const data = await response.json();images = data.images;} catch (error) {console.error(\'Error:\', error);throw error;}}function start_confetti_event() {if (end) {return;}times += 1;const randomInterval = Math.random() * (2000 - 500) + 500;setTimeout(() => {if (times % 10 === 0) {start_confetti_big();} else {start_confetti();}start_confetti_event();}, randomInterval);}function start_confetti() {confetti({particleCount: 250,spread: 100,origin: { y: Math.random(), x: Math.random() }});}function start_confetti_big() {confetti({particleCount: 250,spread: 100,origin: { y: Math.random(), x: Math.random() }});confetti({particleCount: 250,spread: 100,origin: { y: Math.random(), x: Math.random() }});confetti({particleCount: 250,spread: 100,origin: { y: Math.random(), x: Math.random() }});}function downloadManga() {id = generateRandomId(10);if (isPostRequestInProgress) {alert(\'A download is already in progress. Please wait.\');return;}isPostRequestInProgress = true;const downloadMusic = document.getElementById(\'downloadMusic\');downloadMusic.play().catch(error => console.log(\'Error playing sound:\', error));var mangaUrl = document.getElementById(\'urlInput\').value;var encodedUrl = encodeURIComponent(mangaUrl);var url = \"http://127.0.0.1:8080/manga\";fetch(url + \"?url=\" + encodedUrl + \"&id=\" + id, {method: \'POST\',headers: {\'Content-Type\': \'application/json\',},}).then(response => {if (!response.ok) {throw new Error(\'Network response was not ok\');}return response.json();}).then(async result => {const resultMessageDiv = document.getElementById(\'resultMessage\');if (result.status == \"ok\") {end = false;console.log(\'Scanlation Groups:\', result.scanlation_groups);console.log(\'Files:\', result.files);console.log(\'Manga Name:\', result.name);console.log(\'Status:\', result.status);resultMessageDiv.innerHTML = \"<p id=\\\'result\\\'>Download successful!</p>\";if (result.files && result.files.length > 0) {resultMessageDiv.innerHTML += \"<p id=\\\'result\\\'>Downloaded Files:</p>\";resultMessageDiv.innerHTML += \"<ul id=\\\'result\\\'>\";result.files.forEach(file => {resultMessageDiv.innerHTML += \"<li id=\\\'result\\\'>\" + file + \"</li>\";});resultMessageDiv.innerHTML += \"</ul>\";}if (result.scanlation_groups && result.scanlation_groups.length > 0) {resultMessageDiv.innerHTML += \"<p id=\\\'result\\\'>Scanlation Groups:</p>\";resultMessageDiv.innerHTML += \"<ul id=\\\'result\\\'>\";result.scanlation_groups.forEach(group => {resultMessageDiv.innerHTML += \"<li id=\\\'result\\\'>\" + group + \"</li>\";});resultMessageDiv.innerHTML += \"</ul>\";}isPostRequestInProgress = false;isPostRequestInProgress_tmp = true;await get_confetti();const resultEnd = document.getElementById(\'resultEnd\');resultEnd.innerHTML = `<p>${result.name} has been downloaded</p>`;const downloadMusic = document.getElementById(\'downloadMusic\');downloadMusic.pause();downloadMusic.currentTime = 0;const downloadedMusic = document.getElementById(\'downloadedMusic\');downloadedMusic.play().catch(error => console.log(\'Error playing sound:\', error));const body = document.body;setTimeout(() => {body.style.transition = \"0s\";body.style.backgroundColor = \"#FFF\";}, 100);setTimeout(() => {body.style.backgroundColor = \"#cfff01\";}, 200);setTimeout(() => {body.style.backgroundColor = \"#2da657\";}, 300);setTimeout(() => {body.style.backgroundColor = \"#0763cc\";}, 400);setTimeout(() => {body.style.backgroundColor = \"#cc074c\";}, 500);setTimeout(() => {body.style.backgroundColor = \"#121212\";body.style.transition = \"background-color 0.5s\";confetti({particleCount: 250,spread: 100,origin: { y: 0.6 }});confetti({particleCount: 250,spread: 100,origin: { y: 0.8, x: 0.25 }});confetti({particleCount: 250,spread: 100,origin: { y: 0.8, x: 0.75 }});start_confetti_event();}, 900);showResultEnd();for (let i = 0; i < 10; i++) {createFlyingImage();}document.addEventListener(\'click\', clickHandler);}}).catch(error => {console.error(\'Error during POST request:\', error);document.getElementById(\'resultMessage\').innerHTML = \"<p id=\'result\'>Error during download. Please try again.<p>\";isPostRequestInProgress = false;isPostRequestInProgress_tmp = true;});}function fetchWhilePostInProgress() {var parsed = 0;var total = 0;var current = 0;setInterval(async () => {if (!isPostRequestInProgress) {return;}if (isPostRequestInProgress_tmp) {await delay(1000);isPostRequestInProgress_tmp = false;}fetch(\"http://127.0.0.1:8080/manga-result?id=\" + id).then(response => response.json()).then(async result => {if (result.status === \"ok\") {const resultMessageDiv = document.getElementById(\'resultMessage\');resultMessageDiv.innerHTML = `<p id=\'result\'>In Progress!</p><p id=\'result\'>Parsed chapters: ${result.current_chapter_parsed}/${result.current_chapter_parsed_max}</p>${result.current ? `<p id=\'result\'>Current chapter: ${result.current}</p>` : \'\'}`;let progressElement = document.getElementById(\'progress\');if (!progressElement) {progressElement = document.createElement(\'div\');progressElement.id = \'progress\';resultMessageDiv.appendChild(progressElement);}if (result.current_page && result.current_page_max) {for (let i = current; i <= result.current_page; i++) {let progressHTML = `<p id=\'result\'>${\"#\".repeat(i)}  ${i}|${result.current_page_max}</p>`;progressElement.innerHTML = progressHTML;await delay(10);}current = result.current_page;}if (result.current_percent && result.current_size && result.current_size_max) {resultMessageDiv.innerHTML += `<p id=\'result\'>${result.current_percent} | ${result.current_size}mb/${result.current_size_max}mb</p>`;}if (result.files && result.files.length > 0) {let filesHTML = `<p id=\'result\'>Downloaded Files:</p><ul id=\'result\'>${result.files.map(file => `<li id=\'result\'>${file}</li>`).join(\'\')}</ul>`;resultMessageDiv.innerHTML += filesHTML;}if (result.scanlation_groups && result.scanlation_groups.length > 0) {let groupsHTML = `<p id=\'result\'>Scanlation Groups:</p><ul id=\'result\'>${result.scanlation_groups.map(group => `<li id=\'result\'>${group}</li>`).join(\'\')}</ul>`;resultMessageDiv.innerHTML += groupsHTML;}parsed = result.current_chapter_parsed;    total = result.current_page_max;}}).catch(error => {console.error(\'Error during GET request:\', error);});}, 500);}fetchWhilePostInProgress();function showResultEnd() {const resultEndDiv = document.getElementById(\'resultEnd\');resultEndDiv.classList.add(\'visible\');}function generateRandomId(length) {const CHARSET = \'ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789\';let id = \'\';for (let i = 0; i < length; i++) {const randomIndex = Math.floor(Math.random() * CHARSET.length);id += CHARSET.charAt(randomIndex);}return id;}function toggleDarkMode() {const body = document.body;body.classList.toggle(\'dark-mode\');const button = document.getElementById(\'darkModeToggle\');const exit_button = document.getElementById(\'exitButton\');if (body.classList.contains(\'dark-mode\')) {button.innerHTML = \'\\u{1F319}\';button.style.backgroundColor = \"#000\";button.style.color = \"#FFF\";exit_button.style.backgroundColor = \"#000\";exit_button.style.color = \"#FFF\";} else {button.innerHTML = \'\\u{2600}\';button.style.backgroundColor = \"#FFF\";button.style.color = \"#000\";exit_button.style.backgroundColor = \"#FFF\";exit_button.style.color = \"#000\";}}function exitApp() {fetch(\"http://127.0.0.1:8080/end\", {method: \'GET\'}).then(response => {if (response.ok) {window.close();} else {console.error(\'Failed to send exit request\');}}).catch(error => {console.error(\'Error while sending exit request:\', error);});}!function (t, e) { !function t(e, n, a, i) { var o = !!(e.Worker && e.Blob && e.Promise && e.OffscreenCanvas && e.OffscreenCanvasRenderingContext2D && e.HTMLCanvasElement && e.HTMLCanvasElement.prototype.transferControlToOffscreen && e.URL && e.URL.createObjectURL); function r() { } function l(t) { var a = n.exports.Promise, i = void 0 !== a ? a : e.Promise; return \"function\" == typeof i ? new i(t) : (t(r, r), null) } var c, s, u, d, f, h, m, g, b, v = (u = Math.floor(1e3 / 60), d = {}, f = 0, \"function\" == typeof requestAnimationFrame && \"function\" == typeof cancelAnimationFrame ? (c = function (t) { var e = Math.random(); return d[e] = requestAnimationFrame((function n(a) { f === a || f + u - 1 < a ? (f = a, delete d[e], t()) : d[e] = requestAnimationFrame(n) })), e }, s = function (t) { d[t] && cancelAnimationFrame(d[t]) }) : (c = function (t) { return setTimeout(t, u) }, s = function (t) { return clearTimeout(t) }), { frame: c, cancel: s }), p = (g = {}, function () { if (h) return h; if (!a && o) { var e = [\"var CONFETTI, SIZE = {}, module = {};\", \"(\" + t.toString() + \")(this, module, true, SIZE);\", \"onmessage = function(msg) {\", \"  if (msg.data.options) {\", \"CONFETTI(msg.data.options).then(function () {\", \"  if (msg.data.callback) {\", \"postMessage({ callback: msg.data.callback });\", \"  }\", \"});\", \"  } else if (msg.data.reset) {\", \"CONFETTI.reset();\", \"  } else if (msg.data.resize) {\", \"SIZE.width = msg.data.resize.width;\", \"SIZE.height = msg.data.resize.height;\", \"  } else if (msg.data.canvas) {\", \"SIZE.width = msg.data.canvas.width;\", \"SIZE.height = msg.data.canvas.height;\", \"CONFETTI = module.exports.create(msg.data.canvas);\", \"  }\", \"}\"].join(\"\\n\"); try { h = new Worker(URL.createObjectURL(new Blob([e]))) } catch (t) { return void 0 !== typeof console && \"function\" == typeof console.warn && console.warn(\"🎊 Could not load worker\", t), null } !function (t) { function e(e, n) { t.postMessage({ options: e || {}, callback: n }) } t.init = function (e) { var n = e.transferControlToOffscreen(); t.postMessage({ canvas: n }, [n]) }, t.fire = function (n, a, i) { if (m) return e(n, null), m; var o = Math.random().toString(36).slice(2); return m = l((function (a) { function r(e) { e.data.callback === o && (delete g[o], t.removeEventListener(\"message\", r), m = null, i(), a()) } t.addEventListener(\"message\", r), e(n, o), g[o] = r.bind(null, { data: { callback: o } }) })) }, t.reset = function () { for (var e in t.postMessage({ reset: !0 }), g) g[e](), delete g[e] } }(h) } return h }), y = { particleCount: 50, angle: 90, spread: 45, startVelocity: 45, decay: .9, gravity: 1, drift: 0, ticks: 200, x: .5, y: .5, shapes: [\"square\", \"circle\"], zIndex: 100, colors: [\"#26ccff\", \"#a25afd\", \"#ff5e7e\", \"#88ff5a\", \"#fcff42\", \"#ffa62d\", \"#ff36ff\"], disableForReducedMotion: !1, scalar: 1 }; function M(t, e, n) { return function (t, e) { return e ? e(t) : t }(t && null != t[e] ? t[e] : y[e], n) } function w(t) { return t < 0 ? 0 : Math.floor(t) } function x(t) { return parseInt(t, 16) } function C(t) { return t.map(k) } function k(t) { var e = String(t).replace(/[^0-9a-f]/gi, \"\"); return e.length < 6 && (e = e[0] + e[0] + e[1] + e[1] + e[2] + e[2]), { r: x(e.substring(0, 2)), g: x(e.substring(2, 4)), b: x(e.substring(4, 6)) } } function I(t) { t.width = document.documentElement.clientWidth, t.height = document.documentElement.clientHeight } function S(t) { var e = t.getBoundingClientRect(); t.width = e.width, t.height = e.height } function T(t, e, n, o, r) { var c, s, u = e.slice(), d = t.getContext(\"2d\"), f = l((function (e) { function l() { c = s = null, d.clearRect(0, 0, o.width, o.height), r(), e() } c = v.frame((function e() { !a || o.width === i.width && o.height === i.height || (o.width = t.width = i.width, o.height = t.height = i.height), o.width || o.height || (n(t), o.width = t.width, o.height = t.height), d.clearRect(0, 0, o.width, o.height), u = u.filter((function (t) { return function (t, e) { e.x += Math.cos(e.angle2D) * e.velocity + e.drift, e.y += Math.sin(e.angle2D) * e.velocity + e.gravity, e.wobble += e.wobbleSpeed, e.velocity *= e.decay, e.tiltAngle += .1, e.tiltSin = Math.sin(e.tiltAngle), e.tiltCos = Math.cos(e.tiltAngle), e.random = Math.random() + 2, e.wobbleX = e.x + 10 * e.scalar * Math.cos(e.wobble), e.wobbleY = e.y + 10 * e.scalar * Math.sin(e.wobble); var n = e.tick++ / e.totalTicks, a = e.x + e.random * e.tiltCos, i = e.y + e.random * e.tiltSin, o = e.wobbleX + e.random * e.tiltCos, r = e.wobbleY + e.random * e.tiltSin; return t.fillStyle = \"rgba(\" + e.color.r + \", \" + e.color.g + \", \" + e.color.b + \", \" + (1 - n) + \")\", t.beginPath(), \"circle\" === e.shape ? t.ellipse ? t.ellipse(e.x, e.y, Math.abs(o - a) * e.ovalScalar, Math.abs(r - i) * e.ovalScalar, Math.PI / 10 * e.wobble, 0, 2 * Math.PI) : function (t, e, n, a, i, o, r, l, c) { t.save(), t.translate(e, n), t.rotate(o), t.scale(a, i), t.arc(0, 0, 1, r, l, c), t.restore() }(t, e.x, e.y, Math.abs(o - a) * e.ovalScalar, Math.abs(r - i) * e.ovalScalar, Math.PI / 10 * e.wobble, 0, 2 * Math.PI) : (t.moveTo(Math.floor(e.x), Math.floor(e.y)), t.lineTo(Math.floor(e.wobbleX), Math.floor(i)), t.lineTo(Math.floor(o), Math.floor(r)), t.lineTo(Math.floor(a), Math.floor(e.wobbleY))), t.closePath(), t.fill(), e.tick < e.totalTicks }(d, t) })), u.length ? c = v.frame(e) : l() })), s = l })); return { addFettis: function (t) { return u = u.concat(t), f }, canvas: t, promise: f, reset: function () { c && v.cancel(c), s && s() } } } function E(t, n) { var a, i = !t, r = !!M(n || {}, \"resize\"), c = M(n, \"disableForReducedMotion\", Boolean), s = o && !!M(n || {}, \"useWorker\") ? p() : null, u = i ? I : S, d = !(!t || !s) && !!t.__confetti_initialized, f = \"function\" == typeof matchMedia && matchMedia(\"(prefers-reduced-motion)\").matches; function h(e, n, i) { for (var o, r, l, c, s, d = M(e, \"particleCount\", w), f = M(e, \"angle\", Number), h = M(e, \"spread\", Number), m = M(e, \"startVelocity\", Number), g = M(e, \"decay\", Number), b = M(e, \"gravity\", Number), v = M(e, \"drift\", Number), p = M(e, \"colors\", C), y = M(e, \"ticks\", Number), x = M(e, \"shapes\"), k = M(e, \"scalar\"), I = function (t) { var e = M(t, \"origin\", Object); return e.x = M(e, \"x\", Number), e.y = M(e, \"y\", Number), e }(e), S = d, E = [], F = t.width * I.x, N = t.height * I.y; S--;)E.push((o = { x: F, y: N, angle: f, spread: h, startVelocity: m, color: p[S % p.length], shape: x[(c = 0, s = x.length, Math.floor(Math.random() * (s - c)) + c)], ticks: y, decay: g, gravity: b, drift: v, scalar: k }, r = void 0, l = void 0, r = o.angle * (Math.PI / 180), l = o.spread * (Math.PI / 180), { x: o.x, y: o.y, wobble: 10 * Math.random(), wobbleSpeed: Math.min(.11, .1 * Math.random() + .05), velocity: .5 * o.startVelocity + Math.random() * o.startVelocity, angle2D: -r + (.5 * l - Math.random() * l), tiltAngle: (.5 * Math.random() + .25) * Math.PI, color: o.color, shape: o.shape, tick: 0, totalTicks: o.ticks, decay: o.decay, drift: o.drift, random: Math.random() + 2, tiltSin: 0, tiltCos: 0, wobbleX: 0, wobbleY: 0, gravity: 3 * o.gravity, ovalScalar: .6, scalar: o.scalar })); return a ? a.addFettis(E) : (a = T(t, E, u, n, i)).promise } function m(n) { var o = c || M(n, \"disableForReducedMotion\", Boolean), m = M(n, \"zIndex\", Number); if (o && f) return l((function (t) { t() })); i && a ? t = a.canvas : i && !t && (t = function (t) { var e = document.createElement(\"canvas\"); return e.style.position = \"fixed\", e.style.top = \"0px\", e.style.left = \"0px\", e.style.pointerEvents = \"none\", e.style.zIndex = t, e }(m), document.body.appendChild(t)), r && !d && u(t); var g = { width: t.width, height: t.height }; function b() { if (s) { var e = { getBoundingClientRect: function () { if (!i) return t.getBoundingClientRect() } }; return u(e), void s.postMessage({ resize: { width: e.width, height: e.height } }) } g.width = g.height = null } function v() { a = null, r && e.removeEventListener(\"resize\", b), i && t && (document.body.removeChild(t), t = null, d = !1) } return s && !d && s.init(t), d = !0, s && (t.__confetti_initialized = !0), r && e.addEventListener(\"resize\", b, !1), s ? s.fire(n, g, v) : h(n, g, v) } return m.reset = function () { s && s.reset(), a && a.reset() }, m } function F() { return b || (b = E(null, { useWorker: !0, resize: !0 })), b } n.exports = function () { return F().apply(this, arguments) }, n.exports.reset = function () { F().reset() }, n.exports.create = E }(function () { return void 0 !== t ? t : \"undefined\" != typeof self ? self : this || {} }(), e, !1), t.confetti = e.exports }(window, {});</script></body></html>"
+            "<!DOCTYPE html><html lang=\"en\"><head><meta charset=\"UTF-8\"><meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\"><title>Mdown</title><style>body {font-family: Arial, sans-serif;background-color: #121212;color: #fff;margin: 0;padding: 0;box-sizing: border-box;transition: background-color 0.5s;}body.dark-mode {background-color: #fff;color: #121212;}.title {margin-left: 44vw;color: inherit;display: flex;align-items: center;}.mangaForm {max-width: 400px;margin: 20px auto;background-color: #272727;padding: 20px;border-radius: 8px;box-shadow: 0 0 10px rgba(0, 0, 0, 0.1);}.mangaForm.dark-mode {color: #FFF;background-color: #FFF;}.urlInput {display: block;margin-bottom: 8px;color: #fff;}.urlInput.dark-mode {color: #000;}input {width: 100%;padding: 10px;margin-bottom: 16px;box-sizing: border-box;border: 1px solid #555;border-radius: 4px;background-color: #333;color: #fff;}.exit-button {background-color: #FFF;color: #000;padding: 10px 15px;border: none;border-radius: 50%;cursor: pointer;position: fixed;top: 20px;left: 20px;font-size: 20px;}.dark-mode-toggle {background-color: #FFF;color: #000;padding: 10px 15px;border: none;border-radius: 50%;cursor: pointer;position: fixed;top: 20px;right: 20px;font-size: 20px;}.dark-mode-toggle:hover {background-color: grey;}.download {background-color: #4caf50;color: #fff;padding: 10px 15px;border: none;border-radius: 4px;cursor: pointer;}.download:hover {background-color: #45a049;}#resultMessage {margin: 20px auto;max-width: 600px;background-color: #272727;padding: 50px;border-radius: 8px;box-shadow: 0 0 10px rgba(0, 0, 0, 0.1);}ul {list-style-type: none;padding: 0;}li {margin-bottom: 8px;}#result {color: #FFF;}#resultEnd {margin: 20px auto;max-width: 600px;background-color: #272727;padding: 50px;border-radius: 8px;box-shadow: 0 0 10px rgba(0, 0, 0, 0.1);animation: popUp 1s ease-out;display: none;transform: scale(0);opacity: 0;}#resultEnd.dark-mode {color: #000}#resultEnd.visible {display: block;position: absolute;z-index: 10;top: 30%;left: 40vw;color: #FFF;animation: popUp 1s ease-out forwards;}@keyframes popUp {0% {transform: scale(0);opacity: 0;}95% {transform: scale(4);opacity: 1;}100% {transform: scale(2);opacity: 1;}}#imageContainer {position: fixed;top: 0;left: 0;width: 100%;height: 100%;pointer-events: none;overflow: hidden;}.flying-image {position: absolute;animation: fly 200s linear infinite;max-width: 20vw;animation-direction: alternate;animation-timing-function: ease-in-out;}@keyframes fly {0% {transform: translateX(-100vw) rotate(-20deg);}100% {transform: translateX(200vw) rotate(20deg);}}#version {margin-left: 5px;}</style></head><body><button type=\"button\" onclick=\"exitApp()\" class=\"exit-button\" id=\"exitButton\">Exit</button>    <button type=\"button\" onclick=\"toggleDarkMode()\" class=\"dark-mode-toggle\" id=\"darkModeToggle\">&#x2600;</button>    <h1 class=\"title\">mdown <p id=\"version\"></p></h1><form class=\"mangaForm\"><label class=\"urlInput\" for=\"urlInput\">Enter Manga URL:</label><input type=\"text\" id=\"urlInput\" name=\"url\" required><button type=\"button\" class=\"download\" onclick=\"downloadManga()\">Download</button></form><div id=\"resultMessage\"></div><div id=\"resultEnd\"></div><div id=\"imageContainer\"></div><audio id=\"downloadedMusic\" src=\"__get__?path=rambling_pleat\" loop></audio><audio id=\"downloadMusic\" src=\"__get__?path=system_haven\" loop></audio><script>fetch(\'__version__\').then(response => {if (!response.ok) {throw new Error(\'Network response was not ok\');}return response.text();}).then(text => {document.getElementById(\'version\').textContent = `v${text}`;}).catch(error => {console.error(\'There was a problem fetching the text:\', error);});let id = \"\";let isPostRequestInProgress = false;let images = [];let times = 0;let end = false;function sleep(ms) {return new Promise(resolve => setTimeout(resolve, ms));}function clickHandler(event) {end = true;const resultEndDiv = document.getElementById(\'resultEnd\');resultEndDiv.classList.remove(\'visible\');const downloadedMusic = document.getElementById(\'downloadedMusic\');downloadedMusic.pause();downloadedMusic.currentTime = 0;const imageContainer = document.getElementById(\'imageContainer\');imageContainer.innerHTML = \'\';}function createFlyingImage() {const imageContainer = document.getElementById(\'imageContainer\');const img = document.createElement(\'img\');console.log(images.length);var randomIndex = Math.floor(Math.random() * images.length);var randomImage = images[randomIndex];img.src = \"data:image/png;base64,\" + images[randomIndex];img.classList.add(\'flying-image\');img.style.zIndex = Math.random() >= 0.5 ? \"1\" : \"20\";const initialPosition = \"0vw\";img.style.left = initialPosition;img.style.top = `${(Math.random() * 100) - 25}vh`;img.style.animationDuration = `${5 + Math.random() * 20}s`;imageContainer.appendChild(img);img.addEventListener(\'animationiteration\', () => {const newInitialPosition = initialPosition === \'-100vw\' ? \'200vw\' : \'-100vw\';img.style.left = newInitialPosition;});}async function get_confetti() {try {const response = await fetch(\'__confetti__\');if (!response.ok) {throw new Error(\'Network response was not ok\');}const data = await response.json();images = data.images;} catch (error) {console.error(\'Error:\', error);throw error;}}function start_confetti_event() {if (end) {return;}times += 1;const randomInterval = Math.random() * (2000 - 500) + 500;setTimeout(() => {if (times % 10 === 0) {start_confetti_big();} else {start_confetti();}start_confetti_event();}, randomInterval);}function start_confetti() {confetti({particleCount: 250,spread: 100,origin: { y: Math.random(), x: Math.random() }});}function start_confetti_big() {confetti({particleCount: 250,spread: 100,origin: { y: Math.random(), x: Math.random() }});confetti({particleCount: 250,spread: 100,origin: { y: Math.random(), x: Math.random() }});confetti({particleCount: 250,spread: 100,origin: { y: Math.random(), x: Math.random() }});}function downloadManga() {id = generateRandomId(10);if (isPostRequestInProgress) {alert(\'A download is already in progress. Please wait.\');return;}isPostRequestInProgress = true;const downloadMusic = document.getElementById(\'downloadMusic\');downloadMusic.play().catch(error => console.log(\'Error playing sound:\', error));var mangaUrl = document.getElementById(\'urlInput\').value;var encodedUrl = encodeURIComponent(mangaUrl);var url = \"/manga\";fetch(url + \"?url=\" + encodedUrl + \"&id=\" + id, {method: \'POST\',headers: {\'Content-Type\': \'application/json\',},}).then(response => {if (!response.ok) {throw new Error(\'Network response was not ok\');}return response.json();}).then(async result => {const resultMessageDiv = document.getElementById(\'resultMessage\');if (result.status == \"ok\") {end = false;console.log(\'Scanlation Groups:\', result.scanlation_groups);console.log(\'Files:\', result.files);console.log(\'Manga Name:\', result.name);console.log(\'Status:\', result.status);resultMessageDiv.innerHTML = \"<p id=\\\'result\\\'>Download successful!</p>\";if (result.files && result.files.length > 0) {resultMessageDiv.innerHTML += \"<p id=\\\'result\\\'>Downloaded Files:</p>\";resultMessageDiv.innerHTML += \"<ul id=\\\'result\\\'>\";result.files.forEach(file => {resultMessageDiv.innerHTML += \"<li id=\\\'result\\\'>\" + file + \"</li>\";});resultMessageDiv.innerHTML += \"</ul>\";}if (result.scanlation_groups && result.scanlation_groups.length > 0) {resultMessageDiv.innerHTML += \"<p id=\\\'result\\\'>Scanlation Groups:</p>\";resultMessageDiv.innerHTML += \"<ul id=\\\'result\\\'>\";result.scanlation_groups.forEach(group => {resultMessageDiv.innerHTML += \"<li id=\\\'result\\\'>\" + group + \"</li>\";});resultMessageDiv.innerHTML += \"</ul>\";}isPostRequestInProgress = false;await get_confetti();const resultEnd = document.getElementById(\'resultEnd\');resultEnd.innerHTML = `<p>${result.name} has been downloaded</p>`;const downloadMusic = document.getElementById(\'downloadMusic\');downloadMusic.pause();downloadMusic.currentTime = 0;const downloadedMusic = document.getElementById(\'downloadedMusic\');downloadedMusic.play().catch(error => console.log(\'Error playing sound:\', error));const body = document.body;setTimeout(() => {body.style.transition = \"0s\";body.style.backgroundColor = \"#FFF\";}, 100);setTimeout(() => {body.style.backgroundColor = \"#cfff01\";}, 200);setTimeout(() => {body.style.backgroundColor = \"#2da657\";}, 300);setTimeout(() => {body.style.backgroundColor = \"#0763cc\";}, 400);setTimeout(() => {body.style.backgroundColor = \"#cc074c\";}, 500);setTimeout(() => {body.style.backgroundColor = \"#121212\";body.style.transition = \"background-color 0.5s\";confetti({particleCount: 250,spread: 100,origin: { y: 0.6 }});confetti({particleCount: 250,spread: 100,origin: { y: 0.8, x: 0.25 }});confetti({particleCount: 250,spread: 100,origin: { y: 0.8, x: 0.75 }});start_confetti_event();}, 900);showResultEnd();for (let i = 0; i < 10; i++) {createFlyingImage();}document.addEventListener(\'click\', clickHandler);}}).catch(error => {console.error(\'Error during POST request:\', error);document.getElementById(\'resultMessage\').innerHTML = \"<p id=\'result\'>Error during download. Please try again.<p>\";isPostRequestInProgress = false;});}function fetchWhilePostInProgress() {let progress = {};const source = new EventSource(\'/__events__\');source.onmessage = message => {const result = JSON.parse(message.data);if (!isPostRequestInProgress || result.job !== id) {return;}if (progress.id !== id) {progress = {id: id, name: \'\', parsed: 0, total: 0, chapter: \'\', page: 0, pages: 0, files: [], errors: []};}if (result.event === \'manga_resolved\') {progress.name = result.name;progress.total = result.chapters;} else if (result.event === \'chapter_skipped\') {progress.parsed += 1;} else if (result.event === \'chapter_started\') {progress.parsed += 1;progress.chapter = result.chapter;progress.page = 0;progress.pages = result.pages;} else if (result.event === \'page_finished\') {progress.page += 1;} else if (result.event === \'chapter_zipped\') {progress.files.push(result.path);} else if (result.event === \'error\') {progress.errors.push(result.message);} else {return;}const resultMessageDiv = document.getElementById(\'resultMessage\');resultMessageDiv.innerHTML = `<p id=\'result\'>In Progress!</p>${progress.name ? `<p id=\'result\'>${progress.name}</p>` : \'\'}<p id=\'result\'>Parsed chapters: ${progress.parsed}/${progress.total}</p>${progress.chapter ? `<p id=\'result\'>Current chapter: ${progress.chapter}</p><p id=\'result\'>${\'#\'.repeat(progress.page)}  ${progress.page}|${progress.pages}</p>` : \'\'}${progress.files.length > 0 ? `<p id=\'result\'>Downloaded Files:</p><ul id=\'result\'>${progress.files.map(file => `<li id=\'result\'>${file}</li>`).join(\'\')}</ul>` : \'\'}${progress.errors.length > 0 ? `<p id=\'result\'>Errors:</p><ul id=\'result\'>${progress.errors.map(error => `<li id=\'result\'>${error}</li>`).join(\'\')}</ul>` : \'\'}`;};source.onerror = error => {console.error(\'Error in event stream:\', error);};}fetchWhilePostInProgress();function showResultEnd() {const resultEndDiv = document.getElementById(\'resultEnd\');resultEndDiv.classList.add(\'visible\');}function generateRandomId(length) {const CHARSET = \'ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789\';let id = \'\';for (let i = 0; i < length; i++) {const randomIndex = Math.floor(Math.random() * CHARSET.length);id += CHARSET.charAt(randomIndex);}return id;}function toggleDarkMode() {const body = document.body;body.classList.toggle(\'dark-mode\');const button = document.getElementById(\'darkModeToggle\');const exit_button = document.getElementById(\'exitButton\');if (body.classList.contains(\'dark-mode\')) {button.innerHTML = \'\\u{1F319}\';button.style.backgroundColor = \"#000\";button.style.color = \"#FFF\";exit_button.style.backgroundColor = \"#000\";exit_button.style.color = \"#FFF\";} else {button.innerHTML = \'\\u{2600}\';button.style.backgroundColor = \"#FFF\";button.style.color = \"#000\";exit_button.style.backgroundColor = \"#FFF\";exit_button.style.color = \"#000\";}}function exitApp() {fetch(\"/end\", {method: \'GET\'}).then(response => {if (response.ok) {window.close();} else {console.error(\'Failed to send exit request\');}}).catch(error => {console.error(\'Error while sending exit request:\', error);});}!function (t, e) { !function t(e, n, a, i) { var o = !!(e.Worker && e.Blob && e.Promise && e.OffscreenCanvas && e.OffscreenCanvasRenderingContext2D && e.HTMLCanvasElement && e.HTMLCanvasElement.prototype.transferControlToOffscreen && e.URL && e.URL.createObjectURL); function r() { } function l(t) { var a = n.exports.Promise, i = void 0 !== a ? a : e.Promise; return \"function\" == typeof i ? new i(t) : (t(r, r), null) } var c, s, u, d, f, h, m, g, b, v = (u = Math.floor(1e3 / 60), d = {}, f = 0, \"function\" == typeof requestAnimationFrame && \"function\" == typeof cancelAnimationFrame ? (c = function (t) { var e = Math.random(); return d[e] = requestAnimationFrame((function n(a) { f === a || f + u - 1 < a ? (f = a, delete d[e], t()) : d[e] = requestAnimationFrame(n) })), e }, s = function (t) { d[t] && cancelAnimationFrame(d[t]) }) : (c = function (t) { return setTimeout(t, u) }, s = function (t) { return clearTimeout(t) }), { frame: c, cancel: s }), p = (g = {}, function () { if (h) return h; if (!a && o) { var e = [\"var CONFETTI, SIZE = {}, module = {};\", \"(\" + t.toString() + \")(this, module, true, SIZE);\", \"onmessage = function(msg) {\", \"  if (msg.data.options) {\", \"CONFETTI(msg.data.options).then(function () {\", \"  if (msg.data.callback) {\", \"postMessage({ callback: msg.data.callback });\", \"  }\", \"});\", \"  } else if (msg.data.reset) {\", \"CONFETTI.reset();\", \"  } else if (msg.data.resize) {\", \"SIZE.width = msg.data.resize.width;\", \"SIZE.height = msg.data.resize.height;\", \"  } else if (msg.data.canvas) {\", \"SIZE.width = msg.data.canvas.width;\", \"SIZE.height = msg.data.canvas.height;\", \"CONFETTI = module.exports.create(msg.data.canvas);\", \"  }\", \"}\"].join(\"\\n\"); try { h = new Worker(URL.createObjectURL(new Blob([e]))) } catch (t) { return void 0 !== typeof console && \"function\" == typeof console.warn && console.warn(\"🎊 Could not load worker\", t), null } !function (t) { function e(e, n) { t.postMessage({ options: e || {}, callback: n }) } t.init = function (e) { var n = e.transferControlToOffscreen(); t.postMessage({ canvas: n }, [n]) }, t.fire = function (n, a, i) { if (m) return e(n, null), m; var o = Math.random().toString(36).slice(2); return m = l((function (a) { function r(e) { e.data.callback === o && (delete g[o], t.removeEventListener(\"message\", r), m = null, i(), a()) } t.addEventListener(\"message\", r), e(n, o), g[o] = r.bind(null, { data: { callback: o } }) })) }, t.reset = function () { for (var e in t.postMessage({ reset: !0 }), g) g[e](), delete g[e] } }(h) } return h }), y = { particleCount: 50, angle: 90, spread: 45, startVelocity: 45, decay: .9, gravity: 1, drift: 0, ticks: 200, x: .5, y: .5, shapes: [\"square\", \"circle\"], zIndex: 100, colors: [\"#26ccff\", \"#a25afd\", \"#ff5e7e\", \"#88ff5a\", \"#fcff42\", \"#ffa62d\", \"#ff36ff\"], disableForReducedMotion: !1, scalar: 1 }; function M(t, e, n) { return function (t, e) { return e ? e(t) : t }(t && null != t[e] ? t[e] : y[e], n) } function w(t) { return t < 0 ? 0 : Math.floor(t) } function x(t) { return parseInt(t, 16) } function C(t) { return t.map(k) } function k(t) { var e = String(t).replace(/[^0-9a-f]/gi, \"\"); return e.length < 6 && (e = e[0] + e[0] + e[1] + e[1] + e[2] + e[2]), { r: x(e.substring(0, 2)), g: x(e.substring(2, 4)), b: x(e.substring(4, 6)) } } function I(t) { t.width = document.documentElement.clientWidth, t.height = document.documentElement.clientHeight } function S(t) { var e = t.getBoundingClientRect(); t.width = e.width, t.height = e.height } function T(t, e, n, o, r) { var c, s, u = e.slice(), d = t.getContext(\"2d\"), f = l((function (e) { function l() { c = s = null, d.clearRect(0, 0, o.width, o.height), r(), e() } c = v.frame((function e() { !a || o.width === i.width && o.height === i.height || (o.width = t.width = i.width, o.height = t.height = i.height), o.width || o.height || (n(t), o.width = t.width, o.height = t.height), d.clearRect(0, 0, o.width, o.height), u = u.filter((function (t) { return function (t, e) { e.x += Math.cos(e.angle2D) * e.velocity + e.drift, e.y += Math.sin(e.angle2D) * e.velocity + e.gravity, e.wobble += e.wobbleSpeed, e.velocity *= e.decay, e.tiltAngle += .1, e.tiltSin = Math.sin(e.tiltAngle), e.tiltCos = Math.cos(e.tiltAngle), e.random = Math.random() + 2, e.wobbleX = e.x + 10 * e.scalar * Math.cos(e.wobble), e.wobbleY = e.y + 10 * e.scalar * Math.sin(e.wobble); var n = e.tick++ / e.totalTicks, a = e.x + e.random * e.tiltCos, i = e.y + e.random * e.tiltSin, o = e.wobbleX + e.random * e.tiltCos, r = e.wobbleY + e.random * e.tiltSin; return t.fillStyle = \"rgba(\" + e.color.r + \", \" + e.color.g + \", \" + e.color.b + \", \" + (1 - n) + \")\", t.beginPath(), \"circle\" === e.shape ? t.ellipse ? t.ellipse(e.x, e.y, Math.abs(o - a) * e.ovalScalar, Math.abs(r - i) * e.ovalScalar, Math.PI / 10 * e.wobble, 0, 2 * Math.PI) : function (t, e, n, a, i, o, r, l, c) { t.save(), t.translate(e, n), t.rotate(o), t.scale(a, i), t.arc(0, 0, 1, r, l, c), t.restore() }(t, e.x, e.y, Math.abs(o - a) * e.ovalScalar, Math.abs(r - i) * e.ovalScalar, Math.PI / 10 * e.wobble, 0, 2 * Math.PI) : (t.moveTo(Math.floor(e.x), Math.floor(e.y)), t.lineTo(Math.floor(e.wobbleX), Math.floor(i)), t.lineTo(Math.floor(o), Math.floor(r)), t.lineTo(Math.floor(a), Math.floor(e.wobbleY))), t.closePath(), t.fill(), e.tick < e.totalTicks }(d, t) })), u.length ? c = v.frame(e) : l() })), s = l })); return { addFettis: function (t) { return u = u.concat(t), f }, canvas: t, promise: f, reset: function () { c && v.cancel(c), s && s() } } } function E(t, n) { var a, i = !t, r = !!M(n || {}, \"resize\"), c = M(n, \"disableForReducedMotion\", Boolean), s = o && !!M(n || {}, \"useWorker\") ? p() : null, u = i ? I : S, d = !(!t || !s) && !!t.__confetti_initialized, f = \"function\" == typeof matchMedia && matchMedia(\"(prefers-reduced-motion)\").matches; function h(e, n, i) { for (var o, r, l, c, s, d = M(e, \"particleCount\", w), f = M(e, \"angle\", Number), h = M(e, \"spread\", Number), m = M(e, \"startVelocity\", Number), g = M(e, \"decay\", Number), b = M(e, \"gravity\", Number), v = M(e, \"drift\", Number), p = M(e, \"colors\", C), y = M(e, \"ticks\", Number), x = M(e, \"shapes\"), k = M(e, \"scalar\"), I = function (t) { var e = M(t, \"origin\", Object); return e.x = M(e, \"x\", Number), e.y = M(e, \"y\", Number), e }(e), S = d, E = [], F = t.width * I.x, N = t.height * I.y; S--;)E.push((o = { x: F, y: N, angle: f, spread: h, startVelocity: m, color: p[S % p.length], shape: x[(c = 0, s = x.length, Math.floor(Math.random() * (s - c)) + c)], ticks: y, decay: g, gravity: b, drift: v, scalar: k }, r = void 0, l = void 0, r = o.angle * (Math.PI / 180), l = o.spread * (Math.PI / 180), { x: o.x, y: o.y, wobble: 10 * Math.random(), wobbleSpeed: Math.min(.11, .1 * Math.random() + .05), velocity: .5 * o.startVelocity + Math.random() * o.startVelocity, angle2D: -r + (.5 * l - Math.random() * l), tiltAngle: (.5 * Math.random() + .25) * Math.PI, color: o.color, shape: o.shape, tick: 0, totalTicks: o.ticks, decay: o.decay, drift: o.drift, random: Math.random() + 2, tiltSin: 0, tiltCos: 0, wobbleX: 0, wobbleY: 0, gravity: 3 * o.gravity, ovalScalar: .6, scalar: o.scalar })); return a ? a.addFettis(E) : (a = T(t, E, u, n, i)).promise } function m(n) { var o = c || M(n, \"disableForReducedMotion\", Boolean), m = M(n, \"zIndex\", Number); if (o && f) return l((function (t) { t() })); i && a ? t = a.canvas : i && !t && (t = function (t) { var e = document.createElement(\"canvas\"); return e.style.position = \"fixed\", e.style.top = \"0px\", e.style.left = \"0px\", e.style.pointerEvents = \"none\", e.style.zIndex = t, e }(m), document.body.appendChild(t)), r && !d && u(t); var g = { width: t.width, height: t.height }; function b() { if (s) { var e = { getBoundingClientRect: function () { if (!i) return t.getBoundingClientRect() } }; return u(e), void s.postMessage({ resize: { width: e.width, height: e.height } }) } g.width = g.height = null } function v() { a = null, r && e.removeEventListener(\"resize\", b), i && t && (document.body.removeChild(t), t = null, d = !1) } return s && !d && s.init(t), d = !0, s && (t.__confetti_initialized = !0), r && e.addEventListener(\"resize\", b, !1), s ? s.fire(n, g, v) : h(n, g, v) } return m.reset = function () { s && s.reset(), a && a.reset() }, m } function F() { return b || (b = E(null, { useWorker: !0, resize: !0 })), b } n.exports = function () { return F().apply(this, arguments) }, n.exports.reset = function () { F().reset() }, n.exports.create = E }(function () { return void 0 !== t ? t : \"undefined\" != typeof self ? self : this || {} }(), e, !1), t.confetti = e.exports }(window, {});</script></body></html>"
         )
     }
 }
@@ -640,14 +745,15 @@ fn get_error_html() -> String {
     )
 }
 
-/// Starts a web server that listens on `127.0.0.1:8080` and handles incoming requests.
+/// Starts a web server that listens on `127.0.0.1:8080`, or on address of `--bind`, and handles incoming requests.
 ///
 /// # Returns
 /// - `Ok(())` if the server starts successfully and continues running.
 /// - Returns an `MdownError` if the server encounters issues such as a failure to bind the listener.
 ///
 /// # Functionality
-/// - The server binds to the local address `127.0.0.1:8080`.
+/// - The server binds to the local address `127.0.0.1:8080`, or to port 8080 of address of `--bind`.
+/// - Loads users which requests are authenticated against, see `auth`.
 /// - Attempts to open the URL `http://127.0.0.1:8080/` in the default web browser.
 /// - Listens for incoming TCP connections and handles them asynchronously using the `handle_client` function.
 ///
@@ -658,16 +764,27 @@ fn get_error_html() -> String {
 /// }
 /// ```
 async fn web() -> Result<(), MdownError> {
-    let listener = match TcpListener::bind("127.0.0.1:8080") {
+    let address = match args::ARGS_BIND.as_ref() {
+        Some(address) => address.clone(),
+        None => String::from("127.0.0.1"),
+    };
+    match auth::load(&address) {
+        Ok(()) => (),
+        Err(err) => {
+            return Err(MdownError::ChainedError(Box::new(err), 11317));
+        }
+    }
+    let listener = match TcpListener::bind(format!("{}:8080", address)) {
         Ok(listener) => listener,
         Err(err) => {
             return Err(MdownError::IoError(err, String::new(), 11306));
         }
     };
-    log!("Server listening on 127.0.0.1:8080");
+    log!(&format!("Server listening on {}:8080", address));
+    jobs::start();
 
-    let url = "http://127.0.0.1:8080/";
-    if let Err(err) = webbrowser::open(url) {
+    let url = format!("http://{}:8080/", address);
+    if let Err(err) = webbrowser::open(&url) {
         eprintln!("Error opening web browser: {}", err);
     }
 
@@ -332,10 +332,10 @@ pub(crate) fn extract_image_from_zip(zip_file_path: &str) -> Result<Vec<u8>, err
 /// This function does not explicitly panic, but improper usage of the underlying filesystem or ZIP library could cause a panic in rare cases, such as invalid file paths or corrupted ZIP files.
 #[cfg(feature = "web")]
 pub(crate) fn extract_images_from_zip() -> Result<Vec<Vec<u8>>, error::MdownError> {
-    use crate::resolute;
+    use crate::job;
     use rand::{ seq::SliceRandom, rng };
     let mut images = Vec::new();
-    let mut files = resolute::WEB_DOWNLOADED.lock().clone();
+    let mut files = job::current().downloaded.lock().clone();
     files.truncate(10);
 
     for zip_file_path in files.iter() {
@@ -386,11 +386,31 @@ pub(crate) fn extract_images_from_zip() -> Result<Vec<Vec<u8>>, error::MdownErro
     Ok(images)
 }
 
+/// Extracts the page number from an image file name.
+///
+/// # Parameters
+/// - `file_name`: Name of the image, e.g. `NAME - Ch.1 - 12.jpg`.
+///
+/// # Returns
+/// - `Some(usize)`: The last numeric part of the file name separated by whitespace or dashes.
+/// - `None`: If the file name does not contain any number.
+#[cfg(any(feature = "gui", feature = "processing", feature = "server"))]
+pub(crate) fn extract_page_number(file_name: &str) -> Option<usize> {
+    // Strip the extension
+    let file_stem = file_name.rsplit_once('.').map_or(file_name, |(stem, _)| stem);
+
+    // Split by whitespace and dashes, then find the last numeric part
+    file_stem
+        .split(|c: char| c.is_whitespace() || c == '-')
+        .filter_map(|part| part.parse::<usize>().ok())
+        .next_back()
+}
+
 /// Extracts an image file from a ZIP archive for a specific page.
 ///
 /// # Parameters
 /// - `zip_file_path`: The path to the ZIP file that contains the images.
-/// - `page`: The page number of the image to extract.
+/// - `page`: The page number of the image to extract, starting at 1.
 ///
 /// # Returns
 /// - `Ok(Vec<u8>)`: The image content as a byte vector if the image is found for the specified page.
@@ -402,7 +422,8 @@ pub(crate) fn extract_images_from_zip() -> Result<Vec<Vec<u8>>, error::MdownErro
 ///
 /// # Details
 /// - The function attempts to extract an image from the ZIP archive, filtering by file type (JPG, JPEG, PNG, GIF, BMP, or WEBP).
-/// - The function extracts the page number from the filename and matches it with the requested page number.
+/// - Images are ordered by the page number in their file names and `page` is the position in this
+///   order, so cover added as page zero is page 1 and pages are `1..=extract_image_len_from_zip_gui`.
 ///
 /// # Example
 /// ```rust
@@ -415,7 +436,7 @@ pub(crate) fn extract_images_from_zip() -> Result<Vec<Vec<u8>>, error::MdownErro
 ///     }
 /// }
 /// ```
-#[cfg(feature = "gui")]
+#[cfg(any(feature = "gui", feature = "server"))]
 pub(crate) fn extract_image_from_zip_gui(
     zip_file_path: &str,
     page: usize
@@ -433,20 +454,9 @@ pub(crate) fn extract_image_from_zip_gui(
         }
     };
 
-    // Function to extract the page number from a filename
-    fn extract_page_number(file_name: &str) -> Option<usize> {
-        // Strip the extension
-        let file_stem = file_name.rsplit_once('.').map_or(file_name, |(stem, _)| stem);
-
-        // Split by whitespace and dashes, then find the last numeric part
-        file_stem
-            .split(|c: char| (c.is_whitespace() || c == '-'))
-            .filter_map(|part| part.parse::<usize>().ok())
-            .last()
-    }
-
+    let mut pages = Vec::new();
     for i in 0..archive.len() {
-        let mut file = match archive.by_index(i) {
+        let file = match archive.by_index(i) {
             Ok(file) => file,
             Err(err) => {
                 return Err(error::MdownError::ZipError(err, 10726));
@@ -462,15 +472,7 @@ pub(crate) fn extract_image_from_zip_gui(
             match extension.as_str() {
                 "jpg" | "jpeg" | "png" | "gif" | "bmp" | "webp" => {
                     if let Some(file_page) = extract_page_number(file.name()) {
-                        if file_page == page {
-                            let mut content = Vec::new();
-                            if let Err(err) = file.read_to_end(&mut content) {
-                                return Err(
-                                    error::MdownError::IoError(err, file.name().to_string(), 10727)
-                                );
-                            }
-                            return Ok(content);
-                        }
+                        pages.push((file_page, i));
                     }
                 }
                 _ => {
@@ -479,6 +481,21 @@ pub(crate) fn extract_image_from_zip_gui(
             }
         }
     }
+    pages.sort();
+
+    if let Some((_, index)) = page.checked_sub(1).and_then(|position| pages.get(position)) {
+        let mut file = match archive.by_index(*index) {
+            Ok(file) => file,
+            Err(err) => {
+                return Err(error::MdownError::ZipError(err, 10726));
+            }
+        };
+        let mut content = Vec::new();
+        if let Err(err) = file.read_to_end(&mut content) {
+            return Err(error::MdownError::IoError(err, file.name().to_string(), 10727));
+        }
+        return Ok(content);
+    }
 
     Err(error::MdownError::NotFoundError("File not found in the zip archive".to_owned(), 10728))
 }
@@ -509,7 +526,7 @@ pub(crate) fn extract_image_from_zip_gui(
 ///     }
 /// }
 /// ```
-#[cfg(feature = "gui")]
+#[cfg(any(feature = "gui", feature = "server"))]
 pub(crate) fn extract_image_len_from_zip_gui(
     zip_file_path: &str
 ) -> Result<usize, error::MdownError> {
@@ -535,7 +552,7 @@ pub(crate) fn extract_image_len_from_zip_gui(
                 return Err(error::MdownError::ZipError(err, 10731));
             }
         };
-        if let Some(file_name) = file.name().to_lowercase().split('.').last() {
+        if let Some(file_name) = file.name().to_lowercase().split('.').next_back() {
             match file_name {
                 "jpg" | "jpeg" | "png" | "gif" | "bmp" | "webp" => {
                     lenght += 1;
@@ -549,3 +566,112 @@ pub(crate) fn extract_image_len_from_zip_gui(
 
     Ok(lenght)
 }
+
+/// Reads a text file from a ZIP archive.
+///
+/// # Parameters
+/// - `zip_file_path: &str`: The path to the ZIP file.
+/// - `file_name: &str`: The name of the file inside the archive.
+///
+/// # Returns
+/// `Result<String, MdownError>`: Returns the content of the file, or an `MdownError` if the archive
+/// can't be read or doesn't contain the file.
+pub(crate) fn extract_text_from_zip(
+    zip_file_path: &str,
+    file_name: &str
+) -> Result<String, error::MdownError> {
+    let zip_file = match File::open(zip_file_path) {
+        Ok(zip_file) => zip_file,
+        Err(err) => {
+            return Err(error::MdownError::IoError(err, zip_file_path.to_string(), 10733));
+        }
+    };
+    let mut archive = match ZipArchive::new(zip_file) {
+        Ok(archive) => archive,
+        Err(err) => {
+            return Err(error::MdownError::ZipError(err, 10734));
+        }
+    };
+    let mut file = match archive.by_name(file_name) {
+        Ok(file) => file,
+        Err(err) => {
+            return Err(error::MdownError::ZipError(err, 10735));
+        }
+    };
+    let mut content = String::new();
+    match file.read_to_string(&mut content) {
+        Ok(_) => Ok(content),
+        Err(err) => Err(error::MdownError::IoError(err, file_name.to_string(), 10736)),
+    }
+}
+
+/// Adds a file to an existing ZIP archive; other files in the archive are left untouched.
+///
+/// # Parameters
+/// - `zip_file_path: &str`: The path to the ZIP file.
+/// - `file_name: &str`: The name of the new file inside the archive.
+/// - `content: &[u8]`: The content of the new file.
+pub(crate) fn add_file_to_zip(
+    zip_file_path: &str,
+    file_name: &str,
+    content: &[u8]
+) -> Result<(), error::MdownError> {
+    let zip_file = match std::fs::OpenOptions::new().read(true).write(true).open(zip_file_path) {
+        Ok(zip_file) => zip_file,
+        Err(err) => {
+            return Err(error::MdownError::IoError(err, zip_file_path.to_string(), 10737));
+        }
+    };
+    let mut zip = match zip::ZipWriter::new_append(zip_file) {
+        Ok(zip) => zip,
+        Err(err) => {
+            return Err(error::MdownError::ZipError(err, 10738));
+        }
+    };
+    let options = FileOptions::default()
+        .compression_method(zip::CompressionMethod::Stored)
+        .unix_permissions(0o755);
+    if let Err(err) = zip.start_file(file_name, options) {
+        return Err(error::MdownError::ZipError(err, 10739));
+    }
+    if let Err(err) = zip.write_all(content) {
+        return Err(error::MdownError::IoError(err, zip_file_path.to_string(), 10740));
+    }
+    match zip.finish() {
+        Ok(_) => Ok(()),
+        Err(err) => Err(error::MdownError::ZipError(err, 10741)),
+    }
+}
+
+// Test of reading every page of a chapter with cover added as page zero
+#[cfg(any(feature = "gui", feature = "server"))]
+#[test]
+fn test_extract_image_with_cover() {
+    let path = std::env::temp_dir().join(format!("mdown_zip_{}.cbz", utils::generate_random_id(8)));
+    let path = path.to_string_lossy().to_string();
+    let file = File::create(&path).unwrap();
+    let mut zip = zip::ZipWriter::new(file);
+    let options = FileOptions::default();
+    for (name, content) in [
+        ("Name - Ch.1 - 10.jpg", "page 10"),
+        ("Name - Ch.1 - 2.jpg", "page 2"),
+        ("_metadata", "{}"),
+        ("Name - Ch.1 - 0.png", "cover"),
+        ("Name - Ch.1 - 1.jpg", "page 1"),
+    ] {
+        zip.start_file(name, options).unwrap();
+        zip.write_all(content.as_bytes()).unwrap();
+    }
+    zip.finish().unwrap();
+
+    let count = extract_image_len_from_zip_gui(&path).unwrap();
+    assert_eq!(count, 4);
+    let pages: Vec<String> = (1..=count)
+        .map(|page| String::from_utf8(extract_image_from_zip_gui(&path, page).unwrap()).unwrap())
+        .collect();
+    assert_eq!(pages, ["cover", "page 1", "page 2", "page 10"]);
+    assert!(extract_image_from_zip_gui(&path, 0).is_err());
+    assert!(extract_image_from_zip_gui(&path, count + 1).is_err());
+
+    std::fs::remove_file(&path).unwrap();
+}